@@ -13,28 +13,32 @@
 //! cargo run --bin client -- --config config/client_stress.toml \
 //!   --machine-id 1 --client-id 1 \
 //!   --image-dir ./test_images \
-//!   --metrics-output ./metrics/machine_1_client_1.json
+//!   --metrics-output ./metrics/machine_1_client_1.json \
+//!   --prometheus-output ./metrics/machine_1_client_1.prom \
+//!   --csv-output ./metrics/machine_1_client_1.csv
 //! ```
 //!
+//! For JSON log lines (e.g. for ELK or Loki), add `--log-format json` or set
+//! `CLOUDP2P_LOG_FORMAT=json`.
+//!
 //! The client will:
-//! 1. Load configuration from the specified TOML file
+//! 1. Load configuration from the specified TOML file, failing fast if its
+//!    image directory is missing or holds no images
 //! 2. Initialize the client core (image transmission service)
 //! 3. Initialize the client middleware (request coordination)
 //! 4. Discover the current leader
 //! 5. Submit encryption tasks at the configured rate
 //! 6. Handle retries and failover automatically
-//! 7. Track metrics and export to JSON (if metrics-output specified)
+//! 7. Track metrics and export to JSON, Prometheus text format and/or CSV (if requested)
 
 use clap::Parser;
-use env_logger::Builder;
-use log::LevelFilter;
-use std::io::Write;
 use std::sync::Arc;
 
 // Import from the library crate
 use cloud_p2p::client::middleware::ClientConfig;
 use cloud_p2p::client::{ClientCore, ClientMetrics, ClientMiddleware};
-use cloud_p2p::common::config::load_config;
+use cloud_p2p::common::config::load_config_with_env;
+use cloud_p2p::common::logging::{self, LogFormat};
 
 /// Command-line arguments for the client binary
 #[derive(Parser, Debug)]
@@ -50,40 +54,39 @@ struct Args {
     #[arg(long)]
     metrics_output: Option<String>,
 
+    /// Path to write metrics in Prometheus text format (optional)
+    #[arg(long)]
+    prometheus_output: Option<String>,
+
+    /// Path to write one CSV row per request (optional)
+    #[arg(long)]
+    csv_output: Option<String>,
+
+    /// Include raw request latencies in the JSON metrics, so reports from many
+    /// clients can be merged with exact percentiles
+    #[arg(long)]
+    raw_latencies: bool,
+
     /// Client ID (appended to name from config, e.g., "Machine_1" + "_Client_5")
     #[arg(long)]
     client_id: Option<u32>,
-}
 
-/// Initialize the logging system with timestamp, level, and message formatting.
-///
-/// Logs are printed to stdout with INFO level by default.
-/// Format: `[HH:MM:SS] [LEVEL] message`
-fn init_logger() {
-    Builder::new()
-        .format(|buf, record| {
-            writeln!(
-                buf,
-                "[{}] [{}] {}",
-                chrono::Local::now().format("%H:%M:%SS"),
-                record.level(),
-                record.args()
-            )
-        })
-        .filter_level(LevelFilter::Info)
-        .init();
+    /// Log line format: `text` or `json` (defaults to $CLOUDP2P_LOG_FORMAT, then text)
+    #[arg(long)]
+    log_format: Option<LogFormat>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize logging
-    init_logger();
-
     // Parse command-line arguments
     let args = Args::parse();
 
-    // Load client configuration from TOML file
-    let mut config: ClientConfig = load_config(&args.config)?;
+    // Initialize logging
+    logging::init_logger(LogFormat::resolve(args.log_format)?);
+
+    // Load client configuration from TOML file (CLOUDP2P_* env vars override it)
+    let mut config: ClientConfig = load_config_with_env(&args.config)?;
+    config.validate()?;
 
     // Append client ID to name if provided
     let client_name = if let Some(id) = args.client_id {
@@ -94,17 +97,27 @@ async fn main() -> anyhow::Result<()> {
 
     config.client.name = client_name.clone();
 
+    // Tag every JSON log line with this client's name
+    logging::set_context("client", client_name.clone());
+
     // Create the client core (handles image transmission)
-    let core = Arc::new(ClientCore::new(client_name.clone()));
+    let core = Arc::new(
+        ClientCore::new(client_name.clone(), &config.client.output_dir)
+            .with_max_message_size(config.client.max_message_size)
+            .with_metadata(config.client.metadata.clone()),
+    );
 
     // Create the client middleware (handles request coordination)
     let mut middleware = ClientMiddleware::new(config, core);
 
-    // Initialize metrics if output path is specified
-    let metrics = if args.metrics_output.is_some() {
-        let m = Arc::new(std::sync::Mutex::new(ClientMetrics::new(
-            client_name.clone(),
-        )));
+    // Initialize metrics if an output path is specified
+    let metrics = if args.metrics_output.is_some()
+        || args.prometheus_output.is_some()
+        || args.csv_output.is_some()
+    {
+        let m = Arc::new(std::sync::Mutex::new(
+            ClientMetrics::new(client_name.clone()).with_raw_latencies(args.raw_latencies),
+        ));
         middleware = middleware.with_metrics(m.clone());
         Some(m)
     } else {
@@ -116,11 +129,19 @@ async fn main() -> anyhow::Result<()> {
 
     // Export metrics if enabled
     if let Some(metrics) = metrics {
+        let metrics = metrics.lock().unwrap();
         if let Some(output_path) = args.metrics_output {
-            let metrics = metrics.lock().unwrap();
             metrics.export_to_json(&output_path)?;
             println!("Metrics exported to: {}", output_path);
         }
+        if let Some(output_path) = args.prometheus_output {
+            metrics.export_to_prometheus(&output_path)?;
+            println!("Prometheus metrics exported to: {}", output_path);
+        }
+        if let Some(output_path) = args.csv_output {
+            metrics.export_to_csv(&output_path)?;
+            println!("Request records exported to: {}", output_path);
+        }
     }
 
     Ok(())
@@ -8,20 +8,22 @@
 //! cargo run --bin server -- --config config/server1.toml
 //! ```
 //!
+//! For JSON log lines (e.g. for ELK or Loki), add `--log-format json` or set
+//! `CLOUDP2P_LOG_FORMAT=json`.
+//!
 //! The server will:
 //! 1. Load configuration from the specified TOML file
 //! 2. Initialize the server core (encryption service)
 //! 3. Initialize the server middleware (distributed coordination)
 //! 4. Start all server tasks (listener, heartbeat, peer connections, monitoring)
 //! 5. Participate in leader election using Modified Bully Algorithm
+//! 6. On Ctrl-C, resign leadership (if leader) and finish in-flight tasks before exiting
 
 use clap::Parser;
-use env_logger::Builder;
-use log::LevelFilter;
-use std::io::Write;
 
 // Import from the library crate
-use cloud_p2p::common::config::load_config;
+use cloud_p2p::common::config::load_config_with_env;
+use cloud_p2p::common::logging::{self, LogFormat};
 use cloud_p2p::server::middleware::ServerConfig;
 use cloud_p2p::server::{ServerCore, ServerMiddleware};
 
@@ -34,48 +36,39 @@ struct Args {
     /// Example: config/server1.toml
     #[arg(short, long)]
     config: String,
-}
 
-/// Initialize the logging system with timestamp, level, and message formatting.
-///
-/// Logs are printed to stdout with INFO level by default.
-/// Format: `[HH:MM:SS] [LEVEL] message`
-fn init_logger() {
-    Builder::new()
-        .format(|buf, record| {
-            writeln!(
-                buf,
-                "[{}] [{}] {}",
-                chrono::Local::now().format("%H:%M:%S"),
-                record.level(),
-                record.args()
-            )
-        })
-        .filter_level(LevelFilter::Info)
-        .init();
+    /// Log line format: `text` or `json` (defaults to $CLOUDP2P_LOG_FORMAT, then text)
+    #[arg(long)]
+    log_format: Option<LogFormat>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize logging
-    init_logger();
-
     // Parse command-line arguments
     let args = Args::parse();
 
-    // Load server configuration from TOML file
-    let config: ServerConfig = load_config(&args.config)?;
+    // Initialize logging
+    logging::init_logger(LogFormat::resolve(args.log_format)?);
+
+    // Load server configuration from TOML file (CLOUDP2P_* env vars override it)
+    let config: ServerConfig = load_config_with_env(&args.config)?;
+    config.validate()?;
+
+    // Tag every JSON log line with this server's ID
+    logging::set_context("server_id", config.server.id);
 
     // Create the server core (handles encryption)
-    // ServerCore will load the cover image from the path specified in config
-    let core = std::sync::Arc::new(
-        ServerCore::new(config.server.id, &config.server.cover_image)?
-    );
+    // ServerCore loads a carrier pool if configured, otherwise the single cover image
+    let core = match &config.server.carrier_dir {
+        Some(carrier_dir) => ServerCore::with_carrier_pool(config.server.id, carrier_dir)?,
+        None => ServerCore::new(config.server.id, &config.server.cover_image)?,
+    };
+    let core = std::sync::Arc::new(core);
 
     // Create the server middleware (handles distributed coordination)
     let middleware = ServerMiddleware::new(config, core);
 
-    // Start the server (runs indefinitely until error or shutdown)
+    // Start the server (runs until Ctrl-C, then hands off leadership and drains tasks)
     middleware.run().await;
 
     Ok(())
@@ -0,0 +1,178 @@
+//! # Steganography Tool Entry Point
+//!
+//! Embeds a secret image in a carrier, or extracts it again, locally and without
+//! a cluster, using the same functions the servers and clients do.
+//!
+//! ## Usage
+//!
+//! ```bash
+//! cargo run --bin stego -- embed --carrier carrier.png --secret secret.png --out out.png
+//! cargo run --bin stego -- extract --carrier out.png --out secret.png
+//! ```
+//!
+//! Errors (unreadable files, a carrier too small for the secret, a carrier
+//! holding no secret) are reported on stderr with a non-zero exit code.
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
+
+use cloud_p2p::processing::steganography;
+
+/// Command-line arguments for the stego binary
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// What to do with the carrier
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Hide a secret image in a carrier image
+    Embed {
+        /// Carrier image to hide the secret in
+        #[arg(long)]
+        carrier: PathBuf,
+
+        /// Secret image to hide
+        #[arg(long)]
+        secret: PathBuf,
+
+        /// Where to write the carrier with the secret embedded (PNG)
+        #[arg(long)]
+        out: PathBuf,
+    },
+
+    /// Recover the secret image from a carrier
+    Extract {
+        /// Carrier image holding a secret
+        #[arg(long)]
+        carrier: PathBuf,
+
+        /// Where to write the extracted secret image
+        #[arg(long)]
+        out: PathBuf,
+    },
+}
+
+fn main() -> Result<()> {
+    run(Args::parse())
+}
+
+/// Carry out the parsed command.
+fn run(args: Args) -> Result<()> {
+    match args.command {
+        Command::Embed {
+            carrier,
+            secret,
+            out,
+        } => {
+            let carrier_bytes = read(&carrier)?;
+            let secret_bytes = read(&secret)?;
+            let embedded = steganography::embed_image_bytes(&carrier_bytes, &secret_bytes)
+                .with_context(|| {
+                    format!(
+                        "Failed to embed {} in {}",
+                        secret.display(),
+                        carrier.display()
+                    )
+                })?;
+            write(&out, &embedded)?;
+            println!(
+                "Embedded {} ({} bytes) in {}",
+                secret.display(),
+                secret_bytes.len(),
+                out.display()
+            );
+        }
+        Command::Extract { carrier, out } => {
+            let carrier_bytes = read(&carrier)?;
+            let secret = steganography::extract_image_bytes(&carrier_bytes).with_context(|| {
+                format!("Failed to extract a secret from {}", carrier.display())
+            })?;
+            write(&out, &secret)?;
+            println!(
+                "Extracted {} bytes from {} to {}",
+                secret.len(),
+                carrier.display(),
+                out.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn write(path: &Path, bytes: &[u8]) -> Result<()> {
+    std::fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, ImageFormat, Rgba};
+    use std::io::Cursor;
+
+    fn png(width: u32, height: u32, shade: u8) -> Vec<u8> {
+        let image = ImageBuffer::from_pixel(width, height, Rgba([shade, 80, 120, 255]));
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        bytes
+    }
+
+    fn run_with(args: &[&str]) -> Result<()> {
+        run(Args::try_parse_from(std::iter::once("stego").chain(args.iter().copied())).unwrap())
+    }
+
+    #[test]
+    fn test_embed_then_extract_round_trips_the_secret() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        let secret = png(4, 4, 200);
+        std::fs::write(path("carrier.png"), png(64, 64, 10)).unwrap();
+        std::fs::write(path("secret.png"), &secret).unwrap();
+
+        run_with(&[
+            "embed",
+            "--carrier",
+            &path("carrier.png"),
+            "--secret",
+            &path("secret.png"),
+            "--out",
+            &path("out.png"),
+        ])
+        .unwrap();
+        run_with(&[
+            "extract",
+            "--carrier",
+            &path("out.png"),
+            "--out",
+            &path("extracted.png"),
+        ])
+        .unwrap();
+        assert_eq!(std::fs::read(path("extracted.png")).unwrap(), secret);
+
+        // A carrier without a secret is an error naming the file, not a panic
+        let error = run_with(&[
+            "extract",
+            "--carrier",
+            &path("carrier.png"),
+            "--out",
+            &path("nothing.png"),
+        ])
+        .unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("carrier.png"),
+            "{:#}",
+            error
+        );
+        assert!(!dir.path().join("nothing.png").exists());
+    }
+}
@@ -1,23 +1,37 @@
 //! Web server for image steganography API
+//!
+//! Set `CLOUDP2P_LOG_FORMAT=json` for JSON log lines.
 
 use axum::{
-    extract::{multipart::Multipart, State},
+    extract::{
+        multipart::Multipart,
+        ws::{Message as WsMessage, WebSocket, WebSocketUpgrade},
+        DefaultBodyLimit, State,
+    },
     http::StatusCode,
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
 use base64::{engine::general_purpose, Engine as _};
-use log::{error, info};
+use log::{error, info, warn};
 use serde::Serialize;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc, Mutex};
 use tower_http::cors::CorsLayer;
 use tower_http::services::ServeDir;
 
 // Import your existing client middleware
 use cloud_p2p::client::client::ClientCore;
-use cloud_p2p::client::middleware::{ClientConfig, ClientMiddleware};
+use cloud_p2p::client::middleware::{ClientConfig, ClientMiddleware, TaskProgress};
+use cloud_p2p::common::config::load_config_with_env;
+use cloud_p2p::common::logging::{self, LogFormat};
+use cloud_p2p::processing::SteganographyError;
+use cloud_p2p::CloudP2PError;
+
+/// How long an encrypt request waits for a leader to take its task, unless the
+/// config sets `failover.assignment_timeout_secs`, so no HTTP request hangs forever.
+const DEFAULT_ASSIGNMENT_TIMEOUT_SECS: u64 = 60;
 
 #[derive(Serialize)]
 struct EncryptResponse {
@@ -25,6 +39,22 @@ struct EncryptResponse {
     message: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     carrier_image_base64: Option<String>,
+    /// ID of the server that encrypted the image (the leader, in leader-routed mode)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    processed_by_server: Option<u32>,
+}
+
+#[derive(Serialize)]
+struct DecryptResponse {
+    success: bool,
+    message: String,
+    secret_image_base64: String,
+    /// Views left on a policy-protected carrier after this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remaining_views: Option<u32>,
+    /// The policy-protected carrier with this view used up, to replace the uploaded one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    updated_carrier_base64: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -34,38 +64,65 @@ struct ErrorResponse {
 
 struct AppState {
     client: Arc<Mutex<ClientMiddleware>>,
+    /// Separate handle for cluster polling, so it isn't blocked behind running tasks
+    monitor: ClientMiddleware,
+    core: Arc<ClientCore>,
+    /// Largest accepted image upload, in bytes
+    max_upload_bytes: usize,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    env_logger::init();
+    logging::init_logger(LogFormat::resolve(None)?);
 
     info!("🚀 Initializing web server...");
 
     // Load client configuration
-    let config = ClientConfig::from_file("config/client1.toml")?;
+    let mut config: ClientConfig = load_config_with_env("config/client1.toml")?;
+    config
+        .failover
+        .assignment_timeout_secs
+        .get_or_insert(DEFAULT_ASSIGNMENT_TIMEOUT_SECS);
+    logging::set_context("client", config.client.name.clone());
 
     // Create client core
-    let core = Arc::new(ClientCore::new(config.client.name.clone()));
+    let core = Arc::new(
+        ClientCore::new(config.client.name.clone(), &config.client.output_dir)
+            .with_max_message_size(config.client.max_message_size)
+            .with_metadata(config.client.metadata.clone()),
+    );
+
+    let max_upload_bytes = config.client.max_upload_bytes;
 
     // Create client middleware
-    let client = ClientMiddleware::new(config, core);
+    let client = ClientMiddleware::new(config, core.clone());
 
     let state = Arc::new(AppState {
+        monitor: client.clone(),
         client: Arc::new(Mutex::new(client)),
+        core,
+        max_upload_bytes,
     });
 
     // Build router
     let app = Router::new()
         .route("/api/encrypt", post(encrypt_image_handler))
+        .route("/api/decrypt", post(decrypt_image_handler))
         .route("/api/health", get(health_check))
+        .route("/api/cluster", get(cluster_handler))
+        .route("/ws/encrypt", get(encrypt_ws_handler))
         .nest_service("/", ServeDir::new("frontend/build"))
+        // Uploads are capped by `max_upload_bytes` while streaming instead
+        .layer(DefaultBodyLimit::disable())
         .layer(CorsLayer::permissive())
         .with_state(state);
 
     let addr = "127.0.0.1:3000";
     info!("🌐 Web server running on http://{}", addr);
     info!("📡 API endpoint: http://{}/api/encrypt", addr);
+    info!("📡 API endpoint: http://{}/api/decrypt", addr);
+    info!("📡 API endpoint: http://{}/api/cluster", addr);
+    info!("📡 WebSocket endpoint: ws://{}/ws/encrypt", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app).await?;
@@ -82,11 +139,27 @@ async fn health_check() -> impl IntoResponse {
     }))
 }
 
-async fn encrypt_image_handler(
-    State(state): State<Arc<AppState>>,
-    mut multipart: Multipart,
-) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let mut secret_image_data: Option<Vec<u8>> = None;
+/// Report the current leader and each server's load.
+///
+/// Returns a [`ClusterStatus`](cloud_p2p::client::middleware::ClusterStatus), e.g.
+/// `{"leader_id":1,"servers":[{"address":"127.0.0.1:8001","reachable":true,"server_id":1,
+/// "load":12.5,"cpu_usage":20.0,"active_tasks":2,"total_tasks":40,"saturated":false}]}`.
+async fn cluster_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.monitor.cluster_status().await)
+}
+
+/// Read the `image` field of a multipart upload.
+///
+/// The field is streamed chunk by chunk, so an image over `max_bytes` is rejected
+/// with 413 before it is fully buffered.
+///
+/// Returns the uploaded file name and bytes, a 400 response if the upload is
+/// malformed or has no `image` field, or a 413 response if the image is too large.
+async fn read_image_field(
+    multipart: &mut Multipart,
+    max_bytes: usize,
+) -> Result<(String, Vec<u8>), (StatusCode, Json<ErrorResponse>)> {
+    let mut image_data: Option<Vec<u8>> = None;
     let mut filename = String::from("uploaded_image.jpg");
 
     // Parse multipart form data
@@ -98,23 +171,39 @@ async fn encrypt_image_handler(
             }),
         )
     })? {
+        let mut field = field;
         let name = field.name().unwrap_or("").to_string();
 
         if name == "image" {
             filename = field.file_name().unwrap_or("image.jpg").to_string();
-            let data = field.bytes().await.map_err(|e| {
+            let mut data = Vec::new();
+            while let Some(chunk) = field.chunk().await.map_err(|e| {
                 (
                     StatusCode::BAD_REQUEST,
                     Json(ErrorResponse {
                         error: format!("Failed to read image data: {}", e),
                     }),
                 )
-            })?;
-            secret_image_data = Some(data.to_vec());
+            })? {
+                if data.len() + chunk.len() > max_bytes {
+                    warn!(
+                        "🚫 Rejected upload '{}': larger than {} bytes",
+                        filename, max_bytes
+                    );
+                    return Err((
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        Json(ErrorResponse {
+                            error: format!("Image exceeds the {} byte upload limit", max_bytes),
+                        }),
+                    ));
+                }
+                data.extend_from_slice(&chunk);
+            }
+            image_data = Some(data);
         }
     }
 
-    let secret_image_data = secret_image_data.ok_or_else(|| {
+    let image_data = image_data.ok_or_else(|| {
         (
             StatusCode::BAD_REQUEST,
             Json(ErrorResponse {
@@ -123,6 +212,16 @@ async fn encrypt_image_handler(
         )
     })?;
 
+    Ok((filename, image_data))
+}
+
+async fn encrypt_image_handler(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let (filename, secret_image_data) =
+        read_image_field(&mut multipart, state.max_upload_bytes).await?;
+
     info!(
         "📤 Received secret image: {} ({} bytes)",
         filename,
@@ -133,14 +232,19 @@ async fn encrypt_image_handler(
 
     // Submit to distributed system for encryption
     let mut client = state.client.lock().await;
-    match client.submit_task(request_id, secret_image_data).await {
-        Ok(carrier_image_with_secret) => {
+    match client
+        .submit_task(request_id, secret_image_data, None)
+        .await
+    {
+        Ok((server_id, carrier)) => {
             info!(
-                "✅ Encryption complete! Carrier size: {} bytes",
-                carrier_image_with_secret.len()
+                "✅ Encryption complete on Server {}! Carrier size: {} bytes, saved to {}",
+                server_id,
+                carrier.data.len(),
+                carrier.path.display()
             );
 
-            let carrier_base64 = general_purpose::STANDARD.encode(&carrier_image_with_secret);
+            let carrier_base64 = general_purpose::STANDARD.encode(&carrier.data);
 
             Ok((
                 StatusCode::OK,
@@ -148,13 +252,18 @@ async fn encrypt_image_handler(
                     success: true,
                     message: format!("Successfully encrypted {}", filename),
                     carrier_image_base64: Some(carrier_base64),
+                    processed_by_server: Some(server_id),
                 }),
             ))
         }
         Err(e) => {
             error!("❌ Encryption failed: {}", e);
+            let status = match e.downcast_ref::<CloudP2PError>() {
+                Some(CloudP2PError::AssignmentTimeout { .. }) => StatusCode::GATEWAY_TIMEOUT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
             Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
+                status,
                 Json(ErrorResponse {
                     error: format!("Server-side encryption failed: {}", e),
                 }),
@@ -162,3 +271,208 @@ async fn encrypt_image_handler(
         }
     }
 }
+
+async fn decrypt_image_handler(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let (filename, carrier_image_data) =
+        read_image_field(&mut multipart, state.max_upload_bytes).await?;
+
+    info!(
+        "📥 Received carrier image: {} ({} bytes)",
+        filename,
+        carrier_image_data.len()
+    );
+
+    // Extraction is local - no server round trip needed
+    match state.core.decrypt_carrier_image(&carrier_image_data) {
+        Ok(decrypted) => {
+            info!(
+                "✅ Decryption complete! Secret size: {} bytes",
+                decrypted.secret.len()
+            );
+
+            Ok((
+                StatusCode::OK,
+                Json(DecryptResponse {
+                    success: true,
+                    message: format!("Successfully decrypted {}", filename),
+                    secret_image_base64: general_purpose::STANDARD.encode(&decrypted.secret),
+                    remaining_views: decrypted.remaining_views,
+                    updated_carrier_base64: decrypted
+                        .updated_carrier
+                        .map(|carrier| general_purpose::STANDARD.encode(carrier)),
+                }),
+            ))
+        }
+        Err(e) => {
+            error!("❌ Decryption failed: {}", e);
+            // The carrier is fine, but its access policy refuses us
+            let status = match e.downcast_ref::<SteganographyError>() {
+                Some(SteganographyError::AccessDenied { .. } | SteganographyError::NoViewsLeft) => {
+                    StatusCode::FORBIDDEN
+                }
+                _ => StatusCode::BAD_REQUEST,
+            };
+            Err((
+                status,
+                Json(ErrorResponse {
+                    error: format!("Could not extract a secret image: {}", e),
+                }),
+            ))
+        }
+    }
+}
+
+/// Encrypt an image over a WebSocket, streaming progress while the task runs.
+///
+/// The frontend sends the secret image as one binary message. The server replies
+/// with a JSON text message per [`TaskProgress`] event, e.g.
+/// `{"event":"assigned","server_id":2,"leader_id":1}` or
+/// `{"event":"resubmitting","attempt":1,"max_attempts":5,"reason":"..."}`, then a
+/// final `EncryptResponse`
+/// (`{"success":true,"message":"...","carrier_image_base64":"...","processed_by_server":2}`)
+/// and closes the socket.
+async fn encrypt_ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+) -> impl IntoResponse {
+    // Oversized images are refused by the WebSocket protocol layer
+    ws.max_message_size(state.max_upload_bytes)
+        .on_upgrade(move |socket| encrypt_over_socket(socket, state))
+}
+
+async fn encrypt_over_socket(mut socket: WebSocket, state: Arc<AppState>) {
+    // Wait for the secret image, ignoring pings and text
+    let secret_image_data = loop {
+        match socket.recv().await {
+            Some(Ok(WsMessage::Binary(data))) => break data,
+            Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => return,
+            Some(Ok(_)) => continue,
+        }
+    };
+
+    info!(
+        "📤 Received secret image over WebSocket ({} bytes)",
+        secret_image_data.len()
+    );
+
+    let request_id = rand::random::<u64>();
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel::<TaskProgress>();
+
+    let submit = async {
+        let mut client = state.client.lock().await;
+        client
+            .submit_task(request_id, secret_image_data, Some(progress_tx))
+            .await
+    };
+    let forward = async {
+        // Ends when submit_task drops the sender. A closed socket is ignored so the
+        // task still runs to completion.
+        while let Some(event) = progress_rx.recv().await {
+            if let Ok(json) = serde_json::to_string(&event) {
+                let _ = socket.send(WsMessage::Text(json)).await;
+            }
+        }
+    };
+    let (result, ()) = tokio::join!(submit, forward);
+
+    let response = match result {
+        Ok((server_id, carrier)) => {
+            info!(
+                "✅ Encryption complete on Server {}! Carrier size: {} bytes, saved to {}",
+                server_id,
+                carrier.data.len(),
+                carrier.path.display()
+            );
+            EncryptResponse {
+                success: true,
+                message: "Successfully encrypted image".to_string(),
+                carrier_image_base64: Some(general_purpose::STANDARD.encode(&carrier.data)),
+                processed_by_server: Some(server_id),
+            }
+        }
+        Err(e) => {
+            error!("❌ Encryption failed: {}", e);
+            EncryptResponse {
+                success: false,
+                message: format!("Server-side encryption failed: {}", e),
+                carrier_image_base64: None,
+                processed_by_server: None,
+            }
+        }
+    };
+
+    if let Ok(json) = serde_json::to_string(&response) {
+        let _ = socket.send(WsMessage::Text(json)).await;
+    }
+    let _ = socket.send(WsMessage::Close(None)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::extract::FromRequest;
+    use axum::http::Request;
+
+    fn state(max_upload_bytes: usize) -> Arc<AppState> {
+        let config: ClientConfig = toml::from_str(
+            r#"
+            [client]
+            name = "WebClient"
+            server_addresses = ["127.0.0.1:1"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+            "#,
+        )
+        .unwrap();
+        let core = Arc::new(ClientCore::new("WebClient".to_string(), "unused"));
+
+        let client = ClientMiddleware::new(config, core.clone());
+        Arc::new(AppState {
+            monitor: client.clone(),
+            client: Arc::new(Mutex::new(client)),
+            core,
+            max_upload_bytes,
+        })
+    }
+
+    /// Build a multipart upload with `image` as its `image` field.
+    async fn upload(state: &Arc<AppState>, image: &[u8]) -> Multipart {
+        let mut body = b"--BOUNDARY\r\n\
+            Content-Disposition: form-data; name=\"image\"; filename=\"big.png\"\r\n\
+            Content-Type: image/png\r\n\r\n"
+            .to_vec();
+        body.extend_from_slice(image);
+        body.extend_from_slice(b"\r\n--BOUNDARY--\r\n");
+
+        let request = Request::builder()
+            .method("POST")
+            .uri("/api/encrypt")
+            .header("content-type", "multipart/form-data; boundary=BOUNDARY")
+            .body(Body::from(body))
+            .unwrap();
+        Multipart::from_request(request, state).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_upload_is_rejected_with_413() {
+        let state = state(1024);
+
+        let multipart = upload(&state, &[0u8; 4096]).await;
+        let response = encrypt_image_handler(State(state.clone()), multipart)
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        // An image at the limit is read in full
+        let mut multipart = upload(&state, &[7u8; 1024]).await;
+        let (filename, image) = read_image_field(&mut multipart, 1024).await.ok().unwrap();
+        assert_eq!((filename.as_str(), image.len()), ("big.png", 1024));
+    }
+}
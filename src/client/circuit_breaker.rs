@@ -0,0 +1,123 @@
+//! # Per-Server Circuit Breaker
+//!
+//! Stops the client from hammering a server that keeps accepting tasks but
+//! failing them (e.g. a broken carrier image or a disk full on that machine).
+//!
+//! Each server has a breaker that counts consecutive failures:
+//!
+//! ```text
+//! closed --(threshold consecutive failures)--> open --(cooldown)--> half-open
+//!   ^                                                                  |
+//!   +----------------------------(success)-----------------------------+
+//! ```
+//!
+//! While open, the server is treated as failed without contacting it. Once the
+//! cooldown passes it gets one more try: a success closes the breaker, another
+//! failure re-opens it immediately.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Failure count and open state of one server's breaker.
+#[derive(Debug, Clone, Default)]
+struct Breaker {
+    /// Failures since the last success
+    consecutive_failures: u32,
+    /// When the breaker closes again (None while closed)
+    open_until: Option<Instant>,
+}
+
+/// Circuit breakers for every server the client has talked to, keyed by server ID.
+#[derive(Debug, Clone)]
+pub struct CircuitBreakers {
+    /// Consecutive failures that open a server's breaker
+    failure_threshold: u32,
+    /// How long an open breaker stays open
+    cooldown: Duration,
+    /// Per-server breaker state
+    breakers: HashMap<u32, Breaker>,
+}
+
+impl CircuitBreakers {
+    /// Create breakers that open after `failure_threshold` consecutive failures
+    /// and stay open for `cooldown`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut breakers = CircuitBreakers::new(3, Duration::from_secs(30));
+    /// breakers.record_failure(2, Instant::now());
+    /// ```
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            breakers: HashMap::new(),
+        }
+    }
+
+    /// Record a failed task on `server_id`.
+    ///
+    /// # Returns
+    /// `true` if this failure opened the server's breaker
+    pub fn record_failure(&mut self, server_id: u32, now: Instant) -> bool {
+        let breaker = self.breakers.entry(server_id).or_default();
+        breaker.consecutive_failures = breaker.consecutive_failures.saturating_add(1);
+
+        if breaker.consecutive_failures >= self.failure_threshold {
+            breaker.open_until = Some(now + self.cooldown);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Record a successful task on `server_id`, closing its breaker.
+    pub fn record_success(&mut self, server_id: u32) {
+        self.breakers.remove(&server_id);
+    }
+
+    /// Whether `server_id`'s breaker is open at `now`.
+    pub fn is_open(&self, server_id: u32, now: Instant) -> bool {
+        self.breakers
+            .get(&server_id)
+            .and_then(|breaker| breaker.open_until)
+            .is_some_and(|open_until| now < open_until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_opens_after_threshold_and_half_opens_after_cooldown() {
+        let mut breakers = CircuitBreakers::new(3, Duration::from_secs(30));
+        let now = Instant::now();
+
+        assert!(!breakers.record_failure(2, now));
+        assert!(!breakers.record_failure(2, now));
+        assert!(!breakers.is_open(2, now));
+
+        // The 3rd consecutive failure opens it; other servers are unaffected
+        assert!(breakers.record_failure(2, now));
+        assert!(breakers.is_open(2, now + Duration::from_secs(29)));
+        assert!(!breakers.is_open(1, now));
+
+        // After the cooldown it gets one more try, and one failure re-opens it
+        let later = now + Duration::from_secs(30);
+        assert!(!breakers.is_open(2, later));
+        assert!(breakers.record_failure(2, later));
+        assert!(breakers.is_open(2, later));
+    }
+
+    #[test]
+    fn test_success_resets_failure_count() {
+        let mut breakers = CircuitBreakers::new(2, Duration::from_secs(30));
+        let now = Instant::now();
+
+        breakers.record_failure(1, now);
+        breakers.record_success(1);
+        assert!(!breakers.record_failure(1, now));
+        assert!(!breakers.is_open(1, now));
+    }
+}
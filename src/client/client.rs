@@ -6,19 +6,24 @@
 //! ## Responsibility
 //!
 //! The [`ClientCore`] struct focuses on a single, well-defined responsibility:
-//! - Connect to an assigned server
+//! - Connect to an assigned server, reusing an idle connection to it when there is one
 //! - Send a task request with image data and text to embed
 //! - Receive the encrypted image response
-//! - Save the encrypted image locally
-//! - Verify the encryption by extracting and comparing the embedded text
+//! - Save the encrypted image to the configured output directory
+//! - Verify the encryption by extracting the embedded secret and comparing it with the original
+//! - Decrypt a carrier image back into its secret image, enforcing its access
+//!   policy (if any) with the client's name as the viewer
+//! - Decrypt a carrier image file, writing the used-up view back to it
 //!
 //! ## Design Philosophy
 //!
-//! This core component is intentionally minimal and stateless. It does not handle:
+//! This core component is intentionally minimal. Its only state is a small pool of
+//! idle connections per server address, kept alive by a background task that pings
+//! each one every `keepalive_interval` and drops those that don't answer in time.
+//! It does not handle:
 //! - Leader discovery
 //! - Server assignment logic
 //! - Retry mechanisms
-//! - Connection pooling
 //! - Configuration management
 //!
 //! Those concerns are delegated to the [`ClientMiddleware`](super::middleware::ClientMiddleware).
@@ -28,26 +33,96 @@
 //! ```rust,ignore
 //! use cloudp2p::client::client::ClientCore;
 //!
-//! let core = ClientCore::new("Client1".to_string());
+//! let core = ClientCore::new("Client1".to_string(), "user-data/outputs");
 //!
 //! // Called by middleware after obtaining server assignment
-//! core.send_and_receive_encrypted_image(
+//! let carrier = core.send_and_receive_encrypted_image(
 //!     "127.0.0.1:5001",  // assigned server address
 //!     request_id,
-//!     image_data,
+//!     &image_data,
 //!     "photo.jpg",
 //!     "username:alice,views:5",
 //!     leader_id
 //! ).await?;
+//! println!("Saved to {}", carrier.path.display());
 //! ```
 
 use anyhow::Result;
-use log::{error, info};
-use tokio::net::TcpStream;
+use log::{error, info, warn};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::task::JoinSet;
 
-use crate::common::connection::Connection;
+use crate::common::connection::{Connection, ConnectionError, DEFAULT_MAX_MESSAGE_SIZE};
+use crate::common::error::CloudP2PError;
 use crate::common::messages::Message;
-use crate::processing::steganography;
+use crate::common::transport::Stream;
+use crate::processing::steganography::{self, ProgressCallback, SteganographyError};
+
+/// How long to wait for the server's `TaskResponse`, including its encryption time.
+/// A dead server is detected after this instead of hanging on a half-open connection.
+const RESPONSE_TIMEOUT_SECS: u64 = 30;
+
+/// How long sending the task request (with the secret image) may take.
+const REQUEST_WRITE_TIMEOUT_SECS: u64 = 10;
+
+/// Most idle connections kept open to each server for reuse by later requests.
+const MAX_IDLE_CONNECTIONS_PER_SERVER: usize = 4;
+
+/// Default time between keepalive pings on idle connections; a ping unanswered
+/// within the same time drops the connection.
+const KEEPALIVE_INTERVAL_SECS: u64 = 5;
+
+/// Idle connections by server address.
+type IdlePool = Mutex<HashMap<String, Vec<Connection<Stream>>>>;
+
+/// The leader refused a request because this client exceeded its request rate.
+///
+/// Returned wrapped in an [`anyhow::Error`]; use `downcast_ref::<RateLimited>()` to
+/// find out how long to wait before retrying.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimited {
+    /// How long until the leader accepts requests from this client again
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Rate limited by leader, retry after {}ms",
+            self.retry_after.as_millis()
+        )
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// A verified carrier image received from a server, and where it was saved.
+#[derive(Debug, Clone)]
+pub struct CarrierImage {
+    /// The carrier image bytes, with the secret embedded
+    pub data: Vec<u8>,
+    /// Where the carrier was written: `{output_dir}/{client_name}_{request_id}.png`
+    pub path: PathBuf,
+}
+
+/// A secret image extracted by [`ClientCore::decrypt_carrier_image`].
+#[derive(Debug, Clone)]
+pub struct DecryptedImage {
+    /// The secret image bytes
+    pub secret: Vec<u8>,
+    /// Views left after this one (None if the carrier has no access policy)
+    pub remaining_views: Option<u32>,
+    /// The carrier with this view used up, to store in place of the original
+    /// (None if the carrier has no access policy)
+    pub updated_carrier: Option<Vec<u8>>,
+}
 
 /// The minimal core client that handles direct image transmission and encryption verification.
 ///
@@ -58,9 +133,28 @@ use crate::processing::steganography;
 /// # Fields
 ///
 /// * `client_name` - Unique identifier for this client, used in requests and logging
+/// * `output_dir` - Directory received carrier images are saved to
+/// * `max_message_size` - Largest server response accepted, in bytes
+/// * `metadata` - Text to have embedded alongside every secret image
+/// * `idle_connections` - Connections to reuse, by server address
+/// * `keepalive_interval` - Time between pings on idle connections
+/// * `keepalive_running` - Whether a task is pinging the idle connections
 pub struct ClientCore {
     /// The unique name identifying this client
     client_name: String,
+    /// Directory received carrier images are saved to (created if missing)
+    output_dir: PathBuf,
+    /// Largest server response accepted, in bytes
+    max_message_size: usize,
+    /// Text sent with every task, for the server to embed alongside the secret
+    metadata: Option<String>,
+    /// Connections whose last exchange completed, by server address. A request
+    /// takes one out for its exclusive use, so parallel requests never share one.
+    idle_connections: Arc<IdlePool>,
+    /// Time between pings on idle connections, and how long each may take
+    keepalive_interval: Duration,
+    /// Set while a keepalive task is running, so at most one is
+    keepalive_running: Arc<AtomicBool>,
 }
 
 impl ClientCore {
@@ -69,6 +163,7 @@ impl ClientCore {
     /// # Arguments
     ///
     /// * `client_name` - A unique identifier for this client
+    /// * `output_dir` - Directory to save received carrier images in
     ///
     /// # Returns
     ///
@@ -77,20 +172,84 @@ impl ClientCore {
     /// # Examples
     ///
     /// ```rust,ignore
-    /// let core = ClientCore::new("Client1".to_string());
+    /// let core = ClientCore::new("Client1".to_string(), "user-data/outputs");
+    /// ```
+    pub fn new(client_name: String, output_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            client_name,
+            output_dir: output_dir.into(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            metadata: None,
+            idle_connections: Arc::new(Mutex::new(HashMap::new())),
+            keepalive_interval: Duration::from_secs(KEEPALIVE_INTERVAL_SECS),
+            keepalive_running: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Sets the largest server response (in bytes) this client accepts.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_message_size` - Maximum frame size; larger responses are rejected
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let core = ClientCore::new("Client1".to_string(), "user-data/outputs")
+    ///     .with_max_message_size(config.client.max_message_size);
     /// ```
-    pub fn new(client_name: String) -> Self {
-        Self { client_name }
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Sets text for the server to embed alongside every secret image this client
+    /// sends, readable from the carrier with [`steganography::extract_metadata`].
+    /// Tasks routed via the leader go without it.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - Text to embed (at most 65535 bytes), or `None` for none
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let core = ClientCore::new("Client1".to_string(), "user-data/outputs")
+    ///     .with_metadata(Some("owner: alice".to_string()));
+    /// ```
+    pub fn with_metadata(mut self, metadata: Option<String>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Sets how often idle connections are pinged. A connection whose server
+    /// doesn't answer within the same interval is closed instead of reused.
+    ///
+    /// # Arguments
+    ///
+    /// * `interval` - Time between pings (default 5s)
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let core = ClientCore::new("Client1".to_string(), "user-data/outputs")
+    ///     .with_keepalive_interval(Duration::from_secs(10));
+    /// ```
+    pub fn with_keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = interval;
+        self
     }
 
     /// Sends a secret image to a server for encryption and receives the carrier image result.
     ///
     /// This method performs the complete image processing workflow:
-    /// 1. Connects to the assigned server address
+    /// 1. Connects to the assigned server address, or reuses an idle connection to it
     /// 2. Sends a `TaskRequest` containing the secret image data
     /// 3. Waits for and receives a `TaskResponse` with the carrier image (containing the embedded secret)
-    /// 4. Saves the carrier image to the local filesystem
-    /// 5. Verifies the encryption by extracting and validating the embedded secret image
+    /// 4. Verifies the encryption by extracting the embedded secret image and checking it
+    ///    is byte-for-byte the one that was sent (by SHA-256)
+    /// 5. Saves the carrier image to `{output_dir}/{client_name}_{request_id}.png`,
+    ///    creating the directory if missing
     ///
     /// # Arguments
     ///
@@ -101,7 +260,8 @@ impl ClientCore {
     ///
     /// # Returns
     ///
-    /// * `Ok(())` - If the secret image was successfully sent, embedded, received, saved, and verified
+    /// * `Ok(CarrierImage)` - The carrier image and the path it was saved to, once the
+    ///   secret image was successfully sent, embedded, received, verified, and saved
     /// * `Err(anyhow::Error)` - If any step fails (connection, transmission, encryption, or verification)
     ///
     /// # Errors
@@ -110,71 +270,455 @@ impl ClientCore {
     /// * Connection to the server fails
     /// * Message transmission fails
     /// * The server returns an error response
+    /// * The server rejects the task because it is at capacity (`TaskRejected`)
+    /// * The leader refuses the task because this client is over its rate limit
+    ///   ([`RateLimited`])
     /// * Writing the carrier image to disk fails
-    /// * The carrier image verification fails
+    /// * The carrier image verification fails, or the embedded secret differs from
+    ///   `secret_image_data`
     ///
     /// # Examples
     ///
     /// ```rust,ignore
-    /// let core = ClientCore::new("Client1".to_string());
+    /// let core = ClientCore::new("Client1".to_string(), "user-data/outputs");
     /// let secret_image = std::fs::read("secret.jpg")?;
     ///
-    /// core.send_and_receive_encrypted_image(
+    /// let carrier = core.send_and_receive_encrypted_image(
     ///     "127.0.0.1:5001",
     ///     42,
-    ///     secret_image,
+    ///     &secret_image,
     ///     1  // leader ID
     /// ).await?;
+    /// println!("Saved to {}", carrier.path.display()); // user-data/outputs/Client1_42.png
     /// ```
     pub async fn send_and_receive_encrypted_image(
         &self,
         assigned_address: &str,
         request_id: u64,
-        secret_image_data: Vec<u8>,
+        secret_image_data: &[u8],
         assigned_by_leader: u32,
-    ) -> Result<Vec<u8>> {
+    ) -> Result<CarrierImage> {
+        self.send_and_receive_encrypted_image_with_progress(
+            assigned_address,
+            request_id,
+            secret_image_data,
+            assigned_by_leader,
+            &|_| {},
+        )
+        .await
+    }
+
+    /// Sends a task like
+    /// [`send_and_receive_encrypted_image`](Self::send_and_receive_encrypted_image),
+    /// passing the percentage from each `TaskProgress` the server sends while
+    /// embedding to `on_progress`.
+    ///
+    /// Servers only send progress with `server.report_progress` set. Each message
+    /// restarts the response timeout, so a long embed that reports progress
+    /// doesn't time out.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let carrier = core
+    ///     .send_and_receive_encrypted_image_with_progress(address, 42, &secret, 1, &|percent| {
+    ///         println!("{}% embedded", percent);
+    ///     })
+    ///     .await?;
+    /// ```
+    pub async fn send_and_receive_encrypted_image_with_progress(
+        &self,
+        assigned_address: &str,
+        request_id: u64,
+        secret_image_data: &[u8],
+        assigned_by_leader: u32,
+        on_progress: &ProgressCallback<'_>,
+    ) -> Result<CarrierImage> {
         info!(
             "📤 {} Sending task #{} to server at {}",
             self.client_name, request_id, assigned_address
         );
 
-        // Connect to the assigned server
-        let stream = TcpStream::connect(assigned_address).await?;
-        let mut conn = Connection::new(stream);
+        // Remember what we sent, to verify the secret embedded in the result
+        let expected_digest = Sha256::digest(secret_image_data).into();
 
-        // Construct and send the task request
+        // Construct the task request; the image is sent from the caller's buffer
         let task_request = Message::TaskRequest {
             client_name: self.client_name.clone(),
             request_id,
-            secret_image_data,
+            secret_image_data: Vec::new(),
             assigned_by_leader,
+            metadata: self.metadata.clone(),
+        };
+
+        self.exchange(
+            assigned_address,
+            task_request,
+            secret_image_data,
+            expected_digest,
+            on_progress,
+        )
+        .await
+    }
+
+    /// Sends a secret image to the leader, which forwards it to the least-loaded server.
+    ///
+    /// Used in leader-routed mode: the client only ever talks to the leader, which
+    /// relays the assigned server's `TaskResponse` (or `TaskRejected`) back. The
+    /// response is verified and acknowledged exactly as in
+    /// [`send_and_receive_encrypted_image`](Self::send_and_receive_encrypted_image).
+    ///
+    /// # Arguments
+    ///
+    /// * `leader_address` - Network address of the current leader
+    /// * `request_id` - Unique identifier for this request
+    /// * `secret_image_data` - Raw bytes of the secret image to hide
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(CarrierImage)` - The verified carrier image and the path it was saved to
+    /// * `Err(anyhow::Error)` - Same failure cases as `send_and_receive_encrypted_image`,
+    ///   plus a rejection if the server is no longer the leader
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let carrier = core
+    ///     .send_via_leader("127.0.0.1:5001", 42, &secret_image)
+    ///     .await?;
+    /// ```
+    pub async fn send_via_leader(
+        &self,
+        leader_address: &str,
+        request_id: u64,
+        secret_image_data: &[u8],
+    ) -> Result<CarrierImage> {
+        info!(
+            "📤 {} Sending task #{} to leader at {} for routing",
+            self.client_name, request_id, leader_address
+        );
+
+        let expected_digest = Sha256::digest(secret_image_data).into();
+
+        let task_request = Message::RoutedTaskRequest {
+            client_name: self.client_name.clone(),
+            request_id,
+            secret_image_data: Vec::new(),
+        };
+
+        // Progress isn't relayed through the leader
+        self.exchange(
+            leader_address,
+            task_request,
+            secret_image_data,
+            expected_digest,
+            &|_| {},
+        )
+        .await
+    }
+
+    /// Extracts the secret image embedded in a carrier image.
+    ///
+    /// The inverse of the server-side encryption, done locally: no server is
+    /// contacted. If the carrier has an
+    /// [`AccessPolicy`](steganography::AccessPolicy), this client's name must be
+    /// on it and a view must remain; the view is then used up in
+    /// [`DecryptedImage::updated_carrier`], which should replace the original.
+    ///
+    /// # Arguments
+    ///
+    /// * `carrier_bytes` - A carrier image returned by a `TaskResponse`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DecryptedImage)` - The secret image bytes, plus the updated carrier
+    ///   and remaining views for policy-protected carriers
+    /// * `Err(anyhow::Error)` - If the carrier can't be decoded, holds no valid
+    ///   payload, or its policy refuses this client
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`SteganographyError`] (wrapped in `anyhow`) if the image has no
+    /// embedded payload, was corrupted, or is key-protected, or if its access policy
+    /// doesn't allow this client (`AccessDenied`) or has no views left (`NoViewsLeft`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let carrier = std::fs::read("carrier.png")?;
+    /// let decrypted = core.decrypt_carrier_image(&carrier)?;
+    /// std::fs::write("secret.png", &decrypted.secret)?;
+    /// if let Some(updated) = decrypted.updated_carrier {
+    ///     std::fs::write("carrier.png", updated)?;
+    /// }
+    /// ```
+    pub fn decrypt_carrier_image(&self, carrier_bytes: &[u8]) -> Result<DecryptedImage> {
+        let decrypted = match steganography::extract_image_bytes(carrier_bytes) {
+            Ok(secret) => DecryptedImage {
+                secret,
+                remaining_views: None,
+                updated_carrier: None,
+            },
+            Err(e)
+                if e.downcast_ref::<SteganographyError>()
+                    == Some(&SteganographyError::PolicyProtected) =>
+            {
+                let view = steganography::view_image_bytes(carrier_bytes, &self.client_name)
+                    .inspect_err(|e| {
+                        warn!("🔒 {} Refused secret image: {}", self.client_name, e)
+                    })?;
+                info!(
+                    "🔑 {} Viewed protected secret image ({} views left)",
+                    self.client_name, view.policy.remaining_views
+                );
+                DecryptedImage {
+                    secret: view.secret,
+                    remaining_views: Some(view.policy.remaining_views),
+                    updated_carrier: Some(view.carrier),
+                }
+            }
+            Err(e) => return Err(e),
+        };
+
+        info!(
+            "🔓 {} Extracted {} byte secret image from {} byte carrier",
+            self.client_name,
+            decrypted.secret.len(),
+            carrier_bytes.len()
+        );
+        Ok(decrypted)
+    }
+
+    /// Extracts the secret image from the carrier image file at `path`.
+    ///
+    /// Like [`decrypt_carrier_image`](Self::decrypt_carrier_image), but the carrier
+    /// file is the source of truth: for a policy-protected carrier the updated
+    /// carrier (one view fewer) replaces the file before the secret is returned, so
+    /// once its views are used up the file can no longer be decrypted.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A carrier image file, e.g. one saved by
+    ///   [`send_and_receive_encrypted_image`](Self::send_and_receive_encrypted_image)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DecryptedImage)` - As for `decrypt_carrier_image`
+    /// * `Err(anyhow::Error)` - Same failure cases as `decrypt_carrier_image`, plus
+    ///   failing to read the file or write the updated carrier back
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let decrypted = core.decrypt_carrier_file("user-data/outputs/Client1_42.png")?;
+    /// println!("{:?} views left", decrypted.remaining_views);
+    /// ```
+    pub fn decrypt_carrier_file(&self, path: impl AsRef<Path>) -> Result<DecryptedImage> {
+        let path = path.as_ref();
+        let carrier = std::fs::read(path)?;
+        let decrypted = self.decrypt_carrier_image(&carrier)?;
+
+        if let Some(updated) = &decrypted.updated_carrier {
+            // Write then rename, so a failed write can't leave a truncated carrier
+            let mut temp = path.as_os_str().to_owned();
+            temp.push(".tmp");
+            std::fs::write(&temp, updated)?;
+            std::fs::rename(&temp, path)?;
+        }
+
+        Ok(decrypted)
+    }
+
+    /// Sends a task request to `address`, then verifies and acknowledges the response.
+    ///
+    /// `task_request` is sent with `secret_image_data` as its image, written straight
+    /// from the caller's buffer so retries don't copy it. The secret extracted from
+    /// the returned carrier must hash to `expected_digest` (the SHA-256 of the
+    /// secret that was sent).
+    ///
+    /// An idle pooled connection to `address` is used if there is one. The server
+    /// may have closed it since, so if it fails (other than by timing out) the
+    /// request is sent once more on a new connection. Once the server has answered,
+    /// the connection goes back to the pool.
+    async fn exchange(
+        &self,
+        address: &str,
+        task_request: Message,
+        secret_image_data: &[u8],
+        expected_digest: [u8; 32],
+        on_progress: &ProgressCallback<'_>,
+    ) -> Result<CarrierImage> {
+        let (mut conn, reused) = match self.take_idle_connection(address) {
+            Some(conn) => (conn, true),
+            None => (self.connect(address).await?, false),
         };
 
-        conn.write_message(&task_request).await?;
+        let sent = Self::send_task(&mut conn, &task_request, secret_image_data, on_progress).await;
+        let response = match sent {
+            Err(e) if reused && !is_read_timeout(&e) => {
+                info!(
+                    "🔌 {} Idle connection to {} is gone ({}), reconnecting",
+                    self.client_name, address, e
+                );
+                conn = self.connect(address).await?;
+                Self::send_task(&mut conn, &task_request, secret_image_data, on_progress).await?
+            }
+            result => result?,
+        };
+
+        let reusable = matches!(
+            response,
+            Message::TaskResponse { .. }
+                | Message::TaskRejected { .. }
+                | Message::RateLimited { .. }
+        );
+        let result = self
+            .handle_response(&mut conn, address, response, expected_digest)
+            .await;
+        if reusable {
+            self.return_idle_connection(address, conn);
+        }
+        result
+    }
+
+    /// Opens a new connection to `address` (a `host:port` or Unix socket path).
+    async fn connect(&self, address: &str) -> Result<Connection<Stream>> {
+        let stream = Stream::connect(address).await?;
+        let mut conn = Connection::with_timeouts(
+            stream,
+            Duration::from_secs(RESPONSE_TIMEOUT_SECS),
+            Duration::from_secs(REQUEST_WRITE_TIMEOUT_SECS),
+        )
+        .with_max_message_size(self.max_message_size);
+        conn.handshake().await?;
+        Ok(conn)
+    }
+
+    /// Takes an idle connection to `address` out of the pool, if there is one.
+    fn take_idle_connection(&self, address: &str) -> Option<Connection<Stream>> {
+        self.idle_connections
+            .lock()
+            .unwrap()
+            .get_mut(address)
+            .and_then(Vec::pop)
+    }
+
+    /// Puts a connection to `address` back in the pool, unless it's full, and
+    /// starts the keepalive task if it isn't running.
+    fn return_idle_connection(&self, address: &str, conn: Connection<Stream>) {
+        {
+            let mut idle_connections = self.idle_connections.lock().unwrap();
+            let idle = idle_connections.entry(address.to_string()).or_default();
+            if idle.len() >= MAX_IDLE_CONNECTIONS_PER_SERVER {
+                return;
+            }
+            idle.push(conn);
+        }
+
+        if !self.keepalive_running.swap(true, Ordering::SeqCst) {
+            tokio::spawn(Self::keep_idle_connections_alive(
+                Arc::downgrade(&self.idle_connections),
+                self.keepalive_running.clone(),
+                self.keepalive_interval,
+            ));
+        }
+    }
+
+    /// Every `interval`, pings all connections in `pool` at once and puts back the
+    /// ones answered within `interval`; the others are closed.
+    ///
+    /// Runs until the pool is empty or the `ClientCore` owning it is dropped. A
+    /// request finding the pool empty mid-round opens a new connection.
+    async fn keep_idle_connections_alive(
+        pool: Weak<IdlePool>,
+        running: Arc<AtomicBool>,
+        interval: Duration,
+    ) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(pool) = pool.upgrade() else {
+                return;
+            };
 
-        // Wait for and process the response
-        match conn.read_message().await? {
-            Some(Message::TaskResponse {
+            let idle: Vec<_> = pool
+                .lock()
+                .unwrap()
+                .iter_mut()
+                .flat_map(|(address, idle)| {
+                    idle.drain(..)
+                        .map(|conn| (address.clone(), conn))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            if idle.is_empty() {
+                running.store(false, Ordering::SeqCst);
+                // A connection returned since draining found the flag still set;
+                // carry on for it unless a new task has already taken over
+                let empty = pool.lock().unwrap().values().all(Vec::is_empty);
+                if empty || running.swap(true, Ordering::SeqCst) {
+                    return;
+                }
+                continue;
+            }
+
+            let mut pings = JoinSet::new();
+            for (address, mut conn) in idle {
+                pings.spawn(async move {
+                    let answered = conn.ping(interval).await;
+                    (address, conn, answered)
+                });
+            }
+            while let Some(Ok((address, conn, answered))) = pings.join_next().await {
+                match answered {
+                    Ok(_) => {
+                        let mut idle_connections = pool.lock().unwrap();
+                        let idle = idle_connections.entry(address).or_default();
+                        if idle.len() < MAX_IDLE_CONNECTIONS_PER_SERVER {
+                            idle.push(conn);
+                        }
+                    }
+                    Err(e) => warn!("🔌 Dropped idle connection to {}: {}", address, e),
+                }
+            }
+        }
+    }
+
+    /// Writes `task_request`, with `secret_image_data` as its image, on `conn` and
+    /// reads the server's reply, passing any `TaskProgress` ahead of it to
+    /// `on_progress`.
+    async fn send_task(
+        conn: &mut Connection<Stream>,
+        task_request: &Message,
+        secret_image_data: &[u8],
+        on_progress: &ProgressCallback<'_>,
+    ) -> Result<Message> {
+        conn.write_message_with_payload(task_request, secret_image_data)
+            .await?;
+        loop {
+            match conn.read_message().await? {
+                Some(Message::TaskProgress { percent, .. }) => on_progress(percent),
+                Some(response) => return Ok(response),
+                None => return Err(anyhow::anyhow!("Connection closed by server")),
+            }
+        }
+    }
+
+    /// Verifies and acknowledges the server's reply to a task request.
+    async fn handle_response(
+        &self,
+        conn: &mut Connection<Stream>,
+        address: &str,
+        response: Message,
+        expected_digest: [u8; 32],
+    ) -> Result<CarrierImage> {
+        match response {
+            Message::TaskResponse {
                 request_id: response_id,
                 encrypted_image_data,
                 success,
                 error_message,
-            }) => {
+            } => {
                 if success {
-                    // Save the encrypted carrier image to disk
-                    // let output_path = format!("test_images/encrypted_image.jpg");
-                    // if let Err(e) = std::fs::write(&output_path, &encrypted_image_data) {
-                    //     error!(
-                    //         "⚠️  {} Failed to save carrier image to '{}': {}",
-                    //         self.client_name, output_path, e
-                    //     );
-                    // } else {
-                    //     info!(
-                    //         "💾 {} Saved carrier image to: {}",
-                    //         self.client_name, output_path
-                    //     );
-                    // }
-
                     // Verify the encryption by extracting the embedded secret image
                     info!(
                         "🔍 {} Verifying encryption for task #{} (carrier image size: {} bytes)",
@@ -183,19 +727,32 @@ impl ClientCore {
                         encrypted_image_data.len()
                     );
 
-                    match steganography::extract_image_bytes(&encrypted_image_data) {
-                        Ok(extracted_image) => {
+                    match steganography::extract_image_with_info(&encrypted_image_data) {
+                        Ok((image_info, extracted_image)) => {
                             info!(
-                                "✅ {} Successfully extracted embedded image for task #{} (size: {} bytes)",
-                                self.client_name, response_id, extracted_image.len()
+                                "✅ {} Successfully extracted embedded image for task #{} (size: {} bytes, {} {}x{})",
+                                self.client_name,
+                                response_id,
+                                extracted_image.len(),
+                                image_info.format.mime_type(),
+                                image_info.width,
+                                image_info.height
                             );
 
-                            // Optional: Verify the extracted image matches the original
-                            // Note: We don't have access to the original secret_image_data here
-                            // In a real application, you might want to:
-                            // 1. Save the carrier image to disk
-                            // 2. Compare extracted image with original (if needed)
-                            // 3. Log verification details
+                            // The embedded secret must be the one we sent, not a corrupted
+                            // or mixed-up one (the payload CRC only covers what was embedded)
+                            let extracted_digest: [u8; 32] =
+                                Sha256::digest(&extracted_image).into();
+                            if extracted_digest != expected_digest {
+                                error!(
+                                    "❌ {} Embedded image for task #{} does not match the secret we sent",
+                                    self.client_name, response_id
+                                );
+                                return Err(CloudP2PError::SecretMismatch {
+                                    request_id: response_id,
+                                }
+                                .into());
+                            }
 
                             info!(
                                 "✅ {} Encryption VERIFIED for task #{}",
@@ -203,14 +760,40 @@ impl ClientCore {
                             );
                         }
                         Err(e) => {
+                            // A checksum mismatch means the carrier was damaged after the
+                            // server embedded it, not that extraction itself is broken
+                            if let Some(SteganographyError::ChecksumMismatch { .. }) =
+                                e.downcast_ref::<SteganographyError>()
+                            {
+                                error!(
+                                    "❌ {} Server sent a corrupt carrier image for task #{}: {}",
+                                    self.client_name, response_id, e
+                                );
+                                return Err(CloudP2PError::ChecksumMismatch {
+                                    request_id: response_id,
+                                    reason: e.to_string(),
+                                }
+                                .into());
+                            }
+
                             error!(
                                 "❌ {} Failed to extract embedded image from task #{}: {}",
                                 self.client_name, response_id, e
                             );
-                            return Err(anyhow::anyhow!("Failed to extract embedded image: {}", e));
+                            return Err(CloudP2PError::EncryptionFailed {
+                                request_id: response_id,
+                                reason: format!("failed to extract embedded image: {}", e),
+                            }
+                            .into());
                         }
                     }
 
+                    // Save before acknowledging, so a failed write leaves the task
+                    // in the server's history rather than losing the result
+                    let path = self
+                        .save_carrier(response_id, &encrypted_image_data)
+                        .await?;
+
                     // CRITICAL: Send acknowledgment to server that we received the response
                     // This allows the server to safely remove the task from history
                     let ack_message = Message::TaskAck {
@@ -229,16 +812,454 @@ impl ClientCore {
                         info!("📨 {} Sent ACK for task #{}", self.client_name, response_id);
                     }
 
-                    Ok(encrypted_image_data)
+                    Ok(CarrierImage {
+                        data: encrypted_image_data,
+                        path,
+                    })
                 } else {
                     // Server reported task failure
-                    Err(anyhow::anyhow!(
-                        "Task failed on server: {}",
-                        error_message.unwrap_or_else(|| "Unknown error".to_string())
-                    ))
+                    Err(CloudP2PError::EncryptionFailed {
+                        request_id: response_id,
+                        reason: error_message.unwrap_or_else(|| "Unknown error".to_string()),
+                    }
+                    .into())
                 }
             }
-            _ => Err(anyhow::anyhow!("Unexpected response or connection closed")),
+            Message::TaskRejected {
+                request_id: rejected_id,
+                reason,
+            } => {
+                warn!(
+                    "🚫 {} Task #{} rejected by server at {}: {}",
+                    self.client_name, rejected_id, address, reason
+                );
+                Err(CloudP2PError::TaskRejected {
+                    request_id: rejected_id,
+                    reason,
+                }
+                .into())
+            }
+            Message::RateLimited {
+                request_id,
+                retry_after_ms,
+            } => {
+                warn!(
+                    "🚦 {} Task #{} rate limited by leader at {}, retry in {}ms",
+                    self.client_name, request_id, address, retry_after_ms
+                );
+                Err(RateLimited {
+                    retry_after: Duration::from_millis(retry_after_ms),
+                }
+                .into())
+            }
+            _ => Err(CloudP2PError::UnexpectedResponse.into()),
         }
     }
+
+    /// Writes a carrier image to `{output_dir}/{client_name}_{request_id}.png`,
+    /// creating the output directory if it doesn't exist.
+    async fn save_carrier(&self, request_id: u64, carrier: &[u8]) -> Result<PathBuf> {
+        let path = self
+            .output_dir
+            .join(format!("{}_{}.png", self.client_name, request_id));
+
+        let written = async {
+            tokio::fs::create_dir_all(&self.output_dir).await?;
+            tokio::fs::write(&path, carrier).await
+        };
+        if let Err(e) = written.await {
+            error!(
+                "⚠️  {} Failed to save carrier image to '{}': {}",
+                self.client_name,
+                path.display(),
+                e
+            );
+            return Err(anyhow::anyhow!(
+                "Failed to save carrier image to '{}': {}",
+                path.display(),
+                e
+            ));
+        }
+
+        info!(
+            "💾 {} Saved carrier image to: {}",
+            self.client_name,
+            path.display()
+        );
+        Ok(path)
+    }
+}
+
+/// Whether `error` is a read timeout, i.e. the server is slow rather than gone.
+fn is_read_timeout(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<ConnectionError>(),
+        Some(ConnectionError::ReadTimeout(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Encode a blank `width`x`height` PNG.
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(width, height)
+            .write_to(&mut bytes, image::ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    /// Serve one task, replying with `secret` embedded in a carrier.
+    async fn server_embedding(secret: Vec<u8>) -> String {
+        server_embedding_with_progress(secret, &[]).await
+    }
+
+    /// Like [`server_embedding`], sending a `TaskProgress` for each of `percents`
+    /// before the reply.
+    async fn server_embedding_with_progress(secret: Vec<u8>, percents: &[u8]) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let percents = percents.to_vec();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut conn = Connection::new(socket);
+            conn.accept_handshake().await.unwrap();
+            if let Ok(Some(Message::TaskRequest {
+                request_id,
+                metadata,
+                ..
+            })) = conn.read_message().await
+            {
+                for percent in percents {
+                    let progress = Message::TaskProgress {
+                        request_id,
+                        percent,
+                    };
+                    conn.write_message(&progress).await.unwrap();
+                }
+                let carrier = match metadata {
+                    Some(text) => {
+                        steganography::embed_image_bytes_with_metadata(&png(64, 64), &secret, &text)
+                    }
+                    None => steganography::embed_image_bytes(&png(64, 64), &secret),
+                }
+                .unwrap();
+                let reply = Message::TaskResponse {
+                    request_id,
+                    encrypted_image_data: carrier,
+                    success: true,
+                    error_message: None,
+                };
+                conn.write_message(&reply).await.unwrap();
+                let _ = conn.read_message().await;
+            }
+        });
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_send_verifies_embedded_secret_against_original() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = ClientCore::new("Client1".to_string(), dir.path());
+        let secret = png(4, 4);
+
+        let address = server_embedding(secret.clone()).await;
+        let carrier = core
+            .send_and_receive_encrypted_image(&address, 1, &secret, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            core.decrypt_carrier_image(&carrier.data).unwrap().secret,
+            secret
+        );
+
+        // A carrier holding some other (valid) image is rejected
+        let address = server_embedding(png(5, 5)).await;
+        let error = core
+            .send_and_receive_encrypted_image(&address, 2, &secret, 1)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<CloudP2PError>(),
+            Some(&CloudP2PError::SecretMismatch { request_id: 2 })
+        );
+        assert!(!dir.path().join("Client1_2.png").exists());
+    }
+
+    #[tokio::test]
+    async fn test_progress_ahead_of_the_response_is_passed_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = ClientCore::new("Client1".to_string(), dir.path());
+        let secret = png(4, 4);
+
+        let address = server_embedding_with_progress(secret.clone(), &[30, 70, 100]).await;
+        let reports = std::sync::Mutex::new(Vec::new());
+        let carrier = core
+            .send_and_receive_encrypted_image_with_progress(&address, 1, &secret, 1, &|percent| {
+                reports.lock().unwrap().push(percent)
+            })
+            .await
+            .unwrap();
+        assert_eq!(reports.into_inner().unwrap(), vec![30, 70, 100]);
+        assert_eq!(
+            core.decrypt_carrier_image(&carrier.data).unwrap().secret,
+            secret
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_is_sent_with_the_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = ClientCore::new("Client1".to_string(), dir.path())
+            .with_metadata(Some("owner: Client1".to_string()));
+        let secret = png(4, 4);
+
+        // The test server embeds whatever metadata the request carried
+        let address = server_embedding(secret.clone()).await;
+        let carrier = core
+            .send_and_receive_encrypted_image(&address, 1, &secret, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            steganography::extract_metadata(&carrier.data).unwrap(),
+            Some("owner: Client1".to_string())
+        );
+    }
+
+    /// Serve tasks on every connection, replying with `secret` embedded in a carrier.
+    /// With `close_after_reply`, each connection is closed after one task.
+    ///
+    /// # Returns
+    /// The server's address and a counter of connections it accepted
+    async fn server_serving_tasks(
+        secret: Vec<u8>,
+        close_after_reply: bool,
+    ) -> (String, Arc<AtomicU32>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let connections = Arc::new(AtomicU32::new(0));
+
+        let counter = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let secret = secret.clone();
+                tokio::spawn(async move {
+                    let mut conn = Connection::new(socket);
+                    conn.accept_handshake().await.unwrap();
+                    while let Ok(Some(message)) = conn.read_message().await {
+                        let request_id = match message {
+                            Message::TaskRequest { request_id, .. } => request_id,
+                            Message::Ping { nonce } => {
+                                conn.write_message(&Message::Pong { nonce }).await.unwrap();
+                                continue;
+                            }
+                            _ => continue,
+                        };
+                        let carrier =
+                            steganography::embed_image_bytes(&png(64, 64), &secret).unwrap();
+                        let reply = Message::TaskResponse {
+                            request_id,
+                            encrypted_image_data: carrier,
+                            success: true,
+                            error_message: None,
+                        };
+                        conn.write_message(&reply).await.unwrap();
+                        if close_after_reply {
+                            // Wait for the ACK, then hang up
+                            let _ = conn.read_message().await;
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+
+        (address, connections)
+    }
+
+    #[tokio::test]
+    async fn test_requests_to_a_server_reuse_one_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = ClientCore::new("Client1".to_string(), dir.path());
+        let secret = png(4, 4);
+
+        let (address, connections) = server_serving_tasks(secret.clone(), false).await;
+        for request_id in 1..=3 {
+            core.send_and_receive_encrypted_image(&address, request_id, &secret, 1)
+                .await
+                .unwrap();
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_closed_idle_connection_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = ClientCore::new("Client1".to_string(), dir.path());
+        let secret = png(4, 4);
+
+        let (address, connections) = server_serving_tasks(secret.clone(), true).await;
+        for request_id in 1..=2 {
+            core.send_and_receive_encrypted_image(&address, request_id, &secret, 1)
+                .await
+                .unwrap();
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_drops_idle_connections_left_unanswered() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = ClientCore::new("Client1".to_string(), dir.path())
+            .with_keepalive_interval(Duration::from_millis(100));
+        let secret = png(4, 4);
+
+        // Serves one task, then hangs: reads on without ever answering a ping
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hung = listener.local_addr().unwrap().to_string();
+        let (closed_tx, closed_rx) = tokio::sync::oneshot::channel();
+        let carrier = steganography::embed_image_bytes(&png(64, 64), &secret).unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut conn = Connection::new(socket);
+            conn.accept_handshake().await.unwrap();
+            let Ok(Some(Message::TaskRequest { request_id, .. })) = conn.read_message().await
+            else {
+                panic!("expected a task request");
+            };
+            let reply = Message::TaskResponse {
+                request_id,
+                encrypted_image_data: carrier,
+                success: true,
+                error_message: None,
+            };
+            conn.write_message(&reply).await.unwrap();
+            while let Ok(Some(_)) = conn.read_message().await {}
+            let _ = closed_tx.send(());
+        });
+
+        let (live, connections) = server_serving_tasks(secret.clone(), false).await;
+        for (request_id, address) in [(1, &live), (2, &hung)] {
+            core.send_and_receive_encrypted_image(address, request_id, &secret, 1)
+                .await
+                .unwrap();
+        }
+
+        tokio::time::timeout(Duration::from_secs(2), closed_rx)
+            .await
+            .expect("unanswered connection was kept")
+            .unwrap();
+
+        // A few more rounds; the answered connection is still there to reuse
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        core.send_and_receive_encrypted_image(&live, 3, &secret, 1)
+            .await
+            .unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        assert!(core.idle_connections.lock().unwrap()[&hung].is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_saves_carrier_to_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = dir.path().join("outputs");
+        let core = ClientCore::new("Client1".to_string(), &output_dir);
+        let secret = png(4, 4);
+
+        let address = server_embedding(secret.clone()).await;
+        let carrier = core
+            .send_and_receive_encrypted_image(&address, 42, &secret, 1)
+            .await
+            .unwrap();
+
+        // The missing directory is created and the carrier written as-is
+        assert_eq!(carrier.path, output_dir.join("Client1_42.png"));
+        assert_eq!(std::fs::read(&carrier.path).unwrap(), carrier.data);
+    }
+
+    #[test]
+    fn test_decrypt_carrier_image_returns_embedded_secret() {
+        let core = ClientCore::new("Client1".to_string(), "unused");
+        let secret = png(4, 4);
+        let carrier = steganography::embed_image_bytes(&png(64, 64), &secret).unwrap();
+
+        let decrypted = core.decrypt_carrier_image(&carrier).unwrap();
+        assert_eq!(decrypted.secret, secret);
+        assert_eq!(decrypted.remaining_views, None);
+        assert!(decrypted.updated_carrier.is_none());
+
+        // A plain image has nothing to extract
+        let error = core.decrypt_carrier_image(&png(64, 64)).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::NoPayload)
+        );
+    }
+
+    #[test]
+    fn test_decrypt_carrier_image_enforces_access_policy() {
+        let secret = png(4, 4);
+        let policy = steganography::AccessPolicy {
+            allowed_users: vec!["Client1".to_string()],
+            remaining_views: 1,
+        };
+        let carrier =
+            steganography::embed_image_bytes_with_policy(&png(64, 64), &secret, &policy).unwrap();
+
+        let stranger = ClientCore::new("Client2".to_string(), "unused");
+        let error = stranger.decrypt_carrier_image(&carrier).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<SteganographyError>(),
+            Some(SteganographyError::AccessDenied { .. })
+        ));
+
+        let owner = ClientCore::new("Client1".to_string(), "unused");
+        let decrypted = owner.decrypt_carrier_image(&carrier).unwrap();
+        assert_eq!(decrypted.secret, secret);
+        assert_eq!(decrypted.remaining_views, Some(0));
+
+        // The used-up view travels with the updated carrier
+        let error = owner
+            .decrypt_carrier_image(&decrypted.updated_carrier.unwrap())
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::NoViewsLeft)
+        );
+    }
+
+    #[test]
+    fn test_decrypt_carrier_file_uses_up_views() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("carrier.png");
+        let secret = png(4, 4);
+        let policy = steganography::AccessPolicy {
+            allowed_users: vec!["Client1".to_string()],
+            remaining_views: 2,
+        };
+        let carrier =
+            steganography::embed_image_bytes_with_policy(&png(64, 64), &secret, &policy).unwrap();
+        std::fs::write(&path, carrier).unwrap();
+
+        let core = ClientCore::new("Client1".to_string(), dir.path());
+        for remaining in [1, 0] {
+            let decrypted = core.decrypt_carrier_file(&path).unwrap();
+            assert_eq!(decrypted.secret, secret);
+            assert_eq!(decrypted.remaining_views, Some(remaining));
+        }
+
+        let error = core.decrypt_carrier_file(&path).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::NoViewsLeft)
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
 }
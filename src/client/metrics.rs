@@ -8,14 +8,15 @@ use std::time::{Duration, Instant};
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RequestMetric {
     pub request_id: u64,
-    pub start_time: u64, // milliseconds since epoch
+    pub start_time: u64, // milliseconds since epoch, when the request was sent
     pub latency_ms: u64,
     pub success: bool,
     pub failure_reason: Option<String>,
     pub assigned_server_id: Option<u32>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AggregatedStats {
     pub total_requests: usize,
     pub successful_requests: usize,
@@ -30,11 +31,183 @@ pub struct AggregatedStats {
     pub latency_p95_ms: u64,
     pub latency_p99_ms: u64,
 
-    // Load balancing - requests per server
+    // Raw latencies of successful requests, if retained, so reports can be merged exactly
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub successful_latencies_ms: Vec<u64>,
+
+    // Load balancing - requests per server, and latency of each server's successful requests
     pub server_distribution: HashMap<u32, usize>,
+    pub per_server_latency: HashMap<u32, LatencyStats>,
 
     // Failure reasons breakdown
     pub failure_reasons: HashMap<String, usize>,
+
+    // Throughput over the span from the first request sent to the last one finished
+    pub duration_secs: f64,
+    pub requests_per_sec: f64,
+    pub successful_requests_per_sec: f64,
+
+    // Requests finished in each second of the run, starting at the first request sent
+    pub timeline: Vec<ThroughputBucket>,
+}
+
+impl AggregatedStats {
+    /// Combine the reports of several clients that ran at the same time into one.
+    ///
+    /// Counts, server distributions and failure reasons are summed. Latency percentiles
+    /// are recomputed from the pooled latencies if every report retained them (see
+    /// [`ClientMetrics::with_raw_latencies`]); otherwise each is the worst of the
+    /// reports' percentiles, an upper bound. Throughput assumes the clients started
+    /// together: the duration is the longest run and timelines are summed per second.
+    pub fn merge(reports: &[AggregatedStats]) -> AggregatedStats {
+        let mut merged = AggregatedStats::default();
+
+        for report in reports {
+            merged.total_requests += report.total_requests;
+            merged.successful_requests += report.successful_requests;
+            merged.failed_requests += report.failed_requests;
+
+            for (server_id, count) in &report.server_distribution {
+                *merged.server_distribution.entry(*server_id).or_insert(0) += count;
+            }
+            for (reason, count) in &report.failure_reasons {
+                *merged.failure_reasons.entry(reason.clone()).or_insert(0) += count;
+            }
+
+            merged.duration_secs = merged.duration_secs.max(report.duration_secs);
+            for bucket in &report.timeline {
+                while merged.timeline.len() <= bucket.second as usize {
+                    let second = merged.timeline.len() as u64;
+                    merged.timeline.push(ThroughputBucket { second, successful: 0, failed: 0 });
+                }
+                let merged_bucket = &mut merged.timeline[bucket.second as usize];
+                merged_bucket.successful += bucket.successful;
+                merged_bucket.failed += bucket.failed;
+            }
+        }
+
+        if merged.total_requests > 0 {
+            merged.failure_rate =
+                (merged.failed_requests as f64 / merged.total_requests as f64) * 100.0;
+        }
+        if merged.duration_secs > 0.0 {
+            merged.requests_per_sec = merged.total_requests as f64 / merged.duration_secs;
+            merged.successful_requests_per_sec =
+                merged.successful_requests as f64 / merged.duration_secs;
+        }
+
+        // Pool the raw latencies if every report kept them
+        let retained: usize = reports.iter().map(|r| r.successful_latencies_ms.len()).sum();
+        let latency = if retained == merged.successful_requests {
+            merged.successful_latencies_ms = reports
+                .iter()
+                .flat_map(|r| r.successful_latencies_ms.iter().copied())
+                .collect();
+            LatencyStats::from_latencies(merged.successful_latencies_ms.clone())
+        } else {
+            let latencies: Vec<LatencyStats> = reports.iter().map(|r| r.latency()).collect();
+            LatencyStats::merge(&latencies)
+        };
+        merged.latency_min_ms = latency.min_ms;
+        merged.latency_max_ms = latency.max_ms;
+        merged.latency_avg_ms = latency.avg_ms;
+        merged.latency_p50_ms = latency.p50_ms;
+        merged.latency_p95_ms = latency.p95_ms;
+        merged.latency_p99_ms = latency.p99_ms;
+
+        let mut per_server: HashMap<u32, Vec<LatencyStats>> = HashMap::new();
+        for report in reports {
+            for (server_id, latency) in &report.per_server_latency {
+                per_server.entry(*server_id).or_default().push(latency.clone());
+            }
+        }
+        merged.per_server_latency = per_server
+            .into_iter()
+            .map(|(server_id, latencies)| (server_id, LatencyStats::merge(&latencies)))
+            .collect();
+
+        merged
+    }
+
+    /// Read the `aggregated_stats` of a report written by [`ClientMetrics::export_to_json`].
+    pub fn from_report_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let report: serde_json::Value = serde_json::from_reader(File::open(path)?)?;
+        Ok(serde_json::from_value(report["aggregated_stats"].clone())?)
+    }
+
+    /// The global latency figures as a [`LatencyStats`].
+    fn latency(&self) -> LatencyStats {
+        LatencyStats {
+            count: self.successful_requests,
+            min_ms: self.latency_min_ms,
+            max_ms: self.latency_max_ms,
+            avg_ms: self.latency_avg_ms,
+            p50_ms: self.latency_p50_ms,
+            p95_ms: self.latency_p95_ms,
+            p99_ms: self.latency_p99_ms,
+        }
+    }
+}
+
+/// Latency statistics (milliseconds) over a set of successful requests.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub avg_ms: f64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+}
+
+impl LatencyStats {
+    /// Compute the statistics of `latencies` (in any order); all zero if empty.
+    fn from_latencies(mut latencies: Vec<u64>) -> Self {
+        if latencies.is_empty() {
+            return Self::default();
+        }
+        latencies.sort_unstable();
+
+        Self {
+            count: latencies.len(),
+            min_ms: *latencies.first().unwrap(),
+            max_ms: *latencies.last().unwrap(),
+            avg_ms: latencies.iter().sum::<u64>() as f64 / latencies.len() as f64,
+            p50_ms: percentile(&latencies, 50.0),
+            p95_ms: percentile(&latencies, 95.0),
+            p99_ms: percentile(&latencies, 99.0),
+        }
+    }
+
+    /// Combine the statistics of disjoint sets of requests. Percentiles can't be
+    /// pooled without the raw latencies, so each is the worst of the inputs'.
+    fn merge(stats: &[LatencyStats]) -> Self {
+        let stats: Vec<&LatencyStats> = stats.iter().filter(|s| s.count > 0).collect();
+        if stats.is_empty() {
+            return Self::default();
+        }
+
+        let count: usize = stats.iter().map(|s| s.count).sum();
+        Self {
+            count,
+            min_ms: stats.iter().map(|s| s.min_ms).min().unwrap(),
+            max_ms: stats.iter().map(|s| s.max_ms).max().unwrap(),
+            avg_ms: stats.iter().map(|s| s.avg_ms * s.count as f64).sum::<f64>() / count as f64,
+            p50_ms: stats.iter().map(|s| s.p50_ms).max().unwrap(),
+            p95_ms: stats.iter().map(|s| s.p95_ms).max().unwrap(),
+            p99_ms: stats.iter().map(|s| s.p99_ms).max().unwrap(),
+        }
+    }
+}
+
+/// Requests that finished during one second of the run.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ThroughputBucket {
+    /// Seconds since the first request was sent
+    pub second: u64,
+    pub successful: usize,
+    pub failed: usize,
 }
 
 #[derive(Debug)]
@@ -42,6 +215,7 @@ pub struct ClientMetrics {
     client_name: String,
     start_time: Instant,
     requests: Vec<RequestMetric>,
+    retain_latencies: bool,
 }
 
 impl ClientMetrics {
@@ -50,9 +224,17 @@ impl ClientMetrics {
             client_name,
             start_time: Instant::now(),
             requests: Vec::new(),
+            retain_latencies: false,
         }
     }
 
+    /// Include the raw latencies of successful requests in the aggregated stats, so
+    /// reports from several clients can be merged with exact percentiles.
+    pub fn with_raw_latencies(mut self, retain_latencies: bool) -> Self {
+        self.retain_latencies = retain_latencies;
+        self
+    }
+
     pub fn record_request(
         &mut self,
         request_id: u64,
@@ -61,15 +243,18 @@ impl ClientMetrics {
         failure_reason: Option<String>,
         assigned_server_id: Option<u32>,
     ) {
-        let start_time = std::time::SystemTime::now()
+        let now = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_millis() as u64;
 
+        // Recorded on completion, so the request was sent `latency` ago
+        let latency_ms = latency.as_millis() as u64;
+
         self.requests.push(RequestMetric {
             request_id,
-            start_time,
-            latency_ms: latency.as_millis() as u64,
+            start_time: now.saturating_sub(latency_ms),
+            latency_ms,
             success,
             failure_reason,
             assigned_server_id,
@@ -89,31 +274,38 @@ impl ClientMetrics {
         stats.failure_rate = (stats.failed_requests as f64 / stats.total_requests as f64) * 100.0;
 
         // Calculate latency statistics from successful requests
-        let mut successful_latencies: Vec<u64> = self.requests
+        let successful_latencies: Vec<u64> = self.requests
             .iter()
             .filter(|r| r.success)
             .map(|r| r.latency_ms)
             .collect();
 
-        if !successful_latencies.is_empty() {
-            successful_latencies.sort_unstable();
-
-            stats.latency_min_ms = *successful_latencies.first().unwrap();
-            stats.latency_max_ms = *successful_latencies.last().unwrap();
-            stats.latency_avg_ms = successful_latencies.iter().sum::<u64>() as f64
-                / successful_latencies.len() as f64;
-
-            stats.latency_p50_ms = percentile(&successful_latencies, 50.0);
-            stats.latency_p95_ms = percentile(&successful_latencies, 95.0);
-            stats.latency_p99_ms = percentile(&successful_latencies, 99.0);
+        if self.retain_latencies {
+            stats.successful_latencies_ms = successful_latencies.clone();
         }
 
-        // Calculate server distribution
+        let latency = LatencyStats::from_latencies(successful_latencies);
+        stats.latency_min_ms = latency.min_ms;
+        stats.latency_max_ms = latency.max_ms;
+        stats.latency_avg_ms = latency.avg_ms;
+        stats.latency_p50_ms = latency.p50_ms;
+        stats.latency_p95_ms = latency.p95_ms;
+        stats.latency_p99_ms = latency.p99_ms;
+
+        // Calculate server distribution and per-server latency
+        let mut server_latencies: HashMap<u32, Vec<u64>> = HashMap::new();
         for request in &self.requests {
             if let Some(server_id) = request.assigned_server_id {
                 *stats.server_distribution.entry(server_id).or_insert(0) += 1;
+                if request.success {
+                    server_latencies.entry(server_id).or_default().push(request.latency_ms);
+                }
             }
         }
+        stats.per_server_latency = server_latencies
+            .into_iter()
+            .map(|(server_id, latencies)| (server_id, LatencyStats::from_latencies(latencies)))
+            .collect();
 
         // Calculate failure reasons
         for request in self.requests.iter().filter(|r| !r.success) {
@@ -122,6 +314,30 @@ impl ClientMetrics {
             }
         }
 
+        // Calculate throughput and the per-second timeline
+        let first_start = self.requests.iter().map(|r| r.start_time).min().unwrap();
+        let last_end = self.requests.iter().map(|r| r.start_time + r.latency_ms).max().unwrap();
+
+        stats.duration_secs = (last_end - first_start) as f64 / 1000.0;
+        if stats.duration_secs > 0.0 {
+            stats.requests_per_sec = stats.total_requests as f64 / stats.duration_secs;
+            stats.successful_requests_per_sec =
+                stats.successful_requests as f64 / stats.duration_secs;
+        }
+
+        stats.timeline = (0..=(last_end - first_start) / 1000)
+            .map(|second| ThroughputBucket { second, successful: 0, failed: 0 })
+            .collect();
+        for request in &self.requests {
+            let second = (request.start_time + request.latency_ms - first_start) / 1000;
+            let bucket = &mut stats.timeline[second as usize];
+            if request.success {
+                bucket.successful += 1;
+            } else {
+                bucket.failed += 1;
+            }
+        }
+
         stats
     }
 
@@ -140,6 +356,129 @@ impl ClientMetrics {
 
         Ok(())
     }
+
+    /// Write one CSV row per recorded request, for analysis outside the aggregated stats.
+    pub fn export_to_csv<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(
+            file,
+            "request_id,start_time,latency_ms,success,failure_reason,assigned_server_id"
+        )?;
+
+        for request in &self.requests {
+            writeln!(
+                file,
+                "{},{},{},{},{},{}",
+                request.request_id,
+                request.start_time,
+                request.latency_ms,
+                request.success,
+                request.failure_reason.as_deref().map(csv_field).unwrap_or_default(),
+                request.assigned_server_id.map(|id| id.to_string()).unwrap_or_default()
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn export_to_prometheus<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(self.to_prometheus().as_bytes())?;
+
+        Ok(())
+    }
+
+    /// Render the aggregated stats in the Prometheus text exposition format.
+    fn to_prometheus(&self) -> String {
+        let stats = self.aggregate();
+        let client = escape_label_value(&self.client_name);
+        let mut out = String::new();
+
+        let counters = [
+            ("cloudp2p_client_requests_total", "Requests sent", stats.total_requests),
+            (
+                "cloudp2p_client_requests_successful_total",
+                "Requests that completed successfully",
+                stats.successful_requests,
+            ),
+            (
+                "cloudp2p_client_requests_failed_total",
+                "Requests that failed",
+                stats.failed_requests,
+            ),
+        ];
+        for (name, help, value) in counters {
+            out.push_str(&format!("# HELP {} {}\n# TYPE {} counter\n", name, help, name));
+            out.push_str(&format!("{}{{client=\"{}\"}} {}\n", name, client, value));
+        }
+
+        // Latency summary over successful requests, in seconds
+        let name = "cloudp2p_client_request_latency_seconds";
+        out.push_str(&format!(
+            "# HELP {} Latency of successful requests\n# TYPE {} summary\n",
+            name, name
+        ));
+        let quantiles = [
+            ("0.5", stats.latency_p50_ms),
+            ("0.95", stats.latency_p95_ms),
+            ("0.99", stats.latency_p99_ms),
+        ];
+        for (quantile, latency_ms) in quantiles {
+            out.push_str(&format!(
+                "{}{{client=\"{}\",quantile=\"{}\"}} {}\n",
+                name,
+                client,
+                quantile,
+                latency_ms as f64 / 1000.0
+            ));
+        }
+        let latency_sum_ms: u64 = self.requests
+            .iter()
+            .filter(|r| r.success)
+            .map(|r| r.latency_ms)
+            .sum();
+        out.push_str(&format!(
+            "{}_sum{{client=\"{}\"}} {}\n",
+            name,
+            client,
+            latency_sum_ms as f64 / 1000.0
+        ));
+        out.push_str(&format!(
+            "{}_count{{client=\"{}\"}} {}\n",
+            name, client, stats.successful_requests
+        ));
+
+        // Requests per assigned server, in server ID order
+        let name = "cloudp2p_client_server_requests_total";
+        out.push_str(&format!(
+            "# HELP {} Requests assigned to each server\n# TYPE {} counter\n",
+            name, name
+        ));
+        let mut servers: Vec<_> = stats.server_distribution.iter().collect();
+        servers.sort_unstable();
+        for (server_id, count) in servers {
+            out.push_str(&format!(
+                "{}{{client=\"{}\",server_id=\"{}\"}} {}\n",
+                name, client, server_id, count
+            ));
+        }
+
+        out
+    }
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Escape a Prometheus label value (backslash, double quote and newline).
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
 }
 
 fn percentile(sorted_data: &[u64], percentile: f64) -> u64 {
@@ -147,8 +486,9 @@ fn percentile(sorted_data: &[u64], percentile: f64) -> u64 {
         return 0;
     }
 
-    let index = (percentile / 100.0 * (sorted_data.len() - 1) as f64).round() as usize;
-    sorted_data[index.min(sorted_data.len() - 1)]
+    // Nearest-rank method: the smallest value with at least `percentile`% of data at or below it
+    let rank = (percentile / 100.0 * sorted_data.len() as f64).ceil() as usize;
+    sorted_data[rank.saturating_sub(1).min(sorted_data.len() - 1)]
 }
 
 #[cfg(test)]
@@ -181,4 +521,186 @@ mod tests {
         assert_eq!(stats.server_distribution.get(&1), Some(&2));
         assert_eq!(stats.server_distribution.get(&2), Some(&1));
     }
+
+    /// Aggregate one client's requests as `(latency_ms, success, server_id)`.
+    fn report(requests: &[(u64, bool, u32)], retain_latencies: bool) -> AggregatedStats {
+        let mut metrics = ClientMetrics::new("TestClient".to_string())
+            .with_raw_latencies(retain_latencies);
+        for (id, &(latency_ms, success, server_id)) in requests.iter().enumerate() {
+            let reason = (!success).then(|| "timeout".to_string());
+            let latency = Duration::from_millis(latency_ms);
+            metrics.record_request(id as u64, latency, success, reason, Some(server_id));
+        }
+        metrics.aggregate()
+    }
+
+    #[test]
+    fn test_merge_reports() {
+        let client_1 = [(100, true, 1), (200, true, 1), (300, false, 2)];
+        let client_2 = [(1000, true, 2), (400, true, 3), (500, true, 3), (50, false, 3)];
+
+        let merged = AggregatedStats::merge(&[report(&client_1, true), report(&client_2, true)]);
+
+        assert_eq!(merged.total_requests, 7);
+        assert_eq!(merged.successful_requests, 5);
+        assert_eq!(merged.failed_requests, 2);
+        assert!((merged.failure_rate - 200.0 / 7.0).abs() < 1e-9);
+        assert_eq!(merged.server_distribution, HashMap::from([(1, 2), (2, 2), (3, 3)]));
+        assert_eq!(merged.failure_reasons["timeout"], 2);
+
+        // Percentiles come from the pooled latencies 100, 200, 400, 500, 1000
+        assert_eq!((merged.latency_min_ms, merged.latency_max_ms), (100, 1000));
+        assert_eq!(merged.latency_avg_ms, 440.0);
+        assert_eq!(merged.latency_p50_ms, 400);
+        assert_eq!(merged.latency_p99_ms, 1000);
+        assert_eq!(merged.per_server_latency[&3].count, 2);
+
+        // Without raw latencies the percentiles are the worst of the two reports
+        let merged = AggregatedStats::merge(&[report(&client_1, false), report(&client_2, false)]);
+        assert!(merged.successful_latencies_ms.is_empty());
+        assert_eq!(merged.latency_avg_ms, 440.0);
+        assert_eq!(merged.latency_p50_ms, 500);
+    }
+
+    #[test]
+    fn test_merge_reads_exported_reports() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client.json");
+
+        let mut metrics = ClientMetrics::new("TestClient".to_string()).with_raw_latencies(true);
+        metrics.record_request(1, Duration::from_millis(100), true, None, Some(1));
+        metrics.export_to_json(&path).unwrap();
+
+        let stats = AggregatedStats::from_report_file(&path).unwrap();
+        assert_eq!(stats.successful_latencies_ms, vec![100]);
+
+        let merged = AggregatedStats::merge(&[stats.clone(), stats]);
+        assert_eq!(merged.total_requests, 2);
+        assert_eq!(merged.latency_p50_ms, 100);
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let mut metrics = ClientMetrics::new("TestClient".to_string());
+        let reason = Some("timeout, \"server 2\"".to_string());
+
+        metrics.record_request(1, Duration::from_millis(100), true, None, Some(1));
+        metrics.record_request(2, Duration::from_millis(250), false, reason, None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metrics.csv");
+        metrics.export_to_csv(&path).unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "request_id,start_time,latency_ms,success,failure_reason,assigned_server_id"
+        );
+
+        let first = metrics.requests[0].start_time;
+        assert_eq!(lines[1], format!("1,{},100,true,,1", first));
+        let second = metrics.requests[1].start_time;
+        assert_eq!(lines[2], format!("2,{},250,false,\"timeout, \"\"server 2\"\"\",", second));
+    }
+
+    #[test]
+    fn test_per_server_latency() {
+        let mut metrics = ClientMetrics::new("TestClient".to_string());
+
+        // Server 1 is fast, server 2 is slow; failures don't count towards latency
+        for (id, latency_ms) in [(1, 100), (2, 120), (3, 110)] {
+            metrics.record_request(id, Duration::from_millis(latency_ms), true, None, Some(1));
+        }
+        for (id, latency_ms) in [(4, 900), (5, 1100)] {
+            metrics.record_request(id, Duration::from_millis(latency_ms), true, None, Some(2));
+        }
+        metrics.record_request(6, Duration::from_millis(5000), false, None, Some(2));
+
+        let stats = metrics.aggregate();
+
+        let fast = &stats.per_server_latency[&1];
+        assert_eq!((fast.count, fast.min_ms, fast.max_ms, fast.p50_ms), (3, 100, 120, 110));
+        assert_eq!(fast.avg_ms, 110.0);
+
+        let slow = &stats.per_server_latency[&2];
+        assert_eq!((slow.count, slow.min_ms, slow.max_ms, slow.p50_ms), (2, 900, 1100, 900));
+        assert_eq!(slow.avg_ms, 1000.0);
+        assert_eq!(stats.server_distribution[&2], 3);
+
+        // The global figures still cover every successful request
+        assert_eq!((stats.latency_min_ms, stats.latency_max_ms), (100, 1100));
+    }
+
+    #[test]
+    fn test_throughput_and_timeline() {
+        let mut metrics = ClientMetrics::new("TestClient".to_string());
+        let metric = |request_id, start_time, latency_ms, success| RequestMetric {
+            request_id,
+            start_time,
+            latency_ms,
+            success,
+            failure_reason: None,
+            assigned_server_id: Some(1),
+        };
+
+        // Sent from t=10s, last one finished at t=14s
+        metrics.requests.push(metric(1, 10_000, 500, true));
+        metrics.requests.push(metric(2, 10_200, 600, false));
+        metrics.requests.push(metric(3, 11_000, 1_500, true));
+        metrics.requests.push(metric(4, 13_000, 1_000, true));
+
+        let stats = metrics.aggregate();
+
+        assert_eq!(stats.duration_secs, 4.0);
+        assert_eq!(stats.requests_per_sec, 1.0);
+        assert_eq!(stats.successful_requests_per_sec, 0.75);
+
+        let counts: Vec<(usize, usize)> =
+            stats.timeline.iter().map(|b| (b.successful, b.failed)).collect();
+        assert_eq!(counts, vec![(1, 1), (0, 0), (1, 0), (0, 0), (1, 0)]);
+        assert_eq!(stats.timeline.last().unwrap().second, 4);
+    }
+
+    #[test]
+    fn test_prometheus_export_is_well_formed() {
+        let mut metrics = ClientMetrics::new("Machine \"1\"".to_string());
+        let timeout = Some("timeout".to_string());
+
+        metrics.record_request(1, Duration::from_millis(100), true, None, Some(2));
+        metrics.record_request(2, Duration::from_millis(300), true, None, Some(1));
+        metrics.record_request(3, Duration::from_millis(150), false, timeout, Some(1));
+
+        let text = metrics.to_prometheus();
+        let client = r#"{client="Machine \"1\""}"#;
+        assert!(text.contains(&format!("cloudp2p_client_requests_total{} 3\n", client)));
+        assert!(text.contains(&format!("cloudp2p_client_requests_failed_total{} 1\n", client)));
+        let latency_sum = format!("cloudp2p_client_request_latency_seconds_sum{} 0.4\n", client);
+        assert!(text.contains(&latency_sum));
+        assert!(text.contains(",server_id=\"1\"} 2\n"));
+
+        // Every sample belongs to a family declared by a preceding # HELP and # TYPE,
+        // and is `name{labels} value` with a numeric value
+        let mut declared = Vec::new();
+        for line in text.lines() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                declared.push(rest.split(' ').next().unwrap().to_string());
+            } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let mut parts = rest.split(' ');
+                assert_eq!(parts.next(), declared.last().map(String::as_str));
+                assert!(matches!(parts.next(), Some("counter" | "summary")));
+            } else {
+                let (series, value) = line.rsplit_once(' ').unwrap();
+                let (name, labels) = series.split_once('{').unwrap();
+                assert!(labels.ends_with('}'), "bad labels in {:?}", line);
+                assert!(value.parse::<f64>().is_ok(), "bad value in {:?}", line);
+
+                let family = declared.last().unwrap();
+                let suffix = name.strip_prefix(family.as_str()).unwrap();
+                assert!(["", "_sum", "_count"].contains(&suffix), "undeclared {:?}", line);
+            }
+        }
+        assert_eq!(declared.len(), 5);
+    }
 }
@@ -8,7 +8,7 @@
 //! The [`ClientMiddleware`] struct manages high-level coordination:
 //! - **Leader Discovery**: Queries multiple servers to find the current leader
 //! - **Request Management**: Sends requests at configured rates with delays
-//! - **Retry Logic**: Implements 3-attempt retry with 10-second timeout and 5-second delays
+//! - **Retry Logic**: Polls, times out and resubmits as tuned in the `[failover]` config section
 //! - **Server Assignment**: Requests server assignment from the leader
 //! - **Fault Tolerance**: Handles server failures and re-discovers leaders
 //! - **Configuration**: Loads and manages client settings from TOML files
@@ -22,10 +22,14 @@
 //!
 //! ## Request Workflow
 //!
-//! 1. **Discover Leader**: Query servers to find who is the current leader
+//! 1. **Discover Leader**: Query servers to find who is the current leader (skipped
+//!    while the leader that answered the last assignment keeps answering)
 //! 2. **Get Assignment**: Ask the leader for a server assignment
 //! 3. **Execute Task**: Delegate to `ClientCore` to send image and receive result
-//! 4. **Retry on Failure**: Retry up to 3 times with timeouts and delays
+//! 4. **Retry on Failure**: Resubmit lost tasks, up to `failover.max_resubmissions` times
+//!
+//! With `route_via_leader` enabled, steps 2-3 collapse into one: the task is sent to
+//! the leader, which forwards it to the least-loaded server and relays the result.
 //!
 //! ## Usage
 //!
@@ -38,7 +42,7 @@
 //! let config = ClientConfig::from_file("client_config.toml")?;
 //!
 //! // Create the core client
-//! let core = Arc::new(ClientCore::new(config.client.name.clone()));
+//! let core = Arc::new(ClientCore::new(config.client.name.clone(), &config.client.output_dir));
 //!
 //! // Create and run the middleware
 //! let mut middleware = ClientMiddleware::new(config, core);
@@ -46,17 +50,24 @@
 //! ```
 
 use anyhow::Result;
-use log::{error, info, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{error, info, instrument, warn};
 
-use crate::client::client::ClientCore;
+use crate::client::circuit_breaker::CircuitBreakers;
+use crate::client::client::{CarrierImage, ClientCore, RateLimited};
 use crate::client::metrics::ClientMetrics;
-use crate::common::connection::Connection;
+use crate::common::connection::{Connection, DEFAULT_MAX_MESSAGE_SIZE};
+use crate::common::error::CloudP2PError;
 use crate::common::messages::Message;
+use crate::common::transport::Stream;
 
 /// Client configuration loaded from TOML file.
 ///
@@ -80,6 +91,9 @@ pub struct ClientConfig {
     pub client: ClientInfo,
     /// Request rate and processing parameters
     pub requests: RequestConfig,
+    /// Polling, timeout and resubmission tuning (optional section)
+    #[serde(default)]
+    pub failover: FailoverConfig,
 }
 
 /// Client identity and server addresses.
@@ -95,12 +109,41 @@ pub struct ClientInfo {
     /// Directory containing images to randomly select from (default: "test_images")
     #[serde(default = "default_image_dir")]
     pub image_dir: String,
+    /// Directory received carrier images are saved to (default: "user-data/outputs")
+    #[serde(default = "default_output_dir")]
+    pub output_dir: String,
+    /// Largest server response accepted, in bytes (default: 100MB)
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+    /// Largest image the web server accepts for upload, in bytes (default: 20MB)
+    #[serde(default = "default_max_upload_bytes")]
+    pub max_upload_bytes: usize,
+    /// Send tasks to the leader, which forwards them to the chosen server and
+    /// relays the result, instead of contacting the assigned server (default: false)
+    #[serde(default)]
+    pub route_via_leader: bool,
+    /// Text for servers to embed alongside every secret image, e.g. an owner or
+    /// caption (default: none)
+    #[serde(default)]
+    pub metadata: Option<String>,
 }
 
 fn default_image_dir() -> String {
     "test_images".to_string()
 }
 
+fn default_output_dir() -> String {
+    "user-data/outputs".to_string()
+}
+
+fn default_max_message_size() -> usize {
+    DEFAULT_MAX_MESSAGE_SIZE
+}
+
+fn default_max_upload_bytes() -> usize {
+    20 * 1024 * 1024
+}
+
 /// Request configuration for stress testing.
 ///
 /// Defines how many requests to send and the delay between them.
@@ -112,6 +155,90 @@ pub struct RequestConfig {
     pub min_delay_ms: u64,
     /// Maximum delay between requests in milliseconds
     pub max_delay_ms: u64,
+    /// Most requests outstanding at once (default: 1, i.e. strictly sequential)
+    #[serde(default = "default_max_inflight")]
+    pub max_inflight: usize,
+    /// Seed for choosing each request's image and delay, so a run can be repeated
+    /// exactly (default: none, a fresh random sequence every run)
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl RequestConfig {
+    /// The random source for image selection and delays: seeded from `seed` if
+    /// set, from entropy otherwise.
+    fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
+}
+
+/// Chooses the image (an index into `image_count` images) for the next request
+/// and the delay before sending it, between `min_delay` and `max_delay` ms.
+fn pick_image_and_delay(
+    rng: &mut StdRng,
+    image_count: usize,
+    min_delay: u64,
+    max_delay: u64,
+) -> (usize, Duration) {
+    let image_index = rng.gen_range(0..image_count);
+    let delay = min_delay + rng.gen_range(0..=max_delay.saturating_sub(min_delay));
+    (image_index, Duration::from_millis(delay))
+}
+
+fn default_max_inflight() -> usize {
+    1
+}
+
+/// Failover tuning: how often to poll servers and when to give up.
+///
+/// Every field is optional; missing ones keep their defaults. Raise the timeouts
+/// and poll counts for high-latency links.
+///
+/// # Example TOML
+///
+/// ```toml
+/// [failover]
+/// poll_interval_secs = 5
+/// connection_timeout_secs = 15
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FailoverConfig {
+    /// Delay between leader discovery and reassignment polls, in seconds (default: 2)
+    pub poll_interval_secs: u64,
+    /// Complete resubmissions of a lost or rejected task before giving up (default: 5)
+    pub max_resubmissions: u32,
+    /// Reassignment polls no server answers before the task counts as lost (default: 5)
+    pub max_consecutive_failures: u32,
+    /// Polls still naming the failed server before retrying it anyway (default: 10)
+    pub max_same_server_polls: u32,
+    /// Timeout for each assignment, status or leader query, in seconds (default: 5)
+    pub connection_timeout_secs: u64,
+    /// Consecutive task failures on one server that open its circuit breaker (default: 3)
+    pub breaker_failure_threshold: u32,
+    /// How long an open circuit breaker keeps a server out of use, in seconds (default: 30)
+    pub breaker_cooldown_secs: u64,
+    /// Longest wait for a leader to take a task, in seconds, before the request fails
+    /// (default: none, wait as long as it takes)
+    pub assignment_timeout_secs: Option<u64>,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: 2,
+            max_resubmissions: 5,
+            max_consecutive_failures: 5,
+            max_same_server_polls: 10,
+            connection_timeout_secs: 5,
+            breaker_failure_threshold: 3,
+            breaker_cooldown_secs: 30,
+            assignment_timeout_secs: None,
+        }
+    }
 }
 
 impl ClientConfig {
@@ -137,6 +264,153 @@ impl ClientConfig {
         let config: ClientConfig = toml::from_str(&content)?;
         Ok(config)
     }
+
+    /// Checks that the client has something to send before it starts: its
+    /// `image_dir` exists and holds at least one `.jpg`, `.jpeg` or `.png` file.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Requests can be sent
+    /// * `Err(anyhow::Error)` - The image directory is unreadable or has no images
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let config = ClientConfig::from_file("configs/client1.toml")?;
+    /// config.validate()?;
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        let image_dir = &self.client.image_dir;
+        match list_images(image_dir) {
+            Ok(images) if !images.is_empty() => Ok(()),
+            Ok(_) => anyhow::bail!(
+                "Invalid client configuration: client.image_dir '{}' contains no .jpg, .jpeg or .png images",
+                image_dir
+            ),
+            Err(e) => anyhow::bail!(
+                "Invalid client configuration: client.image_dir '{}' can't be read: {}",
+                image_dir,
+                e
+            ),
+        }
+    }
+}
+
+/// Names of the secret images (`.jpg`, `.jpeg` or `.png` files) in `dir`, sorted
+/// so the list is the same every run.
+fn list_images(dir: &str) -> std::io::Result<Vec<String>> {
+    let mut images: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext == "jpg" || ext == "jpeg" || ext == "png")
+        })
+        .filter_map(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|s| s.to_string())
+        })
+        .collect();
+    images.sort();
+    Ok(images)
+}
+
+/// How long to wait before retrying after `error`: the leader's `retry_after` if
+/// the client was rate limited, otherwise `poll_interval_secs`.
+fn retry_delay(error: &anyhow::Error, poll_interval_secs: u64) -> Duration {
+    error
+        .downcast_ref::<RateLimited>()
+        .map(|limited| limited.retry_after)
+        .unwrap_or(Duration::from_secs(poll_interval_secs))
+}
+
+/// Whether a failed attempt is worth resubmitting from scratch: the task was lost
+/// (no server has it any more), or a busy server rejected it and kept no record.
+fn is_resubmittable(error: &anyhow::Error) -> bool {
+    CloudP2PError::is_task_lost(error) || CloudP2PError::is_task_rejected(error)
+}
+
+/// A step in the life of one task, reported while [`ClientMiddleware::submit_task`] runs.
+///
+/// Serialized as a JSON object tagged by `event`, e.g.
+/// `{"event":"assigned","server_id":2,"leader_id":1}` or
+/// `{"event":"resubmitting","attempt":1,"max_attempts":5,"reason":"..."}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TaskProgress {
+    /// Asking the leader which server should run the task (`attempt` starts at 1)
+    RequestingAssignment { attempt: u32 },
+    /// The leader assigned the task to `server_id`
+    Assigned { server_id: u32, leader_id: u32 },
+    /// Sending the task to leader `leader_id` to forward (leader-routed mode)
+    RoutingViaLeader { leader_id: u32 },
+    /// The assigned server failed; polling for the leader to reassign the task
+    ServerFailed { server_id: u32, error: String },
+    /// The task was reassigned to `server_id` after a server failure
+    Reassigned { server_id: u32 },
+    /// The assigned server has embedded `percent`% of the secret (only sent by
+    /// servers with `server.report_progress` set)
+    Embedding { percent: u8 },
+    /// The task was lost or rejected and is being submitted again
+    Resubmitting {
+        attempt: u32,
+        max_attempts: u32,
+        reason: String,
+    },
+    /// The carrier image was received and verified
+    Done { server_id: u32 },
+    /// The task failed for good
+    Failed { error: String },
+}
+
+/// Cluster overview from [`ClientMiddleware::cluster_status`].
+///
+/// Serialized as e.g.
+/// `{"leader_id":1,"servers":[{"address":"127.0.0.1:8001","reachable":true,"server_id":1,
+/// "load":12.5,"cpu_usage":20.0,"active_tasks":2,"total_tasks":40,"saturated":false},
+/// {"address":"127.0.0.1:8002","reachable":false}]}`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterStatus {
+    /// Current leader, if any server knows one
+    pub leader_id: Option<u32>,
+    /// One entry per configured server address, in configuration order
+    pub servers: Vec<ServerStatus>,
+}
+
+/// Reachability and load of one configured server.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    pub address: String,
+    /// Whether the server answered the load query in time
+    pub reachable: bool,
+    /// The server's reported load, if it answered
+    #[serde(flatten)]
+    pub load: Option<ServerLoad>,
+}
+
+/// Load reported by a server in a `LoadResponse`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerLoad {
+    pub server_id: u32,
+    /// Priority score (lower = less loaded)
+    pub load: f64,
+    pub cpu_usage: f64,
+    pub active_tasks: u64,
+    pub total_tasks: u64,
+    pub saturated: bool,
+}
+
+/// Where a task's [`TaskProgress`] events are sent.
+pub type ProgressSender = mpsc::UnboundedSender<TaskProgress>;
+
+/// Report `event` if anyone is listening; a closed receiver is ignored.
+fn report(progress: Option<&ProgressSender>, event: TaskProgress) {
+    if let Some(progress) = progress {
+        let _ = progress.send(event);
+    }
 }
 
 /// Client middleware that orchestrates distributed task execution.
@@ -150,6 +424,7 @@ impl ClientConfig {
 ///
 /// * `config` - Client configuration loaded from TOML
 /// * `core` - Shared reference to the core client for image transmission
+#[derive(Clone)]
 pub struct ClientMiddleware {
     /// Client configuration
     config: ClientConfig,
@@ -157,6 +432,11 @@ pub struct ClientMiddleware {
     core: Arc<ClientCore>,
     /// Optional metrics collector for stress testing
     metrics: Option<Arc<Mutex<ClientMetrics>>>,
+    /// Per-server circuit breakers, shared by all in-flight requests
+    breakers: Arc<Mutex<CircuitBreakers>>,
+    /// Leader (ID and address) that answered the last assignment request, asked
+    /// first for the next one instead of broadcasting
+    cached_leader: Arc<Mutex<Option<(u32, String)>>>,
 }
 
 impl ClientMiddleware {
@@ -175,14 +455,21 @@ impl ClientMiddleware {
     ///
     /// ```rust,ignore
     /// let config = ClientConfig::from_file("client_config.toml")?;
-    /// let core = Arc::new(ClientCore::new(config.client.name.clone()));
+    /// let core = Arc::new(ClientCore::new(config.client.name.clone(), &config.client.output_dir));
     /// let middleware = ClientMiddleware::new(config, core);
     /// ```
     pub fn new(config: ClientConfig, core: Arc<ClientCore>) -> Self {
+        let breakers = CircuitBreakers::new(
+            config.failover.breaker_failure_threshold,
+            Duration::from_secs(config.failover.breaker_cooldown_secs),
+        );
+
         Self {
             config,
             core,
             metrics: None,
+            breakers: Arc::new(Mutex::new(breakers)),
+            cached_leader: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -199,12 +486,15 @@ impl ClientMiddleware {
     /// Runs the main client loop, sending requests at the configured rate.
     ///
     /// This method:
-    /// 1. Calculates the delay between requests based on `rate_per_second`
-    /// 2. Sends the total number of requests over the configured duration
-    /// 3. For each request, calls `send_request()` which handles retries
-    /// 4. Only sleeps between requests if the previous request succeeded
+    /// 1. Waits until fewer than `max_inflight` requests are outstanding
+    /// 2. Sleeps a random delay between `min_delay_ms` and `max_delay_ms`
+    ///    (the image and delay come from `requests.seed` when set, so a seeded
+    ///    run repeats the same sequence)
+    /// 3. Spawns `send_request()` (which handles retries) for the next request
     ///
-    /// The loop continues until all requests have been sent or the duration elapses.
+    /// With the default `max_inflight = 1`, each request starts only after the
+    /// previous one has finished and the delay has passed. The method returns
+    /// once all requests have been sent and completed.
     ///
     /// # Examples
     ///
@@ -213,33 +503,13 @@ impl ClientMiddleware {
     /// middleware.run().await;  // Blocks until all requests are sent
     /// ```
     pub async fn run(&mut self) {
-        info!("Client '{}' starting", self.config.client.name);
-
         let total_requests = self.config.requests.total_requests;
         let min_delay = self.config.requests.min_delay_ms;
         let max_delay = self.config.requests.max_delay_ms;
 
         // Load all image files from the directory
-        let image_files: Vec<String> = match fs::read_dir(&self.config.client.image_dir) {
-            Ok(entries) => entries
-                .filter_map(|e| e.ok())
-                .filter(|e| e.path().is_file())
-                .filter_map(|e| {
-                    let path = e.path();
-                    if let Some(ext) = path.extension() {
-                        let ext = ext.to_str().unwrap_or("");
-                        if ext == "jpg" || ext == "jpeg" || ext == "png" {
-                            path.file_name()
-                                .and_then(|n| n.to_str())
-                                .map(|s| s.to_string())
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                })
-                .collect(),
+        let image_files = match list_images(&self.config.client.image_dir) {
+            Ok(images) => images,
             Err(e) => {
                 error!(
                     "Failed to read image directory '{}': {}",
@@ -256,21 +526,31 @@ impl ClientMiddleware {
             );
             return;
         }
+        let mut rng = self.config.requests.rng();
 
+        info!("Client '{}' starting", self.config.client.name);
         info!(
-            "Client '{}' sending {} requests (delay: {}-{}ms, {} images available)...",
+            "Client '{}' sending {} requests (delay: {}-{}ms, up to {} in flight, {} images available)...",
             self.config.client.name,
             total_requests,
             min_delay,
             max_delay,
+            self.config.requests.max_inflight.max(1),
             image_files.len()
         );
 
+        // Up to `max_inflight` requests run concurrently, each on its own task
+        let max_inflight = self.config.requests.max_inflight.max(1);
+        let inflight_slots = Arc::new(Semaphore::new(max_inflight));
+        let middleware = Arc::new(self.clone());
+        let mut in_flight = JoinSet::new();
+
         // Send all requests with random delays and random image selection
         for i in 1..=total_requests {
             // Randomly select a secret image to hide
-            let image_index = (rand::random::<f64>() * image_files.len() as f64) as usize;
-            let image_name = &image_files[image_index % image_files.len()];
+            let (image_index, delay) =
+                pick_image_and_delay(&mut rng, image_files.len(), min_delay, max_delay);
+            let image_name = &image_files[image_index];
 
             // Read the image file
             let image_path = format!("{}/{}", self.config.client.image_dir, image_name);
@@ -282,20 +562,101 @@ impl ClientMiddleware {
                 }
             };
 
-            let result = self.send_request(i, secret_image_data).await;
+            // Wait for a free slot
+            let permit = inflight_slots
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("in-flight semaphore is never closed");
 
-            // Random delay between requests (only if task succeeded)
-            if result.is_some() && i < total_requests {
-                let range = max_delay - min_delay;
-                let random_offset = (rand::random::<f64>() * range as f64) as u64;
-                let delay = Duration::from_millis(min_delay + random_offset);
+            // Random delay between requests
+            if i > 1 {
                 tokio::time::sleep(delay).await;
             }
+
+            let middleware = middleware.clone();
+            in_flight.spawn(async move {
+                // Failures are already logged and recorded in the metrics
+                let _ = middleware.send_request(i, secret_image_data, None).await;
+                drop(permit);
+            });
+        }
+
+        // Wait for the requests still in flight
+        while let Some(result) = in_flight.join_next().await {
+            if let Err(e) = result {
+                error!("❌ Request task failed: {}", e);
+            }
         }
 
         info!("✅ Client finished sending {} requests", total_requests);
     }
 
+    /// Requests a task assignment, from the cached leader if there is one.
+    ///
+    /// The leader that answered the last assignment is asked directly. If it doesn't
+    /// answer in time (it failed, or lost leadership and ignores the request), it is
+    /// forgotten and the request is broadcast with
+    /// [`broadcast_assignment_request`](Self::broadcast_assignment_request), whose
+    /// responder becomes the cached leader.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_num` - Unique identifier for this request
+    ///
+    /// # Returns
+    ///
+    /// Same as [`broadcast_assignment_request`](Self::broadcast_assignment_request)
+    async fn request_assignment(&self, request_num: u64) -> Result<(u32, String, u32)> {
+        let cached = self.cached_leader.lock().unwrap().clone();
+        if let Some((leader_id, leader_address)) = cached {
+            let connection_timeout =
+                Duration::from_secs(self.config.failover.connection_timeout_secs);
+            let result = tokio::time::timeout(
+                connection_timeout,
+                Self::request_assignment_from_server(
+                    &leader_address,
+                    &self.config.client.name,
+                    request_num,
+                ),
+            )
+            .await;
+
+            match result {
+                Ok(Ok((assigned_server_id, assigned_address))) => {
+                    return Ok((assigned_server_id, assigned_address, leader_id));
+                }
+                // The leader is there, it just wants us to slow down
+                Ok(Err(e)) if e.is::<RateLimited>() => return Err(e),
+                _ => {
+                    warn!(
+                        request_id = request_num,
+                        "⚠️  {} Cached leader {} didn't answer for task #{}, rediscovering",
+                        self.config.client.name,
+                        leader_id,
+                        request_num
+                    );
+                    let mut cached_leader = self.cached_leader.lock().unwrap();
+                    // Another request may already have found the new leader
+                    if matches!(&*cached_leader, Some((id, _)) if *id == leader_id) {
+                        *cached_leader = None;
+                    }
+                }
+            }
+        }
+
+        let assignment = self.broadcast_assignment_request(request_num).await?;
+        let leader_id = assignment.2;
+        // Server IDs are 1-indexed positions in `server_addresses`
+        if let Some(address) = (leader_id as usize)
+            .checked_sub(1)
+            .and_then(|idx| self.config.client.server_addresses.get(idx))
+        {
+            *self.cached_leader.lock().unwrap() = Some((leader_id, address.clone()));
+        }
+        Ok(assignment)
+    }
+
     /// Broadcasts a task assignment request to all servers and waits for the leader's response.
     ///
     /// This method:
@@ -313,15 +674,18 @@ impl ClientMiddleware {
     /// # Returns
     ///
     /// * `Ok((assigned_server_id, assigned_address, leader_id))` - Assignment details and which server was leader
-    /// * `Err(anyhow::Error)` - If no server responded with a valid assignment
+    /// * `Err(anyhow::Error)` - If no server responded with a valid assignment, or
+    ///   [`RateLimited`] if the leader refused this client for exceeding its rate
     ///
     /// # Timeout
     ///
-    /// Each server connection attempt has a 2-second timeout. Returns the first valid response.
+    /// Each server connection attempt times out after `failover.connection_timeout_secs`.
+    /// Returns the first valid response.
     async fn broadcast_assignment_request(&self, request_num: u64) -> Result<(u32, String, u32)> {
-        const CONNECTION_TIMEOUT_SECS: u64 = 5;
+        let connection_timeout = Duration::from_secs(self.config.failover.connection_timeout_secs);
 
         info!(
+            request_id = request_num,
             "📡 {} Broadcasting assignment request for task #{} to {} servers",
             self.config.client.name,
             request_num,
@@ -339,14 +703,15 @@ impl ClientMiddleware {
             let task = tokio::spawn(async move {
                 // Wrap in timeout
                 let result = tokio::time::timeout(
-                    Duration::from_secs(CONNECTION_TIMEOUT_SECS),
+                    connection_timeout,
                     Self::request_assignment_from_server(&address, &client_name, request_num),
                 )
                 .await;
 
                 match result {
-                    Ok(Ok(assignment)) => Some((assignment, server_id)),
-                    Ok(Err(_)) | Err(_) => None,
+                    Ok(Ok(assignment)) => Ok((assignment, server_id)),
+                    Ok(Err(e)) => Err(e),
+                    Err(elapsed) => Err(elapsed.into()),
                 }
             });
 
@@ -354,19 +719,31 @@ impl ClientMiddleware {
         }
 
         // Wait for all tasks and collect the first successful response
+        let mut rate_limited = None;
         for task in tasks {
-            if let Ok(Some(((assigned_server_id, assigned_address), responder_id))) = task.await {
-                info!(
-                    "✅ {} Received assignment from leader (Server {}): Task #{} → Server {}",
-                    self.config.client.name, responder_id, request_num, assigned_server_id
-                );
-                return Ok((assigned_server_id, assigned_address, responder_id));
+            match task.await {
+                Ok(Ok(((assigned_server_id, assigned_address), responder_id))) => {
+                    info!(
+                        request_id = request_num,
+                        "✅ {} Received assignment from leader (Server {}): Task #{} → Server {}",
+                        self.config.client.name,
+                        responder_id,
+                        request_num,
+                        assigned_server_id
+                    );
+                    return Ok((assigned_server_id, assigned_address, responder_id));
+                }
+                Ok(Err(e)) if e.is::<RateLimited>() => rate_limited = Some(e),
+                _ => {}
             }
         }
 
-        Err(anyhow::anyhow!(
-            "No server responded with a task assignment (no leader available)"
-        ))
+        // The leader answered, just not with an assignment
+        if let Some(e) = rate_limited {
+            return Err(e);
+        }
+
+        Err(CloudP2PError::NoLeader.into())
     }
 
     /// Helper method to request assignment from a specific server.
@@ -387,8 +764,9 @@ impl ClientMiddleware {
         request_num: u64,
     ) -> Result<(u32, String)> {
         // Connect to server
-        let stream = TcpStream::connect(address).await?;
+        let stream = Stream::connect(address).await?;
         let mut conn = Connection::new(stream);
+        conn.handshake().await?;
 
         // Send assignment request
         let request = Message::TaskAssignmentRequest {
@@ -404,7 +782,11 @@ impl ClientMiddleware {
                 assigned_server_id,
                 assigned_server_address,
             }) => Ok((assigned_server_id, assigned_server_address)),
-            _ => Err(anyhow::anyhow!("Invalid or no response from server")),
+            Some(Message::RateLimited { retry_after_ms, .. }) => Err(RateLimited {
+                retry_after: Duration::from_millis(retry_after_ms),
+            }
+            .into()),
+            _ => Err(CloudP2PError::UnexpectedResponse.into()),
         }
     }
 
@@ -420,10 +802,11 @@ impl ClientMiddleware {
     ///
     /// # Returns
     ///
-    /// * `Ok((assigned_server_id, assigned_address))` - Current server assignment
+    /// * `Ok((assigned_server_id, assigned_address, leader_id))` - Current server
+    ///   assignment, and the leader the answering server follows
     /// * `Err` - If no server responded with valid status
-    async fn broadcast_status_query(&self, request_num: u64) -> Result<(u32, String)> {
-        const CONNECTION_TIMEOUT_SECS: u64 = 5;
+    async fn broadcast_status_query(&self, request_num: u64) -> Result<(u32, String, Option<u32>)> {
+        let connection_timeout = Duration::from_secs(self.config.failover.connection_timeout_secs);
 
         info!(
             "🔍 {} Broadcasting status query for task #{} to {} servers",
@@ -442,7 +825,7 @@ impl ClientMiddleware {
             let task = tokio::spawn(async move {
                 // Wrap in timeout
                 let result = tokio::time::timeout(
-                    Duration::from_secs(CONNECTION_TIMEOUT_SECS),
+                    connection_timeout,
                     Self::query_task_status(&address, &client_name, request_num),
                 )
                 .await;
@@ -458,18 +841,19 @@ impl ClientMiddleware {
 
         // Wait for first successful response
         for task in tasks {
-            if let Ok(Some((assigned_server_id, assigned_address))) = task.await {
+            if let Ok(Some((assigned_server_id, assigned_address, leader_id))) = task.await {
                 info!(
                     "✅ {} Task #{} is assigned to Server {}",
                     self.config.client.name, request_num, assigned_server_id
                 );
-                return Ok((assigned_server_id, assigned_address));
+                return Ok((assigned_server_id, assigned_address, leader_id));
             }
         }
 
-        Err(anyhow::anyhow!(
-            "No server responded with task status (task may not exist)"
-        ))
+        Err(CloudP2PError::TaskNotFound {
+            request_id: request_num,
+        }
+        .into())
     }
 
     /// Helper method to query task status from a specific server.
@@ -482,16 +866,18 @@ impl ClientMiddleware {
     ///
     /// # Returns
     ///
-    /// * `Ok((assigned_server_id, assigned_address))` - Current assignment
+    /// * `Ok((assigned_server_id, assigned_address, leader_id))` - Current assignment
+    ///   and the server's leader
     /// * `Err` - If connection failed or no valid response
     async fn query_task_status(
         address: &str,
         client_name: &str,
         request_num: u64,
-    ) -> Result<(u32, String)> {
+    ) -> Result<(u32, String, Option<u32>)> {
         // Connect to server
-        let stream = TcpStream::connect(address).await?;
+        let stream = Stream::connect(address).await?;
         let mut conn = Connection::new(stream);
+        conn.handshake().await?;
 
         // Send status query
         let query = Message::TaskStatusQuery {
@@ -506,8 +892,9 @@ impl ClientMiddleware {
                 request_id: _,
                 assigned_server_id,
                 assigned_server_address,
-            }) => Ok((assigned_server_id, assigned_server_address)),
-            _ => Err(anyhow::anyhow!("Invalid or no response from server")),
+                leader_id,
+            }) => Ok((assigned_server_id, assigned_server_address, leader_id)),
+            _ => Err(CloudP2PError::UnexpectedResponse.into()),
         }
     }
 
@@ -516,9 +903,10 @@ impl ClientMiddleware {
     /// When the assigned server fails, this method polls all servers (via broadcast)
     /// to get the current task assignment. The strategy is:
     /// 1. Prefer reassignment to a **different** server (immediate return)
-    /// 2. If same server keeps being returned, retry after MAX_SAME_SERVER_POLLS attempts
+    /// 2. If same server keeps being returned, retry after `failover.max_same_server_polls` attempts
     ///    (in case the server came back online)
-    /// 3. If no server responds for MAX_CONSECUTIVE_FAILURES attempts, assume task is lost
+    /// 3. If no server responds for `failover.max_consecutive_failures` attempts, assume task is lost
+    ///    (a server whose circuit breaker is open counts as not responding)
     ///    and return error to trigger resubmission
     ///
     /// # Arguments
@@ -528,28 +916,27 @@ impl ClientMiddleware {
     ///
     /// # Returns
     ///
-    /// * `Ok((assigned_server_id, assigned_address))` - Server assignment
+    /// * `Ok((assigned_server_id, assigned_address, leader_id))` - Server assignment,
+    ///   and the current leader if the answering server knows it
     /// * `Err` - Task appears to be lost (all servers failed or lost history)
     ///
     /// # Polling Behavior
     ///
-    /// - Polls with 10-second intervals
+    /// - Polls every `failover.poll_interval_secs` seconds
     /// - Immediately accepts reassignment to a different server
-    /// - Retries same server after MAX_SAME_SERVER_POLLS attempts (server might have recovered)
-    /// - Gives up after MAX_CONSECUTIVE_FAILURES consecutive failures (triggers task resubmission)
+    /// - Retries same server after `failover.max_same_server_polls` attempts (server might have recovered)
+    /// - Gives up after `failover.max_consecutive_failures` consecutive failures (triggers task resubmission)
     /// - Logs every polling attempt
     async fn wait_for_reassignment(
         &self,
         request_num: u64,
         failed_address: &str,
-    ) -> Result<(u32, String)> {
-        const POLL_INTERVAL_SECS: u64 = 2;
-        const MAX_SAME_SERVER_POLLS: u32 = 10; // After 10 polls (100s), retry same server in case it recovered
-        const MAX_CONSECUTIVE_FAILURES: u32 = 5; // After 5 consecutive failures (10s), assume task is lost
+    ) -> Result<(u32, String, Option<u32>)> {
+        let failover = &self.config.failover;
 
         info!(
             "⏳ {} Polling for task #{} assignment after {} failed (max {} consecutive failures before resubmission)...",
-            self.config.client.name, request_num, failed_address, MAX_CONSECUTIVE_FAILURES
+            self.config.client.name, request_num, failed_address, failover.max_consecutive_failures
         );
 
         let mut attempt = 1;
@@ -562,8 +949,17 @@ impl ClientMiddleware {
                 self.config.client.name, attempt, request_num
             );
 
-            match self.broadcast_status_query(request_num).await {
-                Ok((server_id, address)) => {
+            // A server whose circuit breaker is open counts as no answer, so we keep
+            // polling until the task moves elsewhere (or is declared lost)
+            let status = match self.broadcast_status_query(request_num).await {
+                Ok((server_id, _, _)) if self.is_breaker_open(server_id) => {
+                    Err(CloudP2PError::ServerUnavailable { server_id }.into())
+                }
+                status => status,
+            };
+
+            match status {
+                Ok((server_id, address, leader_id)) => {
                     // Reset consecutive failure counter - we got a response
                     consecutive_failures = 0;
 
@@ -573,22 +969,22 @@ impl ClientMiddleware {
                             "✅ {} Task #{} reassigned to different Server {} at {}",
                             self.config.client.name, request_num, server_id, address
                         );
-                        return Ok((server_id, address));
+                        return Ok((server_id, address, leader_id));
                     } else {
                         // Same server - might have recovered, but wait a bit first
                         same_server_count += 1;
 
-                        if same_server_count >= MAX_SAME_SERVER_POLLS {
+                        if same_server_count >= failover.max_same_server_polls {
                             info!(
                                 "🔄 {} Task #{} still at {} after {} polls - will retry in case server recovered",
                                 self.config.client.name, request_num, address, same_server_count
                             );
-                            return Ok((server_id, address));
+                            return Ok((server_id, address, leader_id));
                         } else {
                             warn!(
                                 "⏸️  {} Poll {}: Task #{} still at {} ({}/{} polls) - waiting for reassignment or recovery...",
                                 self.config.client.name, attempt, request_num, failed_address,
-                                same_server_count, MAX_SAME_SERVER_POLLS
+                                same_server_count, failover.max_same_server_polls
                             );
                         }
                     }
@@ -597,60 +993,128 @@ impl ClientMiddleware {
                     consecutive_failures += 1;
                     warn!(
                         "Polling attempt {} failed for task #{}: {} ({}/{} consecutive failures)",
-                        attempt, request_num, e, consecutive_failures, MAX_CONSECUTIVE_FAILURES
+                        attempt,
+                        request_num,
+                        e,
+                        consecutive_failures,
+                        failover.max_consecutive_failures
                     );
 
                     // If we've had too many consecutive failures, assume task is lost
-                    if consecutive_failures >= MAX_CONSECUTIVE_FAILURES {
+                    if consecutive_failures >= failover.max_consecutive_failures {
                         error!(
                             "❌ {} Task #{} appears to be LOST - no server has record after {} consecutive failures. Task will be resubmitted.",
                             self.config.client.name, request_num, consecutive_failures
                         );
-                        return Err(anyhow::anyhow!(
-                            "Task #{} lost - all servers failed or lost task history after {} consecutive polling failures",
-                            request_num, consecutive_failures
-                        ));
+                        return Err(CloudP2PError::TaskLost {
+                            request_id: request_num,
+                            reason: format!(
+                                "all servers failed or lost task history after {} consecutive polling failures",
+                                consecutive_failures
+                            ),
+                        }
+                        .into());
                     }
                 }
             }
 
-            tokio::time::sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+            tokio::time::sleep(Duration::from_secs(failover.poll_interval_secs)).await;
             attempt += 1;
         }
     }
 
+    /// Calls `attempt` until it succeeds, `failover.poll_interval_secs` apart (or
+    /// after the leader's `retry_after`, if rate limited).
+    ///
+    /// Used while there's no leader to take task `request_num`. Gives up with
+    /// [`CloudP2PError::AssignmentTimeout`] once `failover.assignment_timeout_secs`
+    /// have passed, if set.
+    async fn wait_for_leader<T, F, Fut>(&self, request_num: u64, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let failover = &self.config.failover;
+        let timeout = failover.assignment_timeout_secs.map(Duration::from_secs);
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            let e = match attempt().await {
+                Ok(found) => return Ok(found),
+                Err(e) => e,
+            };
+
+            let mut delay = retry_delay(&e, failover.poll_interval_secs);
+            if let (Some(timeout), Some(deadline)) = (timeout, deadline) {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    warn!(
+                        "⌛ {} No leader took task #{} within {:?}: {}",
+                        self.config.client.name, request_num, timeout, e
+                    );
+                    return Err(CloudP2PError::AssignmentTimeout {
+                        request_id: request_num,
+                        timeout,
+                    }
+                    .into());
+                }
+                delay = delay.min(remaining);
+            }
+
+            warn!(
+                "No leader for task #{} yet: {} - waiting for leader...",
+                request_num, e
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
     /// Sends a request with server-side failover handling and automatic resubmission.
     ///
     /// This method implements the complete workflow:
-    /// 1. Polls indefinitely to get initial server assignment from leader (waits for leader if none available)
+    /// 1. Polls to get initial server assignment from leader (waits for leader if none
+    ///    available, for up to `failover.assignment_timeout_secs` if set)
     /// 2. Executes task on assigned server
     /// 3. If server fails, polls for reassignment (up to 6 consecutive failures = 60s)
     /// 4. If task is lost (all servers failed/lost history), gets fresh assignment and resubmits
-    /// 5. Retries complete workflow with MAX_RESUBMISSION_ATTEMPTS attempts
+    /// 5. Retries complete workflow up to `failover.max_resubmissions` times
     ///
     /// # Arguments
     ///
     /// * `request_num` - Unique identifier for this request
     /// * `secret_image_data` - Binary data of the secret image to hide
+    /// * `progress` - Optional channel to report each step of the workflow to
     ///
     /// # Returns
     ///
-    /// * `Some(Vec<u8>)` - If the request succeeded, returns the encrypted carrier image
-    /// * `None` - If the request failed
+    /// * `Ok((server_id, CarrierImage))` - If the request succeeded, the server that
+    ///   processed it (the leader it was routed through, in leader-routed mode) and the
+    ///   carrier image with where it was saved
+    /// * `Err(anyhow::Error)` - Why the last attempt failed, e.g.
+    ///   [`CloudP2PError::AssignmentTimeout`] if no leader came forward in time
     ///
     /// # Resubmission Strategy
     ///
-    /// When task is lost (execute_task returns error after consecutive polling failures):
+    /// When task is lost (execute_task returns error after consecutive polling failures)
+    /// or the assigned server rejected it for being at capacity:
     /// - Get a fresh assignment from the current leader
     /// - Retry the entire task workflow
-    /// - Maximum 3 complete resubmission attempts
+    /// - At most `failover.max_resubmissions` complete resubmission attempts
+    ///
+    /// Everything logged for this request happens inside a `request` span keyed by
+    /// `(client, request_id)`.
+    #[instrument(
+        name = "request",
+        skip_all,
+        fields(client = %self.config.client.name, request_id = request_num)
+    )]
     async fn send_request(
-        &mut self,
+        &self,
         request_num: u64,
         secret_image_data: Vec<u8>,
-    ) -> Option<Vec<u8>> {
-        const POLL_INTERVAL_SECS: u64 = 2;
-        const MAX_RESUBMISSION_ATTEMPTS: u32 = 5;
+        progress: Option<&ProgressSender>,
+    ) -> Result<(u32, CarrierImage)> {
+        let failover = &self.config.failover;
 
         // Start tracking latency
         let start_time = Instant::now();
@@ -664,47 +1128,100 @@ impl ClientMiddleware {
                     self.config.client.name,
                     request_num,
                     resubmission_attempt,
-                    MAX_RESUBMISSION_ATTEMPTS
+                    failover.max_resubmissions
                 );
             }
 
-            // Step 1: Get task assignment (poll indefinitely if no leader available)
-            info!(
-                "📡 {} Getting task assignment for task #{}",
-                self.config.client.name, request_num
-            );
+            // In leader-routed mode the leader picks the server and relays the result
+            let (assigned_server_id, result) = if self.config.client.route_via_leader {
+                let leader = self
+                    .wait_for_leader(request_num, || self.find_leader())
+                    .await;
+                let (leader_id, leader_address) = match leader {
+                    Ok(leader) => leader,
+                    Err(e) => {
+                        return Err(self.give_up(
+                            request_num,
+                            start_time,
+                            None,
+                            resubmission_attempt,
+                            e,
+                            progress,
+                        ));
+                    }
+                };
+                let result = self
+                    .execute_routed_task(
+                        request_num,
+                        leader_id,
+                        &leader_address,
+                        &secret_image_data,
+                        progress,
+                    )
+                    .await;
+                (leader_id, result)
+            } else {
+                // Step 1: Get task assignment (wait for a leader if none is available)
+                info!(
+                    "📡 {} Getting task assignment for task #{}",
+                    self.config.client.name, request_num
+                );
+                report(
+                    progress,
+                    TaskProgress::RequestingAssignment {
+                        attempt: resubmission_attempt + 1,
+                    },
+                );
 
-            let (assigned_server_id, assigned_address, leader_id) = loop {
-                match self.broadcast_assignment_request(request_num).await {
-                    Ok(assignment) => break assignment,
+                let assignment = self
+                    .wait_for_leader(request_num, || self.request_assignment(request_num))
+                    .await;
+                let (assigned_server_id, assigned_address, leader_id) = match assignment {
+                    Ok(assignment) => assignment,
                     Err(e) => {
-                        warn!(
-                            "Assignment request failed for task #{}: {} - waiting for leader...",
-                            request_num, e
-                        );
-                        tokio::time::sleep(Duration::from_secs(POLL_INTERVAL_SECS)).await;
+                        return Err(self.give_up(
+                            request_num,
+                            start_time,
+                            None,
+                            resubmission_attempt,
+                            e,
+                            progress,
+                        ));
                     }
-                }
-            };
+                };
 
-            info!(
-                "✅ {} Task #{} assigned to Server {} by leader {}",
-                self.config.client.name, request_num, assigned_server_id, leader_id
-            );
+                info!(
+                    "✅ {} Task #{} assigned to Server {} by leader {}",
+                    self.config.client.name, request_num, assigned_server_id, leader_id
+                );
+                report(
+                    progress,
+                    TaskProgress::Assigned {
+                        server_id: assigned_server_id,
+                        leader_id,
+                    },
+                );
 
-            // Step 2: Execute task on assigned server (handles failover internally)
-            let result = self
-                .execute_task(
-                    assigned_server_id,
-                    assigned_address,
-                    leader_id,
-                    request_num,
-                    secret_image_data.clone(),
-                )
-                .await;
+                // Step 2: Execute task on assigned server (handles failover internally);
+                // after a failover the task completes on a different server
+                match self
+                    .execute_task(
+                        assigned_server_id,
+                        assigned_address,
+                        leader_id,
+                        request_num,
+                        &secret_image_data,
+                        progress,
+                    )
+                    .await
+                {
+                    Ok((processed_by, carrier)) => (processed_by, Ok(carrier)),
+                    Err(e) => (assigned_server_id, Err(e)),
+                }
+            };
 
             match result {
-                Ok(encrypted_image_data) => {
+                Ok(carrier) => {
                     // Calculate total latency
                     let latency = start_time.elapsed();
 
@@ -721,77 +1238,120 @@ impl ClientMiddleware {
                     }
 
                     info!(
-                        "✅ {} Task #{} completed successfully{}",
+                        "✅ {} Task #{} completed successfully{}, carrier saved to {}",
                         self.config.client.name,
                         request_num,
                         if resubmission_attempt > 0 {
                             format!(" (after {} resubmission(s))", resubmission_attempt)
                         } else {
                             String::new()
-                        }
+                        },
+                        carrier.path.display()
+                    );
+                    report(
+                        progress,
+                        TaskProgress::Done {
+                            server_id: assigned_server_id,
+                        },
                     );
-                    return Some(encrypted_image_data);
+                    return Ok((assigned_server_id, carrier));
                 }
                 Err(e) => {
-                    // Check if this is a task loss error (eligible for resubmission)
+                    // Only a lost or rejected task is eligible for resubmission
                     let error_msg = e.to_string();
-                    let is_task_lost = error_msg.contains("lost")
-                        || error_msg.contains("consecutive polling failures");
-
-                    if is_task_lost && resubmission_attempt < MAX_RESUBMISSION_ATTEMPTS {
-                        // Task was lost - try complete resubmission
+                    if is_resubmittable(&e) && resubmission_attempt < failover.max_resubmissions {
+                        // Task was lost or rejected - try complete resubmission
                         resubmission_attempt += 1;
                         warn!(
                             "🔄 {} Task #{} lost - attempting resubmission ({}/{})",
                             self.config.client.name,
                             request_num,
                             resubmission_attempt,
-                            MAX_RESUBMISSION_ATTEMPTS
+                            failover.max_resubmissions
                         );
+                        report(
+                            progress,
+                            TaskProgress::Resubmitting {
+                                attempt: resubmission_attempt,
+                                max_attempts: failover.max_resubmissions,
+                                reason: error_msg,
+                            },
+                        );
+                        if CloudP2PError::is_task_rejected(&e) {
+                            // Give the leader a heartbeat to learn the server is saturated
+                            tokio::time::sleep(Duration::from_secs(failover.poll_interval_secs))
+                                .await;
+                        }
                         // Continue to next iteration to get fresh assignment
                         continue;
                     } else {
                         // Either not a task loss error, or we've exhausted resubmission attempts
-                        let latency = start_time.elapsed();
-
-                        // Record metrics if enabled
-                        if let Some(metrics) = &self.metrics {
-                            let mut metrics = metrics.lock().unwrap();
-                            metrics.record_request(
-                                request_num,
-                                latency,
-                                false,
-                                Some(error_msg.clone()),
-                                Some(assigned_server_id),
-                            );
-                        }
-
-                        error!(
-                            "❌ {} Task #{} FAILED{}: {}",
-                            self.config.client.name,
+                        return Err(self.give_up(
                             request_num,
-                            if resubmission_attempt > 0 {
-                                format!(" (after {} resubmission attempts)", resubmission_attempt)
-                            } else {
-                                String::new()
-                            },
-                            e
-                        );
-                        return None;
+                            start_time,
+                            Some(assigned_server_id),
+                            resubmission_attempt,
+                            e,
+                            progress,
+                        ));
                     }
                 }
             }
         }
     }
 
+    /// Records request `request_num` as failed with `error` and reports it, handing
+    /// `error` back for `send_request` to return.
+    ///
+    /// `server_id` is the server it last went to, if it got that far.
+    fn give_up(
+        &self,
+        request_num: u64,
+        start_time: Instant,
+        server_id: Option<u32>,
+        resubmission_attempt: u32,
+        error: anyhow::Error,
+        progress: Option<&ProgressSender>,
+    ) -> anyhow::Error {
+        let error_msg = error.to_string();
+
+        // Record metrics if enabled
+        if let Some(metrics) = &self.metrics {
+            let mut metrics = metrics.lock().unwrap();
+            metrics.record_request(
+                request_num,
+                start_time.elapsed(),
+                false,
+                Some(error_msg.clone()),
+                server_id,
+            );
+        }
+
+        error!(
+            "❌ {} Task #{} FAILED{}: {}",
+            self.config.client.name,
+            request_num,
+            if resubmission_attempt > 0 {
+                format!(" (after {} resubmission attempts)", resubmission_attempt)
+            } else {
+                String::new()
+            },
+            error
+        );
+        report(progress, TaskProgress::Failed { error: error_msg });
+        error
+    }
+
     /// Executes a task with automatic server-side failover handling.
     ///
     /// This method:
     /// 1. Uses the provided image data directly (already loaded)
     /// 2. Attempts to send task to assigned server
     /// 3. If server fails (TCP disconnect), polls for reassignment
-    /// 4. Polling: up to MAX_CONSECUTIVE_FAILURES attempts, 10 seconds interval via broadcast to all servers
-    /// 5. Retries with new server - if that server also fails, polls again
+    /// 4. Polling: up to `failover.max_consecutive_failures` attempts, `failover.poll_interval_secs` apart, via broadcast to all servers
+    /// 5. Retries with new server - if that server also fails, polls again; after
+    ///    `failover.breaker_failure_threshold` failures in a row a server's circuit
+    ///    breaker opens and it is skipped for `failover.breaker_cooldown_secs`
     /// 6. If all servers fail or lose task history, returns error to trigger complete resubmission
     ///
     /// # Arguments
@@ -801,10 +1361,12 @@ impl ClientMiddleware {
     /// * `leader_id` - ID of the leader that made the assignment
     /// * `request_num` - Unique identifier for this request
     /// * `secret_image_data` - Binary data of the secret image to hide
+    /// * `progress` - Optional channel to report server failures and reassignments to
     ///
     /// # Returns
     ///
-    /// * `Ok(Vec<u8>)` - The encrypted carrier image with embedded secret
+    /// * `Ok((server_id, CarrierImage))` - The server that finally processed the task
+    ///   (after any reassignments) and the encrypted carrier image with embedded secret
     /// * `Err(anyhow::Error)` - Only for non-connection errors (e.g., validation errors)
     /// * `Ok(())` - If the task completed successfully (possibly after multiple reassignments)
     /// * `Err(anyhow::Error)` - If task is lost (all servers failed/lost history) or other fatal errors
@@ -821,35 +1383,68 @@ impl ClientMiddleware {
     ///
     /// - **Input**: `{image_dir}/{image_name}` (secret image to hide)
     /// - **Output**: Carrier image with embedded secret (returned by server)
+    #[instrument(skip_all, fields(assigned_server_id = assigned_server_id))]
     async fn execute_task(
         &self,
-        _assigned_server_id: u32,
+        mut assigned_server_id: u32,
         mut assigned_address: String,
         mut leader_id: u32,
         request_num: u64,
-        secret_image_data: Vec<u8>,
-    ) -> Result<Vec<u8>> {
+        secret_image_data: &[u8],
+        progress: Option<&ProgressSender>,
+    ) -> Result<(u32, CarrierImage)> {
         loop {
             // Attempt to send task to assigned server
             let result = self
                 .core
-                .send_and_receive_encrypted_image(
+                .send_and_receive_encrypted_image_with_progress(
                     &assigned_address,
                     request_num,
-                    secret_image_data.clone(), // Clone cached data
+                    secret_image_data,
                     leader_id,
+                    &|percent| report(progress, TaskProgress::Embedding { percent }),
                 )
                 .await;
 
             match result {
-                Ok(encrypted_image_data) => {
-                    return Ok(encrypted_image_data);
+                Ok(carrier) => {
+                    self.breakers
+                        .lock()
+                        .unwrap()
+                        .record_success(assigned_server_id);
+                    return Ok((assigned_server_id, carrier));
+                }
+                Err(e) if CloudP2PError::is_task_rejected(&e) => {
+                    // The server is alive but busy and has dropped the task from
+                    // history - no reassignment will come, so resubmit via the leader
+                    return Err(e);
                 }
                 Err(e) => {
                     warn!(
                         "⚠️  {} Server failure detected for task #{} at {}: {}",
                         self.config.client.name, request_num, assigned_address, e
                     );
+                    report(
+                        progress,
+                        TaskProgress::ServerFailed {
+                            server_id: assigned_server_id,
+                            error: e.to_string(),
+                        },
+                    );
+
+                    let opened = self
+                        .breakers
+                        .lock()
+                        .unwrap()
+                        .record_failure(assigned_server_id, Instant::now());
+                    if opened {
+                        warn!(
+                            "🔌 {} Circuit breaker open for Server {} - not using it for {}s",
+                            self.config.client.name,
+                            assigned_server_id,
+                            self.config.failover.breaker_cooldown_secs
+                        );
+                    }
 
                     // Store the failed address
                     let failed_address = assigned_address.clone();
@@ -859,14 +1454,25 @@ impl ClientMiddleware {
                         .wait_for_reassignment(request_num, &failed_address)
                         .await
                     {
-                        Ok((new_server_id, new_address)) => {
+                        Ok((new_server_id, new_address, new_leader_id)) => {
                             // Got a new assignment - retry with this server
                             info!(
                                 "✅ {} Received assignment for task #{}: Server {} at {}",
                                 self.config.client.name, request_num, new_server_id, new_address
                             );
+                            report(
+                                progress,
+                                TaskProgress::Reassigned {
+                                    server_id: new_server_id,
+                                },
+                            );
+                            assigned_server_id = new_server_id;
                             assigned_address = new_address;
-                            leader_id = new_server_id;
+                            // Servers only take tasks assigned by their leader, so
+                            // keep naming the one that assigned it unless it changed
+                            if let Some(new_leader_id) = new_leader_id {
+                                leader_id = new_leader_id;
+                            }
                             // Continue loop to retry with new server
                         }
                         Err(reassignment_error) => {
@@ -884,6 +1490,231 @@ impl ClientMiddleware {
         }
     }
 
+    /// Whether `server_id`'s circuit breaker is currently open.
+    fn is_breaker_open(&self, server_id: u32) -> bool {
+        self.breakers
+            .lock()
+            .unwrap()
+            .is_open(server_id, Instant::now())
+    }
+
+    /// Executes a task in leader-routed mode.
+    ///
+    /// Sends the task to the leader `send_request` found, which forwards it to the
+    /// least-loaded server and relays the result. If the leader rate limits the
+    /// client, the task is sent again once allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_num` - Unique identifier for this request
+    /// * `leader_id` - ID of the current leader
+    /// * `leader_address` - Network address of the current leader
+    /// * `secret_image_data` - Binary data of the secret image to hide
+    /// * `progress` - Optional channel to report the leader the task is routed through to
+    ///
+    /// # Returns
+    ///
+    /// The carrier image, or the failure. Failures other than a rejection are reported
+    /// as a lost task, since the leader may have failed mid-task, so `send_request`
+    /// resubmits.
+    #[instrument(skip_all)]
+    async fn execute_routed_task(
+        &self,
+        request_num: u64,
+        leader_id: u32,
+        leader_address: &str,
+        secret_image_data: &[u8],
+        progress: Option<&ProgressSender>,
+    ) -> Result<CarrierImage> {
+        let failover = &self.config.failover;
+
+        loop {
+            info!(
+                "📡 {} Routing task #{} through leader {}",
+                self.config.client.name, request_num, leader_id
+            );
+            report(progress, TaskProgress::RoutingViaLeader { leader_id });
+
+            let result = self
+                .core
+                .send_via_leader(leader_address, request_num, secret_image_data)
+                .await;
+
+            // Over our rate limit: wait it out and send again
+            if let Err(e) = &result {
+                if e.is::<RateLimited>() {
+                    tokio::time::sleep(retry_delay(e, failover.poll_interval_secs)).await;
+                    continue;
+                }
+            }
+
+            return result.map_err(|e| {
+                if CloudP2PError::is_task_rejected(&e) {
+                    e
+                } else {
+                    CloudP2PError::TaskLost {
+                        request_id: request_num,
+                        reason: format!("leader {} failed mid-task: {}", leader_id, e),
+                    }
+                    .into()
+                }
+            });
+        }
+    }
+
+    /// Asks all servers who the current leader is.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((leader_id, leader_address))` - The first leader reported by any server
+    /// * `Err` - If no server knows a leader (e.g. an election is in progress)
+    async fn find_leader(&self) -> Result<(u32, String)> {
+        let connection_timeout = Duration::from_secs(self.config.failover.connection_timeout_secs);
+
+        let mut tasks = Vec::new();
+        for address in &self.config.client.server_addresses {
+            let address = address.clone();
+            tasks.push(tokio::spawn(async move {
+                tokio::time::timeout(connection_timeout, Self::query_leader(&address))
+                    .await
+                    .ok()?
+                    .ok()
+            }));
+        }
+
+        // Server IDs are 1-indexed positions in `server_addresses`
+        for task in tasks {
+            if let Ok(Some(leader_id)) = task.await {
+                let address = (leader_id as usize)
+                    .checked_sub(1)
+                    .and_then(|idx| self.config.client.server_addresses.get(idx));
+                if let Some(address) = address {
+                    return Ok((leader_id, address.clone()));
+                }
+            }
+        }
+
+        Err(CloudP2PError::NoLeader.into())
+    }
+
+    /// Helper method to ask a specific server for the current leader.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Server address to query
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(leader_id)` - The leader this server knows of
+    /// * `Err` - If connection failed or the server knows no leader
+    async fn query_leader(address: &str) -> Result<u32> {
+        let stream = Stream::connect(address).await?;
+        let mut conn = Connection::new(stream);
+        conn.handshake().await?;
+
+        conn.write_message(&Message::LeaderQuery).await?;
+
+        match conn.read_message().await? {
+            Some(Message::LeaderResponse { leader_id }) => Ok(leader_id),
+            _ => Err(CloudP2PError::UnexpectedResponse.into()),
+        }
+    }
+
+    /// Polls every configured server for its load and the leader it recognizes.
+    ///
+    /// Servers are queried in parallel, each bounded by `failover.connection_timeout_secs`;
+    /// one that doesn't answer is reported as unreachable. The leader is the one most
+    /// servers agree on (ties go to the lowest ID), so a stale server can't mislead the
+    /// dashboard.
+    ///
+    /// # Returns
+    ///
+    /// The leader (None during an election or if no server answered) and each
+    /// server's status, in `server_addresses` order
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// let status = middleware.cluster_status().await;
+    /// println!("{}", serde_json::to_string(&status)?);
+    /// ```
+    pub async fn cluster_status(&self) -> ClusterStatus {
+        let connection_timeout = Duration::from_secs(self.config.failover.connection_timeout_secs);
+
+        let mut tasks = Vec::new();
+        for address in &self.config.client.server_addresses {
+            let address = address.clone();
+            tasks.push(tokio::spawn(async move {
+                tokio::time::timeout(connection_timeout, Self::query_load(&address))
+                    .await
+                    .ok()?
+                    .ok()
+            }));
+        }
+
+        let mut servers = Vec::new();
+        let mut leader_votes: HashMap<u32, usize> = HashMap::new();
+        for (address, task) in self.config.client.server_addresses.iter().zip(tasks) {
+            let reply = task.await.ok().flatten();
+            if let Some((_, Some(leader_id))) = &reply {
+                *leader_votes.entry(*leader_id).or_insert(0) += 1;
+            }
+            servers.push(ServerStatus {
+                address: address.clone(),
+                reachable: reply.is_some(),
+                load: reply.map(|(load, _)| load),
+            });
+        }
+
+        let leader_id = leader_votes
+            .into_iter()
+            .max_by_key(|&(leader_id, votes)| (votes, std::cmp::Reverse(leader_id)))
+            .map(|(leader_id, _)| leader_id);
+
+        ClusterStatus { leader_id, servers }
+    }
+
+    /// Helper method to ask a specific server for its load.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - Server address to query
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((load, leader_id))` - The server's load and the leader it recognizes
+    /// * `Err` - If connection failed or the server sent something else
+    async fn query_load(address: &str) -> Result<(ServerLoad, Option<u32>)> {
+        let stream = Stream::connect(address).await?;
+        let mut conn = Connection::new(stream);
+        conn.handshake().await?;
+
+        conn.write_message(&Message::LoadQuery).await?;
+
+        match conn.read_message().await? {
+            Some(Message::LoadResponse {
+                server_id,
+                load,
+                cpu_usage,
+                active_tasks,
+                total_tasks,
+                saturated,
+                leader_id,
+            }) => Ok((
+                ServerLoad {
+                    server_id,
+                    load,
+                    cpu_usage,
+                    active_tasks,
+                    total_tasks,
+                    saturated,
+                },
+                leader_id,
+            )),
+            _ => Err(CloudP2PError::UnexpectedResponse.into()),
+        }
+    }
+
     /// Submits a task for web requests by calling send_request.
     ///
     /// This method wraps `send_request` to provide a simpler interface for web requests.
@@ -892,25 +1723,766 @@ impl ClientMiddleware {
     ///
     /// * `request_id` - Unique identifier for this request
     /// * `secret_image_data` - Binary data of the secret image to hide
+    /// * `progress` - Optional channel that receives a [`TaskProgress`] event for each
+    ///   step (assignment, failover, resubmission, completion); it is dropped when the
+    ///   task finishes, closing the receiver
     ///
     /// # Returns
     ///
-    /// * `Ok(Vec<u8>)` - The encrypted carrier image with embedded secret
+    /// * `Ok((server_id, CarrierImage))` - The server that processed the task and the
+    ///   encrypted carrier image with embedded secret, and where it was saved
     /// * `Err(anyhow::Error)` - If the task submission failed
     pub async fn submit_task(
         &mut self,
         request_id: u64,
         secret_image_data: Vec<u8>,
-    ) -> anyhow::Result<Vec<u8>> {
+        progress: Option<ProgressSender>,
+    ) -> anyhow::Result<(u32, CarrierImage)> {
         info!(
+            request_id = request_id,
             "🌐 Web request #{}: Submitting image ({} bytes)",
             request_id,
             secret_image_data.len()
         );
 
-        match self.send_request(request_id, secret_image_data).await {
-            Some(encrypted_image_data) => Ok(encrypted_image_data),
-            None => Err(anyhow::anyhow!("Task submission failed")),
+        self.send_request(request_id, secret_image_data, progress.as_ref())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_requires_images_in_image_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let image_dir = dir.path().to_string_lossy().into_owned();
+        let config = |image_dir: &str| -> ClientConfig {
+            toml::from_str(&format!(
+                r#"
+                [client]
+                name = "Client1"
+                server_addresses = ["127.0.0.1:8001"]
+                image_dir = '{}'
+
+                [requests]
+                total_requests = 1
+                min_delay_ms = 0
+                max_delay_ms = 0
+                "#,
+                image_dir
+            ))
+            .unwrap()
+        };
+
+        let missing = format!("{}/missing", image_dir);
+        let error = config(&missing).validate().unwrap_err().to_string();
+        assert!(error.contains("can't be read"), "{}", error);
+
+        // Files that aren't images don't count
+        std::fs::write(dir.path().join("notes.txt"), "not an image").unwrap();
+        let error = config(&image_dir).validate().unwrap_err().to_string();
+        assert!(error.contains("contains no .jpg"), "{}", error);
+        assert!(error.contains(&image_dir), "{}", error);
+
+        std::fs::write(dir.path().join("secret.png"), [0u8; 4]).unwrap();
+        assert!(config(&image_dir).validate().is_ok());
+        assert_eq!(list_images(&image_dir).unwrap(), vec!["secret.png"]);
+    }
+
+    #[test]
+    fn test_seeded_runs_pick_the_same_images_and_delays() {
+        let requests = |seed| RequestConfig {
+            total_requests: 20,
+            min_delay_ms: 100,
+            max_delay_ms: 2000,
+            max_inflight: 1,
+            seed,
+        };
+        let picks = |config: &RequestConfig| {
+            let mut rng = config.rng();
+            (0..config.total_requests)
+                .map(|_| {
+                    pick_image_and_delay(&mut rng, 5, config.min_delay_ms, config.max_delay_ms)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = picks(&requests(Some(42)));
+        assert_eq!(picks(&requests(Some(42))), first);
+        assert_ne!(picks(&requests(Some(43))), first);
+        for (image_index, delay) in &first {
+            assert!(*image_index < 5);
+            assert!((100..=2000).contains(&delay.as_millis()));
+        }
+    }
+
+    #[test]
+    fn test_failover_section_is_optional() {
+        let base = r#"
+            [client]
+            name = "Client1"
+            server_addresses = ["127.0.0.1:8001"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+        "#;
+
+        // Existing configs without the section keep the defaults
+        let config: ClientConfig = toml::from_str(base).unwrap();
+        assert_eq!(config.failover.poll_interval_secs, 2);
+        assert_eq!(config.failover.max_resubmissions, 5);
+
+        // A partial section only overrides what it names
+        let config: ClientConfig = toml::from_str(&format!(
+            "{}\n[failover]\nconnection_timeout_secs = 15\n",
+            base
+        ))
+        .unwrap();
+        assert_eq!(config.failover.connection_timeout_secs, 15);
+        assert_eq!(config.failover.max_same_server_polls, 10);
+    }
+
+    /// Start a server that accepts every task but fails it, and always reports
+    /// the task as still assigned to itself (Server 1).
+    ///
+    /// # Returns
+    /// The server's address and a counter of task requests it received
+    async fn flaky_server() -> (String, Arc<std::sync::atomic::AtomicU32>) {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let task_requests = Arc::new(AtomicU32::new(0));
+
+        let (own_address, counter) = (address.clone(), task_requests.clone());
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let mut conn = Connection::new(socket);
+                if conn.accept_handshake().await.is_err() {
+                    continue;
+                }
+                let reply = match conn.read_message().await {
+                    Ok(Some(Message::TaskRequest { request_id, .. })) => {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        Message::TaskResponse {
+                            request_id,
+                            encrypted_image_data: Vec::new(),
+                            success: false,
+                            error_message: Some("carrier unreadable".to_string()),
+                        }
+                    }
+                    Ok(Some(Message::TaskStatusQuery { request_id, .. })) => {
+                        Message::TaskStatusResponse {
+                            request_id,
+                            assigned_server_id: 1,
+                            assigned_server_address: own_address.clone(),
+                            leader_id: Some(1),
+                        }
+                    }
+                    _ => continue,
+                };
+                let _ = conn.write_message(&reply).await;
+            }
+        });
+
+        (address, task_requests)
+    }
+
+    /// Start a server that counts the `TaskAssignmentRequest`s it receives. As leader
+    /// it assigns every task to itself (Server `server_id`); otherwise it hangs up.
+    async fn assignment_server(
+        server_id: u32,
+        is_leader: bool,
+    ) -> (String, Arc<std::sync::atomic::AtomicU32>) {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let assignment_requests = Arc::new(AtomicU32::new(0));
+
+        let (own_address, counter) = (address.clone(), assignment_requests.clone());
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let mut conn = Connection::new(socket);
+                if conn.accept_handshake().await.is_err() {
+                    continue;
+                }
+                if let Ok(Some(Message::TaskAssignmentRequest { request_id, .. })) =
+                    conn.read_message().await
+                {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    if is_leader {
+                        let reply = Message::TaskAssignmentResponse {
+                            request_id,
+                            assigned_server_id: server_id,
+                            assigned_server_address: own_address.clone(),
+                        };
+                        let _ = conn.write_message(&reply).await;
+                    }
+                }
+            }
+        });
+
+        (address, assignment_requests)
+    }
+
+    #[tokio::test]
+    async fn test_assignments_go_straight_to_cached_leader() {
+        use std::sync::atomic::Ordering;
+
+        let (follower, follower_requests) = assignment_server(1, false).await;
+        let (leader, leader_requests) = assignment_server(2, true).await;
+        let config: ClientConfig = toml::from_str(&format!(
+            r#"
+            [client]
+            name = "Client1"
+            server_addresses = ["{}", "{}"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+
+            [failover]
+            connection_timeout_secs = 2
+            "#,
+            follower, leader
+        ))
+        .unwrap();
+        let middleware = ClientMiddleware::new(
+            config,
+            Arc::new(ClientCore::new("Client1".into(), "unused")),
+        );
+
+        // The first assignment is broadcast, the next ones only go to the leader
+        for request_num in 1..=3 {
+            let (assigned_server_id, _, leader_id) =
+                middleware.request_assignment(request_num).await.unwrap();
+            assert_eq!((assigned_server_id, leader_id), (2, 2));
+        }
+        assert_eq!(follower_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(leader_requests.load(Ordering::SeqCst), 3);
+
+        // A cached leader that stops answering is dropped in favor of a broadcast
+        *middleware.cached_leader.lock().unwrap() = Some((1, follower.clone()));
+        let (_, _, leader_id) = middleware.request_assignment(4).await.unwrap();
+        assert_eq!(leader_id, 2);
+        assert_eq!(follower_requests.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            *middleware.cached_leader.lock().unwrap(),
+            Some((2, leader.clone()))
+        );
+    }
+
+    /// A server that answers one `LoadQuery` as server `server_id` following `leader_id`.
+    async fn load_server(server_id: u32, leader_id: Option<u32>) -> String {
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut conn = Connection::new(socket);
+            conn.accept_handshake().await.unwrap();
+            if let Ok(Some(Message::LoadQuery)) = conn.read_message().await {
+                let reply = Message::LoadResponse {
+                    server_id,
+                    load: server_id as f64 * 10.0,
+                    cpu_usage: 5.0,
+                    active_tasks: server_id as u64,
+                    total_tasks: 7,
+                    saturated: false,
+                    leader_id,
+                };
+                conn.write_message(&reply).await.unwrap();
+            }
+        });
+
+        address
+    }
+
+    #[tokio::test]
+    async fn test_cluster_status_reports_leader_and_loads() {
+        let server_1 = load_server(1, Some(2)).await;
+        let server_2 = load_server(2, Some(2)).await;
+        // Nothing listens here once the listener is dropped
+        let down = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap().to_string()
+        };
+        let server_3 = load_server(3, Some(1)).await;
+
+        let config: ClientConfig = toml::from_str(&format!(
+            r#"
+            [client]
+            name = "Dashboard"
+            server_addresses = ["{}", "{}", "{}", "{}"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+
+            [failover]
+            connection_timeout_secs = 2
+            "#,
+            server_1, server_2, down, server_3
+        ))
+        .unwrap();
+        let core = Arc::new(ClientCore::new("Dashboard".into(), "unused"));
+        let status = ClientMiddleware::new(config, core).cluster_status().await;
+
+        // Two of the three reachable servers follow Server 2
+        assert_eq!(status.leader_id, Some(2));
+        assert_eq!(status.servers.len(), 4);
+        assert!(!status.servers[2].reachable && status.servers[2].load.is_none());
+        assert_eq!(status.servers[1].load.as_ref().unwrap().active_tasks, 2);
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["servers"][0]["server_id"], 1);
+        assert_eq!(json["servers"][0]["load"], 10.0);
+        assert_eq!(
+            json["servers"][2],
+            serde_json::json!({"address": down, "reachable": false})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_reports_server_failures() {
+        let (address, _) = flaky_server().await;
+        let config: ClientConfig = toml::from_str(&format!(
+            r#"
+            [client]
+            name = "Client1"
+            server_addresses = ["{}"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+
+            [failover]
+            poll_interval_secs = 0
+            max_consecutive_failures = 1
+            max_same_server_polls = 1
+            connection_timeout_secs = 2
+            breaker_failure_threshold = 1
+            "#,
+            address
+        ))
+        .unwrap();
+        let middleware = ClientMiddleware::new(
+            config,
+            Arc::new(ClientCore::new("Client1".into(), "unused")),
+        );
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+
+        let result = middleware
+            .execute_task(1, address, 1, 7, &[1, 2, 3], Some(&progress_tx))
+            .await;
+        assert!(result.is_err());
+
+        let event = progress_rx.try_recv().unwrap();
+        assert_eq!(
+            event,
+            TaskProgress::ServerFailed {
+                server_id: 1,
+                error: "Task failed on server: carrier unreadable".to_string(),
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&event).unwrap(),
+            serde_json::json!({
+                "event": "server_failed",
+                "server_id": 1,
+                "error": "Task failed on server: carrier unreadable",
+            })
+        );
+    }
+
+    /// Start Server 2, a follower of leader 1 past its leader-change grace period:
+    /// it only takes tasks naming Server 1 as their assigner, and reports every
+    /// task as assigned to itself.
+    async fn follower_of_leader_1(secret: Vec<u8>) -> String {
+        use crate::processing::steganography;
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let own_address = address.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let mut conn = Connection::new(socket);
+                if conn.accept_handshake().await.is_err() {
+                    continue;
+                }
+                let reply = match conn.read_message().await {
+                    Ok(Some(Message::TaskStatusQuery { request_id, .. })) => {
+                        Message::TaskStatusResponse {
+                            request_id,
+                            assigned_server_id: 2,
+                            assigned_server_address: own_address.clone(),
+                            leader_id: Some(1),
+                        }
+                    }
+                    Ok(Some(Message::TaskRequest {
+                        request_id,
+                        assigned_by_leader: 1,
+                        ..
+                    })) => {
+                        let mut blank = std::io::Cursor::new(Vec::new());
+                        image::RgbaImage::new(64, 64)
+                            .write_to(&mut blank, image::ImageFormat::Png)
+                            .unwrap();
+                        let carrier = steganography::embed_image_bytes(blank.get_ref(), &secret);
+                        Message::TaskResponse {
+                            request_id,
+                            encrypted_image_data: carrier.unwrap(),
+                            success: true,
+                            error_message: None,
+                        }
+                    }
+                    Ok(Some(Message::TaskRequest {
+                        request_id,
+                        assigned_by_leader,
+                        ..
+                    })) => Message::TaskRejected {
+                        request_id,
+                        reason: format!(
+                            "Task was assigned by Server {}, but the leader is Server 1",
+                            assigned_by_leader
+                        ),
+                    },
+                    _ => continue,
+                };
+                let _ = conn.write_message(&reply).await;
+                let _ = conn.read_message().await;
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_reassigned_task_names_the_leader_not_the_new_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret = vec![5u8; 100];
+        let follower = follower_of_leader_1(secret.clone()).await;
+
+        // Server 3, the task's original server, has failed
+        let dead = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead_address = dead.local_addr().unwrap().to_string();
+        drop(dead);
+
+        let config: ClientConfig = toml::from_str(&format!(
+            r#"
+            [client]
+            name = "Client1"
+            server_addresses = ["{}"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+
+            [failover]
+            poll_interval_secs = 0
+            connection_timeout_secs = 2
+            "#,
+            follower
+        ))
+        .unwrap();
+        let middleware = ClientMiddleware::new(
+            config,
+            Arc::new(ClientCore::new("Client1".into(), dir.path())),
+        );
+
+        let (server_id, _) = middleware
+            .execute_task(3, dead_address, 1, 7, &secret, None)
+            .await
+            .unwrap();
+        assert_eq!(server_id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_opens_on_flaky_server() {
+        let (address, task_requests) = flaky_server().await;
+        let config: ClientConfig = toml::from_str(&format!(
+            r#"
+            [client]
+            name = "Client1"
+            server_addresses = ["{}"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+
+            [failover]
+            poll_interval_secs = 0
+            max_consecutive_failures = 3
+            max_same_server_polls = 1
+            connection_timeout_secs = 2
+            breaker_failure_threshold = 2
+            "#,
+            address
+        ))
+        .unwrap();
+        let middleware = ClientMiddleware::new(
+            config,
+            Arc::new(ClientCore::new("Client1".into(), "unused")),
+        );
+
+        let result = middleware
+            .execute_task(1, address, 1, 7, &[1, 2, 3], None)
+            .await;
+
+        // Two failures open the breaker; after that the server is never retried
+        // and, with no other server to move to, the task is declared lost
+        let error = result.unwrap_err();
+        assert!(
+            CloudP2PError::is_task_lost(&error),
+            "unexpected error: {}",
+            error
+        );
+        assert_eq!(task_requests.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(middleware.is_breaker_open(1));
+    }
+
+    #[test]
+    fn test_only_lost_or_rejected_tasks_are_resubmitted() {
+        let lost = CloudP2PError::TaskLost {
+            request_id: 1,
+            reason: "all servers failed".to_string(),
+        };
+        let rejected = CloudP2PError::TaskRejected {
+            request_id: 1,
+            reason: "at capacity".to_string(),
+        };
+        assert!(is_resubmittable(&lost.into()));
+        assert!(is_resubmittable(&rejected.into()));
+
+        let failed = CloudP2PError::EncryptionFailed {
+            request_id: 1,
+            reason: "carrier unreadable".to_string(),
+        };
+        assert!(!is_resubmittable(&failed.into()));
+        assert!(!is_resubmittable(&anyhow::anyhow!(
+            "Task #1 lost - all servers failed after 3 consecutive polling failures"
+        )));
+    }
+
+    /// Start a leader (Server 1) that assigns every task to itself, hangs up on the
+    /// first `lost` task requests and answers no status queries, so each of those
+    /// tasks is lost, then completes the task requests after them.
+    ///
+    /// # Returns
+    /// The server's address and counters of assignment and task requests received
+    async fn server_losing_tasks(
+        secret: Vec<u8>,
+        lost: u32,
+    ) -> (
+        String,
+        Arc<std::sync::atomic::AtomicU32>,
+        Arc<std::sync::atomic::AtomicU32>,
+    ) {
+        use crate::processing::steganography;
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let assignment_requests = Arc::new(AtomicU32::new(0));
+        let task_requests = Arc::new(AtomicU32::new(0));
+
+        let own_address = address.clone();
+        let (assignments, tasks) = (assignment_requests.clone(), task_requests.clone());
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let mut conn = Connection::new(socket);
+                if conn.accept_handshake().await.is_err() {
+                    continue;
+                }
+                let reply = match conn.read_message().await {
+                    Ok(Some(Message::TaskAssignmentRequest { request_id, .. })) => {
+                        assignments.fetch_add(1, Ordering::SeqCst);
+                        Message::TaskAssignmentResponse {
+                            request_id,
+                            assigned_server_id: 1,
+                            assigned_server_address: own_address.clone(),
+                        }
+                    }
+                    Ok(Some(Message::TaskRequest { request_id, .. }))
+                        if tasks.fetch_add(1, Ordering::SeqCst) >= lost =>
+                    {
+                        let mut blank = std::io::Cursor::new(Vec::new());
+                        image::RgbaImage::new(64, 64)
+                            .write_to(&mut blank, image::ImageFormat::Png)
+                            .unwrap();
+                        let carrier = steganography::embed_image_bytes(blank.get_ref(), &secret);
+                        Message::TaskResponse {
+                            request_id,
+                            encrypted_image_data: carrier.unwrap(),
+                            success: true,
+                            error_message: None,
+                        }
+                    }
+                    _ => continue,
+                };
+                let _ = conn.write_message(&reply).await;
+                let _ = conn.read_message().await;
+            }
+        });
+
+        (address, assignment_requests, task_requests)
+    }
+
+    #[tokio::test]
+    async fn test_successful_request_is_sent_once() {
+        use std::sync::atomic::Ordering;
+
+        let dir = tempfile::tempdir().unwrap();
+        let secret = vec![3u8; 100];
+        let (address, assignment_requests, task_requests) =
+            server_losing_tasks(secret.clone(), 0).await;
+        let config: ClientConfig = toml::from_str(&format!(
+            r#"
+            [client]
+            name = "Client1"
+            server_addresses = ["{}"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+            "#,
+            address
+        ))
+        .unwrap();
+        let middleware = ClientMiddleware::new(
+            config,
+            Arc::new(ClientCore::new("Client1".into(), dir.path())),
+        );
+
+        let (server_id, _) = middleware.send_request(4, secret, None).await.unwrap();
+        assert_eq!(server_id, 1);
+
+        // The request returns on success instead of going round again
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(assignment_requests.load(Ordering::SeqCst), 1);
+        assert_eq!(task_requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_lost_task_is_resubmitted_exactly_once() {
+        use std::sync::atomic::Ordering;
+
+        let dir = tempfile::tempdir().unwrap();
+        let secret = vec![7u8; 100];
+        let (address, assignment_requests, task_requests) =
+            server_losing_tasks(secret.clone(), 1).await;
+        let config: ClientConfig = toml::from_str(&format!(
+            r#"
+            [client]
+            name = "Client1"
+            server_addresses = ["{}"]
+
+            [requests]
+            total_requests = 1
+            min_delay_ms = 0
+            max_delay_ms = 0
+
+            [failover]
+            poll_interval_secs = 0
+            max_consecutive_failures = 1
+            max_resubmissions = 3
+            "#,
+            address
+        ))
+        .unwrap();
+        let middleware = ClientMiddleware::new(
+            config,
+            Arc::new(ClientCore::new("Client1".into(), dir.path())),
+        );
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+
+        let (server_id, carrier) = middleware
+            .send_request(9, secret, Some(&progress_tx))
+            .await
+            .expect("resubmitted task should complete");
+        assert_eq!(server_id, 1);
+        assert_eq!(carrier.path, dir.path().join("Client1_9.png"));
+
+        // One loss, one resubmission: two assignments and two task requests
+        assert_eq!(assignment_requests.load(Ordering::SeqCst), 2);
+        assert_eq!(task_requests.load(Ordering::SeqCst), 2);
+        drop(progress_tx);
+        let mut resubmissions = 0;
+        while let Some(event) = progress_rx.recv().await {
+            if let TaskProgress::Resubmitting { attempt, .. } = event {
+                resubmissions += 1;
+                assert_eq!(attempt, 1);
+            }
+        }
+        assert_eq!(resubmissions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_assignment_wait_gives_up_after_timeout() {
+        // Nothing listens here, so no leader ever comes forward
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+
+        for route_via_leader in [false, true] {
+            let config: ClientConfig = toml::from_str(&format!(
+                r#"
+                [client]
+                name = "Client1"
+                server_addresses = ["{}"]
+                route_via_leader = {}
+
+                [requests]
+                total_requests = 1
+                min_delay_ms = 0
+                max_delay_ms = 0
+
+                [failover]
+                poll_interval_secs = 1
+                assignment_timeout_secs = 1
+                "#,
+                address, route_via_leader
+            ))
+            .unwrap();
+            let middleware = ClientMiddleware::new(
+                config,
+                Arc::new(ClientCore::new("Client1".into(), "unused")),
+            );
+
+            let started = Instant::now();
+            let error = tokio::time::timeout(
+                Duration::from_secs(5),
+                middleware.send_request(1, vec![1, 2, 3], None),
+            )
+            .await
+            .expect("send_request should give up within its deadline")
+            .unwrap_err();
+            assert!(started.elapsed() < Duration::from_secs(3));
+            assert_eq!(
+                error.downcast_ref::<CloudP2PError>(),
+                Some(&CloudP2PError::AssignmentTimeout {
+                    request_id: 1,
+                    timeout: Duration::from_secs(1),
+                })
+            );
         }
     }
 }
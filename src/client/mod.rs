@@ -13,8 +13,11 @@
 //! - Retry logic (3 attempts with timeouts)
 //! - Server assignment request handling
 //! - Failover on server failure
+//! - Circuit breaking of repeatedly failing servers ([`circuit_breaker`])
 //! - Connection management
 
+pub mod circuit_breaker;
+#[allow(clippy::module_inception)]
 pub mod client;
 pub mod middleware;
 pub mod metrics;
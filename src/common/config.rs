@@ -29,6 +29,103 @@ where
     Ok(config)
 }
 
+/// Prefix of the environment variables that override configuration values.
+pub const ENV_PREFIX: &str = "CLOUDP2P_";
+
+/// Load a TOML configuration file, then apply `CLOUDP2P_*` environment overrides.
+///
+/// `CLOUDP2P_<SECTION>_<FIELD>` sets `field` in the file's `[section]` table, so one
+/// file can be shared by every replica of a container. For example:
+/// - `CLOUDP2P_SERVER_ID=2` sets `server.id`
+/// - `CLOUDP2P_SERVER_ADDRESS=0.0.0.0:8002` sets `server.address`
+/// - `CLOUDP2P_ELECTION_FAILURE_TIMEOUT_SECS=10` sets `election.failure_timeout_secs`
+/// - `CLOUDP2P_CLIENT_NAME=Client7` sets `client.name`
+///
+/// Only top-level scalar fields of sections present in the file can be overridden.
+/// A field already in the file keeps its type (integer, float, boolean or string);
+/// a field left to its default is parsed as an integer, float or boolean if it
+/// looks like one, otherwise as a string.
+///
+/// # Arguments
+/// - `path`: Path to the TOML configuration file
+///
+/// # Returns
+/// - `Ok(T)`: The configuration, with overrides applied
+/// - `Err`: File I/O or parsing error, or an override that doesn't fit its field
+///
+/// # Example
+/// ```ignore
+/// // CLOUDP2P_SERVER_ID=3 CLOUDP2P_SERVER_ADDRESS=0.0.0.0:8003 cargo run --bin server ...
+/// let config: ServerConfig = load_config_with_env("config/server1.toml")?;
+/// ```
+pub fn load_config_with_env<T>(path: &str) -> Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let content = fs::read_to_string(path)?;
+    let mut table: toml::Table = toml::from_str(&content)?;
+    apply_env_overrides(&mut table, std::env::vars())?;
+    Ok(toml::Value::Table(table).try_into()?)
+}
+
+/// Apply the `CLOUDP2P_*` variables among `vars` onto a parsed configuration file.
+fn apply_env_overrides<I>(table: &mut toml::Table, vars: I) -> Result<()>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    for (name, raw) in vars {
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let key = key.to_lowercase();
+        let Some((section, field)) = key.split_once('_') else {
+            continue;
+        };
+        let Some(toml::Value::Table(section)) = table.get_mut(section) else {
+            continue;
+        };
+
+        let value = match section.get(field) {
+            Some(existing) => parse_like(existing, &raw).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{}={:?} is not a valid {} for '{}'",
+                    name,
+                    raw,
+                    existing.type_str(),
+                    key
+                )
+            })?,
+            None => infer_value(&raw),
+        };
+        section.insert(field.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Parse `raw` as the same TOML type as `existing` (scalars only).
+fn parse_like(existing: &toml::Value, raw: &str) -> Option<toml::Value> {
+    match existing {
+        toml::Value::String(_) => Some(toml::Value::String(raw.to_string())),
+        toml::Value::Integer(_) => raw.parse().ok().map(toml::Value::Integer),
+        toml::Value::Float(_) => raw.parse().ok().map(toml::Value::Float),
+        toml::Value::Boolean(_) => raw.parse().ok().map(toml::Value::Boolean),
+        _ => None,
+    }
+}
+
+/// Guess the TOML type of an override for a field the file doesn't set.
+fn infer_value(raw: &str) -> toml::Value {
+    if let Ok(integer) = raw.parse() {
+        toml::Value::Integer(integer)
+    } else if let Ok(float) = raw.parse() {
+        toml::Value::Float(float)
+    } else if let Ok(boolean) = raw.parse() {
+        toml::Value::Boolean(boolean)
+    } else {
+        toml::Value::String(raw.to_string())
+    }
+}
+
 /// Information about a peer server in the distributed system.
 ///
 /// Used to configure how servers connect to each other for leader election
@@ -55,10 +152,273 @@ pub struct PeersConfig {
 pub struct ElectionConfig {
     /// How often to send heartbeat messages (seconds)
     pub heartbeat_interval_secs: u64,
+    /// Random spread applied to each heartbeat interval, as a fraction of it
+    /// (default 0.15, i.e. ±15%), so servers' heartbeats don't align
+    #[serde(default = "default_heartbeat_jitter")]
+    pub heartbeat_jitter: f64,
     /// How long to wait for responses during an election (seconds)
     pub election_timeout_secs: u64,
+    /// How long after startup to start the first election if no leader is known
+    /// by then (milliseconds, default 3000); a random 100-500ms is added so
+    /// servers started together don't all stand at once
+    #[serde(default = "default_initial_election_delay_ms")]
+    pub initial_election_delay_ms: u64,
     /// How long before a peer is considered failed (seconds)
     pub failure_timeout_secs: u64,
     /// How often to check for failed peers (seconds)
     pub monitor_interval_secs: u64,
+    /// Optional phi-accrual suspicion level (e.g. 8.0) above which a peer is
+    /// considered failed; when set it replaces `failure_timeout_secs` for peers
+    /// that have sent heartbeats
+    #[serde(default)]
+    pub phi_threshold: Option<f64>,
+    /// Ask the peers whether the leader is really gone before starting an
+    /// election (default false), so a server cut off from the cluster doesn't
+    /// disrupt it with a newer term when it rejoins
+    #[serde(default)]
+    pub pre_vote: bool,
+    /// Only declare victory in an election while a majority of the cluster (us
+    /// plus the configured peers) is reachable, counting the peers heard from
+    /// within `failure_timeout_secs` (default false), so a server in a minority
+    /// partition stays leaderless
+    #[serde(default)]
+    pub require_quorum: bool,
+    /// Weights of the load metrics in the election priority score
+    #[serde(default)]
+    pub priority_weights: PriorityWeights,
+    /// Active task count that counts as full load in the priority score
+    /// (default 10); raise it on machines that run many encryptions in parallel
+    #[serde(default = "default_priority_max_concurrent_tasks")]
+    pub max_concurrent_tasks: u64,
+}
+
+fn default_heartbeat_jitter() -> f64 {
+    0.15
+}
+
+fn default_initial_election_delay_ms() -> u64 {
+    3000
+}
+
+fn default_priority_max_concurrent_tasks() -> u64 {
+    10
+}
+
+/// Weights of each load metric in the election priority score.
+///
+/// Configured under `[election.priority_weights]`; omitted weights keep their
+/// defaults (CPU 0.5, tasks 0.3, memory 0.2, disk 0.0). They must sum to 1.0 so
+/// the score stays on a 0-100 scale.
+///
+/// # Example
+/// ```toml
+/// [election.priority_weights]
+/// cpu = 0.3
+/// tasks = 0.2
+/// memory = 0.3
+/// disk = 0.2
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriorityWeights {
+    /// Weight of CPU usage
+    pub cpu: f64,
+    /// Weight of the normalized active task count
+    pub tasks: f64,
+    /// Weight of memory in use
+    pub memory: f64,
+    /// Weight of disk space in use (on the disk holding the working directory)
+    pub disk: f64,
+}
+
+impl Default for PriorityWeights {
+    fn default() -> Self {
+        Self {
+            cpu: 0.5,
+            tasks: 0.3,
+            memory: 0.2,
+            disk: 0.0,
+        }
+    }
+}
+
+impl PriorityWeights {
+    /// Largest allowed difference between the weights' sum and 1.0.
+    const SUM_TOLERANCE: f64 = 0.01;
+
+    /// Check that every weight is non-negative and that they sum to ~1.0.
+    ///
+    /// # Returns
+    /// - `Ok(())`: The weights are usable
+    /// - `Err`: A weight is negative or not finite, or the sum is off by more than 0.01
+    pub fn validate(&self) -> Result<()> {
+        for (name, weight) in [
+            ("cpu", self.cpu),
+            ("tasks", self.tasks),
+            ("memory", self.memory),
+            ("disk", self.disk),
+        ] {
+            if !weight.is_finite() || weight < 0.0 {
+                anyhow::bail!(
+                    "Priority weight '{}' must be a non-negative number, got {}",
+                    name,
+                    weight
+                );
+            }
+        }
+
+        let sum = self.cpu + self.tasks + self.memory + self.disk;
+        if (sum - 1.0).abs() > Self::SUM_TOLERANCE {
+            anyhow::bail!("Priority weights must sum to 1.0, got {:.3}", sum);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER_TOML: &str = r#"
+        [server]
+        id = 1
+        address = "127.0.0.1:8001"
+
+        [election]
+        heartbeat_interval_secs = 1
+        election_timeout_secs = 2
+        failure_timeout_secs = 5
+        monitor_interval_secs = 1
+    "#;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_overrides_keep_field_types() {
+        let mut table: toml::Table = toml::from_str(SERVER_TOML).unwrap();
+        apply_env_overrides(
+            &mut table,
+            vars(&[
+                ("CLOUDP2P_SERVER_ID", "3"),
+                ("CLOUDP2P_SERVER_ADDRESS", "0.0.0.0:8003"),
+                ("CLOUDP2P_SERVER_MAX_CONCURRENT_TASKS", "2"),
+                ("CLOUDP2P_ELECTION_HEARTBEAT_JITTER", "0.5"),
+                ("CLOUDP2P_PEERS_LIST", "ignored: no [peers] section"),
+                ("HOME", "/root"),
+            ]),
+        )
+        .unwrap();
+
+        let server = &table["server"];
+        assert_eq!(server["id"].as_integer(), Some(3));
+        assert_eq!(server["address"].as_str(), Some("0.0.0.0:8003"));
+        assert_eq!(server["max_concurrent_tasks"].as_integer(), Some(2));
+        assert_eq!(table["election"]["heartbeat_jitter"].as_float(), Some(0.5));
+        assert!(!table.contains_key("peers"));
+
+        // A value that doesn't fit the file's type is an error naming the variable
+        let error = apply_env_overrides(&mut table, vars(&[("CLOUDP2P_SERVER_ID", "two")]))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("CLOUDP2P_SERVER_ID"), "{}", error);
+        assert!(error.contains("integer"), "{}", error);
+    }
+
+    #[test]
+    fn test_env_vars_win_over_file_values() {
+        #[derive(Deserialize)]
+        struct Config {
+            server: Server,
+        }
+        #[derive(Deserialize)]
+        struct Server {
+            id: u32,
+            address: String,
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server.toml");
+        fs::write(&path, SERVER_TOML).unwrap();
+
+        std::env::set_var("CLOUDP2P_SERVER_ID", "7");
+        std::env::set_var("CLOUDP2P_SERVER_ADDRESS", "10.0.0.7:9007");
+        let config: Config = load_config_with_env(path.to_str().unwrap()).unwrap();
+        std::env::remove_var("CLOUDP2P_SERVER_ID");
+        std::env::remove_var("CLOUDP2P_SERVER_ADDRESS");
+
+        assert_eq!(config.server.id, 7);
+        assert_eq!(config.server.address, "10.0.0.7:9007");
+
+        // Without the variables the file's values are used
+        let config: Config = load_config(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            (config.server.id, config.server.address.as_str()),
+            (1, "127.0.0.1:8001")
+        );
+    }
+
+    #[test]
+    fn test_priority_weights_validation() {
+        assert!(PriorityWeights::default().validate().is_ok());
+
+        let memory_heavy = PriorityWeights {
+            cpu: 0.3,
+            tasks: 0.2,
+            memory: 0.5,
+            disk: 0.0,
+        };
+        assert!(memory_heavy.validate().is_ok());
+
+        let unnormalized = PriorityWeights {
+            memory: 0.6,
+            ..memory_heavy
+        };
+        assert!(unnormalized.validate().is_err());
+
+        let with_disk = PriorityWeights {
+            memory: 0.3,
+            disk: 0.2,
+            ..memory_heavy
+        };
+        assert!(with_disk.validate().is_ok());
+
+        let negative = PriorityWeights {
+            cpu: 1.2,
+            tasks: -0.2,
+            memory: 0.0,
+            disk: 0.0,
+        };
+        assert!(negative.validate().is_err());
+    }
+
+    #[test]
+    fn test_priority_weights_default_when_omitted() {
+        let config: ElectionConfig = toml::from_str(
+            r#"
+            heartbeat_interval_secs = 1
+            election_timeout_secs = 2
+            failure_timeout_secs = 5
+            monitor_interval_secs = 1
+
+            [priority_weights]
+            memory = 0.4
+            cpu = 0.3
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.priority_weights,
+            PriorityWeights {
+                cpu: 0.3,
+                tasks: 0.3,
+                memory: 0.4,
+                disk: 0.0,
+            }
+        );
+    }
 }
@@ -2,64 +2,253 @@
 //!
 //! Provides a wrapper around TCP streams with message framing for the CloudP2P protocol.
 //!
+//! [`Connection`] works over any async byte stream, defaulting to [`TcpStream`].
+//! Anything else that is [`AsyncRead`] + [`AsyncWrite`] also works, e.g. a Unix
+//! domain socket, a TLS stream, or an in-memory [`tokio::io::duplex`] pipe in tests.
+//!
 //! ## Wire Protocol
 //!
-//! Messages are sent with a 4-byte length prefix (big-endian) followed by JSON data:
+//! Messages are sent with a 4-byte length prefix (big-endian), a one-byte
+//! [`WireFormat`] tag, and the encoded message:
+//! ```text
+//! [4 bytes: length of tag + data] [1 byte: format tag] [N bytes: message data]
+//! ```
+//!
+//! Control messages are always JSON; image-bearing messages use bincode when the
+//! `bincode` feature is enabled (see [`Message::preferred_format`]).
+//!
+//! ### Image Payloads
+//!
+//! An image-bearing message is sent in two parts: the message with an empty image
+//! (the header) and then the image bytes themselves, with [`PAYLOAD_FLAG`] set in
+//! the format tag:
 //! ```text
-//! [4 bytes: message length] [N bytes: JSON message data]
+//! [4 bytes: length] [1 byte: format tag] [4 bytes: header length] [header] [image]
 //! ```
+//! The writer sends the image straight from the message and the reader reads it
+//! straight into the message's buffer, so a multi-megabyte image is held in memory
+//! once instead of twice (as a frame and again once decoded).
+//!
+//! ### Compression
+//!
+//! A connection built with [`Connection::with_compression_threshold`] deflates any
+//! encoded message at least that many bytes long and sets [`COMPRESSED_FLAG`] in the
+//! format tag. Reading always honours the flag, so only the sender opts in, and small
+//! election/heartbeat messages stay uncompressed. An image that reaches the threshold
+//! is compressed as part of a whole encoded message rather than sent as a payload.
 //!
 //! This length-prefixed protocol allows for:
 //! - Variable-length messages (images can be large)
 //! - Reliable message boundaries over TCP streams
 //! - Protection against incomplete reads
+//!
+//! ## Timeouts
+//!
+//! A connection built with [`Connection::with_timeouts`] bounds every read and write,
+//! failing with a [`ConnectionError`] instead of hanging on a half-open TCP connection
+//! to a dead peer.
+//!
+//! ## Keepalive
+//!
+//! [`Connection::ping`] sends a `Ping` and waits a bounded time for the `Pong`, so
+//! an idle connection whose peer has died or gone half-open is found out promptly
+//! instead of on the next request. Servers answer pings on every connection.
+//!
+//! ## Handshake
+//!
+//! Before any other message, the connecting side calls [`Connection::handshake`] and
+//! the accepting side calls [`Connection::accept_handshake`]. Each side sends a
+//! `Hello` carrying its [`PROTOCOL_VERSION`] and refuses the connection if the
+//! versions differ.
 
 use anyhow::Result;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use log::error;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use std::fmt;
+use std::io::{Read, Write};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-use super::messages::Message;
+use super::messages::{Message, WireFormat, PROTOCOL_VERSION};
+
+/// Default maximum message size (100MB) to prevent memory exhaustion attacks.
+///
+/// Override per connection with [`Connection::with_max_message_size`].
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 100 * 1024 * 1024;
+
+/// Bit set in the format tag when the message body is deflate-compressed.
+pub const COMPRESSED_FLAG: u8 = 0x80;
+
+/// Bit set in the format tag when the body is a header followed by the message's
+/// image (see [Image Payloads](self#image-payloads)).
+pub const PAYLOAD_FLAG: u8 = 0x40;
+
+/// Errors specific to a [`Connection`], as opposed to plain I/O failures.
+///
+/// Returned wrapped in an [`anyhow::Error`]; use `downcast_ref::<ConnectionError>()`
+/// to tell a timed-out peer apart from a refused or reset connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionError {
+    /// No complete message arrived within the read timeout.
+    ReadTimeout(Duration),
+    /// The peer did not accept a message within the write timeout.
+    WriteTimeout(Duration),
+    /// No `Pong` answered a [`Connection::ping`] within its timeout.
+    PingTimeout(Duration),
+    /// An incoming frame (or its decompressed body) exceeds the connection's
+    /// maximum message size.
+    MessageTooLarge { size: usize, max: usize },
+    /// A complete frame arrived but couldn't be decoded into a message. The frame
+    /// has been consumed, so the connection can go on reading the next one.
+    MalformedMessage(String),
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionError::ReadTimeout(timeout) => {
+                write!(f, "Timed out after {:?} waiting for a message", timeout)
+            }
+            ConnectionError::WriteTimeout(timeout) => {
+                write!(f, "Timed out after {:?} sending a message", timeout)
+            }
+            ConnectionError::PingTimeout(timeout) => {
+                write!(f, "No answer to ping within {:?}", timeout)
+            }
+            ConnectionError::MessageTooLarge { size, max } => {
+                write!(f, "Message too large: {} bytes (max: {} bytes)", size, max)
+            }
+            ConnectionError::MalformedMessage(reason) => {
+                write!(f, "Malformed message: {}", reason)
+            }
+        }
+    }
+}
 
-/// Maximum allowed message size (100MB) to prevent memory exhaustion attacks.
-const MAX_MESSAGE_SIZE: usize = 100 * 1024 * 1024;
+impl std::error::Error for ConnectionError {}
 
 /// TCP connection wrapper with message framing support.
 ///
 /// Handles serialization, deserialization, and length-prefixed framing of messages
-/// over a TCP stream.
-pub struct Connection {
-    /// Underlying TCP stream
-    stream: TcpStream,
+/// over a TCP stream, or any other async stream `S`.
+pub struct Connection<S = TcpStream> {
+    /// Underlying stream
+    stream: S,
+    /// Compress outgoing messages whose encoded size is at least this many bytes
+    /// (`None` = never compress)
+    compression_threshold: Option<usize>,
+    /// Maximum time to wait for a complete incoming message (`None` = wait forever)
+    read_timeout: Option<Duration>,
+    /// Maximum time to spend sending a message (`None` = wait forever)
+    write_timeout: Option<Duration>,
+    /// Largest incoming message accepted, in bytes
+    max_message_size: usize,
+    /// Nonce of the last `Ping` sent
+    last_ping: u64,
 }
 
-impl Connection {
-    /// Create a new Connection from an existing TCP stream.
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    /// Create a new Connection from an existing stream.
     ///
     /// # Arguments
-    /// - `stream`: An established TCP connection
+    /// - `stream`: An established TCP connection (or other async stream)
     ///
     /// # Example
     /// ```ignore
     /// let stream = TcpStream::connect("127.0.0.1:8001").await?;
     /// let mut conn = Connection::new(stream);
+    ///
+    /// // In tests, without a socket
+    /// let (a, b) = tokio::io::duplex(64 * 1024);
+    /// let (mut client, mut server) = (Connection::new(a), Connection::new(b));
+    /// ```
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            compression_threshold: None,
+            read_timeout: None,
+            write_timeout: None,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            last_ping: 0,
+        }
+    }
+
+    /// Set the largest incoming message this connection accepts, in bytes.
+    ///
+    /// Larger frames are rejected with [`ConnectionError::MessageTooLarge`] before
+    /// their body is read. The default is [`DEFAULT_MAX_MESSAGE_SIZE`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut conn = Connection::new(stream).with_max_message_size(config.max_message_size);
+    /// ```
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Create a Connection whose reads and writes fail after the given timeouts.
+    ///
+    /// A timed-out operation returns [`ConnectionError::ReadTimeout`] or
+    /// [`ConnectionError::WriteTimeout`]. The read timeout covers the whole message,
+    /// so it must also allow for the peer's processing time before it replies.
+    ///
+    /// # Arguments
+    /// - `stream`: An established TCP connection
+    /// - `read_timeout`: Maximum time [`read_message`](Self::read_message) waits
+    /// - `write_timeout`: Maximum time [`write_message`](Self::write_message) takes
+    ///
+    /// # Example
+    /// ```ignore
+    /// let stream = TcpStream::connect("127.0.0.1:8001").await?;
+    /// let mut conn = Connection::with_timeouts(
+    ///     stream,
+    ///     Duration::from_secs(30),
+    ///     Duration::from_secs(10),
+    /// );
+    /// ```
+    pub fn with_timeouts(stream: S, read_timeout: Duration, write_timeout: Duration) -> Self {
+        Self {
+            read_timeout: Some(read_timeout),
+            write_timeout: Some(write_timeout),
+            ..Self::new(stream)
+        }
+    }
+
+    /// Enable compression for outgoing messages of at least `threshold` bytes.
+    ///
+    /// Compressed messages are only sent when deflating actually shrinks them.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Compress image messages, leave heartbeats alone
+    /// let mut conn = Connection::new(stream).with_compression_threshold(64 * 1024);
     /// ```
-    pub fn new(stream: TcpStream) -> Self {
-        Self { stream }
+    pub fn with_compression_threshold(mut self, threshold: usize) -> Self {
+        self.compression_threshold = Some(threshold);
+        self
     }
 
     /// Read a message from the connection.
     ///
     /// # Returns
     /// - `Ok(Some(Message))`: Successfully read and deserialized a message
-    /// - `Ok(None)`: Connection closed cleanly or message deserialization failed
-    /// - `Err`: I/O error occurred
+    /// - `Ok(None)`: Connection closed cleanly
+    /// - `Err`: I/O error occurred, [`ConnectionError::ReadTimeout`] expired, the
+    ///   frame exceeded the maximum message size ([`ConnectionError::MessageTooLarge`]),
+    ///   or it couldn't be decoded ([`ConnectionError::MalformedMessage`]). After a
+    ///   malformed message the connection is still usable; after the others it isn't.
     ///
     /// # Protocol
     /// 1. Reads 4-byte length prefix (big-endian u32)
-    /// 2. Validates message size (max 50MB)
-    /// 3. Reads message data of specified length
-    /// 4. Deserializes JSON to Message enum
+    /// 2. Validates message size (default max 100MB)
+    /// 3. Reads the format tag and message data of specified length; with
+    ///    [`PAYLOAD_FLAG`] set, the header and then the image into its own buffer
+    /// 4. Inflates the data if the tag has [`COMPRESSED_FLAG`] set
+    /// 5. Deserializes the data (JSON or bincode) to Message enum
     ///
     /// # Example
     /// ```ignore
@@ -72,6 +261,16 @@ impl Connection {
     /// }
     /// ```
     pub async fn read_message(&mut self) -> Result<Option<Message>> {
+        match self.read_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.read_frame())
+                .await
+                .map_err(|_| ConnectionError::ReadTimeout(timeout))?,
+            None => self.read_frame().await,
+        }
+    }
+
+    /// Read and decode one frame, without any timeout.
+    async fn read_frame(&mut self) -> Result<Option<Message>> {
         // First, read 4-byte length prefix that tells us the message size
         let mut length_buf = [0u8; 4];
 
@@ -79,32 +278,97 @@ impl Connection {
             Ok(_) => {
                 let length = u32::from_be_bytes(length_buf) as usize;
 
-                // Sanity check: reject messages larger than MAX_MESSAGE_SIZE
-                if length > MAX_MESSAGE_SIZE {
+                // Sanity check: reject messages larger than the configured maximum
+                if length > self.max_message_size {
                     error!(
                         "❌ Message too large: {} bytes (max: {} bytes)",
-                        length, MAX_MESSAGE_SIZE
+                        length, self.max_message_size
                     );
-                    return Ok(None);
+                    return Err(ConnectionError::MessageTooLarge {
+                        size: length,
+                        max: self.max_message_size,
+                    }
+                    .into());
+                }
+                if length == 0 {
+                    return Err(ConnectionError::MalformedMessage(
+                        "empty frame (missing format tag)".to_string(),
+                    )
+                    .into());
                 }
 
-                // Now read the actual message data
-                let mut data = vec![0u8; length];
-                self.stream.read_exact(&mut data).await?;
+                // Now read the format tag and the actual message data
+                let mut tag = [0u8; 1];
+                self.stream.read_exact(&mut tag).await?;
+                let decoded = if tag[0] & PAYLOAD_FLAG != 0 {
+                    self.read_payload_frame(tag[0] & !PAYLOAD_FLAG, length - 1)
+                        .await?
+                } else {
+                    let mut data = vec![0u8; length];
+                    data[0] = tag[0];
+                    self.stream.read_exact(&mut data[1..]).await?;
+                    decode_frame(&data, self.max_message_size)
+                };
 
                 // Deserialize bytes into a Message enum
-                match Message::from_bytes(&data) {
+                match decoded {
                     Ok(msg) => Ok(Some(msg)),
-                    Err(e) => {
-                        error!("❌ Failed to deserialize message: {}", e);
-                        Ok(None)
-                    }
+                    Err(e) if e.downcast_ref::<ConnectionError>().is_some() => Err(e),
+                    Err(e) => Err(ConnectionError::MalformedMessage(e.to_string()).into()),
                 }
             }
             Err(_) => Ok(None), // Connection closed cleanly
         }
     }
 
+    /// Read the `body_len` bytes of a [`PAYLOAD_FLAG`] frame: the header, then the
+    /// image straight into the buffer the decoded message keeps.
+    ///
+    /// # Returns
+    /// - `Ok(decoded)`: The whole frame was consumed; `decoded` is the message, or
+    ///   why it couldn't be decoded
+    /// - `Err`: I/O error, after which the connection is unusable
+    async fn read_payload_frame(&mut self, tag: u8, body_len: usize) -> Result<Result<Message>> {
+        let mut header_len = [0u8; 4];
+        if body_len < header_len.len() {
+            self.skip(body_len).await?;
+            return Ok(Err(anyhow::anyhow!(
+                "payload frame without a header length"
+            )));
+        }
+        self.stream.read_exact(&mut header_len).await?;
+        let header_len = u32::from_be_bytes(header_len) as usize;
+        let Some(payload_len) = (body_len - 4).checked_sub(header_len) else {
+            self.skip(body_len - 4).await?;
+            return Ok(Err(anyhow::anyhow!(
+                "payload frame header ({} bytes) longer than the frame",
+                header_len
+            )));
+        };
+
+        let mut header = vec![0u8; header_len + 1];
+        header[0] = tag;
+        self.stream.read_exact(&mut header[1..]).await?;
+        let mut payload = vec![0u8; payload_len];
+        self.stream.read_exact(&mut payload).await?;
+
+        Ok(
+            decode_frame(&header, self.max_message_size).and_then(|mut message| {
+                *message.payload_mut().ok_or_else(|| {
+                    anyhow::anyhow!("payload frame for a message that carries no image")
+                })? = payload;
+                Ok(message)
+            }),
+        )
+    }
+
+    /// Read and discard `len` bytes, to stay in step with the frames that follow.
+    async fn skip(&mut self, len: usize) -> Result<()> {
+        let mut remaining = (&mut self.stream).take(len as u64);
+        tokio::io::copy(&mut remaining, &mut tokio::io::sink()).await?;
+        Ok(())
+    }
+
     /// Write a message to the connection.
     ///
     /// # Arguments
@@ -112,13 +376,15 @@ impl Connection {
     ///
     /// # Returns
     /// - `Ok(())`: Message successfully sent
-    /// - `Err`: I/O or serialization error
+    /// - `Err`: I/O or serialization error, or [`ConnectionError::WriteTimeout`] expired
     ///
     /// # Protocol
-    /// 1. Serializes message to JSON
-    /// 2. Writes 4-byte length prefix (big-endian u32)
-    /// 3. Writes message data
-    /// 4. Flushes stream to ensure delivery
+    /// 1. Serializes message in its [preferred format](Message::preferred_format),
+    ///    without its image if it has one and isn't to be compressed
+    /// 2. Deflates it if it reaches the compression threshold
+    /// 3. Writes 4-byte length prefix (big-endian u32) and the format tag
+    /// 4. Writes message data (for an image, the header length, header and image)
+    /// 5. Flushes stream to ensure delivery
     ///
     /// # Example
     /// ```ignore
@@ -126,19 +392,727 @@ impl Connection {
     ///     from_id: 1,
     ///     timestamp: current_timestamp(),
     ///     load: 0.3,
+    ///     saturated: false,
+    ///     total_tasks: 12,
+    ///     leader_id: Some(2),
+    ///     term: 3,
     /// };
     /// conn.write_message(&heartbeat).await?;
     /// ```
     pub async fn write_message(&mut self, message: &Message) -> Result<()> {
-        // Serialize message to JSON bytes
-        let data = message.to_bytes()?;
-        let length = data.len() as u32;
+        let timeout = self.write_timeout;
+        with_write_timeout(timeout, self.write_frame(message)).await
+    }
+
+    /// Write a message whose image is held separately, without copying the image.
+    ///
+    /// The receiver reads it as `message` with `payload` as its image. This lets a
+    /// sender that keeps the image for retries send it by reference.
+    ///
+    /// # Arguments
+    /// - `message`: An image-carrying message (see [`Message::payload`]); its own
+    ///   image is ignored and is best left empty
+    /// - `payload`: The image to send in it
+    ///
+    /// # Returns
+    /// - `Ok(())`: Message successfully sent
+    /// - `Err`: As for [`write_message`](Self::write_message), or if `message`
+    ///   doesn't carry an image
+    ///
+    /// # Example
+    /// ```ignore
+    /// let request = Message::TaskRequest {
+    ///     client_name: "Client1".to_string(),
+    ///     request_id: 42,
+    ///     secret_image_data: Vec::new(),
+    ///     assigned_by_leader: 1,
+    ///     metadata: None,
+    /// };
+    /// conn.write_message_with_payload(&request, &secret_image).await?;
+    /// ```
+    pub async fn write_message_with_payload(
+        &mut self,
+        message: &Message,
+        payload: &[u8],
+    ) -> Result<()> {
+        let Some(header) = message.without_payload() else {
+            anyhow::bail!("message carries no image to send a payload in");
+        };
 
-        // Send: [4 bytes length][message data]
+        // A compressed frame deflates the whole message, so the image must be in it
+        if self.should_compress(payload) {
+            let mut message = header;
+            if let Some(image) = message.payload_mut() {
+                *image = payload.to_vec();
+            }
+            return self.write_message(&message).await;
+        }
+
+        let timeout = self.write_timeout;
+        with_write_timeout(timeout, self.write_payload_frame(&header, payload)).await
+    }
+
+    /// Whether an image of this size is sent in a compressed frame.
+    fn should_compress(&self, payload: &[u8]) -> bool {
+        self.compression_threshold
+            .is_some_and(|threshold| payload.len() >= threshold)
+    }
+
+    /// Encode and send one frame, without any timeout.
+    async fn write_frame(&mut self, message: &Message) -> Result<()> {
+        // Send an image after the rest of the message, unless it's to be compressed
+        if let (Some(payload), Some(header)) = (message.payload(), message.without_payload()) {
+            if !self.should_compress(payload) {
+                return self.write_payload_frame(&header, payload).await;
+            }
+        }
+
+        // Serialize message in its preferred wire format
+        let format = message.preferred_format();
+        let mut data = message.encode(format)?;
+        let mut tag = format.tag();
+
+        // Compress large messages if enabled and it actually saves bytes
+        if let Some(threshold) = self.compression_threshold {
+            if data.len() >= threshold {
+                let compressed = deflate(&data)?;
+                if compressed.len() < data.len() {
+                    data = compressed;
+                    tag |= COMPRESSED_FLAG;
+                }
+            }
+        }
+        let length = (data.len() + 1) as u32;
+
+        // Send: [4 bytes length][1 byte format tag][message data]
         self.stream.write_all(&length.to_be_bytes()).await?;
+        self.stream.write_all(&[tag]).await?;
         self.stream.write_all(&data).await?;
         self.stream.flush().await?;
 
         Ok(())
     }
+
+    /// Send `header` (a message with its image left out) followed by `payload` as
+    /// its image, in one payload frame.
+    async fn write_payload_frame(&mut self, header: &Message, payload: &[u8]) -> Result<()> {
+        let format = header.preferred_format();
+        let header = header.encode(format)?;
+        let length = (1 + 4 + header.len() + payload.len()) as u32;
+
+        // Send: [4 bytes length][1 byte format tag][4 bytes header length][header][image]
+        self.stream.write_all(&length.to_be_bytes()).await?;
+        self.stream
+            .write_all(&[format.tag() | PAYLOAD_FLAG])
+            .await?;
+        self.stream
+            .write_all(&(header.len() as u32).to_be_bytes())
+            .await?;
+        self.stream.write_all(&header).await?;
+        self.stream.write_all(payload).await?;
+        self.stream.flush().await?;
+
+        Ok(())
+    }
+
+    /// Send `frame` (format tag and body) with a length prefix but no encoding, to
+    /// feed a peer malformed input.
+    #[cfg(test)]
+    pub(crate) async fn write_raw_frame(&mut self, frame: &[u8]) -> Result<()> {
+        self.stream
+            .write_all(&(frame.len() as u32).to_be_bytes())
+            .await?;
+        self.stream.write_all(frame).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    /// Perform the client side of the protocol handshake.
+    ///
+    /// Sends a `Hello` with our [`PROTOCOL_VERSION`] and waits for the peer's `Hello`.
+    ///
+    /// # Returns
+    /// - `Ok(())`: Peer speaks the same protocol version
+    /// - `Err`: Peer has a different version, sent something else, or closed the connection
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut conn = Connection::new(TcpStream::connect(address).await?);
+    /// conn.handshake().await?;
+    /// conn.write_message(&request).await?;
+    /// ```
+    pub async fn handshake(&mut self) -> Result<()> {
+        self.write_message(&Message::Hello {
+            protocol_version: PROTOCOL_VERSION,
+        })
+        .await?;
+
+        match self.read_message().await? {
+            Some(Message::Hello { protocol_version }) => check_protocol_version(protocol_version),
+            Some(other) => Err(anyhow::anyhow!(
+                "Handshake failed: expected Hello, got {:?}",
+                other
+            )),
+            None => Err(anyhow::anyhow!(
+                "Handshake failed: connection closed before Hello"
+            )),
+        }
+    }
+
+    /// Check that the peer is still there: send a `Ping` and wait for its `Pong`.
+    ///
+    /// Only for a connection with no exchange in progress: any other message that
+    /// arrives before the `Pong` is discarded.
+    ///
+    /// # Returns
+    /// - `Ok(Duration)`: The round-trip time
+    /// - `Err`: [`ConnectionError::PingTimeout`] if no `Pong` came within `timeout`,
+    ///   or the connection failed or was closed. Either way it is unusable.
+    ///
+    /// # Example
+    /// ```ignore
+    /// if conn.ping(Duration::from_secs(2)).await.is_err() {
+    ///     drop(conn); // dead or half-open
+    /// }
+    /// ```
+    pub async fn ping(&mut self, timeout: Duration) -> Result<Duration> {
+        self.last_ping = self.last_ping.wrapping_add(1);
+        let nonce = self.last_ping;
+        let started = tokio::time::Instant::now();
+
+        let exchange = async {
+            self.write_frame(&Message::Ping { nonce }).await?;
+            loop {
+                match self.read_frame().await? {
+                    Some(Message::Pong { nonce: answered }) if answered == nonce => {
+                        return Ok(started.elapsed());
+                    }
+                    Some(_) => continue,
+                    None => return Err(anyhow::anyhow!("Connection closed before Pong")),
+                }
+            }
+        };
+        tokio::time::timeout(timeout, exchange)
+            .await
+            .map_err(|_| ConnectionError::PingTimeout(timeout))?
+    }
+
+    /// Perform the server side of the protocol handshake.
+    ///
+    /// Waits for the peer's `Hello` and always replies with our own, so an
+    /// incompatible peer learns our version before the connection is dropped.
+    ///
+    /// # Returns
+    /// - `Ok(())`: Peer speaks the same protocol version
+    /// - `Err`: Peer has a different version, skipped the handshake, or closed the connection
+    pub async fn accept_handshake(&mut self) -> Result<()> {
+        match self.read_message().await? {
+            Some(Message::Hello { protocol_version }) => {
+                self.write_message(&Message::Hello {
+                    protocol_version: PROTOCOL_VERSION,
+                })
+                .await?;
+                check_protocol_version(protocol_version)
+            }
+            Some(other) => Err(anyhow::anyhow!(
+                "Handshake failed: expected Hello, got {:?}",
+                other
+            )),
+            None => Err(anyhow::anyhow!(
+                "Handshake failed: connection closed before Hello"
+            )),
+        }
+    }
+}
+
+/// Run a write, failing with [`ConnectionError::WriteTimeout`] if it takes longer
+/// than `timeout` (if any).
+async fn with_write_timeout(
+    timeout: Option<Duration>,
+    write: impl std::future::Future<Output = Result<()>>,
+) -> Result<()> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, write)
+            .await
+            .map_err(|_| ConnectionError::WriteTimeout(timeout))?,
+        None => write.await,
+    }
+}
+
+/// Decode a frame body (`[format tag][data]`), inflating it first if compressed.
+fn decode_frame(frame: &[u8], max_message_size: usize) -> Result<Message> {
+    let tag = frame[0];
+    let format = WireFormat::from_tag(tag & !COMPRESSED_FLAG)?;
+
+    if tag & COMPRESSED_FLAG != 0 {
+        let data = inflate(&frame[1..], max_message_size)?;
+        Message::decode(&data, format)
+    } else {
+        Message::decode(&frame[1..], format)
+    }
+}
+
+/// Deflate an encoded message body.
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Inflate a compressed message body, refusing to expand past `max_message_size`.
+fn inflate(data: &[u8], max_message_size: usize) -> Result<Vec<u8>> {
+    let mut inflated = Vec::new();
+    DeflateDecoder::new(data)
+        .take(max_message_size as u64 + 1)
+        .read_to_end(&mut inflated)?;
+
+    if inflated.len() > max_message_size {
+        return Err(ConnectionError::MessageTooLarge {
+            size: inflated.len(),
+            max: max_message_size,
+        }
+        .into());
+    }
+    Ok(inflated)
+}
+
+/// Reject a peer whose protocol version differs from ours.
+fn check_protocol_version(peer_version: u32) -> Result<()> {
+    if peer_version != PROTOCOL_VERSION {
+        return Err(anyhow::anyhow!(
+            "Incompatible protocol version: peer speaks v{}, we speak v{}",
+            peer_version,
+            PROTOCOL_VERSION
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// Open a connected (client, server) pair over loopback.
+    async fn connection_pair() -> (Connection, Connection) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (client, server) = tokio::join!(TcpStream::connect(address), listener.accept());
+        (
+            Connection::new(client.unwrap()),
+            Connection::new(server.unwrap().0),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_handshake_with_matching_versions() {
+        let (mut client, mut server) = connection_pair().await;
+        let (client_result, server_result) =
+            tokio::join!(client.handshake(), server.accept_handshake());
+        assert!(client_result.is_ok());
+        assert!(server_result.is_ok());
+
+        // Normal traffic flows after the handshake
+        client.write_message(&Message::LeaderQuery).await.unwrap();
+        assert!(matches!(
+            server.read_message().await.unwrap(),
+            Some(Message::LeaderQuery)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_server_refuses_mismatched_client_version() {
+        let (mut client, mut server) = connection_pair().await;
+        client
+            .write_message(&Message::Hello {
+                protocol_version: PROTOCOL_VERSION + 1,
+            })
+            .await
+            .unwrap();
+
+        let err = server.accept_handshake().await.unwrap_err();
+        assert!(err.to_string().contains("Incompatible protocol version"));
+
+        // The old client still learns which version the server speaks
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::Hello { protocol_version }) if protocol_version == PROTOCOL_VERSION
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_client_refuses_mismatched_server_version() {
+        let (mut client, mut server) = connection_pair().await;
+        let fake_server = async {
+            server.read_message().await.unwrap();
+            server
+                .write_message(&Message::Hello {
+                    protocol_version: PROTOCOL_VERSION + 1,
+                })
+                .await
+                .unwrap();
+        };
+
+        let (result, _) = tokio::join!(client.handshake(), fake_server);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Incompatible protocol version"));
+    }
+
+    #[tokio::test]
+    async fn test_image_messages_round_trip_over_the_wire() {
+        let (mut client, mut server) = connection_pair().await;
+        let request = Message::TaskRequest {
+            client_name: "Client1".to_string(),
+            request_id: 7,
+            secret_image_data: vec![0xAB; 64 * 1024],
+            assigned_by_leader: 1,
+            metadata: None,
+        };
+
+        let (written, read) = tokio::join!(client.write_message(&request), server.read_message());
+        written.unwrap();
+        match read.unwrap() {
+            Some(Message::TaskRequest {
+                request_id,
+                secret_image_data,
+                ..
+            }) => {
+                assert_eq!(request_id, 7);
+                assert_eq!(secret_image_data, vec![0xAB; 64 * 1024]);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_over_in_memory_duplex_stream() {
+        // A buffer far smaller than the image, so frames arrive in pieces
+        let (a, b) = tokio::io::duplex(4 * 1024);
+        let mut client = Connection::new(a).with_compression_threshold(1024);
+        let mut server = Connection::new(b);
+        let (client_result, server_result) =
+            tokio::join!(client.handshake(), server.accept_handshake());
+        client_result.unwrap();
+        server_result.unwrap();
+
+        let image: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+        let messages = [
+            Message::LeaderQuery,
+            Message::TaskRequest {
+                client_name: "Client1".to_string(),
+                request_id: 7,
+                secret_image_data: image.clone(),
+                assigned_by_leader: 1,
+                metadata: None,
+            },
+            Message::TaskRequest {
+                client_name: "Client1".to_string(),
+                request_id: 8,
+                secret_image_data: vec![0xAB; 64 * 1024],
+                assigned_by_leader: 1,
+                metadata: None,
+            },
+        ];
+        for sent in &messages {
+            let (written, read) = tokio::join!(client.write_message(sent), server.read_message());
+            written.unwrap();
+            let received = read.unwrap().expect("stream closed");
+            assert_eq!(format!("{:?}", received), format!("{:?}", sent));
+        }
+
+        // Dropping one end reads as a clean close on the other
+        drop(client);
+        assert!(server.read_message().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_compressed_task_response_round_trip() {
+        let (client, mut server) = connection_pair().await;
+        let mut client = client.with_compression_threshold(1024);
+
+        // Large and compressible, like a mostly flat carrier image
+        let encrypted_image_data: Vec<u8> =
+            (0..2 * 1024 * 1024).map(|i| (i / 4096) as u8).collect();
+        let response = Message::TaskResponse {
+            request_id: 9,
+            encrypted_image_data: encrypted_image_data.clone(),
+            success: true,
+            error_message: None,
+        };
+
+        // Inspect the raw frame to confirm it was compressed on the wire
+        let encoded_len = response.encode(response.preferred_format()).unwrap().len();
+        client.write_message(&response).await.unwrap();
+        let mut length_buf = [0u8; 4];
+        server.stream.read_exact(&mut length_buf).await.unwrap();
+        let mut frame = vec![0u8; u32::from_be_bytes(length_buf) as usize];
+        server.stream.read_exact(&mut frame).await.unwrap();
+        assert_ne!(frame[0] & COMPRESSED_FLAG, 0);
+        assert!(frame.len() < encoded_len / 10);
+
+        match decode_frame(&frame, DEFAULT_MAX_MESSAGE_SIZE).unwrap() {
+            Message::TaskResponse {
+                request_id,
+                encrypted_image_data: received,
+                ..
+            } => {
+                assert_eq!(request_id, 9);
+                assert_eq!(received, encrypted_image_data);
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+
+        // Small messages stay below the threshold and are sent uncompressed
+        let (written, read) = tokio::join!(client.write_message(&Message::LeaderQuery), async {
+            server.stream.read_exact(&mut length_buf).await.unwrap();
+            let mut frame = vec![0u8; u32::from_be_bytes(length_buf) as usize];
+            server.stream.read_exact(&mut frame).await.unwrap();
+            frame
+        });
+        written.unwrap();
+        assert_eq!(read[0] & COMPRESSED_FLAG, 0);
+        assert!(matches!(
+            decode_frame(&read, DEFAULT_MAX_MESSAGE_SIZE).unwrap(),
+            Message::LeaderQuery
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_image_is_sent_after_the_message_as_a_payload() {
+        let (mut client, mut server) = connection_pair().await;
+        let result = Message::ForwardedTaskResult {
+            client_name: "Client1".to_string(),
+            request_id: 5,
+            response: Box::new(Message::TaskResponse {
+                request_id: 5,
+                encrypted_image_data: vec![0xCD; 32 * 1024],
+                success: true,
+                error_message: None,
+            }),
+        };
+
+        // The frame ends with the raw image bytes
+        let (written, frame) = tokio::join!(client.write_message(&result), async {
+            let mut length_buf = [0u8; 4];
+            server.stream.read_exact(&mut length_buf).await.unwrap();
+            let mut frame = vec![0u8; u32::from_be_bytes(length_buf) as usize];
+            server.stream.read_exact(&mut frame).await.unwrap();
+            frame
+        });
+        written.unwrap();
+        assert_ne!(frame[0] & PAYLOAD_FLAG, 0);
+        assert!(frame.ends_with(&[0xCD; 32 * 1024]));
+
+        let (written, read) = tokio::join!(client.write_message(&result), server.read_message());
+        written.unwrap();
+        match read.unwrap() {
+            Some(Message::ForwardedTaskResult { response, .. }) => {
+                assert_eq!(response.payload(), Some(&[0xCD; 32 * 1024][..]));
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_separately_held_image_arrives_in_its_message() {
+        let (client, mut server) = connection_pair().await;
+        let mut client = client.with_compression_threshold(64 * 1024);
+        let request = Message::TaskRequest {
+            client_name: "Client1".to_string(),
+            request_id: 3,
+            secret_image_data: Vec::new(),
+            assigned_by_leader: 1,
+            metadata: None,
+        };
+
+        // Below the threshold it goes as a payload frame, above it compressed
+        for image in [vec![0x5A; 1024], vec![0x5A; 256 * 1024]] {
+            let (written, read) = tokio::join!(
+                client.write_message_with_payload(&request, &image),
+                server.read_message()
+            );
+            written.unwrap();
+            match read.unwrap() {
+                Some(Message::TaskRequest {
+                    request_id,
+                    secret_image_data,
+                    ..
+                }) => {
+                    assert_eq!(request_id, 3);
+                    assert_eq!(secret_image_data, image);
+                }
+                other => panic!("Unexpected message: {:?}", other),
+            }
+        }
+
+        let hello = Message::Hello {
+            protocol_version: PROTOCOL_VERSION,
+        };
+        assert!(client
+            .write_message_with_payload(&hello, &[1, 2, 3])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_message_inflates_compressed_frames() {
+        let (client, mut server) = connection_pair().await;
+        let mut client = client.with_compression_threshold(0);
+        let request = Message::TaskRequest {
+            client_name: "Client1".to_string(),
+            request_id: 3,
+            secret_image_data: vec![0u8; 256 * 1024],
+            assigned_by_leader: 2,
+            metadata: None,
+        };
+
+        let (written, read) = tokio::join!(client.write_message(&request), server.read_message());
+        written.unwrap();
+        assert!(matches!(
+            read.unwrap(),
+            Some(Message::TaskRequest { request_id: 3, ref secret_image_data, .. })
+                if secret_image_data.len() == 256 * 1024
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_frame_is_skipped_without_closing() {
+        let (mut client, mut server) = connection_pair().await;
+        let json = WireFormat::Json.tag();
+
+        client.write_message(&Message::LeaderQuery).await.unwrap();
+        client.write_raw_frame(&[json, b'{', b'?']).await.unwrap();
+        client.write_raw_frame(&[]).await.unwrap();
+        client.write_raw_frame(&[0x7f, 1, 2, 3]).await.unwrap();
+        client
+            .write_message(&Message::LeaderResponse { leader_id: 2 })
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            server.read_message().await.unwrap(),
+            Some(Message::LeaderQuery)
+        ));
+        for _ in 0..3 {
+            let err = server.read_message().await.unwrap_err();
+            assert!(
+                matches!(
+                    err.downcast_ref::<ConnectionError>(),
+                    Some(ConnectionError::MalformedMessage(_))
+                ),
+                "{}",
+                err
+            );
+        }
+        assert!(matches!(
+            server.read_message().await.unwrap(),
+            Some(Message::LeaderResponse { leader_id: 2 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout_on_silent_peer() {
+        let (client, _server) = connection_pair().await;
+        let stream = client.stream;
+        let mut client =
+            Connection::with_timeouts(stream, Duration::from_millis(100), Duration::from_secs(1));
+
+        // The peer is alive but never sends anything
+        let err = client.read_message().await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ConnectionError>(),
+            Some(&ConnectionError::ReadTimeout(Duration::from_millis(100)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ping_is_answered_by_matching_pong() {
+        let (mut client, mut server) = connection_pair().await;
+
+        // A stale pong from an earlier ping doesn't count as the answer
+        let responder = async {
+            let Some(Message::Ping { nonce }) = server.read_message().await.unwrap() else {
+                panic!("expected a ping");
+            };
+            let stale = Message::Pong { nonce: nonce + 1 };
+            server.write_message(&stale).await.unwrap();
+            server
+                .write_message(&Message::Pong { nonce })
+                .await
+                .unwrap();
+        };
+        let (rtt, ()) = tokio::join!(client.ping(Duration::from_secs(5)), responder);
+        assert!(rtt.unwrap() < Duration::from_secs(5));
+
+        // A peer that stays connected but never answers is found out
+        let err = client.ping(Duration::from_millis(100)).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ConnectionError>(),
+            Some(&ConnectionError::PingTimeout(Duration::from_millis(100)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_timeout_when_peer_stops_reading() {
+        let (client, _server) = connection_pair().await;
+        let stream = client.stream;
+        let mut client =
+            Connection::with_timeouts(stream, Duration::from_secs(1), Duration::from_millis(200));
+
+        // Far more than the socket buffers hold, and the peer never reads
+        let request = Message::TaskRequest {
+            client_name: "Client1".to_string(),
+            request_id: 1,
+            secret_image_data: vec![0x5A; 32 * 1024 * 1024],
+            assigned_by_leader: 1,
+            metadata: None,
+        };
+        let err = client.write_message(&request).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ConnectionError>(),
+            Some(&ConnectionError::WriteTimeout(Duration::from_millis(200)))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_frame_one_byte_over_limit_is_rejected() {
+        let message = Message::TaskAck {
+            client_name: "Client1".to_string(),
+            request_id: 5,
+        };
+        // Frame length = format tag + encoded body
+        let frame_len = 1 + message.encode(message.preferred_format()).unwrap().len();
+
+        // Exactly at the limit is accepted
+        let (mut client, server) = connection_pair().await;
+        let mut server = server.with_max_message_size(frame_len);
+        let (written, read) = tokio::join!(client.write_message(&message), server.read_message());
+        written.unwrap();
+        assert!(matches!(
+            read.unwrap(),
+            Some(Message::TaskAck { request_id: 5, .. })
+        ));
+
+        // One byte over is rejected before the body is read
+        let (mut client, server) = connection_pair().await;
+        let mut server = server.with_max_message_size(frame_len - 1);
+        let (written, read) = tokio::join!(client.write_message(&message), server.read_message());
+        written.unwrap();
+        assert_eq!(
+            read.unwrap_err().downcast_ref::<ConnectionError>(),
+            Some(&ConnectionError::MessageTooLarge {
+                size: frame_len,
+                max: frame_len - 1,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_server_refuses_connection_without_hello() {
+        let (mut client, mut server) = connection_pair().await;
+        client.write_message(&Message::LeaderQuery).await.unwrap();
+        assert!(server.accept_handshake().await.is_err());
+    }
 }
@@ -0,0 +1,148 @@
+//! # Error Types
+//!
+//! Failures that callers act on, as opposed to just report.
+//!
+//! [`CloudP2PError`] is returned wrapped in an [`anyhow::Error`], like the
+//! module-specific error types ([`ConnectionError`](super::connection::ConnectionError),
+//! [`SteganographyError`](crate::processing::steganography::SteganographyError)).
+//! Callers classify a failure with `downcast_ref::<CloudP2PError>()` and match on
+//! the variant, never on the message text.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! match error.downcast_ref::<CloudP2PError>() {
+//!     Some(CloudP2PError::TaskLost { .. }) => resubmit(),
+//!     Some(CloudP2PError::TaskRejected { .. }) => resubmit_after_a_heartbeat(),
+//!     _ => give_up(),
+//! }
+//! ```
+
+use std::fmt;
+use std::time::Duration;
+
+/// A classified failure of a client request or a query to the cluster.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloudP2PError {
+    /// No server has the task any more (all failed or lost its history), or the
+    /// leader relaying it failed mid-task. Resubmitting it is safe.
+    TaskLost { request_id: u64, reason: String },
+    /// The server is alive but refused the task, e.g. for being at capacity. It
+    /// keeps no record of it, so no reassignment will come.
+    TaskRejected { request_id: u64, reason: String },
+    /// The server accepted the task but reported that encrypting it failed, or
+    /// the carrier it returned holds no readable secret image.
+    EncryptionFailed { request_id: u64, reason: String },
+    /// The carrier's payload checksum doesn't match: it was damaged after the
+    /// server embedded the secret.
+    ChecksumMismatch { request_id: u64, reason: String },
+    /// The carrier holds a valid image, but not the secret that was sent.
+    SecretMismatch { request_id: u64 },
+    /// No server knows of a leader, e.g. while an election is in progress.
+    NoLeader,
+    /// No leader came forward to take the task within the client's
+    /// `assignment_timeout_secs`.
+    AssignmentTimeout { request_id: u64, timeout: Duration },
+    /// No server answered a query about the task.
+    TaskNotFound { request_id: u64 },
+    /// The server is skipped, as its circuit breaker is open.
+    ServerUnavailable { server_id: u32 },
+    /// The server closed the connection or answered with the wrong message.
+    UnexpectedResponse,
+}
+
+impl fmt::Display for CloudP2PError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloudP2PError::TaskLost { request_id, reason } => {
+                write!(f, "Task #{} lost: {}", request_id, reason)
+            }
+            CloudP2PError::TaskRejected { request_id, reason } => {
+                write!(f, "Task #{} rejected by server: {}", request_id, reason)
+            }
+            CloudP2PError::EncryptionFailed { reason, .. } => {
+                write!(f, "Task failed on server: {}", reason)
+            }
+            CloudP2PError::ChecksumMismatch { request_id, reason } => write!(
+                f,
+                "Server sent a corrupt carrier image for task #{}: {}",
+                request_id, reason
+            ),
+            CloudP2PError::SecretMismatch { request_id } => write!(
+                f,
+                "Embedded image for task #{} does not match the original secret image",
+                request_id
+            ),
+            CloudP2PError::NoLeader => write!(f, "No server reported a current leader"),
+            CloudP2PError::AssignmentTimeout {
+                request_id,
+                timeout,
+            } => write!(
+                f,
+                "No leader took task #{} within {:?}",
+                request_id, timeout
+            ),
+            CloudP2PError::TaskNotFound { request_id } => write!(
+                f,
+                "No server responded with the status of task #{}",
+                request_id
+            ),
+            CloudP2PError::ServerUnavailable { server_id } => {
+                write!(f, "Server {} has its circuit breaker open", server_id)
+            }
+            CloudP2PError::UnexpectedResponse => write!(f, "Invalid or no response from server"),
+        }
+    }
+}
+
+impl std::error::Error for CloudP2PError {}
+
+impl CloudP2PError {
+    /// Whether `error` is a [`CloudP2PError::TaskLost`].
+    pub fn is_task_lost(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<CloudP2PError>(),
+            Some(CloudP2PError::TaskLost { .. })
+        )
+    }
+
+    /// Whether `error` is a [`CloudP2PError::TaskRejected`].
+    pub fn is_task_rejected(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<CloudP2PError>(),
+            Some(CloudP2PError::TaskRejected { .. })
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classification_goes_by_variant_not_message() {
+        let lost: anyhow::Error = CloudP2PError::TaskLost {
+            request_id: 7,
+            reason: "leader 2 failed mid-task".to_string(),
+        }
+        .into();
+        assert!(CloudP2PError::is_task_lost(&lost));
+        assert!(!CloudP2PError::is_task_rejected(&lost));
+
+        // Wording alone doesn't classify an error
+        let lookalike = anyhow::anyhow!("Task #7 lost: rejected by server");
+        assert!(!CloudP2PError::is_task_lost(&lookalike));
+        assert!(!CloudP2PError::is_task_rejected(&lookalike));
+
+        let rejected: anyhow::Error = CloudP2PError::TaskRejected {
+            request_id: 7,
+            reason: "at capacity".to_string(),
+        }
+        .into();
+        assert!(CloudP2PError::is_task_rejected(&rejected));
+        assert_eq!(
+            rejected.to_string(),
+            "Task #7 rejected by server: at capacity"
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! # Logging Setup
+//!
+//! Shared `tracing` subscriber for all binaries, printing one of two line formats:
+//!
+//! - `text` (default): `[HH:MM:SS] [LEVEL] spans: message`, for humans
+//! - `json`: one JSON object per line, for log aggregation (ELK, Loki, ...)
+//!
+//! The format is chosen with the `--log-format` flag, or the `CLOUDP2P_LOG_FORMAT`
+//! environment variable when the flag is absent. Modules still using the `log`
+//! macros are bridged into the same subscriber, so their lines look the same.
+//!
+//! ## Request spans
+//!
+//! The coordination code opens a span per request, keyed by `(client, request_id)`:
+//! `request` around the client's `send_request` (with `execute_task` nested inside
+//! it), and `task` around the server's `process_task`. Every event logged while a
+//! span is entered, including from tasks spawned inside it, is tagged with the span:
+//!
+//! ```text
+//! [12:00:00] [INFO] request{client="Client1" request_id=7}:execute_task{assigned_server_id=2}: ...
+//! ```
+//!
+//! ## Fields in JSON lines
+//!
+//! Every line carries `timestamp`, `level`, `target` and `message`, then:
+//!
+//! - **Process context**, registered once with [`set_context`]. Each binary does this
+//!   right after loading its configuration (e.g. `server_id` for servers, `client`
+//!   for clients), and the formatter copies these fields into every subsequent line.
+//! - **Span fields** of every enclosing span (`client`, `request_id`, ...), plus
+//!   `span` (the span names, outermost first) and `span_id`, a process-unique ID of
+//!   the outermost span that all events of one request share.
+//! - **Event fields**, attached at the log call, e.g.
+//!   `info!(request_id = request_id, "...")` on log points outside a request span.
+
+use anyhow::Result;
+use serde_json::{Map, Value as JsonValue};
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Subscriber};
+use tracing_log::NormalizeEvent;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Environment variable selecting the log format when no CLI flag is given.
+pub const LOG_FORMAT_ENV: &str = "CLOUDP2P_LOG_FORMAT";
+
+/// Fields copied into every JSON log line (see [`set_context`]).
+static CONTEXT: RwLock<Vec<(&'static str, JsonValue)>> = RwLock::new(Vec::new());
+
+/// Source of [`SpanFields::id`]; unlike `tracing` span IDs, these are never reused.
+static NEXT_SPAN_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Output format of log lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// `[HH:MM:SS] [LEVEL] spans: message`
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!(
+                "unknown log format '{}' (expected 'text' or 'json')",
+                other
+            )),
+        }
+    }
+}
+
+impl LogFormat {
+    /// Pick the log format: the CLI flag if given, else `CLOUDP2P_LOG_FORMAT`, else text.
+    ///
+    /// # Arguments
+    /// - `flag`: Value of the binary's `--log-format` flag, if any
+    ///
+    /// # Returns
+    /// - `Err` if the environment variable holds an unknown format
+    pub fn resolve(flag: Option<LogFormat>) -> Result<LogFormat> {
+        if let Some(format) = flag {
+            return Ok(format);
+        }
+        match std::env::var(LOG_FORMAT_ENV) {
+            Ok(value) => value
+                .parse()
+                .map_err(|e| anyhow::anyhow!("{}: {}", LOG_FORMAT_ENV, e)),
+            Err(_) => Ok(LogFormat::Text),
+        }
+    }
+}
+
+/// Install the global `tracing` subscriber, and route `log` records into it.
+///
+/// Lines are written to stderr at INFO level by default; `RUST_LOG` overrides the filter.
+///
+/// # Arguments
+/// - `format`: Line format, usually from [`LogFormat::resolve`]
+pub fn init_logger(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(LineLayer::new(format, std::io::stderr))
+        .init();
+}
+
+/// Register a field that is added to every JSON log line from now on.
+///
+/// Setting the same key again replaces its value.
+///
+/// # Example
+/// ```ignore
+/// logging::set_context("server_id", config.server.id);
+/// ```
+pub fn set_context(key: &'static str, value: impl Into<JsonValue>) {
+    let mut context = CONTEXT.write().unwrap();
+    let value = value.into();
+    match context.iter_mut().find(|(k, _)| *k == key) {
+        Some(entry) => entry.1 = value,
+        None => context.push((key, value)),
+    }
+}
+
+/// Layer that writes each event as one line in the chosen [`LogFormat`].
+pub struct LineLayer<W> {
+    format: LogFormat,
+    writer: W,
+}
+
+impl<W> LineLayer<W> {
+    /// Create a layer writing lines of `format` to `writer` (e.g. `std::io::stderr`).
+    pub fn new(format: LogFormat, writer: W) -> Self {
+        LineLayer { format, writer }
+    }
+}
+
+/// Fields recorded on a span, kept in its extensions until it closes.
+struct SpanFields {
+    id: u64,
+    fields: Map<String, JsonValue>,
+}
+
+impl<S, W> Layer<S> for LineLayer<W>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'a> MakeWriter<'a> + 'static,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        attrs.record(&mut FieldCollector(&mut fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanFields {
+                id: NEXT_SPAN_ID.fetch_add(1, Ordering::Relaxed),
+                fields,
+            });
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            if let Some(span_fields) = span.extensions_mut().get_mut::<SpanFields>() {
+                values.record(&mut FieldCollector(&mut span_fields.fields));
+            }
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = Map::new();
+        event.record(&mut FieldCollector(&mut fields));
+        // Bridged `log` records carry their origin as `log.*` fields
+        fields.retain(|key, _| !key.starts_with("log."));
+        let message = match fields.remove("message") {
+            Some(JsonValue::String(message)) => message,
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        let spans: Vec<SpanRef<'_, S>> = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().collect())
+            .unwrap_or_default();
+
+        let line = match self.format {
+            LogFormat::Text => text_line(
+                &chrono::Local::now().format("%H:%M:%S").to_string(),
+                &metadata.level().to_string(),
+                &spans,
+                &message,
+            ),
+            LogFormat::Json => json_line(
+                &chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                &metadata.level().to_string(),
+                metadata.target(),
+                &spans,
+                message,
+                fields,
+            ),
+        };
+        let _ = writeln!(self.writer.make_writer(), "{}", line);
+    }
+}
+
+/// Render `[time] [LEVEL] name{k=v}:name{k=v}: message`.
+fn text_line<S>(time: &str, level: &str, spans: &[SpanRef<'_, S>], message: &str) -> String
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    let mut line = format!("[{}] [{}] ", time, level);
+    for span in spans {
+        line.push_str(span.name());
+        if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+            let fields: Vec<String> = span_fields
+                .fields
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            line.push_str(&format!("{{{}}}", fields.join(" ")));
+        }
+        line.push(':');
+    }
+    if !spans.is_empty() {
+        line.push(' ');
+    }
+    line.push_str(message);
+    line
+}
+
+/// Render one event as a JSON object: standard fields, process context, span
+/// fields (outermost first, so inner spans win on conflicts), then event fields.
+fn json_line<S>(
+    timestamp: &str,
+    level: &str,
+    target: &str,
+    spans: &[SpanRef<'_, S>],
+    message: String,
+    fields: Map<String, JsonValue>,
+) -> String
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    let mut line = Map::new();
+    line.insert("timestamp".into(), timestamp.into());
+    line.insert("level".into(), level.into());
+    line.insert("target".into(), target.into());
+    line.insert("message".into(), message.into());
+
+    for (key, value) in CONTEXT.read().unwrap().iter() {
+        line.insert((*key).into(), value.clone());
+    }
+
+    if !spans.is_empty() {
+        let names: Vec<&str> = spans.iter().map(|span| span.name()).collect();
+        line.insert("span".into(), names.join(":").into());
+    }
+    for (depth, span) in spans.iter().enumerate() {
+        if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+            if depth == 0 {
+                line.insert("span_id".into(), span_fields.id.into());
+            }
+            line.extend(span_fields.fields.clone());
+        }
+    }
+
+    line.extend(fields);
+    JsonValue::Object(line).to_string()
+}
+
+/// Copies span or event fields into a JSON object, keeping numbers and booleans typed.
+struct FieldCollector<'a>(&'a mut Map<String, JsonValue>);
+
+impl Visit for FieldCollector<'_> {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{:?}", value).into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::Registry;
+
+    /// Writer that appends to a shared buffer, so tests can read the lines back.
+    #[derive(Clone)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Run `f` under a subscriber with a [`LineLayer`] and return the lines it wrote.
+    fn capture(format: LogFormat, f: impl FnOnce()) -> Vec<String> {
+        let buffer = Captured(Arc::new(Mutex::new(Vec::new())));
+        let writer = buffer.clone();
+        let subscriber = Registry::default().with(LineLayer::new(format, move || writer.clone()));
+        tracing::subscriber::with_default(subscriber, f);
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        output.lines().map(String::from).collect()
+    }
+
+    fn log_request_events() {
+        let request = tracing::info_span!("request", client = "Client1", request_id = 7u64);
+        let _entered = request.enter();
+        tracing::info!("📡 assigning");
+        tracing::info_span!("execute_task", server_id = 2u32).in_scope(|| {
+            tracing::warn!(attempt = 1u32, "Server {} failed", 2);
+        });
+    }
+
+    #[test]
+    fn test_json_lines_share_request_span() {
+        set_context("server_id", 3);
+
+        let lines = capture(LogFormat::Json, log_request_events);
+        assert_eq!(lines.len(), 2);
+        let first: JsonValue = serde_json::from_str(&lines[0]).unwrap();
+        let second: JsonValue = serde_json::from_str(&lines[1]).unwrap();
+
+        assert_eq!(first["level"], "INFO");
+        assert_eq!(first["target"], "cloud_p2p::common::logging::tests");
+        assert_eq!(first["message"], "📡 assigning");
+        assert_eq!(first["server_id"], 3);
+        assert_eq!(first["span"], "request");
+        assert_eq!(first["client"], "Client1");
+        assert_eq!(first["request_id"], 7);
+        assert!(first["timestamp"].as_str().unwrap().ends_with('Z'));
+
+        assert_eq!(second["level"], "WARN");
+        assert_eq!(second["message"], "Server 2 failed");
+        assert_eq!(second["span"], "request:execute_task");
+        assert_eq!(second["request_id"], 7);
+        assert_eq!(second["attempt"], 1);
+        assert_eq!(second["span_id"], first["span_id"]);
+    }
+
+    #[test]
+    fn test_text_lines_show_span_path() {
+        let lines = capture(LogFormat::Text, log_request_events);
+        assert!(lines[0].ends_with("[INFO] request{client=\"Client1\" request_id=7}: 📡 assigning"));
+        assert!(lines[1].ends_with(
+            "[WARN] request{client=\"Client1\" request_id=7}:execute_task{server_id=2}: Server 2 failed"
+        ));
+    }
+
+    #[test]
+    fn test_log_format_parsing() {
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!("TEXT".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert!("yaml".parse::<LogFormat>().is_err());
+        assert_eq!(
+            LogFormat::resolve(Some(LogFormat::Json)).unwrap(),
+            LogFormat::Json
+        );
+    }
+}
@@ -7,6 +7,11 @@
 //! - Fault tolerance and task history tracking
 //!
 //! Messages are serialized to JSON and sent over TCP with a 4-byte length prefix.
+//! With the `bincode` feature (on by default), the image-bearing task messages
+//! (`TaskRequest`, `TaskResponse` and their leader-routed counterparts) are
+//! serialized with bincode instead, which avoids JSON's
+//! number-array blow-up of `Vec<u8>` payloads. A [`WireFormat`] tag in each frame
+//! tells the reader which encoding was used, so both coexist on the wire.
 
 use serde::{Deserialize, Serialize};
 
@@ -14,9 +19,96 @@ use serde::{Deserialize, Serialize};
 // MESSAGE TYPES - Protocol for Modified Bully Election and Task Distribution
 // ============================================================================
 
+/// Version of the message protocol spoken by this build.
+///
+/// Exchanged in a [`Message::Hello`] right after every connection is opened, and
+/// bumped whenever a message is added, removed, or changes shape. Peers with a
+/// different version are refused instead of silently mis-parsing each other.
+///
+/// - v1: JSON-only frames
+/// - v2: one-byte [`WireFormat`] tag in front of every frame body
+/// - v3: high bit of the format tag marks a deflate-compressed body
+/// - v4: [`Message::TaskRejected`] and the `saturated` flag on heartbeats
+/// - v5: election `term` on [`Message::Election`] and [`Message::Coordinator`]
+/// - v6: believed leader and term on heartbeats, for split-brain reconciliation
+/// - v7: [`Message::Resign`] for leader handoff on shutdown
+/// - v8: [`Message::RoutedTaskRequest`], [`Message::ForwardedTask`] and
+///   [`Message::ForwardedTaskResult`] for leader-routed tasks
+/// - v9: [`Message::RateLimited`] for per-client rate limiting on the leader
+/// - v10: [`Message::ResultReplicate`] for backing up completed results
+/// - v11: [`Message::LoadQuery`] and [`Message::LoadResponse`] for cluster monitoring
+/// - v12: lifetime `total_tasks` on heartbeats
+/// - v13: [`Message::SimulateFail`] for in-process fault injection
+/// - v14: [`Message::RecoveryRequest`] and [`Message::StateSync`] for catching up on rejoin
+/// - v15: [`Message::PreVote`] and [`Message::PreVoteResponse`] for `election.pre_vote`
+/// - v16: image payloads framed after the rest of the message (see
+///   [`Message::payload`])
+/// - v17: [`Message::TaskProgress`] for `server.report_progress`
+/// - v18: `metadata` text on [`Message::TaskRequest`]
+/// - v19: [`Message::Ping`] and [`Message::Pong`] for connection keepalive
+/// - v20: `timestamp` on [`Message::HistoryRemove`]
+/// - v21: `leader_id` on [`Message::TaskStatusResponse`]
+/// - v22: history `timestamp`s in Unix millis, never reused by a server
+pub const PROTOCOL_VERSION: u32 = 22;
+
+/// Encoding of a framed message body, sent as a one-byte tag before the body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireFormat {
+    /// `serde_json` - used for all control messages
+    Json,
+    /// `bincode` - used for image-bearing messages when the feature is enabled
+    Bincode,
+}
+
+impl WireFormat {
+    /// One-byte tag written in front of the message body.
+    pub fn tag(self) -> u8 {
+        match self {
+            WireFormat::Json => 0,
+            WireFormat::Bincode => 1,
+        }
+    }
+
+    /// Parse a tag read from the wire.
+    pub fn from_tag(tag: u8) -> anyhow::Result<Self> {
+        match tag {
+            0 => Ok(WireFormat::Json),
+            1 => Ok(WireFormat::Bincode),
+            other => Err(anyhow::anyhow!("Unknown wire format tag {}", other)),
+        }
+    }
+}
+
 /// Core message enum for all communication in the CloudP2P system
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Message {
+    // ========== CONNECTION HANDSHAKE ==========
+    /// **Hello Message**
+    ///
+    /// First message on every connection, sent by the connecting side and echoed by
+    /// the accepting side, so both can refuse an incompatible peer.
+    ///
+    /// # Fields
+    /// - `protocol_version`: The sender's [`PROTOCOL_VERSION`]
+    Hello { protocol_version: u32 },
+
+    /// **Ping Message**
+    ///
+    /// Sent on an idle connection to check that the other end is still there,
+    /// independently of cluster heartbeats. Answered with a `Pong`.
+    ///
+    /// # Fields
+    /// - `nonce`: Echoed in the `Pong`, to match it to this ping
+    Ping { nonce: u64 },
+
+    /// **Pong Message**
+    ///
+    /// Answer to a `Ping`.
+    ///
+    /// # Fields
+    /// - `nonce`: The nonce of the `Ping` being answered
+    Pong { nonce: u64 },
+
     // ========== LEADER ELECTION MESSAGES ==========
     /// **Election Message**
     ///
@@ -25,11 +117,21 @@ pub enum Message {
     /// # Fields
     /// - `from_id`: ID of the server starting the election
     /// - `priority`: The server's calculated priority score (LOWER = BETTER candidate)
+    /// - `term`: Election term, one higher than any term the sender had seen
     ///
     /// # Modified Bully Algorithm
     /// Unlike classic Bully Algorithm which uses static server IDs, this implementation
     /// uses dynamic load-based priority where lower values indicate less-loaded servers.
-    Election { from_id: u32, priority: f64 },
+    ///
+    /// # Terms
+    /// Servers ignore elections and announcements from a term older than the newest
+    /// one they have seen, so a delayed message from a past election can't flip
+    /// the leader back.
+    Election {
+        from_id: u32,
+        priority: f64,
+        term: u64,
+    },
 
     /// **Alive Message**
     ///
@@ -46,7 +148,39 @@ pub enum Message {
     ///
     /// # Fields
     /// - `leader_id`: ID of the server that won the election
-    Coordinator { leader_id: u32 },
+    /// - `term`: The election term the leader won
+    Coordinator { leader_id: u32, term: u64 },
+
+    /// **Resign Message**
+    ///
+    /// Broadcast by a leader that is shutting down, so peers start a new election
+    /// right away instead of waiting for its heartbeats to time out.
+    ///
+    /// # Fields
+    /// - `leader_id`: ID of the resigning leader
+    Resign { leader_id: u32 },
+
+    /// **Pre-Vote Message**
+    ///
+    /// Sent by a server about to start an election (with `election.pre_vote`
+    /// enabled) to each peer, on a short-lived connection. The peer answers with a
+    /// `PreVoteResponse` on the same connection; nothing changes on either side,
+    /// in particular no term is started.
+    ///
+    /// # Fields
+    /// - `from_id`: ID of the server that wants to start an election
+    PreVote { from_id: u32 },
+
+    /// **Pre-Vote Response Message**
+    ///
+    /// Answer to a PreVote. The election only goes ahead if a majority of the
+    /// peers that answered granted it.
+    ///
+    /// # Fields
+    /// - `from_id`: ID of the responding server
+    /// - `granted`: Whether the responder also thinks the leader is gone: it follows
+    ///   no leader, or hasn't heard from its leader within `failure_timeout_secs`
+    PreVoteResponse { from_id: u32, granted: bool },
 
     /// **Heartbeat Message**
     ///
@@ -57,14 +191,28 @@ pub enum Message {
     /// - `from_id`: ID of the server sending the heartbeat
     /// - `timestamp`: Unix timestamp when heartbeat was sent (seconds since epoch)
     /// - `load`: Current load score (0.0 = no load, 100.0 = maximum load)
+    /// - `saturated`: Whether every task slot is in use; the leader won't assign
+    ///   new tasks to a saturated server while others have capacity
+    /// - `total_tasks`: Tasks the sender has started since it came up
+    /// - `leader_id`: The leader the sender currently follows (None if unknown)
+    /// - `term`: The newest election term the sender has seen
     ///
     /// # Fault Detection
     /// Servers that don't send heartbeats within the configured timeout are
     /// considered failed, triggering orphaned task cleanup and potential re-election.
+    ///
+    /// # Split-Brain Reconciliation
+    /// After a partition heals, a leader that hears of a different leader from a
+    /// newer term (or the same term and a lower leader ID) steps down and starts a
+    /// new election, so the cluster converges on a single leader.
     Heartbeat {
         from_id: u32,
         timestamp: u64,
         load: f64,
+        saturated: bool,
+        total_tasks: u64,
+        leader_id: Option<u32>,
+        term: u64,
     },
 
     // ========== CLIENT-SERVER COMMUNICATION ==========
@@ -82,6 +230,34 @@ pub enum Message {
     /// - `leader_id`: ID of the current leader server
     LeaderResponse { leader_id: u32 },
 
+    /// **Load Query**
+    ///
+    /// Sent by clients (e.g. a monitoring dashboard) to ask a server for its current load.
+    /// Answered by any server with a `LoadResponse` on the same connection.
+    LoadQuery,
+
+    /// **Load Response**
+    ///
+    /// Response to LoadQuery with the server's own metrics.
+    ///
+    /// # Fields
+    /// - `server_id`: ID of the responding server
+    /// - `load`: Current priority score (lower = less loaded), as sent in heartbeats
+    /// - `cpu_usage`: CPU usage percentage (0-100)
+    /// - `active_tasks`: Tasks currently being processed
+    /// - `total_tasks`: Tasks started over the server's lifetime
+    /// - `saturated`: Whether every task slot is busy
+    /// - `leader_id`: The leader this server currently recognizes, if any
+    LoadResponse {
+        server_id: u32,
+        load: f64,
+        cpu_usage: f64,
+        active_tasks: u64,
+        total_tasks: u64,
+        saturated: bool,
+        leader_id: Option<u32>,
+    },
+
     /// **Task Assignment Request**
     ///
     /// Sent by clients to the leader to request which server should process their task.
@@ -109,6 +285,19 @@ pub enum Message {
         assigned_server_address: String,
     },
 
+    /// **Rate Limited**
+    ///
+    /// Sent by the leader instead of an assignment (or a routed task's result) when
+    /// the client has exceeded its per-client request rate.
+    ///
+    /// # Fields
+    /// - `request_id`: ID of the refused request
+    /// - `retry_after_ms`: How long until the client's window has room again
+    RateLimited {
+        request_id: u64,
+        retry_after_ms: u64,
+    },
+
     /// **Task Request**
     ///
     /// Sent by clients to assigned servers to perform steganography encryption.
@@ -117,12 +306,16 @@ pub enum Message {
     /// - `client_name`: Name of the client submitting the task
     /// - `request_id`: Unique ID for tracking
     /// - `secret_image_data`: Raw bytes of the secret image to hide in the server's carrier image
-    /// - `assigned_by_leader`: ID of the leader that assigned this task (for validation)
+    /// - `assigned_by_leader`: ID of the leader that assigned this task; a server
+    ///   following a different leader answers with `TaskRejected`
+    /// - `metadata`: Text to embed alongside the secret image, read back with
+    ///   [`extract_metadata`](crate::processing::steganography::extract_metadata)
     TaskRequest {
         client_name: String,
         request_id: u64,
         secret_image_data: Vec<u8>,
         assigned_by_leader: u32,
+        metadata: Option<String>,
     },
 
     /// **Task Response**
@@ -141,6 +334,93 @@ pub enum Message {
         error_message: Option<String>,
     },
 
+    /// **Task Progress**
+    ///
+    /// Sent by a server with `report_progress` enabled while it embeds a client's
+    /// secret, on the connection the `TaskRequest` arrived on, ahead of the
+    /// `TaskResponse`. Updates may be skipped, but `percent` never goes down.
+    ///
+    /// # Fields
+    /// - `request_id`: ID of the task being processed
+    /// - `percent`: Share of the secret written into the carrier so far (0-100)
+    TaskProgress { request_id: u64, percent: u8 },
+
+    /// **Task Rejected**
+    ///
+    /// Sent by a server instead of a `TaskResponse` when all of its task slots are
+    /// in use (the server then drops the task from history), or when the task was
+    /// assigned by a leader other than the one the server follows. Either way the
+    /// client should ask the leader for a fresh assignment.
+    ///
+    /// # Fields
+    /// - `request_id`: ID of the rejected task
+    /// - `reason`: Human-readable explanation
+    TaskRejected { request_id: u64, reason: String },
+
+    /// **Routed Task Request**
+    ///
+    /// Sent by clients in leader-routed mode: the task goes to the leader, which
+    /// forwards it to the least-loaded server and relays the `TaskResponse` (or
+    /// `TaskRejected`) back over the same connection. The client then sends its
+    /// `TaskAck` on that connection too.
+    ///
+    /// # Fields
+    /// - `client_name`: Name of the client submitting the task
+    /// - `request_id`: Unique ID for tracking
+    /// - `secret_image_data`: Raw bytes of the secret image to hide
+    RoutedTaskRequest {
+        client_name: String,
+        request_id: u64,
+        secret_image_data: Vec<u8>,
+    },
+
+    /// **Forwarded Task**
+    ///
+    /// Sent by the leader to the server chosen for a `RoutedTaskRequest`.
+    ///
+    /// # Fields
+    /// - `from_server_id`: ID of the leader to send the result back to
+    /// - `client_name`: Name of the client that submitted the task
+    /// - `request_id`: The task's request ID
+    /// - `secret_image_data`: Raw bytes of the secret image to hide
+    ForwardedTask {
+        from_server_id: u32,
+        client_name: String,
+        request_id: u64,
+        secret_image_data: Vec<u8>,
+    },
+
+    /// **Forwarded Task Result**
+    ///
+    /// Sent back to the leader by the server that processed a `ForwardedTask`.
+    ///
+    /// # Fields
+    /// - `client_name`: Name of the client that submitted the task
+    /// - `request_id`: The task's request ID
+    /// - `response`: The `TaskResponse` or `TaskRejected` to relay to the client
+    ForwardedTaskResult {
+        client_name: String,
+        request_id: u64,
+        response: Box<Message>,
+    },
+
+    /// **Result Replicate**
+    ///
+    /// Sent by a server that completed a task to its backup (the next server by
+    /// ID) when result replication is enabled. If the server then fails before the
+    /// client's `TaskAck`, the task is reassigned to the backup, which answers the
+    /// resubmitted `TaskRequest` from its copy instead of encrypting again.
+    ///
+    /// # Fields
+    /// - `client_name`: Name of the client that submitted the task
+    /// - `request_id`: The task's request ID
+    /// - `response`: The successful `TaskResponse`
+    ResultReplicate {
+        client_name: String,
+        request_id: u64,
+        response: Box<Message>,
+    },
+
     /// **Task Acknowledgment**
     ///
     /// Sent by clients after successfully receiving a TaskResponse to confirm receipt.
@@ -178,10 +458,13 @@ pub enum Message {
     /// - `request_id`: ID of the task being queried
     /// - `assigned_server_id`: Current server assigned to process this task
     /// - `assigned_server_address`: Network address of the assigned server
+    /// - `leader_id`: Leader the responding server follows (None if it knows of
+    ///   none), which the client names as the assigner when it resends the task
     TaskStatusResponse {
         request_id: u64,
         assigned_server_id: u32,
         assigned_server_address: String,
+        leader_id: Option<u32>,
     },
 
     // ========== FAULT TOLERANCE MESSAGES ==========
@@ -194,7 +477,8 @@ pub enum Message {
     /// - `client_name`: Client that submitted the task
     /// - `request_id`: ID of the task
     /// - `assigned_server_id`: Server responsible for this task
-    /// - `timestamp`: When the assignment was made
+    /// - `timestamp`: When the assignment was made (Unix millis, ordered after
+    ///   every history change the sender had seen)
     HistoryAdd {
         client_name: String,
         request_id: u64,
@@ -205,14 +489,19 @@ pub enum Message {
     /// **History Remove**
     ///
     /// Sent by servers after successfully completing a task to remove it from
-    /// the tracked history.
+    /// the tracked history. A `HistoryAdd` for the task that arrives afterwards
+    /// but is no newer than `timestamp` is ignored, so reordered delivery can't
+    /// leave a phantom entry behind.
     ///
     /// # Fields
     /// - `client_name`: Client that submitted the task
     /// - `request_id`: ID of the completed task
+    /// - `timestamp`: When the task was removed (Unix millis, ordered after
+    ///   every history change the sender had seen)
     HistoryRemove {
         client_name: String,
         request_id: u64,
+        timestamp: u64,
     },
 
     /// **History Sync Request**
@@ -236,6 +525,43 @@ pub enum Message {
         from_server_id: u32,
         history_entries: Vec<(String, u64, u32, u64)>,
     },
+
+    // ========== RECOVERY ==========
+    /// **Recovery Request**
+    ///
+    /// Sent by a server on startup to each peer, on a short-lived connection, to
+    /// catch up on the cluster's state. Only the leader answers, with a
+    /// `StateSync` on the same connection.
+    ///
+    /// # Fields
+    /// - `from_id`: ID of the recovering server
+    RecoveryRequest { from_id: u32 },
+
+    /// **State Sync**
+    ///
+    /// The leader's answer to a RecoveryRequest. The recovering server follows the
+    /// leader right away instead of electing one, and merges the task history.
+    ///
+    /// # Fields
+    /// - `leader_id`: ID of the answering leader
+    /// - `term`: The election term the leader won
+    /// - `history_entries`: List of (client_name, request_id, assigned_server_id, timestamp) tuples
+    StateSync {
+        leader_id: u32,
+        term: u64,
+        history_entries: Vec<(String, u64, u32, u64)>,
+    },
+
+    // ========== FAULT INJECTION ==========
+    /// **Simulate Fail Message**
+    ///
+    /// Sent by a test or operator to make a server play dead: for the duration it
+    /// ignores every incoming message and stops sending heartbeats, so its peers
+    /// detect it as failed. It then recovers and rejoins the cluster.
+    ///
+    /// # Fields
+    /// - `duration_secs`: How long the server stays failed
+    SimulateFail { duration_secs: u64 },
 }
 
 impl Message {
@@ -247,7 +573,7 @@ impl Message {
     ///
     /// # Example
     /// ```ignore
-    /// let msg = Message::Heartbeat { from_id: 1, timestamp: 12345, load: 0.5 };
+    /// let msg = Message::Coordinator { leader_id: 1, term: 3 };
     /// let bytes = msg.to_bytes()?;
     /// ```
     pub fn to_bytes(&self) -> anyhow::Result<Vec<u8>> {
@@ -274,6 +600,180 @@ impl Message {
     pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
         Ok(serde_json::from_slice(bytes)?)
     }
+
+    /// The wire format this message should be sent with.
+    ///
+    /// Image-bearing messages use bincode when the `bincode` feature is enabled;
+    /// everything else stays JSON so control traffic remains human-readable.
+    pub fn preferred_format(&self) -> WireFormat {
+        match self {
+            Message::TaskRequest { .. }
+            | Message::TaskResponse { .. }
+            | Message::RoutedTaskRequest { .. }
+            | Message::ForwardedTask { .. }
+            | Message::ForwardedTaskResult { .. }
+            | Message::ResultReplicate { .. }
+                if cfg!(feature = "bincode") =>
+            {
+                WireFormat::Bincode
+            }
+            _ => WireFormat::Json,
+        }
+    }
+
+    /// The image carried by an image-bearing message, if any.
+    ///
+    /// `ForwardedTaskResult` and `ResultReplicate` carry the image of the response
+    /// they wrap. A [`Connection`](super::connection::Connection) sends it after the
+    /// rest of the message (see [`without_payload`](Self::without_payload)) so it
+    /// is written and read without being copied into an encoding buffer.
+    pub fn payload(&self) -> Option<&[u8]> {
+        match self {
+            Message::TaskRequest {
+                secret_image_data, ..
+            }
+            | Message::RoutedTaskRequest {
+                secret_image_data, ..
+            }
+            | Message::ForwardedTask {
+                secret_image_data, ..
+            } => Some(secret_image_data),
+            Message::TaskResponse {
+                encrypted_image_data,
+                ..
+            } => Some(encrypted_image_data),
+            Message::ForwardedTaskResult { response, .. }
+            | Message::ResultReplicate { response, .. } => response.payload(),
+            _ => None,
+        }
+    }
+
+    /// Mutable access to the image returned by [`payload`](Self::payload).
+    pub fn payload_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            Message::TaskRequest {
+                secret_image_data, ..
+            }
+            | Message::RoutedTaskRequest {
+                secret_image_data, ..
+            }
+            | Message::ForwardedTask {
+                secret_image_data, ..
+            } => Some(secret_image_data),
+            Message::TaskResponse {
+                encrypted_image_data,
+                ..
+            } => Some(encrypted_image_data),
+            Message::ForwardedTaskResult { response, .. }
+            | Message::ResultReplicate { response, .. } => response.payload_mut(),
+            _ => None,
+        }
+    }
+
+    /// A copy of an image-bearing message with an empty image, without copying the
+    /// image itself. `None` for messages without a [`payload`](Self::payload).
+    pub fn without_payload(&self) -> Option<Message> {
+        let header = match self {
+            Message::TaskRequest {
+                client_name,
+                request_id,
+                assigned_by_leader,
+                metadata,
+                ..
+            } => Message::TaskRequest {
+                client_name: client_name.clone(),
+                request_id: *request_id,
+                secret_image_data: Vec::new(),
+                assigned_by_leader: *assigned_by_leader,
+                metadata: metadata.clone(),
+            },
+            Message::TaskResponse {
+                request_id,
+                success,
+                error_message,
+                ..
+            } => Message::TaskResponse {
+                request_id: *request_id,
+                encrypted_image_data: Vec::new(),
+                success: *success,
+                error_message: error_message.clone(),
+            },
+            Message::RoutedTaskRequest {
+                client_name,
+                request_id,
+                ..
+            } => Message::RoutedTaskRequest {
+                client_name: client_name.clone(),
+                request_id: *request_id,
+                secret_image_data: Vec::new(),
+            },
+            Message::ForwardedTask {
+                from_server_id,
+                client_name,
+                request_id,
+                ..
+            } => Message::ForwardedTask {
+                from_server_id: *from_server_id,
+                client_name: client_name.clone(),
+                request_id: *request_id,
+                secret_image_data: Vec::new(),
+            },
+            Message::ForwardedTaskResult {
+                client_name,
+                request_id,
+                response,
+            } => Message::ForwardedTaskResult {
+                client_name: client_name.clone(),
+                request_id: *request_id,
+                response: Box::new(response.without_payload()?),
+            },
+            Message::ResultReplicate {
+                client_name,
+                request_id,
+                response,
+            } => Message::ResultReplicate {
+                client_name: client_name.clone(),
+                request_id: *request_id,
+                response: Box::new(response.without_payload()?),
+            },
+            _ => return None,
+        };
+        Some(header)
+    }
+
+    /// Serialize a message with the given wire format.
+    ///
+    /// # Errors
+    /// - Serialization fails
+    /// - `format` is [`WireFormat::Bincode`] but the `bincode` feature is disabled
+    pub fn encode(&self, format: WireFormat) -> anyhow::Result<Vec<u8>> {
+        match format {
+            WireFormat::Json => self.to_bytes(),
+            #[cfg(feature = "bincode")]
+            WireFormat::Bincode => Ok(bincode::serialize(self)?),
+            #[cfg(not(feature = "bincode"))]
+            WireFormat::Bincode => Err(anyhow::anyhow!(
+                "bincode encoding requested but built without the `bincode` feature"
+            )),
+        }
+    }
+
+    /// Deserialize a message that was encoded with the given wire format.
+    ///
+    /// # Errors
+    /// - The bytes are not a valid message in `format`
+    /// - `format` is [`WireFormat::Bincode`] but the `bincode` feature is disabled
+    pub fn decode(bytes: &[u8], format: WireFormat) -> anyhow::Result<Self> {
+        match format {
+            WireFormat::Json => Self::from_bytes(bytes),
+            #[cfg(feature = "bincode")]
+            WireFormat::Bincode => Ok(bincode::deserialize(bytes)?),
+            #[cfg(not(feature = "bincode"))]
+            WireFormat::Bincode => Err(anyhow::anyhow!(
+                "Received a bincode message but built without the `bincode` feature"
+            )),
+        }
+    }
 }
 
 // ============================================================================
@@ -290,7 +790,15 @@ impl Message {
 /// # Example
 /// ```ignore
 /// let now = current_timestamp();
-/// let msg = Message::Heartbeat { from_id: 1, timestamp: now, load: 0.3 };
+/// let msg = Message::Heartbeat {
+///     from_id: 1,
+///     timestamp: now,
+///     load: 0.3,
+///     saturated: false,
+///     total_tasks: 12,
+///     leader_id: Some(2),
+///     term: 3,
+/// };
 /// ```
 #[allow(dead_code)]
 pub fn current_timestamp() -> u64 {
@@ -299,3 +807,118 @@ pub fn current_timestamp() -> u64 {
         .unwrap()
         .as_secs()
 }
+
+/// Get the current Unix timestamp in milliseconds.
+///
+/// Used where second resolution is too coarse, e.g. measuring heartbeat
+/// inter-arrival times for failure detection.
+#[allow(dead_code)]
+pub fn current_timestamp_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_format_tags_round_trip() {
+        for format in [WireFormat::Json, WireFormat::Bincode] {
+            assert_eq!(WireFormat::from_tag(format.tag()).unwrap(), format);
+        }
+        assert!(WireFormat::from_tag(7).is_err());
+    }
+
+    #[test]
+    fn test_control_messages_stay_json() {
+        let heartbeat = Message::Heartbeat {
+            from_id: 1,
+            timestamp: 0,
+            load: 0.5,
+            saturated: false,
+            total_tasks: 0,
+            leader_id: None,
+            term: 0,
+        };
+        assert_eq!(heartbeat.preferred_format(), WireFormat::Json);
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn test_bincode_vs_json_on_2mb_image() {
+        // 2MB of varied bytes, like an encoded carrier image
+        let encrypted_image_data = (0..2 * 1024 * 1024)
+            .map(|i: u32| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        let message = Message::TaskResponse {
+            request_id: 42,
+            encrypted_image_data,
+            success: true,
+            error_message: None,
+        };
+        assert_eq!(message.preferred_format(), WireFormat::Bincode);
+
+        let mut sizes = Vec::new();
+        for format in [WireFormat::Json, WireFormat::Bincode] {
+            let bytes = message.encode(format).unwrap();
+            let decoded = Message::decode(&bytes, format).unwrap();
+
+            match (&message, decoded) {
+                (
+                    Message::TaskResponse {
+                        encrypted_image_data: expected,
+                        ..
+                    },
+                    Message::TaskResponse {
+                        request_id,
+                        encrypted_image_data,
+                        ..
+                    },
+                ) => {
+                    assert_eq!(request_id, 42);
+                    assert_eq!(&encrypted_image_data, expected);
+                }
+                (_, other) => panic!("Decoded wrong message: {:?}", other),
+            }
+            sizes.push(bytes.len());
+        }
+
+        // JSON writes each byte as a decimal number plus a comma; bincode stores it raw
+        assert!(sizes[1] * 2 < sizes[0]);
+    }
+
+    #[test]
+    fn test_forwarded_task_result_round_trips() {
+        let message = Message::ForwardedTaskResult {
+            client_name: "Client1".to_string(),
+            request_id: 3,
+            response: Box::new(Message::TaskResponse {
+                request_id: 3,
+                encrypted_image_data: vec![1, 2, 3],
+                success: true,
+                error_message: None,
+            }),
+        };
+
+        let format = message.preferred_format();
+        let decoded = Message::decode(&message.encode(format).unwrap(), format).unwrap();
+        match decoded {
+            Message::ForwardedTaskResult {
+                request_id: 3,
+                response,
+                ..
+            } => assert!(matches!(
+                *response,
+                Message::TaskResponse {
+                    request_id: 3,
+                    ref encrypted_image_data,
+                    ..
+                } if encrypted_image_data == &[1, 2, 3]
+            )),
+            other => panic!("Decoded wrong message: {:?}", other),
+        }
+    }
+}
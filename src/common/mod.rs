@@ -6,8 +6,14 @@
 //!
 //! - [`messages`]: Protocol message definitions for client-server and peer-to-peer communication
 //! - [`connection`]: TCP connection abstraction with message framing
+//! - [`transport`]: TCP or Unix domain socket streams and listeners
 //! - [`config`]: Configuration parsing utilities
+//! - [`logging`]: Logger setup with text or JSON line output
+//! - [`error`]: Classified failures ([`error::CloudP2PError`]) callers act on
 
 pub mod messages;
 pub mod connection;
+pub mod transport;
 pub mod config;
+pub mod logging;
+pub mod error;
@@ -0,0 +1,218 @@
+//! # Transports
+//!
+//! Listens and connects over TCP or, for co-located servers, Unix domain sockets.
+//!
+//! The address decides which: one containing a `/` (e.g. `/tmp/cloudp2p/server1.sock`)
+//! is a Unix socket path, anything else is a TCP `host:port`. Configuring every
+//! server of a single-machine cluster with a socket path avoids loopback TCP and
+//! port management; mixing both kinds of address in one cluster also works.
+//!
+//! [`Stream`] is what a [`Connection`](super::connection::Connection) runs over, so
+//! the framing protocol is the same on both transports.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! let listener = Listener::bind("/tmp/cloudp2p/server1.sock").await?;
+//! let (stream, from) = listener.accept().await?;
+//!
+//! let mut conn = Connection::new(Stream::connect("127.0.0.1:8002").await?);
+//! ```
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
+/// Whether `address` is a Unix domain socket path rather than a TCP `host:port`.
+pub fn is_unix_address(address: &str) -> bool {
+    address.contains('/')
+}
+
+/// A connected stream over either transport.
+pub enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    /// Connect to `address`, over a Unix socket if it is a path and TCP otherwise.
+    pub async fn connect(address: &str) -> io::Result<Self> {
+        if is_unix_address(address) {
+            #[cfg(unix)]
+            return Ok(Stream::Unix(UnixStream::connect(address).await?));
+            #[cfg(not(unix))]
+            return Err(unsupported(address));
+        }
+        Ok(Stream::Tcp(TcpStream::connect(address).await?))
+    }
+}
+
+impl From<TcpStream> for Stream {
+    fn from(stream: TcpStream) -> Self {
+        Stream::Tcp(stream)
+    }
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Stream::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+/// A listener on either transport.
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    /// Listen on `address`, a Unix socket path or a TCP `host:port`.
+    ///
+    /// A socket file left behind at the path by a previous run is replaced.
+    pub async fn bind(address: &str) -> io::Result<Self> {
+        if is_unix_address(address) {
+            #[cfg(unix)]
+            {
+                match std::fs::remove_file(address) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+                return Ok(Listener::Unix(UnixListener::bind(address)?));
+            }
+            #[cfg(not(unix))]
+            return Err(unsupported(address));
+        }
+        Ok(Listener::Tcp(TcpListener::bind(address).await?))
+    }
+
+    /// Accept the next connection, with a description of where it came from.
+    pub async fn accept(&self) -> io::Result<(Stream, String)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok((Stream::Tcp(stream), addr.to_string()))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                // Connecting Unix sockets are normally unnamed
+                let (stream, _) = listener.accept().await?;
+                Ok((Stream::Unix(stream), "local socket".to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn unsupported(address: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "Unix socket address '{}' is not supported on this platform",
+            address
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::connection::Connection;
+    use crate::common::messages::Message;
+
+    /// Handshake and exchange a message over a listener bound to `address`.
+    async fn round_trip(listener: Listener, address: &str) {
+        let server = async {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut conn = Connection::new(stream);
+            conn.accept_handshake().await.unwrap();
+            conn.read_message().await.unwrap()
+        };
+        let client = async {
+            let mut conn = Connection::new(Stream::connect(address).await.unwrap());
+            conn.handshake().await.unwrap();
+            conn.write_message(&Message::LeaderQuery).await.unwrap();
+        };
+        let (received, ()) = tokio::join!(server, client);
+        assert!(matches!(received, Some(Message::LeaderQuery)));
+    }
+
+    #[test]
+    fn test_paths_are_unix_addresses() {
+        assert!(is_unix_address("/tmp/cloudp2p/server1.sock"));
+        assert!(is_unix_address("run/server1.sock"));
+        assert!(!is_unix_address("127.0.0.1:8001"));
+        assert!(!is_unix_address("localhost:8001"));
+        assert!(!is_unix_address("[::1]:8001"));
+    }
+
+    #[tokio::test]
+    async fn test_host_port_address_uses_tcp() {
+        let listener = Listener::bind("127.0.0.1:0").await.unwrap();
+        let address = match &listener {
+            Listener::Tcp(tcp) => tcp.local_addr().unwrap().to_string(),
+            #[cfg(unix)]
+            Listener::Unix(_) => panic!("host:port bound a Unix socket"),
+        };
+        round_trip(listener, &address).await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_path_address_uses_unix_socket_and_replaces_stale_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("server1.sock");
+        let address = path.to_str().unwrap();
+
+        // A previous run's socket file doesn't stop the next one binding
+        drop(Listener::bind(address).await.unwrap());
+        assert!(path.exists());
+
+        let listener = Listener::bind(address).await.unwrap();
+        assert!(matches!(listener, Listener::Unix(_)));
+        round_trip(listener, address).await;
+    }
+}
@@ -32,5 +32,6 @@ pub mod server;
 
 // Re-export commonly used types for convenience
 pub use client::middleware::ClientMiddleware;
+pub use common::error::CloudP2PError;
 pub use common::messages::Message;
 pub use server::middleware::ServerMiddleware;
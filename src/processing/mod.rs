@@ -6,4 +6,9 @@
 pub mod steganography;
 
 // Re-export main functions for convenience
-pub use steganography::{embed_text_bytes, extract_text_bytes};
+pub use steganography::{
+    capacity_bytes, embed_image_bytes_with_policy, embed_text_bytes, extract_image_with_info,
+    extract_text_bytes, has_embedded_payload, image_capacity_bytes, view_image_bytes,
+    AccessPolicy, EmbedOptions, ExtractedImageInfo, PolicyView, SecretImageFormat,
+    SteganographyError,
+};
@@ -1,141 +1,1493 @@
 //! # LSB Steganography Implementation
 //!
-//! Implements text embedding and extraction using Least Significant Bit (LSB) steganography.
+//! Implements text and image embedding/extraction using Least Significant Bit (LSB)
+//! steganography.
 //!
 //! ## Algorithm
 //!
-//! The LSB steganography technique hides text within an image by modifying the least
-//! significant bit of each color channel (R, G, B) in the image pixels.
+//! The LSB steganography technique hides data within an image by modifying the
+//! low-order bits of each color channel (R, G, B) in the image pixels.
+//!
+//! ### Payload Layout
+//!
+//! Every embedded payload starts with a small fixed-size header, always written
+//! with 1 bit per channel so it can be read before the bit-depth is known:
+//!
+//! ```text
+//! [2 bytes: magic "SG"] [1 byte: version] [1 byte: bits per channel] [1 byte: flags]
+//! [8 bytes: scatter seed] [4 bytes: payload length] [4 bytes: CRC32 of payload]
+//! ```
+//!
+//! The payload itself follows immediately after the header pixels and is written
+//! with `bits_per_channel` low bits per channel (1-4). The CRC32 lets the extractor
+//! detect a carrier that was truncated, re-encoded, or corrupted in transit.
+//!
+//! ### Image Info
+//!
+//! Secret images are prefixed with a small typed info block before embedding, so
+//! [`extract_image_with_info`] can report what was extracted without the caller
+//! having to sniff the bytes:
+//!
+//! ```text
+//! [1 byte: format (PNG/JPEG/BMP/unknown)] [4 bytes: width] [4 bytes: height]
+//! ```
+//!
+//! The `IMAGE_INFO` flag marks its presence, and it counts against carrier
+//! capacity like any other payload byte (see [`image_capacity_bytes`]).
+//!
+//! ### Access Policy
+//!
+//! Secret images embedded with [`embed_image_bytes_with_policy`] are prefixed
+//! (before the info block) with an [`AccessPolicy`]: the users allowed to view
+//! the secret and how many views remain.
+//!
+//! ```text
+//! [4 bytes: remaining views] [1 byte: user count] ([1 byte: name length] [name])*
+//! ```
+//!
+//! The `ACCESS_POLICY` flag marks its presence. The plain extraction functions
+//! refuse such payloads; [`view_image_bytes`] checks the policy, and returns the
+//! secret together with a re-embedded carrier that records the used-up view. The
+//! policy is advisory: it is not encrypted, so it only binds well-behaved readers.
+//!
+//! ### Metadata
+//!
+//! Secret images embedded with [`embed_image_bytes_with_metadata`] carry a short
+//! UTF-8 text (a caption, an owner, a timestamp) in a block after any access
+//! policy and before the info block:
+//!
+//! ```text
+//! [2 bytes: text length] [text]
+//! ```
+//!
+//! The `METADATA` flag marks its presence. [`extract_metadata`] reads the text back
+//! on its own; the image extraction functions skip over it.
+//!
+//! ### Multiple Images
+//!
+//! [`embed_images`] hides several secret images in one carrier. The payload starts
+//! with a table of their lengths, followed by each image behind its own info
+//! block:
+//!
+//! ```text
+//! [2 bytes: image count] ([4 bytes: image length])* ([info block] [image])*
+//! ```
+//!
+//! The `MULTI_IMAGE` flag marks this layout. [`extract_image_at`] returns one
+//! image by its index; the single-image extraction functions refuse such payloads.
+//!
+//! ### Alpha Channel
+//!
+//! The alpha channel is skipped by default for compatibility. With
+//! [`EmbedOptions::use_alpha`] set, the payload is written into all four channels
+//! (R, G, B, A) for 33% more capacity, and the `ALPHA` flag tells the extractor to
+//! read four channels per pixel. The header itself always uses R, G, B only.
+//!
+//! ### Scattering
+//!
+//! By default payload bits are written into pixels sequentially after the header.
+//! With [`EmbedOptions::scatter_seed`] set, the payload pixels are instead a seeded
+//! pseudo-random sample of the whole image, which defeats simple statistical
+//! analysis of the top rows and survives localized cropping of a region better.
+//! The seed is stored in the header so the extractor can rebuild the same order.
+//!
+//! ### Compression
+//!
+//! With [`EmbedOptions::compress`] set, the payload is deflated before embedding and
+//! the `COMPRESSED` flag is recorded in the header so extraction inflates it again.
+//! If deflating does not shrink the payload, it is stored raw.
+//!
+//! ### Key Protection
+//!
+//! Payloads embedded with a key are XORed with a ChaCha20 keystream seeded from the
+//! SHA-256 of the key. The CRC32 is computed over the stored (possibly compressed)
+//! bytes before obfuscation, so extracting with the wrong key fails the checksum
+//! instead of returning junk.
+//!
+//! ### Parallel Embedding
+//!
+//! With the `parallel` cargo feature (on by default), sequential payloads are
+//! written by [rayon](https://docs.rs/rayon) tasks, each taking a band of whole
+//! rows. Payload pixel `i` always holds payload bits from `i * channels *
+//! bits_per_channel`, so each band works out where its share of the payload starts
+//! without waiting for the bands before it. Scattered payloads are written on one
+//! thread. The output is identical either way.
+//!
+//! ### Progress
+//!
+//! [`embed_image_bytes_with_progress`] reports how much of the payload has been
+//! written through a [`ProgressCallback`], an `Fn(u8) + Sync` called with the
+//! percentage done (0-100). It is called from whichever thread finished a piece
+//! of the payload, at most once per percentage point and never with a lower value
+//! than before, and always ends with 100. Encoding the carrier as PNG follows the
+//! last call.
+//!
+//! ### Output Format
+//!
+//! Carriers are written as PNG unless [`EmbedOptions::output_format`] picks another
+//! [`CarrierFormat`]. Only lossless formats are offered: a lossy encoder such as
+//! JPEG rewrites the low bits the payload lives in, so asking for one (by name or
+//! as an [`image::ImageFormat`]) fails with [`SteganographyError::LossyFormat`];
+//! [`is_lossless_format`] tells which formats are safe. Extraction reads any of
+//! them, and when it fails on a carrier that was re-saved in a lossy format the
+//! error says so.
 //!
 //! ### Encoding Process
-//! 1. Convert text to bytes and prepend 4-byte length prefix
-//! 2. For each bit in the text data:
-//!    - Get the next pixel's RGB channels
-//!    - Clear the LSB of each channel
-//!    - Set the LSB to match the data bit
+//! 1. Build the header and write it into the LSBs of the first pixels
+//! 2. For each group of `bits_per_channel` payload bits:
+//!    - Get the next pixel's RGB channel
+//!    - Clear the low `bits_per_channel` bits of the channel
+//!    - Set them to the data bits
 //!    - Move to next channel (R → G → B → next pixel)
-//! 3. Save the modified image as PNG
+//! 3. Save the modified image as PNG (or the chosen [`CarrierFormat`])
 //!
 //! ### Decoding Process
-//! 1. Read the first 32 bits (4 bytes) to get the text length
-//! 2. Extract the next N bits (where N = length * 8)
-//! 3. Convert bits back to bytes and then to UTF-8 string
+//! 1. Read the header (1 bit per channel) to get the bit-depth and payload length
+//! 2. Extract `length * 8` bits from the payload pixels at the stored bit-depth
+//! 3. Convert bits back to bytes and verify the CRC32
 //!
 //! ### Capacity
-//! An image can store approximately `(width * height * 3) / 8` bytes of text,
+//! An image can store approximately `(width * height * 3 * bits_per_channel) / 8` bytes,
 //! where 3 represents the RGB channels.
 //!
-//! Example: An 800x600 image can store ~180 KB of text.
+//! Example: An 800x600 image can store ~180 KB at 1 bit per channel, ~720 KB at 4.
 
 use anyhow::Result;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
 use image::GenericImageView;
+use rand::seq::index;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+/// Number of low bits used per channel when no bit-depth is specified.
+pub const DEFAULT_BITS_PER_CHANNEL: u8 = 1;
+
+/// Maximum supported bits per channel. Beyond 4 bits the visual distortion
+/// becomes obvious.
+pub const MAX_BITS_PER_CHANNEL: u8 = 4;
+
+/// Magic bytes identifying a CloudP2P steganography header.
+const HEADER_MAGIC: [u8; 2] = *b"SG";
+
+/// Current header layout version.
+///
+/// - Version 1: magic, version, bit-depth, length
+/// - Version 2: adds a CRC32 of the payload after the length
+/// - Version 3: adds a flags byte after the bit-depth
+/// - Version 4: adds a scatter seed after the flags
+const HEADER_VERSION: u8 = 4;
 
-/// Embed text into an image using LSB steganography.
+/// Size of the encoded header in bytes:
+/// magic (2) + version (1) + bit-depth (1) + flags (1) + seed (8) + length (4) + CRC32 (4).
+const HEADER_SIZE: usize = 21;
+
+/// Header flag: payload is XORed with a key-derived keystream.
+const FLAG_KEYED: u8 = 0b0000_0001;
+
+/// Header flag: payload is deflate-compressed.
+const FLAG_COMPRESSED: u8 = 0b0000_0010;
+
+/// Header flag: payload pixels are a seeded random sample rather than sequential.
+const FLAG_SCATTERED: u8 = 0b0000_0100;
+
+/// Header flag: payload uses all four channels (R, G, B, A) instead of three.
+const FLAG_ALPHA: u8 = 0b0000_1000;
+
+/// Header flag: payload starts with an [`ExtractedImageInfo`] block.
+const FLAG_IMAGE_INFO: u8 = 0b0001_0000;
+
+/// Header flag: payload starts with an [`AccessPolicy`] block (then the image info).
+const FLAG_ACCESS_POLICY: u8 = 0b0010_0000;
+
+/// Header flag: payload has a metadata text block (after any access policy).
+const FLAG_METADATA: u8 = 0b0100_0000;
+
+/// Header flag: payload is a table of several images (each with an info block).
 ///
-/// The text is prefixed with its length (4 bytes, big-endian) and then embedded
-/// into the least significant bits of the image's RGB channels.
+/// This is the last bit of the flags byte, so every flags value now has a
+/// meaning; a further flag needs a new [`HEADER_VERSION`].
+const FLAG_MULTI_IMAGE: u8 = 0b1000_0000;
+
+/// Size in bytes of the image info block prepended to embedded secret images.
+pub const IMAGE_INFO_SIZE: usize = 9;
+
+/// Number of color channels used per pixel (R, G, B - alpha is skipped for compatibility).
+const CHANNELS_PER_PIXEL: usize = 3;
+
+/// Number of channels used per pixel when the alpha channel is included.
+const CHANNELS_WITH_ALPHA: usize = 4;
+
+/// Roughly how many pixels each parallel task embeds into (rounded to whole rows),
+/// and how many are written between progress reports otherwise.
+const BAND_PIXELS: usize = 64 * 1024;
+
+/// Largest size a compressed payload is inflated to on extraction. Nothing bigger
+/// fits in a message (see [`crate::common::connection::DEFAULT_MAX_MESSAGE_SIZE`]),
+/// so a payload inflating past it is corrupt or a decompression bomb.
+const MAX_INFLATED_PAYLOAD_SIZE: usize = 100 * 1024 * 1024;
+
+/// Receives the percentage (0-100) of the payload embedded so far.
 ///
-/// # Arguments
-/// - `image_bytes`: Raw bytes of the input image (any format supported by `image` crate)
-/// - `text`: UTF-8 text to embed into the image
+/// See [Progress](self#progress) for when it is called.
+pub type ProgressCallback<'a> = dyn Fn(u8) + Sync + 'a;
+
+/// Turns payload pixels written into calls to a [`ProgressCallback`].
+struct ProgressTracker<'a> {
+    callback: Option<&'a ProgressCallback<'a>>,
+    /// Payload pixels to write in total
+    total: usize,
+    /// Pixels written so far, and the last percentage reported
+    state: Mutex<(usize, u8)>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    fn new(callback: Option<&'a ProgressCallback<'a>>, total: usize) -> Self {
+        Self {
+            callback,
+            total,
+            state: Mutex::new((0, 0)),
+        }
+    }
+
+    /// Record `pixels` more payload pixels written, reporting a new percentage.
+    ///
+    /// The callback runs under the lock, so concurrent bands report in order.
+    fn advance(&self, pixels: usize) {
+        let Some(callback) = self.callback else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        state.0 += pixels;
+        let percent = match self.total {
+            0 => 100,
+            total => (state.0 * 100 / total).min(100) as u8,
+        };
+        if percent > state.1 {
+            state.1 = percent;
+            callback(percent);
+        }
+    }
+}
+
+/// Errors specific to decoding an embedded payload.
 ///
-/// # Returns
-/// - `Ok(Vec<u8>)`: PNG image bytes with embedded text
-/// - `Err`: If image is too small, can't be loaded, or encoding fails
+/// Returned wrapped in an [`anyhow::Error`]; use `downcast_ref::<SteganographyError>()`
+/// to distinguish them from I/O or image decoding failures.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SteganographyError {
+    /// The image has no steganography header (plain image, or embedded by a
+    /// pre-header version of CloudP2P).
+    NoPayload,
+    /// The header was written with a layout version this build cannot read.
+    UnsupportedVersion { found: u8, expected: u8 },
+    /// The extracted payload does not match the CRC32 stored in the header,
+    /// meaning the carrier was truncated or corrupted after embedding, or the
+    /// wrong key was supplied for a key-protected payload.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The payload is key-protected and must be extracted with
+    /// [`extract_image_bytes_with_key`].
+    KeyRequired,
+    /// The payload has an [`AccessPolicy`] and must be viewed with
+    /// [`view_image_bytes`].
+    PolicyProtected,
+    /// The viewing user is not on the payload's [`AccessPolicy`].
+    AccessDenied { user: String },
+    /// The payload's [`AccessPolicy`] has no views left.
+    NoViewsLeft,
+    /// [`extract_image_at`] was asked for an image past the end of the payload.
+    ImageIndexOutOfRange { index: usize, count: usize },
+    /// The payload holds several images and must be read with [`extract_image_at`].
+    MultipleImages { count: usize },
+    /// A lossy output format was requested, which would destroy the payload.
+    LossyFormat { format: String },
+    /// The compressed payload inflates past the largest size extraction allows.
+    PayloadTooLarge { max: usize },
+}
+
+impl fmt::Display for SteganographyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SteganographyError::NoPayload => {
+                write!(
+                    f,
+                    "Image does not contain an embedded payload (header magic not found)"
+                )
+            }
+            SteganographyError::UnsupportedVersion { found, expected } => write!(
+                f,
+                "Unsupported steganography header version {} (expected {})",
+                found, expected
+            ),
+            SteganographyError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "Embedded payload is corrupt: checksum mismatch (expected {:08x}, got {:08x})",
+                expected, actual
+            ),
+            SteganographyError::KeyRequired => {
+                write!(
+                    f,
+                    "Embedded payload is key-protected; a key is required to extract it"
+                )
+            }
+            SteganographyError::PolicyProtected => {
+                write!(
+                    f,
+                    "Embedded payload has an access policy; it must be viewed as a named user"
+                )
+            }
+            SteganographyError::AccessDenied { user } => {
+                write!(f, "User '{}' is not allowed to view this image", user)
+            }
+            SteganographyError::NoViewsLeft => {
+                write!(f, "This image has no views left")
+            }
+            SteganographyError::ImageIndexOutOfRange { index, count } => write!(
+                f,
+                "No image at index {}: the carrier holds {} image(s)",
+                index, count
+            ),
+            SteganographyError::MultipleImages { count } => write!(
+                f,
+                "Embedded payload holds {} images; extract them one at a time by index",
+                count
+            ),
+            SteganographyError::LossyFormat { format } => write!(
+                f,
+                "{} is lossy and will destroy embedded data; use PNG/BMP/WebP.",
+                format
+            ),
+            SteganographyError::PayloadTooLarge { max } => write!(
+                f,
+                "Embedded payload inflates past {} bytes; refusing to decompress it",
+                max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SteganographyError {}
+
+/// Encoding of an embedded secret image, as recorded in its info block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretImageFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    /// Not a recognised image (or embedded without an info block).
+    Unknown,
+}
+
+impl SecretImageFormat {
+    /// MIME type to label the extracted bytes with.
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            SecretImageFormat::Png => "image/png",
+            SecretImageFormat::Jpeg => "image/jpeg",
+            SecretImageFormat::Bmp => "image/bmp",
+            SecretImageFormat::Unknown => "application/octet-stream",
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            SecretImageFormat::Unknown => 0,
+            SecretImageFormat::Png => 1,
+            SecretImageFormat::Jpeg => 2,
+            SecretImageFormat::Bmp => 3,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Self {
+        match byte {
+            1 => SecretImageFormat::Png,
+            2 => SecretImageFormat::Jpeg,
+            3 => SecretImageFormat::Bmp,
+            _ => SecretImageFormat::Unknown,
+        }
+    }
+}
+
+/// Format and dimensions of an embedded secret image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractedImageInfo {
+    pub format: SecretImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ExtractedImageInfo {
+    /// Sniff the format and dimensions of `image_bytes` without fully decoding it.
+    ///
+    /// Bytes that are not a PNG, JPEG or BMP (or whose dimensions cannot be read)
+    /// yield [`SecretImageFormat::Unknown`] with zero dimensions.
+    fn probe(image_bytes: &[u8]) -> Self {
+        let format = match image::guess_format(image_bytes) {
+            Ok(image::ImageFormat::Png) => SecretImageFormat::Png,
+            Ok(image::ImageFormat::Jpeg) => SecretImageFormat::Jpeg,
+            Ok(image::ImageFormat::Bmp) => SecretImageFormat::Bmp,
+            _ => SecretImageFormat::Unknown,
+        };
+
+        let dimensions = image::io::Reader::new(std::io::Cursor::new(image_bytes))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok());
+
+        match (format, dimensions) {
+            (SecretImageFormat::Unknown, _) | (_, None) => Self {
+                format: SecretImageFormat::Unknown,
+                width: 0,
+                height: 0,
+            },
+            (format, Some((width, height))) => Self {
+                format,
+                width,
+                height,
+            },
+        }
+    }
+
+    fn to_bytes(self) -> [u8; IMAGE_INFO_SIZE] {
+        let mut bytes = [0u8; IMAGE_INFO_SIZE];
+        bytes[0] = self.format.to_byte();
+        bytes[1..5].copy_from_slice(&self.width.to_be_bytes());
+        bytes[5..9].copy_from_slice(&self.height.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < IMAGE_INFO_SIZE {
+            return Err(anyhow::anyhow!(
+                "Corrupt payload: image info block truncated ({} bytes)",
+                bytes.len()
+            ));
+        }
+        Ok(Self {
+            format: SecretImageFormat::from_byte(bytes[0]),
+            width: u32::from_be_bytes(bytes[1..5].try_into()?),
+            height: u32::from_be_bytes(bytes[5..9].try_into()?),
+        })
+    }
+}
+
+/// Who may view an embedded secret image, and how many more times.
 ///
-/// # Errors
-/// - Image is too small to hold the text
-/// - Image format is invalid
-/// - Encoding to PNG fails
+/// # Example
+/// ```ignore
+/// let policy = AccessPolicy {
+///     allowed_users: vec!["alice".to_string()],
+///     remaining_views: 5,
+/// };
+/// let carrier = embed_image_bytes_with_policy(&carrier, &secret, &policy)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessPolicy {
+    /// Users allowed to view the secret (empty means anyone)
+    pub allowed_users: Vec<String>,
+    /// Views left before the secret is no longer revealed
+    pub remaining_views: u32,
+}
+
+impl AccessPolicy {
+    /// Whether `user` may view the secret (views left aside).
+    pub fn allows(&self, user: &str) -> bool {
+        self.allowed_users.is_empty() || self.allowed_users.iter().any(|allowed| allowed == user)
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let count = u8::try_from(self.allowed_users.len()).map_err(|_| {
+            anyhow::anyhow!(
+                "Access policy lists {} users, at most 255 are supported",
+                self.allowed_users.len()
+            )
+        })?;
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&self.remaining_views.to_be_bytes());
+        bytes.push(count);
+        for user in &self.allowed_users {
+            let len = u8::try_from(user.len())
+                .ok()
+                .filter(|len| *len > 0)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Access policy user names must be 1-255 bytes: '{}'", user)
+                })?;
+            bytes.push(len);
+            bytes.extend_from_slice(user.as_bytes());
+        }
+        Ok(bytes)
+    }
+
+    /// Parse a policy block, returning it with the number of bytes it took up.
+    fn from_bytes(bytes: &[u8]) -> Result<(Self, usize)> {
+        let truncated = || anyhow::anyhow!("Corrupt payload: access policy block truncated");
+
+        let remaining_views =
+            u32::from_be_bytes(bytes.get(0..4).ok_or_else(truncated)?.try_into()?);
+        let count = *bytes.get(4).ok_or_else(truncated)?;
+
+        let mut pos = 5;
+        let mut allowed_users = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let len = *bytes.get(pos).ok_or_else(truncated)? as usize;
+            let name = bytes.get(pos + 1..pos + 1 + len).ok_or_else(truncated)?;
+            allowed_users.push(String::from_utf8(name.to_vec())?);
+            pos += 1 + len;
+        }
+
+        Ok((
+            Self {
+                allowed_users,
+                remaining_views,
+            },
+            pos,
+        ))
+    }
+}
+
+/// Size in bytes of the length prefix of a metadata block.
+pub(crate) const METADATA_PREFIX_SIZE: usize = 2;
+
+/// Encode `text` as a metadata block.
+fn metadata_to_bytes(text: &str) -> Result<Vec<u8>> {
+    let len = u16::try_from(text.len()).map_err(|_| {
+        anyhow::anyhow!(
+            "Metadata is {} bytes, at most {} are supported",
+            text.len(),
+            u16::MAX
+        )
+    })?;
+    let mut bytes = Vec::with_capacity(METADATA_PREFIX_SIZE + text.len());
+    bytes.extend_from_slice(&len.to_be_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+    Ok(bytes)
+}
+
+/// Parse the metadata block at the start of `bytes` if `flags` has one, returning
+/// the text with the number of bytes it took up.
+fn metadata_from_bytes(flags: u8, bytes: &[u8]) -> Result<(Option<String>, usize)> {
+    if flags & FLAG_METADATA == 0 {
+        return Ok((None, 0));
+    }
+    let truncated = || anyhow::anyhow!("Corrupt payload: metadata block truncated");
+
+    let len = u16::from_be_bytes(
+        bytes
+            .get(..METADATA_PREFIX_SIZE)
+            .ok_or_else(truncated)?
+            .try_into()?,
+    ) as usize;
+    let end = METADATA_PREFIX_SIZE + len;
+    let text = bytes.get(METADATA_PREFIX_SIZE..end).ok_or_else(truncated)?;
+    Ok((Some(String::from_utf8(text.to_vec())?), end))
+}
+
+/// Size in bytes of each entry of a multi-image length table.
+const IMAGE_LENGTH_SIZE: usize = 4;
+
+/// Split a multi-image payload into its entries, each an info block and an image.
+fn split_images(payload: &[u8]) -> Result<Vec<&[u8]>> {
+    let truncated = || anyhow::anyhow!("Corrupt payload: image table truncated");
+
+    let count = u16::from_be_bytes(payload.get(..2).ok_or_else(truncated)?.try_into()?) as usize;
+    let mut pos = 2 + count * IMAGE_LENGTH_SIZE;
+    let table = payload.get(2..pos).ok_or_else(truncated)?;
+
+    let mut entries = Vec::with_capacity(count);
+    for length in table.chunks_exact(IMAGE_LENGTH_SIZE) {
+        let end = pos + IMAGE_INFO_SIZE + u32::from_be_bytes(length.try_into()?) as usize;
+        entries.push(payload.get(pos..end).ok_or_else(truncated)?);
+        pos = end;
+    }
+    Ok(entries)
+}
+
+/// A secret image viewed through its [`AccessPolicy`] with [`view_image_bytes`].
+#[derive(Debug, Clone)]
+pub struct PolicyView {
+    /// Format and dimensions of the secret image
+    pub info: ExtractedImageInfo,
+    /// The secret image bytes
+    pub secret: Vec<u8>,
+    /// The policy after this view
+    pub policy: AccessPolicy,
+    /// The carrier re-embedded with the updated policy, to store in place of the
+    /// original so the view stays used up
+    pub carrier: Vec<u8>,
+}
+
+/// Lossless image format a carrier is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CarrierFormat {
+    #[default]
+    Png,
+    /// Uncompressed, so the largest of the three
+    Bmp,
+    /// Lossless WebP, usually the smallest of the three
+    WebP,
+}
+
+impl CarrierFormat {
+    fn image_format(self) -> image::ImageFormat {
+        match self {
+            CarrierFormat::Png => image::ImageFormat::Png,
+            CarrierFormat::Bmp => image::ImageFormat::Bmp,
+            CarrierFormat::WebP => image::ImageFormat::WebP,
+        }
+    }
+
+    /// The format of an existing carrier, if it is one of these.
+    fn detect(carrier_bytes: &[u8]) -> Option<Self> {
+        image::guess_format(carrier_bytes)
+            .ok()
+            .and_then(|format| Self::try_from(format).ok())
+    }
+}
+
+impl TryFrom<image::ImageFormat> for CarrierFormat {
+    type Error = anyhow::Error;
+
+    /// Fails with [`SteganographyError::LossyFormat`] for JPEG and other lossy formats.
+    fn try_from(format: image::ImageFormat) -> Result<Self> {
+        match format {
+            image::ImageFormat::Png => Ok(CarrierFormat::Png),
+            image::ImageFormat::Bmp => Ok(CarrierFormat::Bmp),
+            image::ImageFormat::WebP => Ok(CarrierFormat::WebP),
+            lossy if !is_lossless_format(lossy) => Err(SteganographyError::LossyFormat {
+                format: format_name(lossy),
+            }
+            .into()),
+            other => Err(anyhow::anyhow!(
+                "Unsupported carrier format {:?} (expected PNG, BMP or WebP)",
+                other
+            )),
+        }
+    }
+}
+
+/// Whether a carrier saved in `format` keeps its embedded payload intact.
+///
+/// Lossy formats such as JPEG re-compress the pixels, rewriting the low bits the
+/// payload is stored in. WebP counts as lossless because carriers are only ever
+/// written with its lossless encoder; a WebP re-saved lossily elsewhere is not.
 ///
 /// # Example
 /// ```ignore
-/// let image_data = std::fs::read("input.jpg")?;
-/// let encrypted = embed_text_bytes(&image_data, "Secret message")?;
-/// std::fs::write("output.png", encrypted)?;
+/// let format = image::ImageFormat::from_path(&output_path)?;
+/// if !is_lossless_format(format) {
+///     return Err(anyhow::anyhow!("Save the carrier as PNG, not {:?}", format));
+/// }
 /// ```
-pub fn embed_text_bytes(image_bytes: &[u8], text: &str) -> Result<Vec<u8>> {
-    // Load the image from bytes
-    let img = image::load_from_memory(image_bytes)?;
-    let (width, height) = img.dimensions();
+pub fn is_lossless_format(format: image::ImageFormat) -> bool {
+    matches!(
+        format,
+        image::ImageFormat::Png
+            | image::ImageFormat::Bmp
+            | image::ImageFormat::WebP
+            | image::ImageFormat::Tiff
+            | image::ImageFormat::Pnm
+            | image::ImageFormat::Tga
+            | image::ImageFormat::Qoi
+            | image::ImageFormat::Farbfeld
+    )
+}
 
-    // Convert to RGBA format for consistent pixel manipulation
-    let mut img = img.to_rgba8();
+/// Upper-case name of `format` for messages, e.g. "JPEG".
+fn format_name(format: image::ImageFormat) -> String {
+    format!("{:?}", format).to_uppercase()
+}
 
-    // Prepare data to embed: [4 bytes length][text bytes]
-    let text_bytes = text.as_bytes();
-    let length = text_bytes.len() as u32;
-    let mut data_to_embed = Vec::new();
+/// Point out a lossy carrier format as the likely cause of a failed extraction.
+fn explain_lossy_carrier(carrier_bytes: &[u8], error: anyhow::Error) -> anyhow::Error {
+    match image::guess_format(carrier_bytes) {
+        Ok(format) if !is_lossless_format(format) => error.context(format!(
+            "Carrier is a {} image: {}",
+            format_name(format),
+            SteganographyError::LossyFormat {
+                format: format_name(format)
+            }
+        )),
+        _ => error,
+    }
+}
 
-    // Add length prefix (4 bytes, big-endian)
-    data_to_embed.extend_from_slice(&length.to_be_bytes());
-    // Add text content
-    data_to_embed.extend_from_slice(text_bytes);
+impl FromStr for CarrierFormat {
+    type Err = anyhow::Error;
 
-    // Check if image has enough capacity
-    // Each pixel has 3 usable channels (R, G, B), so 3 bits per pixel
-    let available_bits = (width * height * 3) as usize;
-    let required_bits = data_to_embed.len() * 8;
+    /// Parse a file extension such as `"png"`, `"bmp"` or `"webp"`.
+    fn from_str(s: &str) -> Result<Self> {
+        image::ImageFormat::from_extension(s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown carrier format '{}'", s))
+            .and_then(Self::try_from)
+    }
+}
 
-    if required_bits > available_bits {
-        return Err(anyhow::anyhow!(
-            "Image too small for this text: need {} bits but only have {} bits available",
-            required_bits, available_bits
-        ));
+/// Options controlling how a payload is embedded into a carrier image.
+///
+/// # Example
+/// ```ignore
+/// let options = EmbedOptions { bits_per_channel: 2, ..Default::default() };
+/// let result = embed_image_bytes_with_options(&carrier, &secret, &options)?;
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbedOptions {
+    /// Number of low bits to overwrite in each channel (1-4).
+    /// Higher values increase capacity at the cost of image fidelity.
+    pub bits_per_channel: u8,
+    /// Deflate the payload before embedding to fit larger secrets in a given carrier.
+    pub compress: bool,
+    /// Scatter payload bits across the image using this seed instead of writing
+    /// them sequentially.
+    pub scatter_seed: Option<u64>,
+    /// Also embed into the alpha channel, increasing capacity by a third.
+    pub use_alpha: bool,
+    /// Format the carrier is written in.
+    pub output_format: CarrierFormat,
+}
+
+impl Default for EmbedOptions {
+    fn default() -> Self {
+        Self {
+            bits_per_channel: DEFAULT_BITS_PER_CHANNEL,
+            compress: false,
+            scatter_seed: None,
+            use_alpha: false,
+            output_format: CarrierFormat::Png,
+        }
+    }
+}
+
+impl EmbedOptions {
+    /// Number of channels per pixel the payload is written into.
+    fn channels(&self) -> usize {
+        if self.use_alpha {
+            CHANNELS_WITH_ALPHA
+        } else {
+            CHANNELS_PER_PIXEL
+        }
     }
 
-    // Embed data into LSBs of image pixels
-    let mut data_index = 0; // Current byte being embedded
-    let mut bit_index = 0;  // Current bit within the byte (0-7)
+    /// Check that the options are within supported bounds.
+    fn validate(&self) -> Result<()> {
+        if !(1..=MAX_BITS_PER_CHANNEL).contains(&self.bits_per_channel) {
+            return Err(anyhow::anyhow!(
+                "bits_per_channel must be between 1 and {}, got {}",
+                MAX_BITS_PER_CHANNEL,
+                self.bits_per_channel
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Header stored at the start of every embedded payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PayloadHeader {
+    bits_per_channel: u8,
+    flags: u8,
+    seed: u64,
+    length: u32,
+    checksum: u32,
+}
+
+impl PayloadHeader {
+    fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0u8; HEADER_SIZE];
+        bytes[0..2].copy_from_slice(&HEADER_MAGIC);
+        bytes[2] = HEADER_VERSION;
+        bytes[3] = self.bits_per_channel;
+        bytes[4] = self.flags;
+        bytes[5..13].copy_from_slice(&self.seed.to_be_bytes());
+        bytes[13..17].copy_from_slice(&self.length.to_be_bytes());
+        bytes[17..21].copy_from_slice(&self.checksum.to_be_bytes());
+        bytes
+    }
 
-    'outer: for y in 0..height {
-        for x in 0..width {
-            // Stop if all data has been embedded
-            if data_index >= data_to_embed.len() {
-                break 'outer;
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes[0..2] != HEADER_MAGIC {
+            return Err(SteganographyError::NoPayload.into());
+        }
+        if bytes[2] != HEADER_VERSION {
+            return Err(SteganographyError::UnsupportedVersion {
+                found: bytes[2],
+                expected: HEADER_VERSION,
             }
+            .into());
+        }
 
-            let pixel = img.get_pixel(x, y);
-            let mut new_pixel = *pixel;
+        let bits_per_channel = bytes[3];
+        if !(1..=MAX_BITS_PER_CHANNEL).contains(&bits_per_channel) {
+            return Err(anyhow::anyhow!(
+                "Corrupt header: invalid bits_per_channel {}",
+                bits_per_channel
+            ));
+        }
 
-            // Embed into R, G, B channels (skip Alpha channel for compatibility)
-            for channel in 0..3 {
-                if data_index >= data_to_embed.len() {
-                    break 'outer;
-                }
+        let flags = bytes[4];
+        let seed = u64::from_be_bytes(bytes[5..13].try_into()?);
+        let length = u32::from_be_bytes(bytes[13..17].try_into()?);
+        let checksum = u32::from_be_bytes(bytes[17..21].try_into()?);
+        Ok(Self {
+            bits_per_channel,
+            flags,
+            seed,
+            length,
+            checksum,
+        })
+    }
 
-                // Extract the current bit from data (MSB first)
-                let bit = (data_to_embed[data_index] >> (7 - bit_index)) & 1;
+    /// Scatter seed, if the payload was embedded in scattered order.
+    fn scatter_seed(&self) -> Option<u64> {
+        (self.flags & FLAG_SCATTERED != 0).then_some(self.seed)
+    }
 
-                // Clear LSB and set it to our data bit
-                new_pixel[channel] = (pixel[channel] & 0xFE) | bit;
+    /// Number of channels per pixel the payload was written into.
+    fn channels(&self) -> usize {
+        if self.flags & FLAG_ALPHA != 0 {
+            CHANNELS_WITH_ALPHA
+        } else {
+            CHANNELS_PER_PIXEL
+        }
+    }
 
-                // Move to next bit
-                bit_index += 1;
-                if bit_index == 8 {
-                    bit_index = 0;
-                    data_index += 1;
-                }
+    /// Options that reproduce this payload's layout when embedding again.
+    fn embed_options(&self) -> EmbedOptions {
+        EmbedOptions {
+            bits_per_channel: self.bits_per_channel,
+            compress: self.flags & FLAG_COMPRESSED != 0,
+            scatter_seed: self.scatter_seed(),
+            use_alpha: self.flags & FLAG_ALPHA != 0,
+            ..Default::default()
+        }
+    }
+}
+
+/// Number of pixels needed to hold the header at 1 bit per channel.
+fn header_pixels() -> usize {
+    (HEADER_SIZE * 8).div_ceil(CHANNELS_PER_PIXEL)
+}
+
+/// Buffer indices of the first `channels` channels for the given pixels of an RGBA buffer.
+fn channel_slots(
+    pixels: impl IntoIterator<Item = usize>,
+    channels: usize,
+) -> impl Iterator<Item = usize> {
+    pixels
+        .into_iter()
+        .flat_map(move |pixel| (0..channels).map(move |channel| pixel * 4 + channel))
+}
+
+/// Pixels that hold a payload of `byte_len` bytes, in the order they are written.
+///
+/// Sequential payloads start right after the header; scattered payloads are a
+/// seeded random sample of all non-header pixels.
+fn payload_pixel_order(
+    total_pixels: usize,
+    byte_len: usize,
+    bits_per_channel: u8,
+    channels: usize,
+    scatter_seed: Option<u64>,
+) -> Result<Vec<usize>> {
+    let start = header_pixels();
+    let available = total_pixels.saturating_sub(start);
+    let needed = (byte_len * 8).div_ceil(channels * bits_per_channel as usize);
+
+    match scatter_seed {
+        Some(seed) => {
+            if needed > available {
+                return Err(anyhow::anyhow!(
+                    "Corrupt header: payload needs {} pixels but image only has {}",
+                    needed,
+                    available
+                ));
+            }
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            Ok(index::sample(&mut rng, available, needed)
+                .into_iter()
+                .map(|i| start + i)
+                .collect())
+        }
+        None => Ok((start..start + needed.min(available)).collect()),
+    }
+}
+
+/// Write `data` into the low `bits_per_channel` bits of the given channel slots (MSB first).
+fn write_bits(
+    buffer: &mut [u8],
+    slots: impl Iterator<Item = usize>,
+    data: &[u8],
+    bits_per_channel: u8,
+) {
+    write_bits_from(buffer, slots, data, bits_per_channel, 0);
+}
+
+/// Like [`write_bits`], but the first slot gets the bits from `first_bit` of `data` on.
+fn write_bits_from(
+    buffer: &mut [u8],
+    slots: impl Iterator<Item = usize>,
+    data: &[u8],
+    bits_per_channel: u8,
+    first_bit: usize,
+) {
+    let total_bits = data.len() * 8;
+    let mask = (1u8 << bits_per_channel) - 1;
+    let mut bit_pos = first_bit;
+
+    for slot in slots {
+        if bit_pos >= total_bits {
+            break;
+        }
+
+        // Gather the next `bits_per_channel` bits, zero-padding past the end of data
+        let mut value = 0u8;
+        for _ in 0..bits_per_channel {
+            value <<= 1;
+            if bit_pos < total_bits {
+                value |= (data[bit_pos / 8] >> (7 - bit_pos % 8)) & 1;
+            }
+            bit_pos += 1;
+        }
+
+        // Clear the low bits and set them to our data bits
+        buffer[slot] = (buffer[slot] & !mask) | value;
+    }
+}
+
+/// Write a sequential payload into an RGBA buffer `width` pixels wide, one band of
+/// rows per rayon task.
+///
+/// Payload pixel `i` (counting from the first after the header) holds the bits from
+/// `i * channels * bits_per_channel` on, so each band starts at the right bit of
+/// `data` on its own. Produces the same buffer as [`write_bits`] over
+/// [`payload_pixel_order`].
+#[cfg(feature = "parallel")]
+fn write_bits_in_bands(
+    buffer: &mut [u8],
+    width: usize,
+    data: &[u8],
+    bits_per_channel: u8,
+    channels: usize,
+    progress: &ProgressTracker<'_>,
+) {
+    use rayon::prelude::*;
+
+    let bits_per_pixel = channels * bits_per_channel as usize;
+    let start = header_pixels();
+    let end = start + (data.len() * 8).div_ceil(bits_per_pixel);
+    let band_pixels = (BAND_PIXELS / width).max(1) * width;
+
+    buffer
+        .par_chunks_mut(band_pixels * 4)
+        .enumerate()
+        .for_each(|(band, band_buffer)| {
+            let first = band * band_pixels;
+            let from = start.max(first);
+            let to = end.min(first + band_buffer.len() / 4);
+            if from < to {
+                write_bits_from(
+                    band_buffer,
+                    channel_slots(from - first..to - first, channels),
+                    data,
+                    bits_per_channel,
+                    (from - start) * bits_per_pixel,
+                );
+                progress.advance(to - from);
+            }
+        });
+}
+
+/// Write `data` into the payload pixels of an RGBA buffer `width` pixels wide,
+/// reporting to `progress` (if any) as it goes.
+///
+/// Sequential payloads are written in parallel row bands with the `parallel`
+/// feature; otherwise the payload pixels are filled in order on this thread, a
+/// band's worth at a time.
+fn write_payload(
+    buffer: &mut [u8],
+    width: usize,
+    data: &[u8],
+    options: &EmbedOptions,
+    progress: Option<&ProgressCallback<'_>>,
+) -> Result<()> {
+    let channels = options.channels();
+    let bits_per_pixel = channels * options.bits_per_channel as usize;
+    let tracker = ProgressTracker::new(progress, (data.len() * 8).div_ceil(bits_per_pixel));
+
+    #[cfg(feature = "parallel")]
+    if options.scatter_seed.is_none() {
+        write_bits_in_bands(
+            buffer,
+            width,
+            data,
+            options.bits_per_channel,
+            channels,
+            &tracker,
+        );
+        tracker.advance(0);
+        return Ok(());
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    let _ = width;
+    let order = payload_pixel_order(
+        buffer.len() / 4,
+        data.len(),
+        options.bits_per_channel,
+        channels,
+        options.scatter_seed,
+    )?;
+    for (i, pixels) in order.chunks(BAND_PIXELS).enumerate() {
+        write_bits_from(
+            buffer,
+            channel_slots(pixels.iter().copied(), channels),
+            data,
+            options.bits_per_channel,
+            i * BAND_PIXELS * bits_per_pixel,
+        );
+        tracker.advance(pixels.len());
+    }
+    tracker.advance(0);
+    Ok(())
+}
+
+/// Read `byte_len` bytes from the low `bits_per_channel` bits of the given channel slots.
+fn read_bits(
+    buffer: &[u8],
+    slots: impl Iterator<Item = usize>,
+    byte_len: usize,
+    bits_per_channel: u8,
+) -> Vec<u8> {
+    let total_bits = byte_len * 8;
+    let mask = (1u8 << bits_per_channel) - 1;
+    let mut output = vec![0u8; byte_len];
+    let mut bit_pos = 0;
+
+    for slot in slots {
+        if bit_pos >= total_bits {
+            break;
+        }
+
+        let value = buffer[slot] & mask;
+        for shift in (0..bits_per_channel).rev() {
+            if bit_pos < total_bits {
+                output[bit_pos / 8] |= ((value >> shift) & 1) << (7 - bit_pos % 8);
             }
+            bit_pos += 1;
+        }
+    }
+
+    output
+}
+
+/// XOR `data` in place with a ChaCha20 keystream seeded from SHA-256(`key`).
+///
+/// Applying it twice with the same key restores the original bytes.
+fn apply_keystream(data: &mut [u8], key: &str) {
+    let seed: [u8; 32] = Sha256::digest(key.as_bytes()).into();
+    let mut rng = ChaCha20Rng::from_seed(seed);
+
+    let mut keystream = vec![0u8; data.len()];
+    rng.fill_bytes(&mut keystream);
+    for (byte, k) in data.iter_mut().zip(keystream) {
+        *byte ^= k;
+    }
+}
+
+/// Deflate `data`, returning `None` if compression would not make it smaller,
+/// or if it's too big for extraction to inflate again.
+fn compress_payload(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    if data.len() > MAX_INFLATED_PAYLOAD_SIZE {
+        return Ok(None);
+    }
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    let compressed = encoder.finish()?;
 
-            img.put_pixel(x, y, new_pixel);
+    Ok((compressed.len() < data.len()).then_some(compressed))
+}
+
+/// Inflate data previously produced by [`compress_payload`], refusing to expand
+/// past `max_size`.
+fn decompress_payload(data: &[u8], max_size: usize) -> Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    DeflateDecoder::new(data)
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| anyhow::anyhow!("Failed to decompress embedded payload: {}", e))?;
+
+    if decompressed.len() > max_size {
+        return Err(SteganographyError::PayloadTooLarge { max: max_size }.into());
+    }
+    Ok(decompressed)
+}
+
+/// Number of payload bytes a carrier of the given dimensions can hold.
+fn payload_capacity(width: u32, height: u32, bits_per_channel: u8, channels: usize) -> usize {
+    let pixels = width as usize * height as usize;
+    let payload_pixels = pixels.saturating_sub(header_pixels());
+    payload_pixels * channels * bits_per_channel as usize / 8
+}
+
+/// Query how many payload bytes a carrier image can hold at the default bit-depth.
+///
+/// The returned value already excludes the header overhead, so any payload of at
+/// most this many bytes is guaranteed to fit via [`embed_image_bytes`] or
+/// [`embed_text_bytes`].
+///
+/// # Arguments
+/// - `image_bytes`: Raw bytes of the carrier image
+///
+/// # Returns
+/// - `Ok(usize)`: Usable payload capacity in bytes
+/// - `Err`: If the image can't be loaded
+///
+/// # Example
+/// ```ignore
+/// let carrier = std::fs::read("carrier.png")?;
+/// if secret.len() > capacity_bytes(&carrier)? {
+///     println!("Secret won't fit");
+/// }
+/// ```
+pub fn capacity_bytes(image_bytes: &[u8]) -> Result<usize> {
+    let img = image::load_from_memory(image_bytes)?;
+    let (width, height) = img.dimensions();
+    Ok(payload_capacity(
+        width,
+        height,
+        DEFAULT_BITS_PER_CHANNEL,
+        CHANNELS_PER_PIXEL,
+    ))
+}
+
+/// Largest secret image (in bytes) that fits in a carrier at the default bit-depth.
+///
+/// This is [`capacity_bytes`] minus the [`IMAGE_INFO_SIZE`] block that
+/// [`embed_image_bytes`] prepends to every secret image.
+///
+/// # Example
+/// ```ignore
+/// if secret.len() > image_capacity_bytes(&carrier)? {
+///     return Err(anyhow::anyhow!("Secret image too large"));
+/// }
+/// ```
+pub fn image_capacity_bytes(image_bytes: &[u8]) -> Result<usize> {
+    Ok(capacity_bytes(image_bytes)?.saturating_sub(IMAGE_INFO_SIZE))
+}
+
+/// Embed an arbitrary payload (header + data) into a carrier image and encode it in
+/// [`EmbedOptions::output_format`].
+///
+/// The payload is optionally compressed, then checksummed, then (if `key` is provided)
+/// obfuscated with [`apply_keystream`]. `flags` describes the payload contents
+/// (e.g. [`FLAG_IMAGE_INFO`]); the embedding flags are added from `options`.
+fn embed_payload(
+    carrier_bytes: &[u8],
+    payload: &[u8],
+    flags: u8,
+    options: &EmbedOptions,
+    key: Option<&str>,
+    progress: Option<&ProgressCallback<'_>>,
+) -> Result<Vec<u8>> {
+    options.validate()?;
+
+    let img = image::load_from_memory(carrier_bytes)?;
+    let (width, height) = img.dimensions();
+
+    let mut flags = flags;
+    let mut data = payload.to_vec();
+    if options.compress {
+        if let Some(compressed) = compress_payload(payload)? {
+            flags |= FLAG_COMPRESSED;
+            data = compressed;
         }
     }
 
-    // Encode the modified image as PNG
+    // Check if the carrier has enough capacity at the requested bit-depth
+    let capacity = payload_capacity(width, height, options.bits_per_channel, options.channels());
+    if data.len() > capacity || data.len() > u32::MAX as usize {
+        return Err(anyhow::anyhow!(
+            "Image too small: need {} bytes but only have {} bytes available at {} bit(s) per channel",
+            data.len(),
+            capacity,
+            options.bits_per_channel
+        ));
+    }
+
+    // Convert to RGBA format for consistent pixel manipulation
+    let mut img = img.to_rgba8();
+
+    if key.is_some() {
+        flags |= FLAG_KEYED;
+    }
+    if options.scatter_seed.is_some() {
+        flags |= FLAG_SCATTERED;
+    }
+    if options.use_alpha {
+        flags |= FLAG_ALPHA;
+    }
+
+    let header = PayloadHeader {
+        bits_per_channel: options.bits_per_channel,
+        flags,
+        seed: options.scatter_seed.unwrap_or(0),
+        length: data.len() as u32,
+        checksum: crc32fast::hash(&data),
+    };
+
+    // Obfuscate after checksumming so a wrong key fails verification
+    if let Some(key) = key {
+        apply_keystream(&mut data, key);
+    }
+
+    // Header always uses 1 bit per RGB channel; payload follows at the configured depth
+    let buffer: &mut [u8] = &mut img;
+    write_bits(
+        buffer,
+        channel_slots(0..header_pixels(), CHANNELS_PER_PIXEL),
+        &header.to_bytes(),
+        1,
+    );
+    write_payload(buffer, width as usize, &data, options, progress)?;
+
+    // Encode the modified image in the (lossless) output format
     let mut output_bytes = Vec::new();
     img.write_to(
         &mut std::io::Cursor::new(&mut output_bytes),
-        image::ImageFormat::Png,
+        options.output_format.image_format(),
     )?;
 
     Ok(output_bytes)
 }
 
+/// Read and validate the header from the first pixels of an RGBA buffer.
+fn read_header(buffer: &[u8], pixels: usize) -> Result<PayloadHeader> {
+    if pixels < header_pixels() {
+        return Err(anyhow::anyhow!(
+            "Image too small to contain a steganography header"
+        ));
+    }
+
+    let header_bytes = read_bits(
+        buffer,
+        channel_slots(0..header_pixels(), CHANNELS_PER_PIXEL),
+        HEADER_SIZE,
+        1,
+    );
+    PayloadHeader::from_bytes(&header_bytes)
+}
+
+/// Reject headers whose stored length cannot fit in the image, before anything
+/// is allocated for the payload.
+fn check_payload_length(header: &PayloadHeader, width: u32, height: u32) -> Result<()> {
+    let capacity = payload_capacity(width, height, header.bits_per_channel, header.channels());
+    if header.length as usize > capacity {
+        return Err(anyhow::anyhow!(
+            "Corrupt header: payload length {} exceeds image capacity of {} bytes",
+            header.length,
+            capacity
+        ));
+    }
+    Ok(())
+}
+
+/// Check whether an image carries a plausible embedded payload, without extracting it.
+///
+/// Reads only the header and checks its magic, version and bit-depth, and that the
+/// stored length fits in the image's capacity at the stored bit-depth. A plain
+/// image whose LSBs happen to decode to a huge length is rejected here, so callers
+/// can skip extraction instead of allocating a buffer for it. The checksum is not
+/// verified, so a `true` result can still fail extraction if the payload is corrupt.
+///
+/// # Example
+/// ```ignore
+/// if has_embedded_payload(&carrier) {
+///     let secret = extract_image_bytes(&carrier)?;
+/// }
+/// ```
+pub fn has_embedded_payload(image_bytes: &[u8]) -> bool {
+    let Ok(img) = image::load_from_memory(image_bytes) else {
+        return false;
+    };
+    let img = img.to_rgba8();
+    let (width, height) = img.dimensions();
+    let pixels = width as usize * height as usize;
+
+    read_header(&img, pixels)
+        .and_then(|header| check_payload_length(&header, width, height))
+        .is_ok()
+}
+
+/// Extract the payload (without header) previously written by [`embed_payload`],
+/// along with the header it was embedded with.
+///
+/// Key-protected payloads require `key`; a key supplied for an unprotected
+/// payload is ignored.
+fn extract_payload(image_bytes: &[u8], key: Option<&str>) -> Result<(PayloadHeader, Vec<u8>)> {
+    let img = image::load_from_memory(image_bytes)?;
+    let img = img.to_rgba8();
+    let (width, height) = img.dimensions();
+    let pixels = width as usize * height as usize;
+
+    let buffer: &[u8] = &img;
+    let header = read_header(buffer, pixels).map_err(|e| explain_lossy_carrier(image_bytes, e))?;
+    check_payload_length(&header, width, height)?;
+
+    let order = payload_pixel_order(
+        pixels,
+        header.length as usize,
+        header.bits_per_channel,
+        header.channels(),
+        header.scatter_seed(),
+    )?;
+
+    let keyed = header.flags & FLAG_KEYED != 0;
+    if keyed && key.is_none() {
+        return Err(SteganographyError::KeyRequired.into());
+    }
+
+    let mut payload = read_bits(
+        buffer,
+        channel_slots(order, header.channels()),
+        header.length as usize,
+        header.bits_per_channel,
+    );
+
+    if let (true, Some(key)) = (keyed, key) {
+        apply_keystream(&mut payload, key);
+    }
+
+    // Verify integrity before handing bytes to callers
+    let actual = crc32fast::hash(&payload);
+    if actual != header.checksum {
+        let error = SteganographyError::ChecksumMismatch {
+            expected: header.checksum,
+            actual,
+        };
+        return Err(explain_lossy_carrier(image_bytes, error.into()));
+    }
+
+    if header.flags & FLAG_COMPRESSED != 0 {
+        payload = decompress_payload(&payload, MAX_INFLATED_PAYLOAD_SIZE)?;
+    }
+
+    Ok((header, payload))
+}
+
+/// Embed a secret image prefixed with its [`ExtractedImageInfo`] block, and with
+/// a `metadata` block ahead of that if given.
+pub(crate) fn embed_image_payload(
+    carrier_image_bytes: &[u8],
+    secret_image_bytes: &[u8],
+    metadata: Option<&str>,
+    options: &EmbedOptions,
+    key: Option<&str>,
+    progress: Option<&ProgressCallback<'_>>,
+) -> Result<Vec<u8>> {
+    let mut flags = FLAG_IMAGE_INFO;
+    let mut payload = Vec::with_capacity(IMAGE_INFO_SIZE + secret_image_bytes.len());
+    if let Some(text) = metadata {
+        flags |= FLAG_METADATA;
+        payload.extend_from_slice(&metadata_to_bytes(text)?);
+    }
+    let info = ExtractedImageInfo::probe(secret_image_bytes);
+    payload.extend_from_slice(&info.to_bytes());
+    payload.extend_from_slice(secret_image_bytes);
+    embed_payload(carrier_image_bytes, &payload, flags, options, key, progress)
+}
+
+/// Extract a secret image and its info block, stripping the block (and any
+/// metadata block) from the bytes.
+///
+/// Payloads embedded without an info block have their info sniffed from the bytes.
+/// Payloads with an access policy are refused.
+fn extract_image_payload(
+    carrier_image_bytes: &[u8],
+    key: Option<&str>,
+) -> Result<(ExtractedImageInfo, Vec<u8>)> {
+    let (header, payload) = extract_payload(carrier_image_bytes, key)?;
+    image_from_payload(header.flags, payload)
+}
+
+/// The part of [`extract_image_payload`] after the payload has been read.
+fn image_from_payload(flags: u8, mut payload: Vec<u8>) -> Result<(ExtractedImageInfo, Vec<u8>)> {
+    if flags & FLAG_ACCESS_POLICY != 0 {
+        return Err(SteganographyError::PolicyProtected.into());
+    }
+    if flags & FLAG_MULTI_IMAGE != 0 {
+        let count = split_images(&payload)?.len();
+        return Err(SteganographyError::MultipleImages { count }.into());
+    }
+    let (_, metadata_len) = metadata_from_bytes(flags, &payload)?;
+    payload.drain(..metadata_len);
+    if flags & FLAG_IMAGE_INFO == 0 {
+        return Ok((ExtractedImageInfo::probe(&payload), payload));
+    }
+
+    let info = ExtractedImageInfo::from_bytes(&payload)?;
+    payload.drain(..IMAGE_INFO_SIZE);
+    Ok((info, payload))
+}
+
+/// Embed text into an image using LSB steganography.
+///
+/// The text is prefixed with a header (magic, version, bit-depth, length) and then
+/// embedded into the least significant bit of the image's RGB channels.
+///
+/// # Arguments
+/// - `image_bytes`: Raw bytes of the input image (any format supported by `image` crate)
+/// - `text`: UTF-8 text to embed into the image
+///
+/// # Returns
+/// - `Ok(Vec<u8>)`: PNG image bytes with embedded text
+/// - `Err`: If image is too small, can't be loaded, or encoding fails
+///
+/// # Errors
+/// - Image is too small to hold the text
+/// - Image format is invalid
+/// - Encoding to PNG fails
+///
+/// # Example
+/// ```ignore
+/// let image_data = std::fs::read("input.jpg")?;
+/// let encrypted = embed_text_bytes(&image_data, "Secret message")?;
+/// std::fs::write("output.png", encrypted)?;
+/// ```
+pub fn embed_text_bytes(image_bytes: &[u8], text: &str) -> Result<Vec<u8>> {
+    embed_text_bytes_with_options(image_bytes, text, &EmbedOptions::default())
+}
+
+/// Embed text into an image using custom [`EmbedOptions`] (e.g. a higher bit-depth).
+///
+/// # Errors
+/// - `bits_per_channel` is outside 1-4
+/// - Image is too small to hold the text at the requested bit-depth
+/// - Image format is invalid
+///
+/// # Example
+/// ```ignore
+/// let options = EmbedOptions { bits_per_channel: 2, ..Default::default() };
+/// let encrypted = embed_text_bytes_with_options(&image_data, "Secret", &options)?;
+/// ```
+pub fn embed_text_bytes_with_options(
+    image_bytes: &[u8],
+    text: &str,
+    options: &EmbedOptions,
+) -> Result<Vec<u8>> {
+    embed_payload(image_bytes, text.as_bytes(), 0, options, None, None)
+}
+
 /// Extract text that was embedded in an image using LSB steganography.
 ///
-/// Reads the 4-byte length prefix, then extracts that many bytes from the
-/// LSBs of the image's RGB channels.
+/// Reads the header to determine the bit-depth and length, then extracts that
+/// many bytes from the low bits of the image's RGB channels.
 ///
 /// # Arguments
 /// - `image_bytes`: Raw bytes of the steganography-encoded image
@@ -146,8 +1498,9 @@ pub fn embed_text_bytes(image_bytes: &[u8], text: &str) -> Result<Vec<u8>> {
 ///
 /// # Errors
 /// - Image format is invalid
+/// - Image has no valid steganography header
+/// - [`SteganographyError::ChecksumMismatch`] if the payload was corrupted
 /// - Extracted bytes are not valid UTF-8
-/// - Length prefix is corrupted
 ///
 /// # Example
 /// ```ignore
@@ -157,87 +1510,9 @@ pub fn embed_text_bytes(image_bytes: &[u8], text: &str) -> Result<Vec<u8>> {
 /// ```
 #[allow(dead_code)]
 pub fn extract_text_bytes(image_bytes: &[u8]) -> Result<String> {
-    // Load the image
-    let img = image::load_from_memory(image_bytes)?;
-    let img = img.to_rgba8();
-    let (width, height) = img.dimensions();
-
-    // ========== STEP 1: Extract length (first 4 bytes = 32 bits) ==========
-
-    let mut length_bytes = [0u8; 4];
-    let mut data_index = 0;
-    let mut bit_index = 0;
-
-    'length_loop: for y in 0..height {
-        for x in 0..width {
-            if data_index >= 4 {
-                break 'length_loop;
-            }
-
-            let pixel = img.get_pixel(x, y);
-
-            // Extract from R, G, B channels
-            for channel in 0..3 {
-                if data_index >= 4 {
-                    break 'length_loop;
-                }
-
-                // Get the LSB from this channel
-                let bit = pixel[channel] & 1;
-
-                // Set this bit in our length bytes (MSB first)
-                length_bytes[data_index] |= bit << (7 - bit_index);
-
-                bit_index += 1;
-                if bit_index == 8 {
-                    bit_index = 0;
-                    data_index += 1;
-                }
-            }
-        }
-    }
-
-    let length = u32::from_be_bytes(length_bytes) as usize;
-
-    // ========== STEP 2: Extract text data ==========
-
-    let mut text_bytes = vec![0u8; length];
-    data_index = 0;
-    bit_index = 0;
-    let mut skip_bits = 32; // Skip the length prefix we already read
-
-    'outer: for y in 0..height {
-        for x in 0..width {
-            if data_index >= length {
-                break 'outer;
-            }
-
-            let pixel = img.get_pixel(x, y);
-
-            for channel in 0..3 {
-                // Skip the first 32 bits (length prefix)
-                if skip_bits > 0 {
-                    skip_bits -= 1;
-                    continue;
-                }
-
-                if data_index >= length {
-                    break 'outer;
-                }
-
-                // Get the LSB from this channel
-                let bit = pixel[channel] & 1;
-
-                // Set this bit in our text bytes (MSB first)
-                text_bytes[data_index] |= bit << (7 - bit_index);
-
-                bit_index += 1;
-                if bit_index == 8 {
-                    bit_index = 0;
-                    data_index += 1;
-                }
-            }
-        }
+    let (header, text_bytes) = extract_payload(image_bytes, None)?;
+    if header.flags & FLAG_ACCESS_POLICY != 0 {
+        return Err(SteganographyError::PolicyProtected.into());
     }
 
     // Convert bytes to UTF-8 string
@@ -246,8 +1521,8 @@ pub fn extract_text_bytes(image_bytes: &[u8]) -> Result<String> {
 
 /// Embed an image into another (carrier) image using LSB steganography.
 ///
-/// The embedded image is prefixed with its length (4 bytes, big-endian) and then embedded
-/// into the least significant bits of the carrier image's RGB channels.
+/// The embedded image is prefixed with a header (magic, version, bit-depth, length)
+/// and then embedded into the least significant bit of the carrier image's RGB channels.
 ///
 /// # Arguments
 /// - `carrier_image_bytes`: Raw bytes of the carrier image (the image that will hide data)
@@ -270,86 +1545,167 @@ pub fn extract_text_bytes(image_bytes: &[u8]) -> Result<String> {
 /// std::fs::write("output.png", result)?;
 /// ```
 pub fn embed_image_bytes(carrier_image_bytes: &[u8], secret_image_bytes: &[u8]) -> Result<Vec<u8>> {
-    // Load the carrier image
-    let img = image::load_from_memory(carrier_image_bytes)?;
-    let (width, height) = img.dimensions();
-
-    // Convert to RGBA format for consistent pixel manipulation
-    let mut img = img.to_rgba8();
-
-    // Prepare data to embed: [4 bytes length][secret image bytes]
-    let length = secret_image_bytes.len() as u32;
-    let mut data_to_embed = Vec::new();
-
-    // Add length prefix (4 bytes, big-endian)
-    data_to_embed.extend_from_slice(&length.to_be_bytes());
-    // Add secret image content
-    data_to_embed.extend_from_slice(secret_image_bytes);
-
-    // Check if carrier image has enough capacity
-    // Each pixel has 3 usable channels (R, G, B), so 3 bits per pixel
-    let available_bits = (width * height * 3) as usize;
-    let required_bits = data_to_embed.len() * 8;
-
-    if required_bits > available_bits {
-        return Err(anyhow::anyhow!(
-            "Carrier image too small: need {} bits but only have {} bits available. Secret image size: {} bytes",
-            required_bits, available_bits, secret_image_bytes.len()
-        ));
-    }
-
-    // Embed data into LSBs of image pixels
-    let mut data_index = 0; // Current byte being embedded
-    let mut bit_index = 0;  // Current bit within the byte (0-7)
-
-    'outer: for y in 0..height {
-        for x in 0..width {
-            // Stop if all data has been embedded
-            if data_index >= data_to_embed.len() {
-                break 'outer;
-            }
-
-            let pixel = img.get_pixel(x, y);
-            let mut new_pixel = *pixel;
-
-            // Embed into R, G, B channels (skip Alpha channel for compatibility)
-            for channel in 0..3 {
-                if data_index >= data_to_embed.len() {
-                    break 'outer;
-                }
-
-                // Extract the current bit from data (MSB first)
-                let bit = (data_to_embed[data_index] >> (7 - bit_index)) & 1;
+    embed_image_bytes_with_options(
+        carrier_image_bytes,
+        secret_image_bytes,
+        &EmbedOptions::default(),
+    )
+}
 
-                // Clear LSB and set it to our data bit
-                new_pixel[channel] = (pixel[channel] & 0xFE) | bit;
+/// Embed an image into a carrier image using custom [`EmbedOptions`].
+///
+/// Use a higher `bits_per_channel` to hide a large secret in a small carrier, trading
+/// carrier fidelity for capacity. [`extract_image_bytes`] reads the bit-depth from the
+/// embedded header, so no options are needed to extract.
+///
+/// # Errors
+/// - `bits_per_channel` is outside 1-4
+/// - Carrier image is too small to hold the secret image at the requested bit-depth
+/// - Image format is invalid
+///
+/// # Example
+/// ```ignore
+/// let options = EmbedOptions { bits_per_channel: 4, ..Default::default() };
+/// let result = embed_image_bytes_with_options(&carrier, &secret, &options)?;
+/// ```
+pub fn embed_image_bytes_with_options(
+    carrier_image_bytes: &[u8],
+    secret_image_bytes: &[u8],
+    options: &EmbedOptions,
+) -> Result<Vec<u8>> {
+    embed_image_payload(
+        carrier_image_bytes,
+        secret_image_bytes,
+        None,
+        options,
+        None,
+        None,
+    )
+}
 
-                // Move to next bit
-                bit_index += 1;
-                if bit_index == 8 {
-                    bit_index = 0;
-                    data_index += 1;
-                }
-            }
+/// Embed an image like [`embed_image_bytes_with_options`], reporting progress.
+///
+/// `progress` is called with the percentage of the secret written so far, from
+/// whichever thread wrote it; see [Progress](self#progress).
+///
+/// # Example
+/// ```ignore
+/// let result = embed_image_bytes_with_progress(&carrier, &secret, &options, &|percent| {
+///     println!("{}% embedded", percent);
+/// })?;
+/// ```
+pub fn embed_image_bytes_with_progress(
+    carrier_image_bytes: &[u8],
+    secret_image_bytes: &[u8],
+    options: &EmbedOptions,
+    progress: &ProgressCallback<'_>,
+) -> Result<Vec<u8>> {
+    embed_image_payload(
+        carrier_image_bytes,
+        secret_image_bytes,
+        None,
+        options,
+        None,
+        Some(progress),
+    )
+}
 
-            img.put_pixel(x, y, new_pixel);
-        }
-    }
+/// Embed an image into a carrier image, obfuscated with a password-derived keystream.
+///
+/// The secret bytes are XORed with a ChaCha20 keystream seeded from SHA-256(`key`)
+/// before the LSB step, so running [`extract_image_bytes`] on the result without the
+/// key yields [`SteganographyError::KeyRequired`] instead of the secret.
+///
+/// # Arguments
+/// - `carrier_image_bytes`: Raw bytes of the carrier image
+/// - `secret_image_bytes`: Raw bytes of the secret image to embed
+/// - `key`: Password used to derive the keystream
+///
+/// # Example
+/// ```ignore
+/// let result = embed_image_bytes_with_key(&carrier, &secret, "hunter2")?;
+/// let secret = extract_image_bytes_with_key(&result, "hunter2")?;
+/// ```
+pub fn embed_image_bytes_with_key(
+    carrier_image_bytes: &[u8],
+    secret_image_bytes: &[u8],
+    key: &str,
+) -> Result<Vec<u8>> {
+    embed_image_payload(
+        carrier_image_bytes,
+        secret_image_bytes,
+        None,
+        &EmbedOptions::default(),
+        Some(key),
+        None,
+    )
+}
 
-    // Encode the modified image as PNG
-    let mut output_bytes = Vec::new();
-    img.write_to(
-        &mut std::io::Cursor::new(&mut output_bytes),
-        image::ImageFormat::Png,
-    )?;
+/// Embed an image into a carrier image with payload bits scattered pseudo-randomly.
+///
+/// Instead of filling pixels top-left first, the payload pixels are a random sample
+/// of the whole carrier chosen by `seed`. The seed is stored in the header, so
+/// [`extract_image_bytes`] reconstructs the same order without extra arguments.
+///
+/// # Example
+/// ```ignore
+/// let result = embed_image_bytes_scattered(&carrier, &secret, 42)?;
+/// let secret = extract_image_bytes(&result)?;
+/// ```
+pub fn embed_image_bytes_scattered(
+    carrier_image_bytes: &[u8],
+    secret_image_bytes: &[u8],
+    seed: u64,
+) -> Result<Vec<u8>> {
+    let options = EmbedOptions {
+        scatter_seed: Some(seed),
+        ..Default::default()
+    };
+    embed_image_payload(
+        carrier_image_bytes,
+        secret_image_bytes,
+        None,
+        &options,
+        None,
+        None,
+    )
+}
 
-    Ok(output_bytes)
+/// Embed an image into a carrier image together with a `metadata` text.
+///
+/// The text is stored in its own block ahead of the secret, so
+/// [`extract_metadata`] reads it back without the caller handling the image,
+/// and [`extract_image_bytes`] still returns just the secret.
+///
+/// # Errors
+/// - `metadata` is over 65535 bytes
+/// - Carrier image is too small to hold the metadata, info block and secret image
+/// - Image format is invalid
+///
+/// # Example
+/// ```ignore
+/// let result = embed_image_bytes_with_metadata(&carrier, &secret, "owner: alice")?;
+/// assert_eq!(extract_metadata(&result)?.as_deref(), Some("owner: alice"));
+/// ```
+pub fn embed_image_bytes_with_metadata(
+    carrier_image_bytes: &[u8],
+    secret_image_bytes: &[u8],
+    metadata: &str,
+) -> Result<Vec<u8>> {
+    embed_image_payload(
+        carrier_image_bytes,
+        secret_image_bytes,
+        Some(metadata),
+        &EmbedOptions::default(),
+        None,
+        None,
+    )
 }
 
 /// Extract an embedded image from a carrier image using LSB steganography.
 ///
-/// Reads the 4-byte length prefix, then extracts that many bytes from the
-/// LSBs of the carrier image's RGB channels.
+/// Reads the header to determine the bit-depth and length, then extracts that
+/// many bytes from the low bits of the carrier image's RGB channels.
 ///
 /// # Arguments
 /// - `carrier_image_bytes`: Raw bytes of the steganography-encoded carrier image
@@ -360,8 +1716,8 @@ pub fn embed_image_bytes(carrier_image_bytes: &[u8], secret_image_bytes: &[u8])
 ///
 /// # Errors
 /// - Image format is invalid
-/// - Length prefix is corrupted
-/// - Not enough data in the image
+/// - Image has no valid steganography header
+/// - [`SteganographyError::ChecksumMismatch`] if the payload was corrupted
 ///
 /// # Example
 /// ```ignore
@@ -370,88 +1726,971 @@ pub fn embed_image_bytes(carrier_image_bytes: &[u8], secret_image_bytes: &[u8])
 /// std::fs::write("extracted_secret.png", secret_image)?;
 /// ```
 pub fn extract_image_bytes(carrier_image_bytes: &[u8]) -> Result<Vec<u8>> {
-    // Load the carrier image
-    let img = image::load_from_memory(carrier_image_bytes)?;
-    let img = img.to_rgba8();
-    let (width, height) = img.dimensions();
+    Ok(extract_image_payload(carrier_image_bytes, None)?.1)
+}
 
-    // ========== STEP 1: Extract length (first 4 bytes = 32 bits) ==========
+/// Extract an embedded image together with its format and dimensions.
+///
+/// Lets callers label the extracted bytes (e.g. with [`SecretImageFormat::mime_type`])
+/// instead of assuming a format. For payloads embedded without an info block the
+/// info is sniffed from the extracted bytes.
+///
+/// # Errors
+/// Same as [`extract_image_bytes`].
+///
+/// # Example
+/// ```ignore
+/// let (info, secret) = extract_image_with_info(&carrier)?;
+/// println!("{}x{} {}", info.width, info.height, info.format.mime_type());
+/// ```
+pub fn extract_image_with_info(
+    carrier_image_bytes: &[u8],
+) -> Result<(ExtractedImageInfo, Vec<u8>)> {
+    extract_image_payload(carrier_image_bytes, None)
+}
 
-    let mut length_bytes = [0u8; 4];
-    let mut data_index = 0;
-    let mut bit_index = 0;
+/// Extract the metadata text embedded with [`embed_image_bytes_with_metadata`].
+///
+/// Returns `None` for payloads embedded without metadata. Metadata is readable
+/// even when the secret is behind an [`AccessPolicy`].
+///
+/// # Errors
+/// - [`SteganographyError::KeyRequired`] for key-protected payloads
+/// - Same as [`extract_image_bytes`] for invalid or corrupt carriers
+///
+/// # Example
+/// ```ignore
+/// if let Some(text) = extract_metadata(&carrier)? {
+///     println!("Metadata: {}", text);
+/// }
+/// ```
+pub fn extract_metadata(carrier_image_bytes: &[u8]) -> Result<Option<String>> {
+    let (header, payload) = extract_payload(carrier_image_bytes, None)?;
+    let policy_len = if header.flags & FLAG_ACCESS_POLICY != 0 {
+        AccessPolicy::from_bytes(&payload)?.1
+    } else {
+        0
+    };
+    Ok(metadata_from_bytes(header.flags, &payload[policy_len..])?.0)
+}
 
-    'length_loop: for y in 0..height {
-        for x in 0..width {
-            if data_index >= 4 {
-                break 'length_loop;
-            }
+/// Embed several secret images into one carrier image, to extract one at a time.
+///
+/// The images are stored one after another behind a table of their lengths (see
+/// [Multiple Images](self#multiple-images)); [`extract_image_at`] returns the
+/// image at a given index of `secret_images`.
+///
+/// # Errors
+/// - `secret_images` is empty or holds more than 65535 images, or one is over 4GB
+/// - Carrier image is too small to hold the table and every image with its info block
+/// - Image format is invalid
+///
+/// # Example
+/// ```ignore
+/// let result = embed_images(&carrier, &[thumbnail, photo, scan])?;
+/// let photo = extract_image_at(&result, 1)?;
+/// ```
+pub fn embed_images(carrier_image_bytes: &[u8], secret_images: &[Vec<u8>]) -> Result<Vec<u8>> {
+    let count = u16::try_from(secret_images.len())
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Can embed 1-{} images, not {}",
+                u16::MAX,
+                secret_images.len()
+            )
+        })?;
+
+    let table_size = 2 + secret_images.len() * IMAGE_LENGTH_SIZE;
+    let images_size: usize = secret_images
+        .iter()
+        .map(|image| IMAGE_INFO_SIZE + image.len())
+        .sum();
+    let mut payload = Vec::with_capacity(table_size + images_size);
+    payload.extend_from_slice(&count.to_be_bytes());
+    for image in secret_images {
+        let length = u32::try_from(image.len())
+            .map_err(|_| anyhow::anyhow!("Secret image of {} bytes is too large", image.len()))?;
+        payload.extend_from_slice(&length.to_be_bytes());
+    }
+    for image in secret_images {
+        payload.extend_from_slice(&ExtractedImageInfo::probe(image).to_bytes());
+        payload.extend_from_slice(image);
+    }
 
-            let pixel = img.get_pixel(x, y);
+    embed_payload(
+        carrier_image_bytes,
+        &payload,
+        FLAG_MULTI_IMAGE | FLAG_IMAGE_INFO,
+        &EmbedOptions::default(),
+        None,
+        None,
+    )
+}
 
-            // Extract from R, G, B channels
-            for channel in 0..3 {
-                if data_index >= 4 {
-                    break 'length_loop;
-                }
+/// Extract the image at `index` from a carrier embedded with [`embed_images`].
+///
+/// Index 0 of a carrier holding a single image (e.g. from [`embed_image_bytes`])
+/// is that image.
+///
+/// # Errors
+/// - [`SteganographyError::ImageIndexOutOfRange`] if the carrier holds fewer images
+/// - Same as [`extract_image_bytes`] for invalid, corrupt or protected carriers
+///
+/// # Example
+/// ```ignore
+/// for index in 0..3 {
+///     std::fs::write(format!("secret_{}.png", index), extract_image_at(&carrier, index)?)?;
+/// }
+/// ```
+pub fn extract_image_at(carrier_image_bytes: &[u8], index: usize) -> Result<Vec<u8>> {
+    let (header, payload) = extract_payload(carrier_image_bytes, None)?;
+    if header.flags & FLAG_MULTI_IMAGE == 0 {
+        if index > 0 {
+            return Err(SteganographyError::ImageIndexOutOfRange { index, count: 1 }.into());
+        }
+        return Ok(image_from_payload(header.flags, payload)?.1);
+    }
 
-                // Get the LSB from this channel
-                let bit = pixel[channel] & 1;
+    let entries = split_images(&payload)?;
+    let entry = entries
+        .get(index)
+        .ok_or(SteganographyError::ImageIndexOutOfRange {
+            index,
+            count: entries.len(),
+        })?;
+    Ok(entry[IMAGE_INFO_SIZE..].to_vec())
+}
 
-                // Set this bit in our length bytes (MSB first)
-                length_bytes[data_index] |= bit << (7 - bit_index);
+/// Extract a key-protected image embedded with [`embed_image_bytes_with_key`].
+///
+/// # Errors
+/// - [`SteganographyError::ChecksumMismatch`] if the key is wrong or the payload was corrupted
+/// - Image format is invalid or has no valid steganography header
+///
+/// # Example
+/// ```ignore
+/// let secret = extract_image_bytes_with_key(&carrier, "hunter2")?;
+/// ```
+pub fn extract_image_bytes_with_key(carrier_image_bytes: &[u8], key: &str) -> Result<Vec<u8>> {
+    Ok(extract_image_payload(carrier_image_bytes, Some(key))?.1)
+}
 
-                bit_index += 1;
-                if bit_index == 8 {
-                    bit_index = 0;
-                    data_index += 1;
-                }
-            }
+/// Embed an image into a carrier image behind an [`AccessPolicy`].
+///
+/// Only [`view_image_bytes`] reveals the secret, to users the policy allows and
+/// while views remain; the plain extraction functions return
+/// [`SteganographyError::PolicyProtected`].
+///
+/// # Arguments
+/// - `carrier_image_bytes`: Raw bytes of the carrier image
+/// - `secret_image_bytes`: Raw bytes of the secret image to embed
+/// - `policy`: Who may view the secret, and how many times
+///
+/// # Errors
+/// - The policy lists more than 255 users, or a name that is empty or over 255 bytes
+/// - Carrier image is too small to hold the policy, info block and secret image
+/// - Image format is invalid
+///
+/// # Example
+/// ```ignore
+/// let policy = AccessPolicy { allowed_users: vec!["alice".to_string()], remaining_views: 3 };
+/// let result = embed_image_bytes_with_policy(&carrier, &secret, &policy)?;
+/// ```
+pub fn embed_image_bytes_with_policy(
+    carrier_image_bytes: &[u8],
+    secret_image_bytes: &[u8],
+    policy: &AccessPolicy,
+) -> Result<Vec<u8>> {
+    let info = ExtractedImageInfo::probe(secret_image_bytes);
+    let mut payload = policy.to_bytes()?;
+    payload.extend_from_slice(&info.to_bytes());
+    payload.extend_from_slice(secret_image_bytes);
+    embed_payload(
+        carrier_image_bytes,
+        &payload,
+        FLAG_ACCESS_POLICY | FLAG_IMAGE_INFO,
+        &EmbedOptions::default(),
+        None,
+        None,
+    )
+}
+
+/// View a secret image embedded with [`embed_image_bytes_with_policy`] as `user`.
+///
+/// Uses up one view: the returned [`PolicyView::carrier`] is the carrier
+/// re-embedded (with the same bit-depth, scattering, channels and format) with one fewer
+/// remaining view, and should replace the original.
+///
+/// # Errors
+/// - [`SteganographyError::AccessDenied`] if the policy doesn't allow `user`
+/// - [`SteganographyError::NoViewsLeft`] if the views are used up
+/// - The payload has no access policy (use [`extract_image_bytes`] instead)
+/// - Same as [`extract_image_bytes`] for invalid or corrupt carriers
+///
+/// # Example
+/// ```ignore
+/// let view = view_image_bytes(&carrier, "alice")?;
+/// std::fs::write("carrier.png", &view.carrier)?;
+/// println!("{} views left", view.policy.remaining_views);
+/// ```
+pub fn view_image_bytes(carrier_image_bytes: &[u8], user: &str) -> Result<PolicyView> {
+    let (header, payload) = extract_payload(carrier_image_bytes, None)?;
+    if header.flags & FLAG_ACCESS_POLICY == 0 {
+        return Err(anyhow::anyhow!("Embedded payload has no access policy"));
+    }
+
+    let (mut policy, policy_len) = AccessPolicy::from_bytes(&payload)?;
+    if !policy.allows(user) {
+        return Err(SteganographyError::AccessDenied {
+            user: user.to_string(),
+        }
+        .into());
+    }
+    if policy.remaining_views == 0 {
+        return Err(SteganographyError::NoViewsLeft.into());
+    }
+    policy.remaining_views -= 1;
+
+    let content = &payload[policy_len..];
+    let (_, metadata_len) = metadata_from_bytes(header.flags, content)?;
+    let info = ExtractedImageInfo::from_bytes(&content[metadata_len..])?;
+    let secret = content[metadata_len + IMAGE_INFO_SIZE..].to_vec();
+
+    // Record the used view in the carrier itself
+    let mut updated = policy.to_bytes()?;
+    updated.extend_from_slice(content);
+    let options = EmbedOptions {
+        output_format: CarrierFormat::detect(carrier_image_bytes).unwrap_or_default(),
+        ..header.embed_options()
+    };
+    let carrier = embed_payload(
+        carrier_image_bytes,
+        &updated,
+        header.flags & (FLAG_ACCESS_POLICY | FLAG_METADATA | FLAG_IMAGE_INFO),
+        &options,
+        None,
+        None,
+    )?;
+
+    Ok(PolicyView {
+        info,
+        secret,
+        policy,
+        carrier,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a PNG carrier with a simple gradient so pixel values vary.
+    fn test_carrier(width: u32, height: u32) -> Vec<u8> {
+        let img = image::RgbaImage::from_fn(width, height, |x, y| {
+            image::Rgba([(x % 256) as u8, (y % 256) as u8, ((x + y) % 256) as u8, 255])
+        });
+        let mut bytes = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_text_round_trip_default_depth() {
+        let carrier = test_carrier(64, 64);
+        let encoded = embed_text_bytes(&carrier, "username:alice,views:5").unwrap();
+        assert_eq!(
+            extract_text_bytes(&encoded).unwrap(),
+            "username:alice,views:5"
+        );
+    }
+
+    #[test]
+    fn test_image_round_trip_all_depths() {
+        let carrier = test_carrier(64, 64);
+        let secret: Vec<u8> = (0..1000).map(|i| (i * 7 % 251) as u8).collect();
+
+        for bits_per_channel in 1..=MAX_BITS_PER_CHANNEL {
+            let options = EmbedOptions {
+                bits_per_channel,
+                ..Default::default()
+            };
+            let encoded = embed_image_bytes_with_options(&carrier, &secret, &options).unwrap();
+            assert_eq!(extract_image_bytes(&encoded).unwrap(), secret);
+        }
+    }
+
+    #[test]
+    fn test_higher_depth_increases_capacity() {
+        let carrier = test_carrier(32, 32);
+        let secret = vec![0xAB; 1000];
+
+        // 32x32 holds ~376 bytes at 1 bit per channel but ~1500 bytes at 4
+        assert!(embed_image_bytes(&carrier, &secret).is_err());
+        let options = EmbedOptions {
+            bits_per_channel: 4,
+            ..Default::default()
+        };
+        let encoded = embed_image_bytes_with_options(&carrier, &secret, &options).unwrap();
+        assert_eq!(extract_image_bytes(&encoded).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_corrupted_payload_reports_checksum_mismatch() {
+        let carrier = test_carrier(64, 64);
+        let encoded = embed_image_bytes(&carrier, &[0x5A; 200]).unwrap();
+
+        // Flip the LSB of a channel inside the payload region
+        let mut img = image::load_from_memory(&encoded).unwrap().to_rgba8();
+        let slot = channel_slots([header_pixels() + 10], CHANNELS_PER_PIXEL)
+            .next()
+            .unwrap();
+        let buffer: &mut [u8] = &mut img;
+        buffer[slot] ^= 1;
+        let mut corrupted = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut corrupted),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+
+        let err = extract_image_bytes(&corrupted).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(SteganographyError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_image_without_header_fails_gracefully() {
+        let carrier = test_carrier(64, 64);
+        let err = extract_image_bytes(&carrier).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::NoPayload)
+        );
+    }
+
+    #[test]
+    fn test_has_embedded_payload() {
+        let carrier = test_carrier(64, 64);
+        assert!(!has_embedded_payload(&carrier));
+        assert!(!has_embedded_payload(b"not an image"));
+
+        let encoded = embed_text_bytes(&carrier, "hidden").unwrap();
+        assert!(has_embedded_payload(&encoded));
+
+        // A valid header claiming more bytes than the image can hold is rejected
+        let mut img = image::load_from_memory(&encoded).unwrap().to_rgba8();
+        let header = PayloadHeader {
+            bits_per_channel: 1,
+            flags: 0,
+            seed: 0,
+            length: u32::MAX,
+            checksum: 0,
+        };
+        write_bits(
+            &mut img,
+            channel_slots(0..header_pixels(), CHANNELS_PER_PIXEL),
+            &header.to_bytes(),
+            1,
+        );
+        let mut forged = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut forged),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+        assert!(!has_embedded_payload(&forged));
+    }
+
+    #[test]
+    fn test_oversized_length_prefix_errors_instead_of_allocating() {
+        // Random noise: LSBs decode to garbage, including the length field
+        let mut rng = ChaCha20Rng::seed_from_u64(99);
+        let mut noise = image::RgbaImage::new(64, 64);
+        rng.fill_bytes(&mut noise);
+        let mut random = Vec::new();
+        noise
+            .write_to(
+                &mut std::io::Cursor::new(&mut random),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+        assert!(extract_image_bytes(&random).is_err());
+
+        // Forged header with a valid magic but a ~4GB length
+        let mut img = image::load_from_memory(&test_carrier(64, 64))
+            .unwrap()
+            .to_rgba8();
+        let header = PayloadHeader {
+            bits_per_channel: 1,
+            flags: 0,
+            seed: 0,
+            length: u32::MAX,
+            checksum: 0,
+        };
+        write_bits(
+            &mut img,
+            channel_slots(0..header_pixels(), CHANNELS_PER_PIXEL),
+            &header.to_bytes(),
+            1,
+        );
+        let mut forged = Vec::new();
+        img.write_to(
+            &mut std::io::Cursor::new(&mut forged),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+
+        let err = extract_text_bytes(&forged).unwrap_err();
+        assert!(err.to_string().contains("exceeds image capacity"));
+        assert!(extract_image_bytes(&forged).is_err());
+    }
+
+    #[test]
+    fn test_keyed_round_trip_and_wrong_key() {
+        let carrier = test_carrier(64, 64);
+        let secret: Vec<u8> = (0..500).map(|i| (i % 256) as u8).collect();
+        let encoded = embed_image_bytes_with_key(&carrier, &secret, "correct horse").unwrap();
+
+        assert_eq!(
+            extract_image_bytes_with_key(&encoded, "correct horse").unwrap(),
+            secret
+        );
+
+        let err = extract_image_bytes_with_key(&encoded, "battery staple").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(SteganographyError::ChecksumMismatch { .. })
+        ));
+
+        let err = extract_image_bytes(&encoded).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::KeyRequired)
+        );
+    }
+
+    /// Read the stored payload length from an encoded image's header.
+    fn stored_length(encoded: &[u8]) -> u32 {
+        let img = image::load_from_memory(encoded).unwrap().to_rgba8();
+        let header_bytes = read_bits(
+            &img,
+            channel_slots(0..header_pixels(), CHANNELS_PER_PIXEL),
+            HEADER_SIZE,
+            1,
+        );
+        PayloadHeader::from_bytes(&header_bytes).unwrap().length
+    }
+
+    #[test]
+    fn test_compression_reduces_bit_usage() {
+        let carrier = test_carrier(64, 64);
+        let secret = b"ABCD".repeat(300);
+        let options = EmbedOptions {
+            compress: true,
+            ..Default::default()
+        };
+
+        let plain = embed_image_bytes(&carrier, &secret).unwrap();
+        let compressed = embed_image_bytes_with_options(&carrier, &secret, &options).unwrap();
+
+        assert!(stored_length(&compressed) < stored_length(&plain));
+        assert_eq!(extract_image_bytes(&compressed).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_compression_fits_secret_larger_than_raw_capacity() {
+        let carrier = test_carrier(32, 32);
+        let secret = vec![0u8; capacity_bytes(&carrier).unwrap() * 4];
+        let options = EmbedOptions {
+            compress: true,
+            ..Default::default()
+        };
+
+        assert!(embed_image_bytes(&carrier, &secret).is_err());
+        let encoded = embed_image_bytes_with_options(&carrier, &secret, &options).unwrap();
+        assert_eq!(extract_image_bytes(&encoded).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_decompress_payload_refuses_to_inflate_past_limit() {
+        let compressed = compress_payload(&[0u8; 1000]).unwrap().unwrap();
+
+        assert_eq!(decompress_payload(&compressed, 1000).unwrap(), [0u8; 1000]);
+        let error = decompress_payload(&compressed, 999).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<SteganographyError>(),
+            Some(SteganographyError::PayloadTooLarge { max: 999 })
+        ));
+    }
+
+    #[test]
+    fn test_scattered_round_trip_over_several_seeds() {
+        let carrier = test_carrier(64, 64);
+        let secret: Vec<u8> = (0..800).map(|i| (i * 13 % 256) as u8).collect();
+
+        for seed in [0, 1, 42, 0xDEAD_BEEF, u64::MAX] {
+            let encoded = embed_image_bytes_scattered(&carrier, &secret, seed).unwrap();
+            assert_eq!(extract_image_bytes(&encoded).unwrap(), secret);
+        }
+    }
+
+    #[test]
+    fn test_scattered_bits_reach_beyond_sequential_region() {
+        let carrier = test_carrier(64, 64);
+        let secret = vec![0xFF; 100];
+        let sequential_end = header_pixels() + (secret.len() * 8).div_ceil(CHANNELS_PER_PIXEL);
+
+        let order =
+            payload_pixel_order(64 * 64, secret.len(), 1, CHANNELS_PER_PIXEL, Some(7)).unwrap();
+        assert!(order.iter().any(|&pixel| pixel >= sequential_end));
+        assert!(order.iter().all(|&pixel| pixel >= header_pixels()));
+
+        let encoded = embed_image_bytes_scattered(&carrier, &secret, 7).unwrap();
+        assert_ne!(encoded, embed_image_bytes(&carrier, &secret).unwrap());
+    }
+
+    #[test]
+    fn test_alpha_channel_round_trip_on_opaque_and_translucent_carriers() {
+        let opaque = test_carrier(32, 32);
+        let translucent = {
+            let img = image::RgbaImage::from_fn(32, 32, |x, y| {
+                image::Rgba([x as u8, y as u8, 128, ((x * y) % 256) as u8])
+            });
+            let mut bytes = Vec::new();
+            img.write_to(
+                &mut std::io::Cursor::new(&mut bytes),
+                image::ImageFormat::Png,
+            )
+            .unwrap();
+            bytes
+        };
+
+        // Just over the RGB-only capacity, so it only fits with alpha
+        let secret = vec![0x3C; capacity_bytes(&opaque).unwrap() + 50];
+        let options = EmbedOptions {
+            use_alpha: true,
+            ..Default::default()
+        };
+
+        for carrier in [opaque, translucent] {
+            assert!(embed_image_bytes(&carrier, &secret).is_err());
+            let encoded = embed_image_bytes_with_options(&carrier, &secret, &options).unwrap();
+            assert_eq!(extract_image_bytes(&encoded).unwrap(), secret);
+        }
+    }
+
+    #[test]
+    fn test_capacity_bytes_matches_embed_limit() {
+        let carrier = test_carrier(32, 32);
+        let capacity = capacity_bytes(&carrier).unwrap();
+
+        // (32*32 - header pixels) * 3 / 8
+        assert_eq!(capacity, (32 * 32 - header_pixels()) * 3 / 8);
+        assert!(embed_text_bytes(&carrier, &"a".repeat(capacity)).is_ok());
+        assert!(embed_text_bytes(&carrier, &"a".repeat(capacity + 1)).is_err());
+
+        // Secret images also carry the info block
+        let image_capacity = image_capacity_bytes(&carrier).unwrap();
+        assert_eq!(image_capacity, capacity - IMAGE_INFO_SIZE);
+        assert!(embed_image_bytes(&carrier, &vec![1u8; image_capacity]).is_ok());
+        assert!(embed_image_bytes(&carrier, &vec![1u8; image_capacity + 1]).is_err());
+    }
+
+    #[test]
+    fn test_extract_image_with_info_reports_format_and_dimensions() {
+        let carrier = test_carrier(128, 128);
+
+        let png = test_carrier(7, 5);
+        let mut bmp = Vec::new();
+        image::RgbImage::new(3, 4)
+            .write_to(&mut std::io::Cursor::new(&mut bmp), image::ImageFormat::Bmp)
+            .unwrap();
+
+        for (secret, format, width, height) in [
+            (png, SecretImageFormat::Png, 7, 5),
+            (bmp, SecretImageFormat::Bmp, 3, 4),
+            (vec![0xAB; 40], SecretImageFormat::Unknown, 0, 0),
+        ] {
+            let encoded = embed_image_bytes(&carrier, &secret).unwrap();
+            let (info, extracted) = extract_image_with_info(&encoded).unwrap();
+            assert_eq!(extracted, secret);
+            assert_eq!(info.format, format);
+            assert_eq!((info.width, info.height), (width, height));
         }
     }
 
-    let length = u32::from_be_bytes(length_bytes) as usize;
+    #[test]
+    fn test_access_policy_limits_viewers_and_views() {
+        let carrier = test_carrier(64, 64);
+        let secret = test_carrier(6, 6);
+        let policy = AccessPolicy {
+            allowed_users: vec!["alice".to_string(), "bob".to_string()],
+            remaining_views: 2,
+        };
+        let encoded = embed_image_bytes_with_policy(&carrier, &secret, &policy).unwrap();
+
+        // The plain extractors don't bypass the policy
+        let err = extract_image_bytes(&encoded).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::PolicyProtected)
+        );
+
+        let err = view_image_bytes(&encoded, "mallory").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::AccessDenied {
+                user: "mallory".to_string()
+            })
+        );
+
+        // Each view is recorded in the returned carrier
+        let first = view_image_bytes(&encoded, "alice").unwrap();
+        assert_eq!(first.secret, secret);
+        assert_eq!(first.info.format, SecretImageFormat::Png);
+        assert_eq!(first.policy.remaining_views, 1);
+        let second = view_image_bytes(&first.carrier, "bob").unwrap();
+        assert_eq!(second.secret, secret);
+        assert_eq!(second.policy.remaining_views, 0);
+
+        let err = view_image_bytes(&second.carrier, "alice").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::NoViewsLeft)
+        );
+
+        // Unprotected carriers have nothing to view through
+        let plain = embed_image_bytes(&carrier, &secret).unwrap();
+        assert!(view_image_bytes(&plain, "alice").is_err());
+    }
+
+    #[test]
+    fn test_access_policy_view_keeps_embedding_layout() {
+        let carrier = test_carrier(64, 64);
+        let secret: Vec<u8> = (0..300).map(|i| (i % 256) as u8).collect();
+        let policy = AccessPolicy {
+            allowed_users: Vec::new(),
+            remaining_views: 3,
+        };
+        let encoded = embed_image_bytes_with_policy(&carrier, &secret, &policy).unwrap();
+
+        // An empty user list lets anyone view
+        let view = view_image_bytes(&encoded, "anyone").unwrap();
+        assert_eq!(view.secret, secret);
+        assert_eq!(stored_length(&view.carrier), stored_length(&encoded));
+
+        let too_many = AccessPolicy {
+            allowed_users: vec!["x".to_string(); 256],
+            remaining_views: 1,
+        };
+        assert!(embed_image_bytes_with_policy(&carrier, &secret, &too_many).is_err());
+    }
+
+    #[test]
+    fn test_metadata_round_trip_alongside_secret() {
+        let carrier = test_carrier(64, 64);
+        let secret = test_carrier(6, 6);
+        let encoded =
+            embed_image_bytes_with_metadata(&carrier, &secret, "owner: alice, 2024-05-01").unwrap();
+
+        assert_eq!(
+            extract_metadata(&encoded).unwrap().as_deref(),
+            Some("owner: alice, 2024-05-01")
+        );
+        let (info, extracted) = extract_image_with_info(&encoded).unwrap();
+        assert_eq!(extracted, secret);
+        assert_eq!(info.format, SecretImageFormat::Png);
+
+        // Carriers without metadata, or holding text, have none
+        let plain = embed_image_bytes(&carrier, &secret).unwrap();
+        assert_eq!(extract_metadata(&plain).unwrap(), None);
+        let text = embed_text_bytes(&carrier, "hello").unwrap();
+        assert_eq!(extract_metadata(&text).unwrap(), None);
+
+        let too_long = "x".repeat(u16::MAX as usize + 1);
+        assert!(embed_image_bytes_with_metadata(&carrier, &secret, &too_long).is_err());
+    }
+
+    #[test]
+    fn test_multiple_images_extract_independently() {
+        let carrier = test_carrier(128, 128);
+        let secrets = vec![
+            test_carrier(4, 4),
+            (0..500).map(|i| (i % 251) as u8).collect(),
+            test_carrier(9, 3),
+        ];
+        let encoded = embed_images(&carrier, &secrets).unwrap();
+
+        for (index, secret) in secrets.iter().enumerate().rev() {
+            assert_eq!(&extract_image_at(&encoded, index).unwrap(), secret);
+        }
+        let err = extract_image_at(&encoded, 3).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::ImageIndexOutOfRange { index: 3, count: 3 })
+        );
+
+        // Single-image extraction doesn't silently pick one
+        let err = extract_image_bytes(&encoded).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SteganographyError>(),
+            Some(&SteganographyError::MultipleImages { count: 3 })
+        );
+
+        // A single-image carrier is index 0
+        let single = embed_image_bytes(&carrier, &secrets[0]).unwrap();
+        assert_eq!(extract_image_at(&single, 0).unwrap(), secrets[0]);
+        assert!(extract_image_at(&single, 1).is_err());
+    }
 
-    // ========== STEP 2: Extract image data ==========
+    #[test]
+    fn test_multiple_images_capacity_counts_table_and_every_image() {
+        let carrier = test_carrier(32, 32);
+        let capacity = image_capacity_bytes(&carrier).unwrap();
 
-    let mut image_bytes = vec![0u8; length];
-    data_index = 0;
-    bit_index = 0;
-    let mut skip_bits = 32; // Skip the length prefix we already read
+        // Each image fits alone, but not all of them behind the table
+        let half = vec![0x11; capacity / 2];
+        assert!(embed_image_bytes(&carrier, &half).is_ok());
+        assert!(embed_images(&carrier, &[half.clone(), half]).is_err());
+        assert!(embed_images(&carrier, &[]).is_err());
+    }
 
-    'outer: for y in 0..height {
-        for x in 0..width {
-            if data_index >= length {
-                break 'outer;
+    #[test]
+    fn test_round_trip_through_bmp_and_webp_carriers() {
+        let carrier = test_carrier(64, 64);
+        let secret: Vec<u8> = (0..400).map(|i| (i * 13 % 256) as u8).collect();
+
+        for (output_format, image_format) in [
+            (CarrierFormat::Bmp, image::ImageFormat::Bmp),
+            (CarrierFormat::WebP, image::ImageFormat::WebP),
+        ] {
+            for use_alpha in [false, true] {
+                let options = EmbedOptions {
+                    output_format,
+                    use_alpha,
+                    bits_per_channel: 2,
+                    ..Default::default()
+                };
+                let encoded = embed_image_bytes_with_options(&carrier, &secret, &options).unwrap();
+                assert_eq!(image::guess_format(&encoded).unwrap(), image_format);
+                assert_eq!(extract_image_bytes(&encoded).unwrap(), secret);
             }
+        }
 
-            let pixel = img.get_pixel(x, y);
+        // Text and policy views keep working, and a view keeps the BMP format
+        let options = EmbedOptions {
+            output_format: CarrierFormat::Bmp,
+            ..Default::default()
+        };
+        let encoded = embed_text_bytes_with_options(&carrier, "hello", &options).unwrap();
+        assert_eq!(extract_text_bytes(&encoded).unwrap(), "hello");
+        let policy = AccessPolicy {
+            allowed_users: Vec::new(),
+            remaining_views: 2,
+        };
+        let mut payload = policy.to_bytes().unwrap();
+        payload.extend_from_slice(&ExtractedImageInfo::probe(&secret).to_bytes());
+        payload.extend_from_slice(&secret);
+        let flags = FLAG_ACCESS_POLICY | FLAG_IMAGE_INFO;
+        let protected = embed_payload(&carrier, &payload, flags, &options, None, None).unwrap();
+        let view = view_image_bytes(&protected, "anyone").unwrap();
+        assert_eq!(view.secret, secret);
+        assert_eq!(
+            image::guess_format(&view.carrier).unwrap(),
+            image::ImageFormat::Bmp
+        );
+    }
 
-            for channel in 0..3 {
-                // Skip the first 32 bits (length prefix)
-                if skip_bits > 0 {
-                    skip_bits -= 1;
-                    continue;
-                }
+    #[test]
+    fn test_lossy_output_format_is_rejected() {
+        for name in ["jpg", "JPEG"] {
+            let err = name.parse::<CarrierFormat>().unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<SteganographyError>(),
+                Some(&SteganographyError::LossyFormat {
+                    format: "JPEG".to_string()
+                })
+            );
+        }
+        assert_eq!(
+            "jpeg".parse::<CarrierFormat>().unwrap_err().to_string(),
+            "JPEG is lossy and will destroy embedded data; use PNG/BMP/WebP."
+        );
+        assert!(CarrierFormat::try_from(image::ImageFormat::Jpeg).is_err());
+        assert!("gif".parse::<CarrierFormat>().is_err());
+
+        // Every format a carrier can be written in keeps the payload
+        for format in [CarrierFormat::Png, CarrierFormat::Bmp, CarrierFormat::WebP] {
+            assert!(is_lossless_format(format.image_format()));
+        }
+        assert!(!is_lossless_format(image::ImageFormat::Jpeg));
+        assert_eq!(
+            "webp".parse::<CarrierFormat>().unwrap(),
+            CarrierFormat::WebP
+        );
+        assert_eq!("BMP".parse::<CarrierFormat>().unwrap(), CarrierFormat::Bmp);
+    }
 
-                if data_index >= length {
-                    break 'outer;
-                }
+    #[test]
+    fn test_carrier_resaved_as_jpeg_explains_the_failure() {
+        let carrier = test_carrier(64, 64);
+        let encoded = embed_image_bytes(&carrier, &[0x42; 100]).unwrap();
+
+        // What a user re-saving the result as JPEG ends up with
+        let mut jpeg = Vec::new();
+        image::load_from_memory(&encoded)
+            .unwrap()
+            .to_rgb8()
+            .write_to(
+                &mut std::io::Cursor::new(&mut jpeg),
+                image::ImageFormat::Jpeg,
+            )
+            .unwrap();
+
+        let err = extract_image_bytes(&jpeg).unwrap_err();
+        assert!(err.to_string().contains("JPEG is lossy"), "{}", err);
+        // The underlying failure is still there for callers that classify it
+        assert!(err.downcast_ref::<SteganographyError>().is_some());
+    }
+
+    #[test]
+    fn test_invalid_bits_per_channel_rejected() {
+        let carrier = test_carrier(16, 16);
+        for bits_per_channel in [0, 5, 8] {
+            let options = EmbedOptions {
+                bits_per_channel,
+                ..Default::default()
+            };
+            let err = embed_image_bytes_with_options(&carrier, b"x", &options).unwrap_err();
+            assert!(err.to_string().contains("bits_per_channel"));
+        }
+    }
 
-                // Get the LSB from this channel
-                let bit = pixel[channel] & 1;
+    /// An RGBA buffer of `width * height` pixels and a payload filling its capacity.
+    #[cfg(feature = "parallel")]
+    fn full_payload(
+        width: usize,
+        height: usize,
+        bits_per_channel: u8,
+        channels: usize,
+    ) -> (Vec<u8>, Vec<u8>) {
+        let buffer = (0..width * height * 4)
+            .map(|i| (i * 31 % 256) as u8)
+            .collect();
+        let capacity = payload_capacity(width as u32, height as u32, bits_per_channel, channels);
+        let data = (0..capacity).map(|i| (i * 7 % 251) as u8).collect();
+        (buffer, data)
+    }
 
-                // Set this bit in our image bytes (MSB first)
-                image_bytes[data_index] |= bit << (7 - bit_index);
+    /// Write a sequential payload the single-threaded way.
+    #[cfg(feature = "parallel")]
+    fn write_sequentially(buffer: &mut [u8], data: &[u8], bits_per_channel: u8, channels: usize) {
+        let order = payload_pixel_order(
+            buffer.len() / 4,
+            data.len(),
+            bits_per_channel,
+            channels,
+            None,
+        )
+        .unwrap();
+        write_bits(
+            buffer,
+            channel_slots(order, channels),
+            data,
+            bits_per_channel,
+        );
+    }
 
-                bit_index += 1;
-                if bit_index == 8 {
-                    bit_index = 0;
-                    data_index += 1;
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_banded_embedding_matches_sequential_across_band_boundaries() {
+        // Bands of many rows, bands of one row, and rows narrower than the header
+        for (width, height) in [(1001, 300), (70_000, 3), (7, 20_000)] {
+            for bits_per_channel in 1..=MAX_BITS_PER_CHANNEL {
+                for channels in [CHANNELS_PER_PIXEL, CHANNELS_WITH_ALPHA] {
+                    let (mut buffer, data) =
+                        full_payload(width, height, bits_per_channel, channels);
+                    let mut expected = buffer.clone();
+                    write_sequentially(&mut expected, &data, bits_per_channel, channels);
+                    write_bits_in_bands(
+                        &mut buffer,
+                        width,
+                        &data,
+                        bits_per_channel,
+                        channels,
+                        &ProgressTracker::new(None, 0),
+                    );
+                    assert!(
+                        buffer == expected,
+                        "{}x{} at {} bit(s) over {} channels differs",
+                        width,
+                        height,
+                        bits_per_channel,
+                        channels
+                    );
                 }
             }
         }
     }
 
-    Ok(image_bytes)
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_banded_embedding_of_large_carrier_is_identical() {
+        let (width, height) = (2400, 2000);
+        let (mut buffer, data) = full_payload(width, height, 1, CHANNELS_PER_PIXEL);
+        let mut sequential = buffer.clone();
+
+        write_sequentially(&mut sequential, &data, 1, CHANNELS_PER_PIXEL);
+        write_bits_in_bands(
+            &mut buffer,
+            width,
+            &data,
+            1,
+            CHANNELS_PER_PIXEL,
+            &ProgressTracker::new(None, 0),
+        );
+        assert!(buffer == sequential);
+
+        // A full embed over several bands still round-trips
+        let carrier = test_carrier(800, 400);
+        let secret: Vec<u8> = (0..100_000).map(|i| (i * 13 % 251) as u8).collect();
+        let encoded = embed_image_bytes(&carrier, &secret).unwrap();
+        assert_eq!(extract_image_bytes(&encoded).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_progress_rises_to_100_and_leaves_output_unchanged() {
+        // Several bands' worth of payload pixels, sequential and scattered
+        let carrier = test_carrier(800, 400);
+        let secret: Vec<u8> = (0..100_000).map(|i| (i * 13 % 251) as u8).collect();
+        for scatter_seed in [None, Some(7)] {
+            let options = EmbedOptions {
+                scatter_seed,
+                ..Default::default()
+            };
+            let reports = Mutex::new(Vec::new());
+            let encoded =
+                embed_image_bytes_with_progress(&carrier, &secret, &options, &|percent| {
+                    reports.lock().unwrap().push(percent)
+                })
+                .unwrap();
+
+            let reports = reports.into_inner().unwrap();
+            assert!(reports.len() > 2, "too few reports: {:?}", reports);
+            assert!(reports.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(reports.last(), Some(&100));
+            assert_eq!(
+                encoded,
+                embed_image_bytes_with_options(&carrier, &secret, &options).unwrap()
+            );
+        }
+    }
 }
@@ -7,8 +7,17 @@
 //!
 //! The priority score is calculated as a weighted combination of:
 //! - **CPU Usage** (50% weight): 0-100% from system metrics
-//! - **Active Tasks** (30% weight): Normalized task count (10 tasks = 100%)
+//! - **Active Tasks** (30% weight): Normalized task count (10 tasks = 100% by
+//!   default, see [`ServerMetrics::with_max_concurrent_tasks`])
 //! - **Memory Usage** (20% weight): 100% - available memory percentage
+//! - **Disk Usage** (0% weight): 100% - available disk space percentage, on the disk
+//!   holding the working directory (where results and task history are written)
+//!
+//! The weights above are the defaults; they can be changed per server with
+//! [`PriorityWeights`] under `[election.priority_weights]`.
+//!
+//! The CPU, memory and disk readings come from a [`MetricSource`]: the host's by
+//! default ([`SystemMetricSource`]), or any other via [`ServerMetrics::with_source`].
 //!
 //! **Lower scores indicate better candidates** (less loaded servers).
 //!
@@ -17,13 +26,16 @@
 //! priority = 0.5 * 20 + 0.3 * 20 + 0.2 * 20 = 20.0
 //! ```
 
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use sysinfo::System;
+use std::sync::{Arc, Mutex};
+use sysinfo::{Disks, System};
+
+use crate::common::config::PriorityWeights;
 
 /// Server performance metrics used for leader election priority calculation.
 ///
-/// Tracks real-time CPU usage, memory availability, and active task count
+/// Tracks real-time CPU usage, memory and disk availability, and active task count
 /// to determine which server is least loaded and should become the leader.
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
@@ -32,26 +44,202 @@ pub struct ServerMetrics {
     active_tasks: Arc<AtomicU64>,
     /// Total number of tasks processed over server lifetime (for statistics)
     total_tasks: Arc<AtomicU64>,
-    /// System information provider for CPU and memory metrics
-    system: Arc<std::sync::Mutex<System>>,
+    /// Provider of the CPU, memory and disk readings
+    source: Arc<dyn MetricSource>,
+    /// Weights of each metric in the priority score
+    weights: PriorityWeights,
+    /// Active task count that counts as full load in the priority score
+    max_concurrent_tasks: u64,
+}
+
+/// CPU, memory and disk readings taken together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SystemReadings {
+    /// CPU usage percentage (0.0 to 100.0)
+    pub cpu_usage: f64,
+    /// Available memory percentage (0.0 to 100.0)
+    pub available_memory_percent: f64,
+    /// Available disk space percentage (0.0 to 100.0)
+    pub available_disk_percent: f64,
+}
+
+/// Where [`ServerMetrics`] gets its system readings from.
+///
+/// [`SystemMetricSource`] reads the host; tests can inject fixed values instead.
+pub trait MetricSource: std::fmt::Debug + Send + Sync {
+    /// CPU usage percentage (0.0 to 100.0)
+    fn cpu_usage(&self) -> f64;
+
+    /// Available memory percentage (0.0 to 100.0)
+    fn available_memory_percent(&self) -> f64;
+
+    /// Available disk space percentage (0.0 to 100.0)
+    fn available_disk_percent(&self) -> f64;
+
+    /// All readings at once. Override this if they can be taken more consistently
+    /// together than one by one.
+    fn sample(&self) -> SystemReadings {
+        SystemReadings {
+            cpu_usage: self.cpu_usage(),
+            available_memory_percent: self.available_memory_percent(),
+            available_disk_percent: self.available_disk_percent(),
+        }
+    }
+}
+
+/// [`MetricSource`] reading this host through `sysinfo`.
+#[derive(Debug)]
+pub struct SystemMetricSource {
+    system: Mutex<System>,
+    disks: Mutex<Disks>,
+    /// Path whose disk is measured (the working directory)
+    disk_path: PathBuf,
+}
+
+impl SystemMetricSource {
+    /// Create a source measuring the disk that holds the working directory.
+    pub fn new() -> Self {
+        Self {
+            system: Mutex::new(System::new_all()),
+            disks: Mutex::new(Disks::new_with_refreshed_list()),
+            disk_path: std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/")),
+        }
+    }
+
+    fn memory_percent(sys: &System) -> f64 {
+        let total = sys.total_memory();
+        if total == 0 {
+            return 100.0;
+        }
+        (sys.available_memory() as f64 / total as f64) * 100.0
+    }
+}
+
+impl Default for SystemMetricSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetricSource for SystemMetricSource {
+    fn cpu_usage(&self) -> f64 {
+        let mut sys = self.system.lock().unwrap();
+
+        // Refresh CPU information to get current readings
+        sys.refresh_cpu_all();
+
+        // Get global CPU usage (average across all cores)
+        sys.global_cpu_usage() as f64
+    }
+
+    fn available_memory_percent(&self) -> f64 {
+        let mut sys = self.system.lock().unwrap();
+        sys.refresh_memory();
+        Self::memory_percent(&sys)
+    }
+
+    fn available_disk_percent(&self) -> f64 {
+        let mut disks = self.disks.lock().unwrap();
+        disks.refresh();
+
+        // The disk mounted closest to the working directory holds it
+        let disk = disks
+            .list()
+            .iter()
+            .filter(|disk| self.disk_path.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len());
+
+        match disk {
+            Some(disk) if disk.total_space() > 0 => {
+                (disk.available_space() as f64 / disk.total_space() as f64) * 100.0
+            }
+            _ => 100.0,
+        }
+    }
+
+    fn sample(&self) -> SystemReadings {
+        // CPU and memory are refreshed under a single lock
+        let (cpu_usage, available_memory_percent) = {
+            let mut sys = self.system.lock().unwrap();
+            sys.refresh_cpu_all();
+            sys.refresh_memory();
+            (sys.global_cpu_usage() as f64, Self::memory_percent(&sys))
+        };
+        SystemReadings {
+            cpu_usage,
+            available_memory_percent,
+            available_disk_percent: self.available_disk_percent(),
+        }
+    }
+}
+
+/// All [`ServerMetrics`] readings captured together, so they are consistent with
+/// each other (e.g. `priority` is computed from exactly these values).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServerMetricsSnapshot {
+    /// CPU usage percentage (0.0 to 100.0)
+    pub cpu_usage: f64,
+    /// Available memory percentage (0.0 to 100.0)
+    pub available_memory_percent: f64,
+    /// Available disk space percentage (0.0 to 100.0)
+    pub available_disk_percent: f64,
+    /// Tasks being processed when the snapshot was taken
+    pub active_tasks: u64,
+    /// Tasks started over the server's lifetime
+    pub total_tasks: u64,
+    /// Priority score from the readings above (lower = better candidate)
+    pub priority: f64,
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        Self::new(PriorityWeights::default())
+    }
 }
 
 #[allow(dead_code)]
 impl ServerMetrics {
     /// Create a new ServerMetrics instance with all counters at zero.
     ///
+    /// # Arguments
+    /// - `weights`: Weights of each metric in the priority score
+    ///
     /// # Example
     /// ```ignore
-    /// let metrics = ServerMetrics::new();
+    /// let metrics = ServerMetrics::new(config.election.priority_weights);
     /// ```
-    pub fn new() -> Self {
+    pub fn new(weights: PriorityWeights) -> Self {
         Self {
             active_tasks: Arc::new(AtomicU64::new(0)),
             total_tasks: Arc::new(AtomicU64::new(0)),
-            system: Arc::new(std::sync::Mutex::new(System::new_all())),
+            source: Arc::new(SystemMetricSource::new()),
+            weights,
+            max_concurrent_tasks: 10,
         }
     }
 
+    /// Set how many active tasks count as full load in the priority score (default 10).
+    ///
+    /// # Example
+    /// ```ignore
+    /// let metrics = ServerMetrics::new(weights).with_max_concurrent_tasks(50);
+    /// ```
+    pub fn with_max_concurrent_tasks(mut self, max_concurrent_tasks: u64) -> Self {
+        self.max_concurrent_tasks = max_concurrent_tasks.max(1);
+        self
+    }
+
+    /// Take CPU, memory and disk readings from `source` instead of this host.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let metrics = ServerMetrics::new(weights).with_source(Arc::new(FixedReadings { .. }));
+    /// ```
+    pub fn with_source(mut self, source: Arc<dyn MetricSource>) -> Self {
+        self.source = source;
+        self
+    }
+
     /// Get current CPU usage as a percentage (0.0 to 100.0).
     ///
     /// Returns the average CPU usage across all cores.
@@ -65,13 +253,7 @@ impl ServerMetrics {
     /// println!("CPU usage: {:.1}%", cpu);
     /// ```
     pub fn get_cpu_usage(&self) -> f64 {
-        let mut sys = self.system.lock().unwrap();
-
-        // Refresh CPU information to get current readings
-        sys.refresh_cpu_all();
-
-        // Get global CPU usage (average across all cores)
-        sys.global_cpu_usage() as f64
+        self.source.cpu_usage()
     }
 
     /// Get the number of currently active (running) tasks.
@@ -88,6 +270,17 @@ impl ServerMetrics {
         self.active_tasks.load(Ordering::Relaxed)
     }
 
+    /// Get the total number of tasks started over the server's lifetime.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let total = metrics.get_total_tasks();
+    /// println!("Tasks processed: {}", total);
+    /// ```
+    pub fn get_total_tasks(&self) -> u64 {
+        self.total_tasks.load(Ordering::Relaxed)
+    }
+
     /// Get available memory as a percentage (0.0 to 100.0).
     ///
     /// # Returns
@@ -99,19 +292,21 @@ impl ServerMetrics {
     /// println!("Available memory: {:.1}%", mem);
     /// ```
     pub fn get_available_memory_percent(&self) -> f64 {
-        let mut sys = self.system.lock().unwrap();
-
-        // Refresh memory information
-        sys.refresh_memory();
-
-        let total = sys.total_memory();
-        let available = sys.available_memory();
-
-        if total == 0 {
-            return 100.0;
-        }
+        self.source.available_memory_percent()
+    }
 
-        (available as f64 / total as f64) * 100.0
+    /// Get available disk space as a percentage (0.0 to 100.0).
+    ///
+    /// Measured on the disk holding the working directory, where results and
+    /// task history are written.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let disk = metrics.get_available_disk_percent();
+    /// println!("Available disk: {:.1}%", disk);
+    /// ```
+    pub fn get_available_disk_percent(&self) -> f64 {
+        self.source.available_disk_percent()
     }
 
     /// Increment the active task counter when a task starts processing.
@@ -150,13 +345,16 @@ impl ServerMetrics {
     /// # Formula
     ///
     /// ```text
-    /// priority = 0.5 * CPU_usage + 0.3 * normalized_tasks + 0.2 * memory_used
+    /// priority = 0.5 * CPU_usage + 0.3 * normalized_tasks + 0.2 * memory_used + 0.0 * disk_used
     /// ```
     ///
-    /// Where:
+    /// (with the default [`PriorityWeights`]), where:
     /// - `CPU_usage`: 0-100% from system metrics
-    /// - `normalized_tasks`: (active_tasks / 10) * 100, capped at 100%
+    /// - `normalized_tasks`: (active_tasks / max_concurrent_tasks) * 100, capped at 100%
+    ///   (`max_concurrent_tasks` is 10 unless set with
+    ///   [`with_max_concurrent_tasks`](Self::with_max_concurrent_tasks))
     /// - `memory_used`: 100% - available_memory_percent
+    /// - `disk_used`: 100% - available_disk_percent
     ///
     /// # Returns
     /// - Priority score (0.0 = best/unloaded, 100.0 = worst/overloaded)
@@ -181,24 +379,7 @@ impl ServerMetrics {
     /// priority = 0.5*80 + 0.3*100 + 0.2*80 = 86.0 (poor)
     /// ```
     pub fn calculate_priority(&self) -> f64 {
-        const W_CPU: f64 = 0.5;     // Weight for CPU usage (50%)
-        const W_TASKS: f64 = 0.3;   // Weight for active tasks (30%)
-        const W_MEMORY: f64 = 0.2;  // Weight for memory (20%)
-
-        let cpu_usage = self.get_cpu_usage();
-        let active_tasks = self.get_active_tasks() as f64;
-        let memory_available = self.get_available_memory_percent();
-
-        // Normalize active tasks (assuming max 10 concurrent tasks = "full load")
-        let tasks_normalized = (active_tasks / 10.0).min(1.0) * 100.0;
-
-        // Memory score: lower available memory = higher score (worse)
-        let memory_score = 100.0 - memory_available;
-
-        // Calculate composite score (lower = better candidate)
-        let priority = W_CPU * cpu_usage + W_TASKS * tasks_normalized + W_MEMORY * memory_score;
-
-        priority
+        self.snapshot().priority
     }
 
     /// Get the current load value as a percentage (0.0 to 100.0).
@@ -219,4 +400,219 @@ impl ServerMetrics {
     pub fn get_load(&self) -> f64 {
         self.calculate_priority()
     }
+
+    /// Load that `tasks` more active tasks would add to this server's priority score.
+    ///
+    /// Unlike the score itself this isn't capped, so each extra task keeps counting
+    /// even past `max_concurrent_tasks`.
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Default weights and 10 concurrent tasks: each task adds 0.3 * 10% = 3.0
+    /// assert_eq!(metrics.task_load(2), 6.0);
+    /// ```
+    pub fn task_load(&self, tasks: u64) -> f64 {
+        self.weights.tasks * tasks as f64 / self.max_concurrent_tasks.max(1) as f64 * 100.0
+    }
+
+    /// Capture every metric at once, with the priority computed from the same readings.
+    ///
+    /// Unlike calling the individual getters in a row, the system readings come from
+    /// one [`MetricSource::sample`] and the task counters are read once, so the values
+    /// (and the priority derived from them) describe one moment.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let snapshot = metrics.snapshot();
+    /// debug!("load {:.2} from {} tasks", snapshot.priority, snapshot.active_tasks);
+    /// ```
+    pub fn snapshot(&self) -> ServerMetricsSnapshot {
+        let readings = self.source.sample();
+        let active_tasks = self.get_active_tasks();
+        let total_tasks = self.get_total_tasks();
+
+        ServerMetricsSnapshot {
+            cpu_usage: readings.cpu_usage,
+            available_memory_percent: readings.available_memory_percent,
+            available_disk_percent: readings.available_disk_percent,
+            active_tasks,
+            total_tasks,
+            priority: priority_score(
+                &self.weights,
+                &readings,
+                active_tasks,
+                self.max_concurrent_tasks,
+            ),
+        }
+    }
+}
+
+/// Weighted priority score for the given metric readings (lower = better candidate).
+fn priority_score(
+    weights: &PriorityWeights,
+    readings: &SystemReadings,
+    active_tasks: u64,
+    max_concurrent_tasks: u64,
+) -> f64 {
+    // Normalize active tasks (`max_concurrent_tasks` tasks = "full load")
+    let tasks_normalized =
+        (active_tasks as f64 / max_concurrent_tasks.max(1) as f64).min(1.0) * 100.0;
+
+    // Memory and disk scores: less available = higher score (worse)
+    let memory_score = 100.0 - readings.available_memory_percent;
+    let disk_score = 100.0 - readings.available_disk_percent;
+
+    // Calculate composite score (lower = better candidate)
+    weights.cpu * readings.cpu_usage
+        + weights.tasks * tasks_normalized
+        + weights.memory * memory_score
+        + weights.disk * disk_score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fixed readings, so priority tests don't depend on the host.
+    #[derive(Debug, Clone, Copy)]
+    struct FixedReadings(SystemReadings);
+
+    impl MetricSource for FixedReadings {
+        fn cpu_usage(&self) -> f64 {
+            self.0.cpu_usage
+        }
+
+        fn available_memory_percent(&self) -> f64 {
+            self.0.available_memory_percent
+        }
+
+        fn available_disk_percent(&self) -> f64 {
+            self.0.available_disk_percent
+        }
+    }
+
+    fn readings(cpu: f64, memory_available: f64, disk_available: f64) -> SystemReadings {
+        SystemReadings {
+            cpu_usage: cpu,
+            available_memory_percent: memory_available,
+            available_disk_percent: disk_available,
+        }
+    }
+
+    #[test]
+    fn test_default_weights_match_documented_examples() {
+        let weights = PriorityWeights::default();
+        let score =
+            |cpu, tasks, memory| priority_score(&weights, &readings(cpu, memory, 5.0), tasks, 10);
+        assert!((score(40.0, 5, 60.0) - 43.0).abs() < 1e-9);
+        assert!((score(80.0, 10, 20.0) - 86.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_custom_weights_favor_memory() {
+        let weights = PriorityWeights {
+            cpu: 0.2,
+            tasks: 0.2,
+            memory: 0.6,
+            disk: 0.0,
+        };
+        // CPU 40%, 5 tasks (50%), 30% memory available (70% used)
+        // = 0.2*40 + 0.2*50 + 0.6*70 = 8 + 10 + 42
+        assert!(
+            (priority_score(&weights, &readings(40.0, 30.0, 100.0), 5, 10) - 60.0).abs() < 1e-9
+        );
+
+        // A memory-starved but otherwise idle server now scores worse than a busy one
+        let starved = priority_score(&weights, &readings(0.0, 10.0, 100.0), 0, 10);
+        let busy = priority_score(&weights, &readings(90.0, 90.0, 100.0), 10, 10);
+        assert!(starved > busy);
+    }
+
+    #[test]
+    fn test_disk_weight_penalizes_full_disk() {
+        let weights = PriorityWeights {
+            cpu: 0.4,
+            tasks: 0.2,
+            memory: 0.2,
+            disk: 0.2,
+        };
+        let metrics = |disk_available| {
+            ServerMetrics::new(weights).with_source(Arc::new(FixedReadings(readings(
+                20.0,
+                80.0,
+                disk_available,
+            ))))
+        };
+
+        // CPU 20%, no tasks, 20% memory used, 95% disk used
+        // = 0.4*20 + 0.2*0 + 0.2*20 + 0.2*95 = 8 + 0 + 4 + 19
+        let full_disk = metrics(5.0);
+        assert_eq!(full_disk.get_available_disk_percent(), 5.0);
+        assert!((full_disk.calculate_priority() - 31.0).abs() < 1e-9);
+
+        // Same server with plenty of disk is the better candidate
+        assert!(metrics(90.0).calculate_priority() < full_disk.calculate_priority());
+
+        // With the default weights disk space doesn't count
+        let default_weights = ServerMetrics::default()
+            .with_source(Arc::new(FixedReadings(readings(20.0, 80.0, 5.0))));
+        assert!((default_weights.calculate_priority() - 14.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_task_divisor_scales_task_contribution() {
+        let weights = PriorityWeights::default();
+        let idle = readings(0.0, 100.0, 100.0);
+
+        // 10 active tasks saturate the task term at the default divisor...
+        let small = priority_score(&weights, &idle, 10, 10);
+        assert!((small - 30.0).abs() < 1e-9);
+
+        // ...but are only a fifth of full load on a machine sized for 50
+        let large = priority_score(&weights, &idle, 10, 50);
+        assert!((large - 6.0).abs() < 1e-9);
+
+        let metrics = ServerMetrics::default()
+            .with_max_concurrent_tasks(50)
+            .with_source(Arc::new(FixedReadings(idle)));
+        for _ in 0..10 {
+            metrics.task_started();
+        }
+        assert!((metrics.calculate_priority() - large).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_system_source_reports_percentages() {
+        let source = SystemMetricSource::new();
+        let readings = source.sample();
+        assert!((0.0..=100.0).contains(&readings.cpu_usage));
+        assert!((0.0..=100.0).contains(&readings.available_memory_percent));
+        assert!((0.0..=100.0).contains(&readings.available_disk_percent));
+    }
+
+    #[test]
+    fn test_snapshot_priority_matches_its_own_readings() {
+        let metrics = ServerMetrics::default();
+        metrics.task_started();
+        metrics.task_started();
+        metrics.task_finished();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.active_tasks, 1);
+        assert_eq!(snapshot.total_tasks, 2);
+        assert!((0.0..=100.0).contains(&snapshot.cpu_usage));
+        assert!((0.0..=100.0).contains(&snapshot.available_memory_percent));
+
+        let expected = priority_score(
+            &PriorityWeights::default(),
+            &readings(
+                snapshot.cpu_usage,
+                snapshot.available_memory_percent,
+                snapshot.available_disk_percent,
+            ),
+            snapshot.active_tasks,
+            10,
+        );
+        assert_eq!(snapshot.priority, expected);
+    }
 }
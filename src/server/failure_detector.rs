@@ -0,0 +1,154 @@
+//! # Phi-Accrual Failure Detector
+//!
+//! Adaptive failure detection based on the heartbeat history of each peer
+//! (Hayashibara et al., "The φ Accrual Failure Detector").
+//!
+//! Instead of a fixed timeout, the detector learns the distribution of the
+//! intervals between a peer's heartbeats and reports a suspicion level `phi`
+//! for the time since the last one:
+//!
+//! ```text
+//! phi = -log10(P(next heartbeat arrives later than now))
+//! ```
+//!
+//! A phi of 1 means a ~10% chance the peer is still alive and just late, 2 means
+//! ~1%, 3 means ~0.1%, and so on. Peers whose heartbeats are usually regular are
+//! suspected quickly when they stop, while peers with jittery heartbeats (e.g. GC
+//! pauses or a busy network) get more slack.
+
+use std::collections::VecDeque;
+
+/// Number of recent inter-arrival intervals kept per peer.
+pub const DEFAULT_MAX_SAMPLES: usize = 100;
+
+/// Floor on the standard deviation, so perfectly regular heartbeats don't make
+/// the detector suspect a peer a few milliseconds after it is due.
+pub const DEFAULT_MIN_STD_DEVIATION_MS: f64 = 200.0;
+
+/// Tracks one peer's heartbeat inter-arrival times and computes its phi.
+#[derive(Debug, Clone)]
+pub struct PhiAccrualDetector {
+    /// Most recent intervals between heartbeats, in milliseconds
+    intervals: VecDeque<f64>,
+    /// Largest number of intervals kept
+    max_samples: usize,
+    /// Floor on the standard deviation of the intervals
+    min_std_deviation_ms: f64,
+    /// Arrival time of the last heartbeat (Unix milliseconds)
+    last_arrival_ms: Option<u64>,
+}
+
+impl PhiAccrualDetector {
+    /// Create a detector primed with the expected heartbeat interval.
+    ///
+    /// The expected interval seeds the history so phi is meaningful right after
+    /// the first heartbeat, before any real interval has been measured.
+    ///
+    /// # Arguments
+    /// - `expected_interval_ms`: Configured heartbeat interval, in milliseconds
+    ///
+    /// # Example
+    /// ```ignore
+    /// let mut detector = PhiAccrualDetector::new(1_000.0);
+    /// detector.heartbeat(now_ms);
+    /// ```
+    pub fn new(expected_interval_ms: f64) -> Self {
+        Self {
+            intervals: VecDeque::from([expected_interval_ms]),
+            max_samples: DEFAULT_MAX_SAMPLES,
+            min_std_deviation_ms: DEFAULT_MIN_STD_DEVIATION_MS,
+            last_arrival_ms: None,
+        }
+    }
+
+    /// Record a heartbeat arriving at `now_ms` (Unix milliseconds).
+    pub fn heartbeat(&mut self, now_ms: u64) {
+        if let Some(last) = self.last_arrival_ms {
+            if self.intervals.len() == self.max_samples {
+                self.intervals.pop_front();
+            }
+            self.intervals.push_back(now_ms.saturating_sub(last) as f64);
+        }
+        self.last_arrival_ms = Some(now_ms);
+    }
+
+    /// Suspicion level at `now_ms` (Unix milliseconds); 0.0 before the first heartbeat.
+    pub fn phi(&self, now_ms: u64) -> f64 {
+        let Some(last) = self.last_arrival_ms else {
+            return 0.0;
+        };
+        let elapsed = now_ms.saturating_sub(last) as f64;
+
+        let count = self.intervals.len() as f64;
+        let mean = self.intervals.iter().sum::<f64>() / count;
+        let variance = self
+            .intervals
+            .iter()
+            .map(|interval| (interval - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        let std_deviation = variance.sqrt().max(self.min_std_deviation_ms);
+
+        // Logistic approximation of the normal CDF's upper tail (as used by Akka/Cassandra)
+        let y = (elapsed - mean) / std_deviation;
+        let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+        if elapsed > mean {
+            -(e / (1.0 + e)).log10()
+        } else {
+            -(1.0 - 1.0 / (1.0 + e)).log10()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phi_rises_when_heartbeats_stop() {
+        let mut detector = PhiAccrualDetector::new(1_000.0);
+        assert_eq!(detector.phi(0), 0.0);
+
+        // Steady heartbeats every ~1s with a little jitter
+        let mut now = 0;
+        for jitter in [0, 40, -30, 10, -20, 50, 0, -40, 20, -10] {
+            now = (now as i64 + 1_000 + jitter) as u64;
+            detector.heartbeat(now);
+        }
+
+        // On time: no suspicion
+        let on_time = detector.phi(now + 1_000);
+        assert!(on_time < 1.0, "phi {} on time", on_time);
+
+        // Heartbeats stop: phi grows monotonically as silence lengthens
+        let mut previous = on_time;
+        for silence in [1_500, 2_000, 3_000, 5_000] {
+            let phi = detector.phi(now + silence);
+            assert!(
+                phi > previous,
+                "phi should rise: {} after {}ms",
+                phi,
+                silence
+            );
+            previous = phi;
+        }
+        assert!(detector.phi(now + 3_000) > 8.0);
+    }
+
+    #[test]
+    fn test_jittery_peer_gets_more_slack() {
+        let mut steady = PhiAccrualDetector::new(1_000.0);
+        let mut jittery = PhiAccrualDetector::new(1_000.0);
+
+        let (mut steady_now, mut jittery_now) = (0, 0);
+        for i in 0..20 {
+            steady_now += 1_000;
+            steady.heartbeat(steady_now);
+            jittery_now += if i % 2 == 0 { 400 } else { 1_600 };
+            jittery.heartbeat(jittery_now);
+        }
+
+        // Same silence, less suspicion for the peer that's usually irregular
+        assert!(jittery.phi(jittery_now + 2_500) < steady.phi(steady_now + 2_500));
+    }
+}
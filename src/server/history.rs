@@ -0,0 +1,217 @@
+//! # Persistent Task History Log
+//!
+//! Append-only log of task history changes, so a restarted server can rebuild the
+//! task assignments it knew about before crashing instead of treating every
+//! in-flight task as lost.
+//!
+//! ## File Format
+//!
+//! One JSON record per line, replayed in order on startup:
+//! ```text
+//! {"Add":{"client_name":"Client1","request_id":7,"assigned_server_id":2,"timestamp":1700000000}}
+//! {"Remove":{"client_name":"Client1","request_id":7}}
+//! ```
+//!
+//! On load the file is compacted to one `Add` per live entry, so it only grows
+//! with the tasks in flight since the last restart.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A task history entry: (client_name, request_id, assigned_server_id, timestamp).
+pub type HistoryEntry = (String, u64, u32, u64);
+
+/// One line of the history log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+enum HistoryRecord {
+    /// A task was assigned (or reassigned) to a server
+    Add {
+        client_name: String,
+        request_id: u64,
+        assigned_server_id: u32,
+        timestamp: u64,
+    },
+    /// A task completed and was acknowledged (or expired)
+    Remove {
+        client_name: String,
+        request_id: u64,
+    },
+}
+
+/// Append-only, line-oriented log of task history changes.
+pub struct HistoryLog {
+    /// Location of the log file
+    path: PathBuf,
+    /// Open handle in append mode
+    file: Mutex<File>,
+}
+
+impl HistoryLog {
+    /// Open (or create) the log at `path` and replay it into a list of live entries.
+    ///
+    /// Lines that fail to parse (e.g. a record torn by a crash mid-write) are
+    /// skipped. The file is then compacted to the replayed entries.
+    ///
+    /// # Returns
+    /// - `Ok((HistoryLog, entries))`: The open log and the restored history
+    /// - `Err`: If the file can't be read, compacted, or reopened
+    ///
+    /// # Example
+    /// ```ignore
+    /// let (log, entries) = HistoryLog::open("data/server1_history.jsonl")?;
+    /// ```
+    pub fn open(path: impl AsRef<Path>) -> Result<(Self, Vec<HistoryEntry>)> {
+        let path = path.as_ref().to_path_buf();
+        let entries = Self::replay(&path)?;
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Compact: rewrite the file as one Add per live entry
+        Self::write_snapshot(&path, &entries)?;
+
+        let file = OpenOptions::new().append(true).open(&path)?;
+        Ok((
+            Self {
+                path,
+                file: Mutex::new(file),
+            },
+            entries,
+        ))
+    }
+
+    /// Record that a task was assigned to `assigned_server_id`.
+    pub fn record_add(
+        &self,
+        client_name: &str,
+        request_id: u64,
+        assigned_server_id: u32,
+        timestamp: u64,
+    ) -> Result<()> {
+        self.append(&HistoryRecord::Add {
+            client_name: client_name.to_string(),
+            request_id,
+            assigned_server_id,
+            timestamp,
+        })
+    }
+
+    /// Record that a task was removed from history.
+    pub fn record_remove(&self, client_name: &str, request_id: u64) -> Result<()> {
+        self.append(&HistoryRecord::Remove {
+            client_name: client_name.to_string(),
+            request_id,
+        })
+    }
+
+    /// Replace the whole log with `entries` (e.g. after a leader merges peer histories).
+    pub fn replace(&self, entries: &[HistoryEntry]) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        Self::write_snapshot(&self.path, entries)?;
+        *file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+
+    fn append(&self, record: &HistoryRecord) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&line)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Read the log at `path` (if any) and apply its records in order.
+    fn replay(path: &Path) -> Result<Vec<HistoryEntry>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut history: HashMap<(String, u64), (u32, u64)> = HashMap::new();
+        for line in BufReader::new(file).lines() {
+            let Ok(record) = serde_json::from_str::<HistoryRecord>(&line?) else {
+                continue;
+            };
+            match record {
+                HistoryRecord::Add {
+                    client_name,
+                    request_id,
+                    assigned_server_id,
+                    timestamp,
+                } => {
+                    history.insert((client_name, request_id), (assigned_server_id, timestamp));
+                }
+                HistoryRecord::Remove {
+                    client_name,
+                    request_id,
+                } => {
+                    history.remove(&(client_name, request_id));
+                }
+            }
+        }
+
+        Ok(history
+            .into_iter()
+            .map(|((client_name, request_id), (server_id, timestamp))| {
+                (client_name, request_id, server_id, timestamp)
+            })
+            .collect())
+    }
+
+    /// Atomically replace the file at `path` with one `Add` record per entry.
+    fn write_snapshot(path: &Path, entries: &[HistoryEntry]) -> Result<()> {
+        let tmp_path = path.with_extension("tmp");
+        let mut tmp = File::create(&tmp_path)?;
+        for (client_name, request_id, assigned_server_id, timestamp) in entries {
+            let record = HistoryRecord::Add {
+                client_name: client_name.clone(),
+                request_id: *request_id,
+                assigned_server_id: *assigned_server_id,
+                timestamp: *timestamp,
+            };
+            serde_json::to_writer(&mut tmp, &record)?;
+            tmp.write_all(b"\n")?;
+        }
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_applies_adds_and_removes_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+
+        let (log, entries) = HistoryLog::open(&path).unwrap();
+        assert!(entries.is_empty());
+        log.record_add("Client1", 1, 2, 100).unwrap();
+        log.record_add("Client1", 2, 3, 101).unwrap();
+        log.record_add("Client1", 1, 3, 102).unwrap(); // reassigned
+        log.record_remove("Client1", 2).unwrap();
+        drop(log);
+
+        // A torn final line from a crash is ignored
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"Add\":{\"client_na").unwrap();
+
+        let (_, entries) = HistoryLog::open(&path).unwrap();
+        assert_eq!(entries, vec![("Client1".to_string(), 1, 3, 102)]);
+
+        // Compacted to a single record
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+    }
+}
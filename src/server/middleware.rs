@@ -9,6 +9,8 @@
 //! - Calculates priority based on CPU usage, active tasks, and available memory
 //! - Broadcasts election messages and handles responses
 //! - Announces leader and maintains leader state
+//! - Optionally reports each step as an [`ElectionEvent`] (see
+//!   [`ServerMiddleware::with_election_events`])
 //!
 //! ### 2. Heartbeat Management
 //! - Sends periodic heartbeats to all peers with current load metrics
@@ -18,9 +20,12 @@
 //! ### 3. Task Distribution & Load Balancing
 //! - Receives task assignment requests from clients
 //! - Determines optimal server based on current load across cluster
-//! - Routes tasks to the least-loaded server
+//! - Routes tasks to the least-loaded server, or per another [`AssignmentStrategy`]
 //! - Maintains task history for fault tolerance
 //!
+//! - Optionally forwards tasks to the chosen server and relays the result, so
+//!   clients only ever talk to the leader
+//!
 //! ### 4. Fault Tolerance
 //! - Detects when peers fail (missing heartbeats)
 //! - Cleans up orphaned tasks when servers fail
@@ -47,21 +52,38 @@
 //! Server -> ServerCore (encrypt_image)
 //! Server -> Client (TaskResponse)
 //! ```
+//!
+//! In leader-routed mode the client never contacts the assigned server:
+//!
+//! ```text
+//! Client -> Leader (RoutedTaskRequest)
+//! Leader -> Assigned Server (ForwardedTask)
+//! Assigned Server -> Leader (ForwardedTaskResult)
+//! Leader -> Client (TaskResponse)
+//! Client -> Leader (TaskAck)
+//! ```
 
 use anyhow::Result;
-use log::{debug, error, info, warn};
+use tracing::{debug, error, info, instrument, warn, Instrument};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{mpsc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::JoinSet;
 
 use crate::common::config::{ElectionConfig, PeersConfig};
-use crate::common::connection::Connection;
+use crate::common::connection::{Connection, ConnectionError, DEFAULT_MAX_MESSAGE_SIZE};
 use crate::common::messages::*;
+use crate::common::transport::{is_unix_address, Listener, Stream};
 use crate::server::election::ServerMetrics;
+use crate::server::failure_detector::PhiAccrualDetector;
+use crate::server::history::HistoryLog;
 use crate::server::server::ServerCore;
 
 // ============================================================================
@@ -88,17 +110,100 @@ pub struct ServerConfig {
 pub struct ServerInfo {
     /// Unique identifier for this server (1, 2, 3, etc.)
     pub id: u32,
-    /// Network address where this server listens (e.g., "127.0.0.1:8001")
+    /// Network address where this server listens (e.g., "127.0.0.1:8001"), or a
+    /// Unix socket path (e.g., "/tmp/cloudp2p/server1.sock") for co-located servers
     pub address: String,
     /// Path to the cover/carrier image file (default: "test_images/medium.jpg")
     #[serde(default = "default_cover_image_path")]
     pub cover_image: String,
+    /// Optional directory of carrier images; when set, it replaces `cover_image`
+    /// and each task uses the smallest carrier that fits its secret
+    #[serde(default)]
+    pub carrier_dir: Option<String>,
+    /// Largest message accepted from clients and peers, in bytes (default: 100MB)
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+    /// Optional file the task history is append-logged to and restored from on startup
+    #[serde(default)]
+    pub history_log_path: Option<String>,
+    /// Most encryption tasks run at once; further requests are rejected (default: 8)
+    #[serde(default = "default_max_concurrent_tasks")]
+    pub max_concurrent_tasks: usize,
+    /// Messages queued for each peer before further ones are dropped or wait (see
+    /// [`PeerDelivery`]) (default: 100)
+    #[serde(default = "default_peer_channel_capacity")]
+    pub peer_channel_capacity: usize,
+    /// Longest one encryption task may run before it's cancelled and the client gets
+    /// a failed `TaskResponse`, in seconds (default: 300)
+    #[serde(default = "default_task_timeout_secs")]
+    pub task_timeout_secs: u64,
+    /// Most task requests the leader accepts from one client per rate window;
+    /// further requests get `RateLimited` (default: unlimited)
+    #[serde(default)]
+    pub client_rate_limit: Option<usize>,
+    /// Length of the sliding window `client_rate_limit` applies to, in seconds (default: 10)
+    #[serde(default = "default_client_rate_window_secs")]
+    pub client_rate_window_secs: u64,
+    /// Copy each completed result to the next server by ID, so it survives this
+    /// server failing before the client's ACK (default: false)
+    #[serde(default)]
+    pub replicate_results: bool,
+    /// How the leader picks the server for each new task (default: `lowest_load`)
+    #[serde(default)]
+    pub assignment_strategy: AssignmentStrategy,
+    /// Keep assigning each client to the server its last task went to, while that
+    /// server is alive and has free task slots (default: false)
+    #[serde(default)]
+    pub client_affinity: bool,
+    /// Send clients `TaskProgress` messages while their secret is embedded, on the
+    /// connection their `TaskRequest` came in on (default: false)
+    #[serde(default)]
+    pub report_progress: bool,
+}
+
+/// How the leader chooses the server for a new task, set with
+/// `server.assignment_strategy`.
+///
+/// Whatever the strategy, servers reporting saturation are only chosen when every
+/// server is saturated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentStrategy {
+    /// Lowest load, counting tasks assigned but not yet acknowledged
+    #[default]
+    LowestLoad,
+    /// Each server in turn, by ID
+    RoundRobin,
+    /// Fewest tasks assigned but not yet acknowledged, ties broken by load
+    LeastConnections,
+    /// A uniformly random server
+    Random,
 }
 
 fn default_cover_image_path() -> String {
     "test_images/medium.jpg".to_string()
 }
 
+fn default_max_message_size() -> usize {
+    DEFAULT_MAX_MESSAGE_SIZE
+}
+
+fn default_max_concurrent_tasks() -> usize {
+    8
+}
+
+fn default_peer_channel_capacity() -> usize {
+    100
+}
+
+fn default_task_timeout_secs() -> u64 {
+    300
+}
+
+fn default_client_rate_window_secs() -> u64 {
+    10
+}
+
 #[allow(dead_code)]
 impl ServerConfig {
     /// Load server configuration from a TOML file.
@@ -108,7 +213,8 @@ impl ServerConfig {
     ///
     /// # Returns
     /// - `Ok(ServerConfig)`: Successfully loaded configuration
-    /// - `Err`: File I/O or parsing error
+    /// - `Err`: File I/O or parsing error, or a configuration that fails
+    ///   [`validate`](Self::validate)
     ///
     /// # Example
     /// ```ignore
@@ -117,8 +223,85 @@ impl ServerConfig {
     pub fn from_file(path: &str) -> Result<Self> {
         let content = fs::read_to_string(path)?;
         let config: ServerConfig = toml::from_str(&content)?;
+        config.validate()?;
         Ok(config)
     }
+
+    /// Check the configuration for mistakes that would otherwise only surface later
+    /// as confusing runtime failures.
+    ///
+    /// Checks that:
+    /// - this server's and every peer's address parses as a `SocketAddr` (`ip:port`)
+    ///   or is a Unix socket path
+    /// - peer IDs are unique and differ from `server.id`
+    /// - timeouts, intervals and limits are non-zero
+    /// - the election priority weights are valid
+    ///
+    /// # Returns
+    /// - `Ok(())`: The configuration is usable
+    /// - `Err`: One error listing every problem found, one per line
+    ///
+    /// # Example
+    /// ```ignore
+    /// let config: ServerConfig = load_config("config/server1.toml")?;
+    /// config.validate()?;
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if !is_valid_address(&self.server.address) {
+            problems.push(format!(
+                "server.address '{}' is not a valid ip:port address or socket path",
+                self.server.address
+            ));
+        }
+
+        let mut peer_ids = HashSet::new();
+        for peer in &self.peers.peers {
+            if peer.id == self.server.id {
+                problems.push(format!("peer id {} is this server's own id", peer.id));
+            } else if !peer_ids.insert(peer.id) {
+                problems.push(format!("peer id {} is listed more than once", peer.id));
+            }
+            if !is_valid_address(&peer.address) {
+                problems.push(format!(
+                    "address '{}' of peer {} is not a valid ip:port address or socket path",
+                    peer.address, peer.id
+                ));
+            }
+        }
+
+        let election = &self.election;
+        for (name, value) in [
+            ("election.heartbeat_interval_secs", election.heartbeat_interval_secs),
+            ("election.election_timeout_secs", election.election_timeout_secs),
+            ("election.failure_timeout_secs", election.failure_timeout_secs),
+            ("election.monitor_interval_secs", election.monitor_interval_secs),
+            ("election.max_concurrent_tasks", election.max_concurrent_tasks),
+            ("server.client_rate_window_secs", self.server.client_rate_window_secs),
+            ("server.max_concurrent_tasks", self.server.max_concurrent_tasks as u64),
+            ("server.task_timeout_secs", self.server.task_timeout_secs),
+            ("server.peer_channel_capacity", self.server.peer_channel_capacity as u64),
+            ("server.max_message_size", self.server.max_message_size as u64),
+        ] {
+            if value == 0 {
+                problems.push(format!("{} must be greater than 0", name));
+            }
+        }
+
+        if let Err(e) = election.priority_weights.validate() {
+            problems.push(e.to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!(
+                "Invalid server configuration:\n  - {}",
+                problems.join("\n  - ")
+            ))
+        }
+    }
 }
 
 // ============================================================================
@@ -138,10 +321,264 @@ struct TaskHistoryEntry {
     _timestamp: u64,
 }
 
+/// Wire format of a task history entry: (client_name, request_id, assigned_server_id, timestamp).
+type HistoryEntryTuple = (String, u64, u32, u64);
+
+// ============================================================================
+// TASK ASSIGNMENT - Choosing a server for each task
+// ============================================================================
+
+/// A server the leader could assign a task to, as the leader currently sees it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    id: u32,
+    /// Last reported load (live for the leader itself) plus what its pending tasks add
+    load: f64,
+    /// Tasks assigned to it that haven't been acknowledged yet
+    pending: u64,
+    /// Whether all of its task slots were in use when it last reported
+    saturated: bool,
+}
+
+impl AssignmentStrategy {
+    /// Choose one of `candidates`, preferring unsaturated ones.
+    ///
+    /// # Arguments
+    /// - `candidates`: Every server that can take tasks, in ID order; never empty
+    /// - `round`: How many servers were chosen before this one (for `RoundRobin`)
+    fn choose(self, candidates: &[Candidate], round: u64) -> Candidate {
+        let unsaturated: Vec<Candidate> = candidates
+            .iter()
+            .filter(|candidate| !candidate.saturated)
+            .copied()
+            .collect();
+        let pool = if unsaturated.is_empty() {
+            candidates
+        } else {
+            &unsaturated
+        };
+
+        let by_load = |a: &&Candidate, b: &&Candidate| {
+            a.load
+                .partial_cmp(&b.load)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        };
+        match self {
+            AssignmentStrategy::LowestLoad => *pool.iter().min_by(by_load).expect("no candidates"),
+            AssignmentStrategy::LeastConnections => *pool
+                .iter()
+                .min_by(|a, b| a.pending.cmp(&b.pending).then_with(|| by_load(a, b)))
+                .expect("no candidates"),
+            AssignmentStrategy::RoundRobin => pool[(round % pool.len() as u64) as usize],
+            AssignmentStrategy::Random => pool[rand::thread_rng().gen_range(0..pool.len())],
+        }
+    }
+}
+
+/// Whether `address` is an `ip:port` or a Unix socket path (see [`Listener::bind`]).
+fn is_valid_address(address: &str) -> bool {
+    is_unix_address(address) || address.parse::<SocketAddr>().is_ok()
+}
+
+/// Number of tasks in `history` assigned to each server.
+fn pending_counts(history: &HashMap<(String, u64), TaskHistoryEntry>) -> HashMap<u32, u64> {
+    let mut counts = HashMap::new();
+    for entry in history.values() {
+        *counts.entry(entry.assigned_server_id).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Take one task off `server_id`'s pending count, dropping the server at zero.
+fn release_pending(pending: &mut HashMap<u32, u64>, server_id: u32) {
+    if let Some(count) = pending.get_mut(&server_id) {
+        *count -= 1;
+        if *count == 0 {
+            pending.remove(&server_id);
+        }
+    }
+}
+
+/// Leader's routed tasks awaiting a `ForwardedTaskResult`: (client_name, request_id) -> reply slot.
+type PendingForwards = HashMap<(String, u64), oneshot::Sender<Message>>;
+
+/// Read/write timeout for outgoing peer connections, so a half-open peer is
+/// dropped and reconnected instead of blocking its sender task forever. Writes
+/// that still fit in the socket buffer don't block, so the heartbeat monitor also
+/// drops the link to a peer it stops hearing from (see [`ServerMiddleware::drop_peer_link`]).
+const PEER_IO_TIMEOUT_SECS: u64 = 5;
+
+/// How long an outgoing peer link may go without a message before it is pinged,
+/// so a peer that died while the link was idle is noticed and reconnected. The
+/// ping then has [`PEER_IO_TIMEOUT_SECS`] to be answered.
+const PEER_KEEPALIVE_SECS: u64 = 5;
+
+/// How long a completed task stays in history waiting for the client's `TaskAck`
+/// before it is removed anyway, so lost ACKs don't leak history entries.
+const ACK_TIMEOUT_SECS: u64 = 60;
+
+/// How long a removed history entry is remembered, so a `HistoryAdd` for it
+/// delivered after its `HistoryRemove` is recognised as stale and ignored.
+const HISTORY_TOMBSTONE_TTL_SECS: u64 = 300;
+
+/// Most completed responses kept for answering duplicate `TaskRequest`s. Each holds
+/// a full carrier image, so this stays small.
+const RESULT_CACHE_CAPACITY: usize = 16;
+
+/// How long a completed response is kept for answering duplicate `TaskRequest`s.
+const RESULT_CACHE_TTL_SECS: u64 = 300;
+
+/// How long the leader waits for the server it forwarded a routed task to. Kept
+/// below the client's response timeout so the client hears back either way.
+const FORWARD_TIMEOUT_SECS: u64 = 25;
+
+/// Delay before the first peer reconnect attempt; doubles on each failure.
+const RECONNECT_BASE_DELAY_MS: u64 = 250;
+
+/// How long a shutting-down server waits for queued peer messages (like
+/// `Resign`) to be written before exiting.
+const SHUTDOWN_FLUSH_MS: u64 = 250;
+
+/// Upper bound on the peer reconnect delay.
+const RECONNECT_MAX_DELAY_MS: u64 = 8_000;
+
+/// For how long after the leader we follow changes a `TaskRequest` is accepted
+/// whichever leader it claims, since clients and servers learn of a new leader at
+/// slightly different times.
+const LEADER_CHANGE_GRACE_SECS: u64 = 5;
+
+/// Backoff before peer reconnect attempt `attempt` (0-based): 250ms doubling up to 8s.
+fn next_backoff(attempt: u32) -> Duration {
+    let delay = RECONNECT_BASE_DELAY_MS.saturating_mul(1u64 << attempt.min(32));
+    Duration::from_millis(delay.min(RECONNECT_MAX_DELAY_MS))
+}
+
+/// Whether a server with `priority` and `id` beats another in an election.
+///
+/// Lower priority scores win; equal scores (e.g. two idle servers at 0.0) are
+/// broken by server ID, lower ID winning, so exactly one leader emerges. This is
+/// the only place candidates are compared: don't compare scores directly.
+fn outranks(priority: f64, id: u32, other_priority: f64, other_id: u32) -> bool {
+    priority < other_priority || (priority == other_priority && id < other_id)
+}
+
+/// Randomize a backoff to between half and all of it, so servers reconnecting to
+/// a restarted peer don't all retry in lockstep.
+fn with_jitter(delay: Duration) -> Duration {
+    let millis = delay.as_millis() as u64;
+    Duration::from_millis(rand::thread_rng().gen_range(millis / 2..=millis))
+}
+
+/// What happens to a message for a peer whose send queue is full.
+///
+/// - `Heartbeat`s are best effort: the next one, a second later, carries fresher
+///   data anyway, so a full queue drops them rather than stall the heartbeat task.
+/// - Everything else (election, coordinator, history and task messages) is
+///   reliable: the sender waits for room, since losing one can leave the cluster
+///   leaderless or with diverging history. The peer link's write timeout bounds
+///   that wait; a stuck peer is disconnected and its queue dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PeerDelivery {
+    BestEffort,
+    Reliable,
+}
+
+impl PeerDelivery {
+    fn of(message: &Message) -> Self {
+        match message {
+            Message::Heartbeat { .. } => PeerDelivery::BestEffort,
+            _ => PeerDelivery::Reliable,
+        }
+    }
+}
+
+/// A successful `TaskResponse`, kept so a resubmitted task isn't encrypted twice.
+#[derive(Debug, Clone)]
+struct CachedResult {
+    response: Message,
+    completed_at: Instant,
+}
+
+/// An encryption task in progress: it counts as active and holds a task slot until
+/// dropped, even if its task is aborted rather than finishing.
+struct RunningTask {
+    metrics: ServerMetrics,
+    _slot: OwnedSemaphorePermit,
+}
+
+impl RunningTask {
+    fn start(metrics: &ServerMetrics, slot: OwnedSemaphorePermit) -> Self {
+        metrics.task_started();
+        Self {
+            metrics: metrics.clone(),
+            _slot: slot,
+        }
+    }
+}
+
+impl Drop for RunningTask {
+    fn drop(&mut self) {
+        self.metrics.task_finished();
+    }
+}
+
+/// Randomize a heartbeat interval by up to `jitter` (a fraction, clamped to
+/// 0.0..=0.5) in either direction, so servers started together don't keep
+/// broadcasting at the same instants.
+fn jittered_interval(interval: Duration, jitter: f64) -> Duration {
+    let jitter = if jitter.is_finite() {
+        jitter.clamp(0.0, 0.5)
+    } else {
+        0.0
+    };
+    let factor = 1.0 + rand::thread_rng().gen_range(-jitter..=jitter);
+    interval.mul_f64(factor)
+}
+
+/// The server that backs up `server_id`'s results: the next server by ID among
+/// `server_ids`, wrapping around to the lowest. `None` if there is no other server.
+fn next_server_by_id(server_id: u32, server_ids: impl IntoIterator<Item = u32>) -> Option<u32> {
+    let others: Vec<u32> = server_ids
+        .into_iter()
+        .filter(|id| *id != server_id)
+        .collect();
+    others
+        .iter()
+        .filter(|id| **id > server_id)
+        .min()
+        .or_else(|| others.iter().min())
+        .copied()
+}
+
 // ============================================================================
 // SERVER MIDDLEWARE - Main coordination component
 // ============================================================================
 
+/// Election progress on one server, for tests and dashboards that would otherwise
+/// have to parse logs.
+///
+/// Sent on the channel given to [`ServerMiddleware::with_election_events`]:
+/// - `ElectionStarted`: from `initiate_election`, once our priority is computed
+/// - `ReceivedAlive`: from `handle_message` on an `Alive` answer to our election
+/// - `WonElection` / `LostElection`: from `initiate_election` once the election
+///   timeout expires (an election superseded by a newer term reports neither)
+/// - `LeaderChanged`: whenever the leader we follow changes, i.e. on winning, on
+///   a `Coordinator` or `Resign`, when the leader fails, when a heartbeat reveals a
+///   newer leader, and when we resign on shutdown
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElectionEvent {
+    /// We started an election for `term`, announcing `priority`
+    ElectionStarted { term: u64, priority: f64 },
+    /// A peer that outranks us answered our election
+    ReceivedAlive { from_id: u32 },
+    /// Nobody outranked us, so we became leader for `term`
+    WonElection { term: u64 },
+    /// A peer outranked us in the election for `term`
+    LostElection { term: u64 },
+    /// The leader we follow changed (`None` while there is no leader)
+    LeaderChanged { leader_id: Option<u32> },
+}
+
 /// Server middleware that handles all distributed system coordination.
 ///
 /// This struct manages:
@@ -183,6 +620,9 @@ pub struct ServerMiddleware {
     /// Flag indicating if we received ALIVE response during election
     received_alive: Arc<RwLock<bool>>,
 
+    /// Newest election term seen; messages from older terms are ignored
+    current_term: Arc<RwLock<u64>>,
+
     /// Peer connections: peer_id -> channel to send messages to that peer
     /// We use channels so we can send messages from anywhere in the code
     peer_connections: Arc<RwLock<HashMap<u32, mpsc::Sender<Message>>>>,
@@ -190,23 +630,94 @@ pub struct ServerMiddleware {
     /// Last time we heard from each peer (used to detect failures)
     last_heartbeat_times: Arc<RwLock<HashMap<u32, u64>>>,
 
-    /// Active task handles for cancellation if needed
+    /// Heartbeat inter-arrival history per peer, for phi-accrual failure detection
+    heartbeat_detectors: Arc<RwLock<HashMap<u32, PhiAccrualDetector>>>,
+
+    /// Peers already reported as failed, so they aren't reported again until they
+    /// send a heartbeat
+    failed_peers: Arc<RwLock<HashSet<u32>>>,
+
+    /// When this server started (Unix seconds); configured peers not heard from
+    /// within `failure_timeout_secs` of this are considered failed
+    started_at: u64,
+
+    /// Handles of running encryption tasks by request ID; each task removes its own
+    /// entry when it finishes, and [`cancel_task`](Self::cancel_task) aborts one
     active_tasks: Arc<RwLock<HashMap<u64, tokio::task::JoinHandle<()>>>>,
 
     /// Current load values for each peer (reported via heartbeats)
     peer_loads: Arc<RwLock<HashMap<u32, f64>>>,
 
+    /// Peers whose last heartbeat reported all task slots in use
+    saturated_peers: Arc<RwLock<HashSet<u32>>>,
+
+    /// Tasks each peer has started since it came up (reported via heartbeats)
+    peer_total_tasks: Arc<RwLock<HashMap<u32, u64>>>,
+
+    /// Leader and term each peer's last heartbeat reported, if it follows one
+    peer_leaders: Arc<RwLock<HashMap<u32, (u32, u64)>>>,
+
+    /// One permit per concurrently running encryption task
+    task_slots: Arc<Semaphore>,
+
+    /// Recently completed responses: (client_name, request_id) -> result
+    completed_results: Arc<RwLock<HashMap<(String, u64), CachedResult>>>,
+
     /// Task history for fault tolerance: (client_name, request_id) -> entry
     task_history: Arc<RwLock<HashMap<(String, u64), TaskHistoryEntry>>>,
 
+    /// Recently removed history entries: (client_name, request_id) -> removal
+    /// timestamp. A `HistoryAdd` no newer than its removal is a late delivery
+    /// and is ignored
+    removed_history: Arc<RwLock<HashMap<(String, u64), u64>>>,
+
+    /// Latest history timestamp we've stamped or seen (Unix millis). Our own
+    /// stamps come after it, so a task re-added right after its removal still
+    /// orders after the removal
+    history_clock: Arc<AtomicU64>,
+
+    /// Tasks in `task_history` per assigned server: assigned but not yet acknowledged.
+    /// Heartbeats report a server's load only every `heartbeat_interval_secs`, so the
+    /// leader adds these on top when choosing where the next task goes
+    pending_assignments: Arc<RwLock<HashMap<u32, u64>>>,
+
     /// Channel for receiving history sync responses during leader election
-    history_sync_responses: Arc<RwLock<Vec<Vec<(String, u64, u32, u64)>>>>,
+    history_sync_responses: Arc<RwLock<Vec<Vec<HistoryEntryTuple>>>>,
+
+    /// On-disk log of task history changes (None if persistence is disabled)
+    history_log: Option<Arc<HistoryLog>>,
+
+    /// Routed tasks forwarded by us as leader, waiting for their result
+    pending_forwards: Arc<RwLock<PendingForwards>>,
+
+    /// Arrival times of each client's recent task requests, for rate limiting
+    client_request_times: Arc<RwLock<HashMap<String, VecDeque<Instant>>>>,
+
+    /// Where to report election progress (None if nobody is listening)
+    election_events: Option<mpsc::Sender<ElectionEvent>>,
+
+    /// Set while playing dead after a `SimulateFail`: incoming messages are
+    /// dropped and no heartbeats are sent
+    is_failed: Arc<AtomicBool>,
+
+    /// Servers chosen for tasks so far, which `RoundRobin` assignment cycles on
+    assignment_rounds: Arc<AtomicU64>,
+
+    /// When the leader we follow last changed (Unix millis, 0 if never), stamped
+    /// with every `LeaderChanged` election event
+    leader_changed_at: Arc<AtomicU64>,
+
+    /// Server each client's tasks last went to (with `client_affinity` enabled)
+    client_affinity: Arc<RwLock<HashMap<String, u32>>>,
 }
 
 #[allow(dead_code)]
 impl ServerMiddleware {
     /// Create a new server middleware instance.
     ///
+    /// If `history_log_path` is configured, the task history is restored from that
+    /// file. A log that can't be opened is reported and persistence is disabled.
+    ///
     /// # Arguments
     /// - `config`: Server configuration (loaded from TOML)
     /// - `core`: The core encryption service (wrapped in Arc)
@@ -219,7 +730,43 @@ impl ServerMiddleware {
     /// ```
     pub fn new(config: ServerConfig, core: Arc<ServerCore>) -> Self {
         // Initialize metrics for this server
-        let metrics = ServerMetrics::new();
+        let metrics = ServerMetrics::new(config.election.priority_weights)
+            .with_max_concurrent_tasks(config.election.max_concurrent_tasks);
+
+        // Restore task history from disk, if persistence is configured
+        let mut task_history = HashMap::new();
+        let history_log = config.server.history_log_path.as_ref().and_then(|path| {
+            match HistoryLog::open(path) {
+                Ok((log, entries)) => {
+                    info!(
+                        "📂 Server {} restored {} task history entries from {}",
+                        config.server.id,
+                        entries.len(),
+                        path
+                    );
+                    for (client_name, request_id, assigned_server_id, timestamp) in entries {
+                        let entry = TaskHistoryEntry {
+                            _client_name: client_name.clone(),
+                            _request_id: request_id,
+                            assigned_server_id,
+                            _timestamp: timestamp,
+                        };
+                        task_history.insert((client_name, request_id), entry);
+                    }
+                    Some(Arc::new(log))
+                }
+                Err(e) => {
+                    error!(
+                        "❌ Server {} failed to open history log {}: {}, history will not be persisted",
+                        config.server.id, path, e
+                    );
+                    None
+                }
+            }
+        });
+
+        let task_slots = Arc::new(Semaphore::new(config.server.max_concurrent_tasks));
+        let pending_assignments = pending_counts(&task_history);
 
         Self {
             core,
@@ -227,42 +774,145 @@ impl ServerMiddleware {
             metrics,
             current_leader: Arc::new(RwLock::new(None)),
             received_alive: Arc::new(RwLock::new(false)),
+            current_term: Arc::new(RwLock::new(0)),
             peer_connections: Arc::new(RwLock::new(HashMap::new())),
             last_heartbeat_times: Arc::new(RwLock::new(HashMap::new())),
+            heartbeat_detectors: Arc::new(RwLock::new(HashMap::new())),
+            failed_peers: Arc::new(RwLock::new(HashSet::new())),
+            started_at: current_timestamp(),
             active_tasks: Arc::new(RwLock::new(HashMap::new())),
             peer_loads: Arc::new(RwLock::new(HashMap::new())),
-            task_history: Arc::new(RwLock::new(HashMap::new())),
+            saturated_peers: Arc::new(RwLock::new(HashSet::new())),
+            peer_total_tasks: Arc::new(RwLock::new(HashMap::new())),
+            peer_leaders: Arc::new(RwLock::new(HashMap::new())),
+            task_slots,
+            completed_results: Arc::new(RwLock::new(HashMap::new())),
+            task_history: Arc::new(RwLock::new(task_history)),
+            removed_history: Arc::new(RwLock::new(HashMap::new())),
+            history_clock: Arc::new(AtomicU64::new(0)),
+            pending_assignments: Arc::new(RwLock::new(pending_assignments)),
             history_sync_responses: Arc::new(RwLock::new(Vec::new())),
+            history_log,
+            pending_forwards: Arc::new(RwLock::new(HashMap::new())),
+            client_request_times: Arc::new(RwLock::new(HashMap::new())),
+            election_events: None,
+            is_failed: Arc::new(AtomicBool::new(false)),
+            assignment_rounds: Arc::new(AtomicU64::new(0)),
+            leader_changed_at: Arc::new(AtomicU64::new(0)),
+            client_affinity: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
-    /// Main entry point - starts all server tasks and runs forever.
+    /// Report election progress as [`ElectionEvent`]s on `events`.
+    ///
+    /// Events are dropped (with a debug log) if the channel is full, so a slow
+    /// listener never holds up an election.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let (tx, mut rx) = mpsc::channel(64);
+    /// let middleware = ServerMiddleware::new(config, core).with_election_events(tx);
+    /// ```
+    pub fn with_election_events(mut self, events: mpsc::Sender<ElectionEvent>) -> Self {
+        self.election_events = Some(events);
+        self
+    }
+
+    /// The leader this server currently follows (`None` while it knows of none).
+    ///
+    /// # Example
+    /// ```ignore
+    /// if let Some(leader_id) = middleware.current_leader().await {
+    ///     println!("Following Server {}", leader_id);
+    /// }
+    /// ```
+    pub async fn current_leader(&self) -> Option<u32> {
+        *self.current_leader.read().await
+    }
+
+    /// Whether this server currently believes it is the leader.
+    ///
+    /// # Example
+    /// ```ignore
+    /// assert!(middleware.is_leader().await);
+    /// ```
+    pub async fn is_leader(&self) -> bool {
+        self.current_leader().await == Some(self.config.server.id)
+    }
+
+    /// Main entry point - starts all server tasks and runs until Ctrl-C.
+    ///
+    /// Equivalent to [`run_until`](Self::run_until) with Ctrl-C (SIGINT) as the
+    /// shutdown signal.
+    pub async fn run(&self) {
+        let ctrl_c = async {
+            match tokio::signal::ctrl_c().await {
+                Ok(()) => info!(
+                    "🛑 Server {} received Ctrl-C, shutting down...",
+                    self.config.server.id
+                ),
+                Err(e) => {
+                    // Without a signal handler, keep serving rather than exit
+                    error!("❌ Failed to listen for Ctrl-C: {}", e);
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        self.run_until(ctrl_c).await;
+    }
+
+    /// Start all server tasks and run until `shutdown` completes.
     ///
     /// This method:
-    /// 1. Starts initial election timer (3 seconds + random delay)
+    /// 1. Asks the peers for the cluster state (see [`recover_state`](Self::recover_state))
+    ///    and starts the initial election timer (`initial_election_delay_ms` + random
+    ///    delay), whose election is skipped if a leader is known by then
     /// 2. Launches listener for incoming connections
     /// 3. Connects to peer servers
     /// 4. Starts heartbeat broadcasting
     /// 5. Starts heartbeat monitoring
     ///
-    /// All tasks run concurrently and indefinitely.
-    pub async fn run(&self) {
+    /// All tasks run concurrently until `shutdown` completes, which triggers a
+    /// [graceful shutdown](Self::shutdown). The listener, its connections and the
+    /// peer links are all closed once this returns, or if this future is dropped.
+    ///
+    /// # Arguments
+    /// - `shutdown`: Future that resolves when the server should stop
+    ///
+    /// # Example
+    /// ```ignore
+    /// let (stop_tx, stop_rx) = tokio::sync::oneshot::channel::<()>();
+    /// tokio::spawn(async move { middleware.run_until(async { let _ = stop_rx.await; }).await });
+    /// ```
+    pub async fn run_until(&self, shutdown: impl Future<Output = ()>) {
         info!(
             "🚀 Server {} starting on {}",
             self.config.server.id, self.config.server.address
         );
 
-        // After 3 seconds + random delay, start an election
+        // After the configured delay + random delay, start an election
         // Random delay prevents all servers from starting election simultaneously
         let server_clone = self.clone_arc();
-        let mut rng = rand::thread_rng();
-        let random_delay = rng.gen_range(100..500); // 100-500ms random delay
-        tokio::spawn(async move {
-            tokio::time::sleep(Duration::from_secs(3) + Duration::from_millis(random_delay)).await;
+        let random_delay = rand::thread_rng().gen_range(100..500); // 100-500ms random delay
+        let initial_delay = self.config.election.initial_election_delay_ms + random_delay;
+        let mut startup = JoinSet::new();
+        startup.spawn(async move {
+            tokio::time::sleep(Duration::from_millis(initial_delay)).await;
+            if let Some(leader_id) = *server_clone.current_leader.read().await {
+                info!(
+                    "⏰ Initial election timer expired, already following leader {}",
+                    leader_id
+                );
+                return;
+            }
             info!("⏰ Initial election timer expired, starting election...");
             server_clone.initiate_election().await;
         });
 
+        // Catch up with a running cluster instead of waiting out the timer
+        let server_clone = self.clone_arc();
+        startup.spawn(async move { server_clone.recover_state().await });
+
         // Start all long-running tasks
         let listener_task = self.start_listener();
         let peer_task = self.connect_to_peers();
@@ -275,26 +925,72 @@ impl ServerMiddleware {
             _ = peer_task => error!("❌ Peer connection task terminated"),
             _ = heartbeat_task => error!("❌ Heartbeat task terminated"),
             _ = monitor_task => error!("❌ Monitor task terminated"),
+            _ = shutdown => self.shutdown().await,
         }
     }
 
+    /// Leave the cluster cleanly.
+    ///
+    /// 1. If we are the leader, broadcast `Resign` so peers re-elect immediately
+    ///    instead of waiting `failure_timeout_secs` for our heartbeats to stop
+    /// 2. Wait for in-flight encryption tasks to finish and send their responses
+    /// 3. Give the peer connections a moment to flush queued messages
+    ///
+    /// New connections are no longer accepted once [`run_until`](Self::run_until)
+    /// stops its listener.
+    pub async fn shutdown(&self) {
+        let am_i_leader = *self.current_leader.read().await == Some(self.config.server.id);
+        if am_i_leader {
+            info!(
+                "👋 Server {} resigning as leader before shutdown",
+                self.config.server.id
+            );
+            *self.current_leader.write().await = None;
+            self.emit_election_event(ElectionEvent::LeaderChanged { leader_id: None });
+            self.broadcast(Message::Resign {
+                leader_id: self.config.server.id,
+            })
+            .await;
+        }
+
+        // Drain in-flight tasks
+        let handles: Vec<_> = self.active_tasks.write().await.drain().collect();
+        let pending = handles
+            .iter()
+            .filter(|(_, handle)| !handle.is_finished())
+            .count();
+        if pending > 0 {
+            info!(
+                "⏳ Server {} waiting for {} in-flight task(s) to finish...",
+                self.config.server.id, pending
+            );
+        }
+        for (request_id, handle) in handles {
+            if let Err(e) = handle.await {
+                error!("❌ Task #{} failed during shutdown: {}", request_id, e);
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(SHUTDOWN_FLUSH_MS)).await;
+        info!("✅ Server {} shut down cleanly", self.config.server.id);
+    }
+
     // ========================================================================
     // TASK 1: Listen for incoming connections from peers and clients
     // ========================================================================
 
-    /// Start listening for incoming TCP connections.
+    /// Start listening for incoming TCP (or Unix socket) connections.
     ///
     /// For each incoming connection:
     /// 1. Accept the connection
     /// 2. Spawn a new task to handle messages from that connection
     /// 3. Continue listening for more connections
     ///
-    /// This runs forever in a loop.
+    /// This runs forever in a loop. Dropping it closes the listener and every
+    /// connection it accepted.
     async fn start_listener(&self) {
-        use tokio::net::TcpListener;
-
         // Bind to our configured address
-        let listener = match TcpListener::bind(&self.config.server.address).await {
+        let listener = match Listener::bind(&self.config.server.address).await {
             Ok(l) => l,
             Err(e) => {
                 error!("❌ Failed to bind to {}: {}", self.config.server.address, e);
@@ -307,8 +1003,14 @@ impl ServerMiddleware {
             self.config.server.id, self.config.server.address
         );
 
+        // Connection handlers, aborted together when this task is dropped
+        let mut connections = JoinSet::new();
+
         // Accept connections in a loop
         loop {
+            // Reap handlers whose connection has closed
+            while connections.try_join_next().is_some() {}
+
             match listener.accept().await {
                 Ok((socket, addr)) => {
                     debug!(
@@ -318,7 +1020,7 @@ impl ServerMiddleware {
 
                     // Spawn a new task to handle this connection
                     let server = self.clone_arc();
-                    tokio::spawn(async move {
+                    connections.spawn(async move {
                         server.handle_connection(socket).await;
                     });
                 }
@@ -327,23 +1029,39 @@ impl ServerMiddleware {
         }
     }
 
-    /// Handle a single TCP connection - read and process messages in a loop.
+    /// Handle a single connection - read and process messages in a loop.
     ///
     /// # Arguments
-    /// - `socket`: The TCP stream for this connection
+    /// - `socket`: The TCP or Unix stream for this connection
     ///
     /// This method:
     /// 1. Wraps the socket in a Connection
-    /// 2. Reads messages in a loop
-    /// 3. Handles special cases (LeaderQuery)
-    /// 4. Delegates to handle_message for normal messages
-    /// 5. Closes connection when done
-    async fn handle_connection(&self, socket: tokio::net::TcpStream) {
-        let mut conn = Connection::new(socket);
+    /// 2. Validates the peer's protocol version (Hello handshake)
+    /// 3. Reads messages in a loop
+    /// 4. Handles special cases (LeaderQuery)
+    /// 5. Delegates to handle_message for normal messages
+    /// 6. Closes connection when done
+    async fn handle_connection(&self, socket: Stream) {
+        let mut conn =
+            Connection::new(socket).with_max_message_size(self.config.server.max_message_size);
+
+        // Refuse incompatible peers up front instead of mis-parsing their messages
+        if let Err(e) = conn.accept_handshake().await {
+            warn!(
+                "⚠️  Server {} refused connection: {}",
+                self.config.server.id, e
+            );
+            return;
+        }
 
         loop {
             match conn.read_message().await {
                 Ok(Some(message)) => {
+                    // Playing dead after a SimulateFail: drop everything unanswered
+                    if self.is_failed.load(Ordering::SeqCst) {
+                        continue;
+                    }
+
                     // Special case: LeaderQuery requires immediate response
                     if matches!(message, Message::LeaderQuery) {
                         let leader = *self.current_leader.read().await;
@@ -361,6 +1079,18 @@ impl ServerMiddleware {
                     debug!("🔌 Connection closed");
                     break;
                 }
+                // One undecodable frame shouldn't take down an otherwise healthy link
+                Err(e)
+                    if matches!(
+                        e.downcast_ref::<ConnectionError>(),
+                        Some(ConnectionError::MalformedMessage(_))
+                    ) =>
+                {
+                    warn!(
+                        "⚠️  Server {} skipping message: {}",
+                        self.config.server.id, e
+                    );
+                }
                 Err(e) => {
                     error!("❌ Error reading message: {}", e);
                     break;
@@ -376,17 +1106,22 @@ impl ServerMiddleware {
     /// Connect to all peer servers and maintain connections.
     ///
     /// For each peer:
-    /// 1. Try to establish TCP connection
+    /// 1. Try to establish TCP connection (right away: a peer that isn't up yet is
+    ///    retried within the backoff, well before the initial election)
     /// 2. Create a channel for sending messages
     /// 3. Spawn task that reads from channel and sends to peer
-    /// 4. Reconnect if connection is lost
+    /// 4. Reconnect if connection is lost, with jittered exponential backoff
+    ///    (see [`next_backoff`]) that resets after each successful connection
+    ///
+    /// A link counts as lost when a write fails or times out, when a keepalive ping
+    /// sent after [`PEER_KEEPALIVE_SECS`] of silence goes unanswered, or when its
+    /// sender is removed from `peer_connections` by [`Self::drop_peer_link`].
     ///
-    /// This runs forever, maintaining connections to all peers.
+    /// This runs forever, maintaining connections to all peers. Dropping it
+    /// closes them.
     async fn connect_to_peers(&self) {
-        use tokio::net::TcpStream;
-
-        // Wait a bit for servers to start
-        tokio::time::sleep(Duration::from_secs(1)).await;
+        // Per-peer link tasks, aborted together when this task is dropped
+        let mut links = JoinSet::new();
 
         // Try to connect to each peer
         for peer in &self.config.peers.peers {
@@ -395,23 +1130,64 @@ impl ServerMiddleware {
             let server = self.clone_arc();
 
             // Spawn a task that keeps trying to connect to this peer
-            tokio::spawn(async move {
+            links.spawn(async move {
+                // Consecutive failed attempts since the last successful connection
+                let mut attempt: u32 = 0;
+
                 loop {
-                    match TcpStream::connect(&peer_addr).await {
+                    match Stream::connect(&peer_addr).await {
                         Ok(stream) => {
+                            // Bound writes so a half-open peer can't stall this sender forever
+                            let mut conn = Connection::with_timeouts(
+                                stream,
+                                Duration::from_secs(PEER_IO_TIMEOUT_SECS),
+                                Duration::from_secs(PEER_IO_TIMEOUT_SECS),
+                            )
+                            .with_max_message_size(server.config.server.max_message_size);
+                            if let Err(e) = conn.handshake().await {
+                                warn!(
+                                    "⚠️  Server {} refused peer {}: {}",
+                                    server.config.server.id, peer_id, e
+                                );
+                                tokio::time::sleep(with_jitter(next_backoff(attempt))).await;
+                                attempt = attempt.saturating_add(1);
+                                continue;
+                            }
+
+                            // Connected: the next disconnect starts backing off from scratch
+                            attempt = 0;
+
                             info!(
                                 "🤝 Server {} connected to peer {}",
                                 server.config.server.id, peer_id
                             );
 
                             // Create a channel for sending messages to this peer
-                            let (tx, mut rx) = mpsc::channel::<Message>(100);
+                            let (tx, mut rx) = mpsc::channel::<Message>(
+                                server.config.server.peer_channel_capacity,
+                            );
                             server.peer_connections.write().await.insert(peer_id, tx);
 
-                            let mut conn = Connection::new(stream);
-
-                            // Read from the channel and send messages to the peer
-                            while let Some(msg) = rx.recv().await {
+                            // Read from the channel and send messages to the peer, until a
+                            // write or ping fails or the sender is dropped by drop_peer_link
+                            let keepalive = Duration::from_secs(PEER_KEEPALIVE_SECS);
+                            loop {
+                                let next = tokio::time::timeout(keepalive, rx.recv()).await;
+                                let msg = match next {
+                                    Ok(Some(msg)) => msg,
+                                    Ok(None) => break,
+                                    Err(_) => {
+                                        let timeout = Duration::from_secs(PEER_IO_TIMEOUT_SECS);
+                                        if let Err(e) = conn.ping(timeout).await {
+                                            warn!(
+                                                "⚠️  Peer {} missed keepalive ping: {}",
+                                                peer_id, e
+                                            );
+                                            break;
+                                        }
+                                        continue;
+                                    }
+                                };
                                 if let Err(e) = conn.write_message(&msg).await {
                                     error!("❌ Error sending to peer {}: {}", peer_id, e);
                                     break;
@@ -430,8 +1206,9 @@ impl ServerMiddleware {
                         }
                     }
 
-                    // Wait before retrying
-                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    // Wait before retrying, longer after each consecutive failure
+                    tokio::time::sleep(with_jitter(next_backoff(attempt))).await;
+                    attempt = attempt.saturating_add(1);
                 }
             });
         }
@@ -460,23 +1237,59 @@ impl ServerMiddleware {
     /// - **TaskAssignmentRequest**: Assign task to best server (leader only)
     /// - **HistoryAdd**: Add task to history
     /// - **HistoryRemove**: Remove completed task from history
-    async fn handle_message(&self, message: Message, conn: &mut Connection) {
+    async fn handle_message(&self, message: Message, conn: &mut Connection<Stream>) {
         match message {
             // Someone started an election
-            Message::Election { from_id, priority } => {
+            Message::Election {
+                from_id,
+                priority,
+                term,
+            } => {
                 info!(
-                    "🗳️  Server {} received ELECTION from {} (priority: {:.2})",
-                    self.config.server.id, from_id, priority
+                    "🗳️  Server {} received ELECTION from {} (priority: {:.2}, term: {})",
+                    self.config.server.id, from_id, priority, term
                 );
 
+                // Ignore elections from a past term, but tell the sender who won since
+                // so it catches up instead of declaring itself leader. The term lock is
+                // released first, as sending can wait on a full peer queue
+                let stale_since = {
+                    let mut current_term = self.current_term.write().await;
+                    if term < *current_term {
+                        Some(*current_term)
+                    } else {
+                        *current_term = term;
+                        None
+                    }
+                };
+                if let Some(current_term) = stale_since {
+                    warn!(
+                        "⏪ Server {} ignoring stale ELECTION from {} (term {} < {})",
+                        self.config.server.id, from_id, term, current_term
+                    );
+                    let leader_id = *self.current_leader.read().await;
+                    if let Some(leader_id) = leader_id {
+                        self.send_to_peer(
+                            from_id,
+                            Message::Coordinator {
+                                leader_id,
+                                term: current_term,
+                            },
+                        )
+                        .await;
+                    }
+                    return;
+                }
+
                 // Calculate our priority
                 let my_priority = self.metrics.calculate_priority();
 
-                // If we have higher priority (lower score), respond and start our own election
-                if my_priority < priority {
+                // If we have higher priority (lower score, or equal score and lower ID),
+                // respond and start our own election
+                if outranks(my_priority, self.config.server.id, priority, from_id) {
                     info!(
-                        "💪 Server {} has lower priority ({:.2} < {:.2}), responding with ALIVE",
-                        self.config.server.id, my_priority, priority
+                        "💪 Server {} outranks {} ({:.2} vs {:.2}), responding with ALIVE",
+                        self.config.server.id, from_id, my_priority, priority
                     );
 
                     // Send ALIVE message to the sender
@@ -485,16 +1298,17 @@ impl ServerMiddleware {
                     };
                     self.send_to_peer(from_id, alive_msg).await;
 
-                    // Add small delay before starting own election
+                    // Add small delay before starting own election. One is already
+                    // under way, so there's no leader left to pre-vote on
                     let server = self.clone_arc();
                     tokio::spawn(async move {
                         tokio::time::sleep(Duration::from_millis(100)).await;
-                        server.initiate_election().await;
+                        server.run_election().await;
                     });
                 } else {
                     info!(
-                        "📊 Server {} has higher priority ({:.2} > {:.2}), deferring",
-                        self.config.server.id, my_priority, priority
+                        "📊 Server {} is outranked by {} ({:.2} vs {:.2}), deferring",
+                        self.config.server.id, from_id, my_priority, priority
                     );
                 }
             }
@@ -507,35 +1321,129 @@ impl ServerMiddleware {
                 );
                 // We lost the election
                 *self.received_alive.write().await = true;
+                self.emit_election_event(ElectionEvent::ReceivedAlive { from_id });
             }
 
             // Someone won the election and is announcing themselves as leader
-            Message::Coordinator { leader_id } => {
+            Message::Coordinator { leader_id, term } => {
+                {
+                    let mut current_term = self.current_term.write().await;
+                    if term < *current_term {
+                        warn!(
+                            "⏪ Server {} ignoring stale COORDINATOR for {} (term {} < {})",
+                            self.config.server.id, leader_id, term, *current_term
+                        );
+                        return;
+                    }
+                    *current_term = term;
+                }
+
                 info!(
-                    "👑 Server {} acknowledges {} as LEADER",
-                    self.config.server.id, leader_id
+                    "👑 Server {} acknowledges {} as LEADER (term {})",
+                    self.config.server.id, leader_id, term
                 );
-                *self.current_leader.write().await = Some(leader_id);
+                let previous = self.current_leader.write().await.replace(leader_id);
+                if previous != Some(leader_id) {
+                    self.emit_election_event(ElectionEvent::LeaderChanged {
+                        leader_id: Some(leader_id),
+                    });
+                }
             }
 
-            // Received a heartbeat from a peer
-            Message::Heartbeat {
-                from_id,
-                timestamp,
-                load,
-            } => {
-                // Update the last time we heard from this peer
-                self.last_heartbeat_times
+            // The leader is shutting down and handing off leadership
+            Message::Resign { leader_id } => {
+                self.peer_loads.write().await.remove(&leader_id);
+                self.saturated_peers.write().await.remove(&leader_id);
+
+                {
+                    let mut current_leader = self.current_leader.write().await;
+                    if *current_leader != Some(leader_id) {
+                        debug!(
+                            "Server {} ignoring RESIGN from {} (not our leader)",
+                            self.config.server.id, leader_id
+                        );
+                        return;
+                    }
+                    *current_leader = None;
+                }
+                self.emit_election_event(ElectionEvent::LeaderChanged { leader_id: None });
+
+                info!(
+                    "👋 Server {} received RESIGN from leader {}, starting election",
+                    self.config.server.id, leader_id
+                );
+                let server = self.clone_arc();
+                tokio::spawn(async move {
+                    server.initiate_election().await;
+                });
+            }
+
+            // Received a heartbeat from a peer
+            Message::Heartbeat {
+                from_id,
+                timestamp,
+                load,
+                saturated,
+                total_tasks,
+                leader_id,
+                term,
+            } => {
+                // Update the last time we heard from this peer
+                self.last_heartbeat_times
                     .write()
                     .await
                     .insert(from_id, timestamp);
+                let expected_interval_ms =
+                    (self.config.election.heartbeat_interval_secs * 1000) as f64;
+                self.heartbeat_detectors
+                    .write()
+                    .await
+                    .entry(from_id)
+                    .or_insert_with(|| PhiAccrualDetector::new(expected_interval_ms))
+                    .heartbeat(current_timestamp_millis());
+                if self.failed_peers.write().await.remove(&from_id) {
+                    info!(
+                        "💚 Server {} hearing from previously failed peer {} again",
+                        self.config.server.id, from_id
+                    );
+                }
 
                 self.peer_loads.write().await.insert(from_id, load);
 
+                let mut saturated_peers = self.saturated_peers.write().await;
+                if saturated {
+                    saturated_peers.insert(from_id);
+                } else {
+                    saturated_peers.remove(&from_id);
+                }
+
+                drop(saturated_peers);
+
+                self.peer_total_tasks
+                    .write()
+                    .await
+                    .insert(from_id, total_tasks);
+
                 debug!(
-                    "💓 Server {} received heartbeat from {} (load: {:.2})",
-                    self.config.server.id, from_id, load
+                    "💓 Server {} received heartbeat from {} (load: {:.2}, saturated: {}, lifetime tasks: {})",
+                    self.config.server.id, from_id, load, saturated, total_tasks
                 );
+
+                {
+                    let mut peer_leaders = self.peer_leaders.write().await;
+                    match leader_id {
+                        Some(leader_id) => peer_leaders.insert(from_id, (leader_id, term)),
+                        None => peer_leaders.remove(&from_id),
+                    };
+                }
+                self.reconcile_leader(from_id, leader_id, term).await;
+            }
+
+            // Keepalive on an otherwise idle connection
+            Message::Ping { nonce } => {
+                if let Err(e) = conn.write_message(&Message::Pong { nonce }).await {
+                    debug!("🔌 Failed to answer ping: {}", e);
+                }
             }
 
             // Client asking who the leader is
@@ -548,29 +1456,84 @@ impl ServerMiddleware {
                 }
             }
 
+            // Client (or dashboard) asking for our current load
+            Message::LoadQuery => {
+                let snapshot = self.metrics.snapshot();
+                let response = Message::LoadResponse {
+                    server_id: self.config.server.id,
+                    load: snapshot.priority,
+                    cpu_usage: snapshot.cpu_usage,
+                    active_tasks: snapshot.active_tasks,
+                    total_tasks: snapshot.total_tasks,
+                    saturated: self.is_saturated(),
+                    leader_id: *self.current_leader.read().await,
+                };
+                if let Err(e) = conn.write_message(&response).await {
+                    error!("❌ Failed to send load response: {}", e);
+                }
+            }
+
             // Client sending a task
             Message::TaskRequest {
                 client_name,
                 request_id,
                 secret_image_data,
                 assigned_by_leader,
+                metadata,
             } => {
                 info!(
+                    request_id = request_id,
                     "📥 Server {} received task #{} from client '{}' (assigned by leader {})",
                     self.config.server.id, request_id, client_name, assigned_by_leader
                 );
 
+                // Only take tasks our leader assigned, so clients can't bypass load balancing
+                if let Some(leader_id) = self.rejects_assignment_from(assigned_by_leader).await {
+                    warn!(
+                        request_id = request_id,
+                        "🚫 Server {} rejecting task #{} from '{}': assigned by {} but leader is {}",
+                        self.config.server.id,
+                        request_id,
+                        client_name,
+                        assigned_by_leader,
+                        leader_id
+                    );
+                    let rejection = Message::TaskRejected {
+                        request_id,
+                        reason: format!(
+                            "Task was assigned by Server {}, but the leader is Server {}",
+                            assigned_by_leader, leader_id
+                        ),
+                    };
+                    if let Err(e) = conn.write_message(&rejection).await {
+                        error!("❌ Failed to send rejection: {}", e);
+                    }
+                    return;
+                }
+
                 // Create a channel for response
                 let (tx, mut rx) = mpsc::channel::<Message>(1);
 
                 // Process the task (delegates to core for encryption)
-                self.process_task(request_id, client_name.clone(), secret_image_data, Some(tx))
-                    .await;
+                self.process_task(
+                    request_id,
+                    client_name.clone(),
+                    secret_image_data,
+                    metadata,
+                    Some(tx),
+                    self.config.server.report_progress,
+                )
+                .await;
 
-                // Send response back to client
-                if let Some(response) = rx.recv().await {
+                // Send any progress, then the response, back to client
+                while let Some(response) = rx.recv().await {
+                    let is_progress = matches!(response, Message::TaskProgress { .. });
                     if let Err(e) = conn.write_message(&response).await {
                         error!("❌ Failed to send response to client: {}", e);
+                        break;
+                    }
+                    if !is_progress {
+                        break;
                     }
                 }
             }
@@ -585,133 +1548,141 @@ impl ServerMiddleware {
                 let am_i_leader = current_leader == Some(self.config.server.id);
 
                 if am_i_leader {
-                    // IDEMPOTENCY: Check if this task already exists in history
-                    let existing_assignment = self
-                        .task_history
-                        .read()
-                        .await
-                        .get(&(client_name.clone(), request_id))
-                        .map(|entry| entry.assigned_server_id);
-
-                    if let Some(assigned_server_id) = existing_assignment {
-                        // Task already assigned - return same assignment (idempotent retry)
-                        info!(
-                            "🔁 Task #{} from {} already assigned to Server {} (idempotent retry)",
-                            request_id, client_name, assigned_server_id
-                        );
-
-                        // Get the address of the assigned server
-                        let assigned_address = if assigned_server_id == self.config.server.id {
-                            self.config.server.address.clone()
-                        } else {
-                            self.config
-                                .peers
-                                .peers
-                                .iter()
-                                .find(|p| p.id == assigned_server_id)
-                                .map(|p| p.address.clone())
-                                .unwrap_or_default()
-                        };
-
-                        // Send response to client
-                        let response = Message::TaskAssignmentResponse {
+                    if let Some(retry_after) = self.rate_limit(&client_name, Instant::now()).await {
+                        let response = Message::RateLimited {
                             request_id,
-                            assigned_server_id,
-                            assigned_server_address: assigned_address,
+                            retry_after_ms: retry_after.as_millis() as u64,
                         };
-
                         if let Err(e) = conn.write_message(&response).await {
-                            error!("❌ Failed to send assignment response: {}", e);
+                            error!("❌ Failed to send rate limit response: {}", e);
                         }
-                        return; // Early return - don't create duplicate assignment
+                        return;
                     }
 
-                    // NEW TASK: Not in history, proceed with normal assignment
-                    // We're the leader! Let's find the best server
+                    let assigned_server_id = self.assign_task(client_name, request_id).await;
 
-                    // Get our own load
-                    let my_load = self.metrics.get_load();
+                    // Send response to client
+                    let response = Message::TaskAssignmentResponse {
+                        request_id,
+                        assigned_server_id,
+                        assigned_server_address: self.server_address(assigned_server_id),
+                    };
 
-                    // Get all peer loads (from heartbeats)
-                    let peer_loads = self.peer_loads.read().await;
+                    if let Err(e) = conn.write_message(&response).await {
+                        error!("❌ Failed to send assignment response: {}", e);
+                    }
+                } else {
+                    warn!("⚠️  Non-leader received assignment request, ignoring");
+                }
+            }
 
-                    // Log current state
-                    info!("📊 LOAD DISTRIBUTION:");
-                    info!(
-                        "   Server {} (me, leader): {:.2}",
-                        self.config.server.id, my_load
+            // Client sending a task to the leader for forwarding (leader-routed mode)
+            Message::RoutedTaskRequest {
+                client_name,
+                request_id,
+                secret_image_data,
+            } => {
+                let current_leader = *self.current_leader.read().await;
+                let response = if current_leader != Some(self.config.server.id) {
+                    warn!(
+                        "⚠️  Non-leader Server {} received routed task #{}, rejecting",
+                        self.config.server.id, request_id
                     );
-                    for (peer_id, peer_load) in peer_loads.iter() {
-                        info!("   Server {}: {:.2}", peer_id, peer_load);
+                    Message::TaskRejected {
+                        request_id,
+                        reason: format!("Server {} is not the leader", self.config.server.id),
                     }
-
-                    // Find server with lowest load (could be us!)
-                    let mut lowest_load = my_load;
-                    let mut best_server = self.config.server.id;
-
-                    for (peer_id, peer_load) in peer_loads.iter() {
-                        if *peer_load < lowest_load {
-                            lowest_load = *peer_load;
-                            best_server = *peer_id;
-                        }
+                } else if let Some(retry_after) =
+                    self.rate_limit(&client_name, Instant::now()).await
+                {
+                    Message::RateLimited {
+                        request_id,
+                        retry_after_ms: retry_after.as_millis() as u64,
                     }
+                } else {
+                    self.route_task(client_name, request_id, secret_image_data)
+                        .await
+                };
 
-                    // Get the address of the chosen server
-                    let assigned_address = if best_server == self.config.server.id {
-                        // It's us! Use our address
-                        self.config.server.address.clone()
-                    } else {
-                        // It's a peer, look up their address
-                        self.config
-                            .peers
-                            .peers
-                            .iter()
-                            .find(|p| p.id == best_server)
-                            .map(|p| p.address.clone())
-                            .unwrap_or_default()
-                    };
-
-                    info!(
-                        "📌 Task #{} from {} assigned to Server {} (load: {:.2})",
-                        request_id, client_name, best_server, lowest_load
-                    );
+                // Relay the result; the client's TaskAck follows on this connection
+                if let Err(e) = conn.write_message(&response).await {
+                    error!("❌ Failed to send routed response to client: {}", e);
+                }
+            }
 
-                    // Add to history and broadcast to all servers
-                    let timestamp = current_timestamp();
-                    let history_msg = Message::HistoryAdd {
-                        client_name: client_name.clone(),
-                        request_id,
-                        assigned_server_id: best_server,
-                        timestamp,
-                    };
+            // Leader forwarding a routed task to us
+            Message::ForwardedTask {
+                from_server_id,
+                client_name,
+                request_id,
+                secret_image_data,
+            } => {
+                info!(
+                    request_id = request_id,
+                    "📥 Server {} received task #{} from client '{}' (forwarded by leader {})",
+                    self.config.server.id, request_id, client_name, from_server_id
+                );
 
-                    // Add to own history
-                    let entry = TaskHistoryEntry {
-                        _client_name: client_name.clone(),
-                        _request_id: request_id,
-                        assigned_server_id: best_server,
-                        _timestamp: timestamp,
-                    };
-                    self.task_history
-                        .write()
-                        .await
-                        .insert((client_name, request_id), entry);
+                let (tx, mut rx) = mpsc::channel::<Message>(1);
+                self.process_task(
+                    request_id,
+                    client_name.clone(),
+                    secret_image_data,
+                    None,
+                    Some(tx),
+                    false,
+                )
+                .await;
 
-                    // Broadcast to all peers
-                    self.broadcast(history_msg).await;
+                // Wait for the result in the background so this peer connection
+                // keeps delivering the leader's heartbeats meanwhile
+                let server = self.clone_arc();
+                tokio::spawn(async move {
+                    if let Some(response) = rx.recv().await {
+                        let result = Message::ForwardedTaskResult {
+                            client_name,
+                            request_id,
+                            response: Box::new(response),
+                        };
+                        server.send_to_peer(from_server_id, result).await;
+                    }
+                });
+            }
 
-                    // Send response to client
-                    let response = Message::TaskAssignmentResponse {
-                        request_id,
-                        assigned_server_id: best_server,
-                        assigned_server_address: assigned_address,
-                    };
+            // Completed result replicated to us as the sender's backup
+            Message::ResultReplicate {
+                client_name,
+                request_id,
+                response,
+            } => {
+                debug!(
+                    "💾 Server {} storing replicated result for task #{} from '{}'",
+                    self.config.server.id, request_id, client_name
+                );
+                self.cache_result(client_name, request_id, *response).await;
+            }
 
-                    if let Err(e) = conn.write_message(&response).await {
-                        error!("❌ Failed to send assignment response: {}", e);
+            // Result of a task we forwarded as leader
+            Message::ForwardedTaskResult {
+                client_name,
+                request_id,
+                response,
+            } => {
+                let pending = self
+                    .pending_forwards
+                    .write()
+                    .await
+                    .remove(&(client_name, request_id));
+                match pending {
+                    Some(tx) => {
+                        let _ = tx.send(*response);
+                    }
+                    None => {
+                        debug!(
+                            "📭 Server {} dropping late result for forwarded task #{}",
+                            self.config.server.id, request_id
+                        );
                     }
-                } else {
-                    warn!("⚠️  Non-leader received assignment request, ignoring");
                 }
             }
 
@@ -722,37 +1693,34 @@ impl ServerMiddleware {
                 assigned_server_id,
                 timestamp,
             } => {
+                if self.removed_after(&client_name, request_id, timestamp).await {
+                    debug!(
+                        "🗑️  Server {} ignoring history entry ({}, {}): already removed",
+                        self.config.server.id, client_name, request_id
+                    );
+                    return;
+                }
+
                 debug!(
                     "📝 Server {} adding history entry: ({}, {}) -> Server {}",
                     self.config.server.id, client_name, request_id, assigned_server_id
                 );
 
-                let entry = TaskHistoryEntry {
-                    _client_name: client_name.clone(),
-                    _request_id: request_id,
-                    assigned_server_id,
-                    _timestamp: timestamp,
-                };
-
-                self.task_history
-                    .write()
-                    .await
-                    .insert((client_name, request_id), entry);
+                self.insert_history(client_name, request_id, assigned_server_id, timestamp)
+                    .await;
             }
 
             Message::HistoryRemove {
                 client_name,
                 request_id,
+                timestamp,
             } => {
                 debug!(
                     "🗑️  Server {} removing history entry: ({}, {})",
                     self.config.server.id, client_name, request_id
                 );
 
-                self.task_history
-                    .write()
-                    .await
-                    .remove(&(client_name, request_id));
+                self.remove_history(client_name, request_id, timestamp).await;
             }
 
             // Client acknowledges receipt of TaskResponse
@@ -761,24 +1729,13 @@ impl ServerMiddleware {
                 request_id,
             } => {
                 info!(
+                    request_id = request_id,
                     "✅ Server {} received ACK from client '{}' for task #{}",
                     self.config.server.id, client_name, request_id
                 );
 
                 // Now we can safely remove from history and broadcast to all servers
-                let history_remove_msg = Message::HistoryRemove {
-                    client_name: client_name.clone(),
-                    request_id,
-                };
-
-                // Remove from own history
-                self.task_history
-                    .write()
-                    .await
-                    .remove(&(client_name, request_id));
-
-                // Broadcast to all peers so they also remove it
-                self.broadcast(history_remove_msg).await;
+                self.remove_task_from_history(client_name, request_id).await;
 
                 info!(
                     "🗑️  Server {} removed task #{} from history after client ACK",
@@ -822,6 +1779,7 @@ impl ServerMiddleware {
                         request_id,
                         assigned_server_id: entry.assigned_server_id,
                         assigned_server_address: assigned_address,
+                        leader_id: *self.current_leader.read().await,
                     };
 
                     info!(
@@ -850,18 +1808,7 @@ impl ServerMiddleware {
                 );
 
                 // Convert our task history to the wire format
-                let history = self.task_history.read().await;
-                let history_entries: Vec<(String, u64, u32, u64)> = history
-                    .iter()
-                    .map(|((client_name, request_id), entry)| {
-                        (
-                            client_name.clone(),
-                            *request_id,
-                            entry.assigned_server_id,
-                            entry._timestamp,
-                        )
-                    })
-                    .collect();
+                let history_entries = self.history_entries().await;
 
                 info!(
                     "📤 Server {} sending {} history entries to leader {}",
@@ -870,18 +1817,17 @@ impl ServerMiddleware {
                     from_server_id
                 );
 
-                // Send response to the requesting leader
+                // Send response to the requesting leader over our own peer connection.
+                // The request arrived on the leader's outgoing peer connection, which
+                // it only writes to, so a reply on `conn` would never be read.
                 let response = Message::HistorySyncResponse {
                     from_server_id: self.config.server.id,
                     history_entries,
                 };
-
-                if let Err(e) = conn.write_message(&response).await {
-                    error!("❌ Failed to send history sync response: {}", e);
-                }
+                self.send_to_peer(from_server_id, response).await;
             }
 
-            // Newly elected leader sends us their merged history
+            // A peer answered our HistorySyncRequest (we are the new leader)
             Message::HistorySyncResponse {
                 from_server_id,
                 history_entries,
@@ -900,6 +1846,70 @@ impl ServerMiddleware {
                     .push(history_entries);
             }
 
+            // A peer wants to start an election; tell it whether we think the leader is gone
+            Message::PreVote { from_id } => {
+                let granted = self.leader_seems_gone().await;
+                debug!(
+                    "🗳️  Server {} {} pre-vote for {}",
+                    self.config.server.id,
+                    if granted { "granting" } else { "refusing" },
+                    from_id
+                );
+                // The request came on a short-lived connection the peer reads from
+                let response = Message::PreVoteResponse {
+                    from_id: self.config.server.id,
+                    granted,
+                };
+                if let Err(e) = conn.write_message(&response).await {
+                    warn!(
+                        "⚠️  Server {} failed to answer pre-vote from server {}: {}",
+                        self.config.server.id, from_id, e
+                    );
+                }
+            }
+
+            // A peer just (re)started and wants the cluster state; only the leader answers
+            Message::RecoveryRequest { from_id } => {
+                let current_leader = *self.current_leader.read().await;
+                if current_leader != Some(self.config.server.id) {
+                    return;
+                }
+
+                let history_entries = self.history_entries().await;
+                info!(
+                    "🩹 Server {} sending state ({} history entries) to recovering server {}",
+                    self.config.server.id,
+                    history_entries.len(),
+                    from_id
+                );
+                // The request came on a short-lived connection the peer reads from
+                let state = Message::StateSync {
+                    leader_id: self.config.server.id,
+                    term: *self.current_term.read().await,
+                    history_entries,
+                };
+                if let Err(e) = conn.write_message(&state).await {
+                    warn!(
+                        "⚠️  Server {} failed to send state to server {}: {}",
+                        self.config.server.id, from_id, e
+                    );
+                }
+            }
+
+            // The leader answered our RecoveryRequest
+            Message::StateSync {
+                leader_id,
+                term,
+                history_entries,
+            } => {
+                self.apply_state_sync(leader_id, term, history_entries).await;
+            }
+
+            // Play dead for a while, so peers exercise their failover paths
+            Message::SimulateFail { duration_secs } => {
+                self.simulate_failure(Duration::from_secs(duration_secs));
+            }
+
             _ => {
                 // Ignore other messages
             }
@@ -916,28 +1926,50 @@ impl ServerMiddleware {
     /// - Server ID
     /// - Current timestamp
     /// - Current load (priority score)
+    /// - The leader we follow and our election term (for split-brain reconciliation)
     ///
-    /// This runs forever in a loop, sending heartbeats at the configured interval.
+    /// This runs forever in a loop, sending heartbeats at the configured interval
+    /// (randomized by `heartbeat_jitter` so servers don't broadcast in lockstep).
     async fn start_heartbeat(&self) {
-        let interval = self.config.election.heartbeat_interval_secs;
+        let interval = Duration::from_secs(self.config.election.heartbeat_interval_secs);
 
         loop {
-            tokio::time::sleep(Duration::from_secs(interval)).await;
+            tokio::time::sleep(jittered_interval(
+                interval,
+                self.config.election.heartbeat_jitter,
+            ))
+            .await;
+
+            // A failed server stays silent
+            if self.is_failed.load(Ordering::SeqCst) {
+                continue;
+            }
 
-            // Get REAL current load
-            let current_load = self.metrics.get_load();
-            let cpu = self.metrics.get_cpu_usage();
-            let tasks = self.metrics.get_active_tasks();
+            // Get REAL current load, with every reading taken at the same moment
+            let snapshot = self.metrics.snapshot();
 
+            // One lock at a time: a guard in the struct literal below would be held
+            // while awaiting the next, against the term-then-leader order elsewhere
+            let leader_id = *self.current_leader.read().await;
+            let term = *self.current_term.read().await;
             let heartbeat = Message::Heartbeat {
                 from_id: self.config.server.id,
                 timestamp: current_timestamp(),
-                load: current_load,
+                load: snapshot.priority,
+                saturated: self.is_saturated(),
+                total_tasks: snapshot.total_tasks,
+                leader_id,
+                term,
             };
 
             debug!(
-                "💓 Server {} sending heartbeat (load: {:.2}, CPU: {:.1}%, tasks: {})",
-                self.config.server.id, current_load, cpu, tasks
+                "💓 Server {} sending heartbeat (load: {:.2}, CPU: {:.1}%, memory available: {:.1}%, tasks: {}, lifetime tasks: {})",
+                self.config.server.id,
+                snapshot.priority,
+                snapshot.cpu_usage,
+                snapshot.available_memory_percent,
+                snapshot.active_tasks,
+                snapshot.total_tasks
             );
 
             self.broadcast(heartbeat).await;
@@ -948,6 +1980,49 @@ impl ServerMiddleware {
     // TASK 4: Monitor heartbeats and detect failures
     // ========================================================================
 
+    /// Find configured peers that should now be considered failed.
+    ///
+    /// A peer has failed if its last heartbeat is older than `failure_timeout_secs`
+    /// (or, with `phi_threshold` set, if its phi-accrual suspicion level exceeds the
+    /// threshold), or if it has never sent one and `failure_timeout_secs` has passed
+    /// since we started (so a peer that never came up, or died before its first
+    /// heartbeat, is caught too). Each failure is reported once; the peer is
+    /// reported again only after it sends another heartbeat.
+    ///
+    /// # Arguments
+    /// - `now_ms`: Current Unix timestamp (milliseconds)
+    ///
+    /// # Returns
+    /// IDs of the peers that newly failed
+    async fn detect_failed_peers(&self, now_ms: u64) -> Vec<u32> {
+        let now = now_ms / 1000;
+        let timeout = self.config.election.failure_timeout_secs;
+        let heartbeats = self.last_heartbeat_times.read().await;
+        let detectors = self.heartbeat_detectors.read().await;
+        let mut failed_peers = self.failed_peers.write().await;
+
+        let mut newly_failed = Vec::new();
+        for peer in &self.config.peers.peers {
+            let timed_out = match (self.config.election.phi_threshold, detectors.get(&peer.id)) {
+                (Some(threshold), Some(detector)) => detector.phi(now_ms) > threshold,
+                _ => {
+                    let last_seen = heartbeats.get(&peer.id).copied().unwrap_or(self.started_at);
+                    now.saturating_sub(last_seen) > timeout
+                }
+            };
+            if timed_out && failed_peers.insert(peer.id) {
+                if !heartbeats.contains_key(&peer.id) {
+                    warn!(
+                        "⚠️  Server {} has never heard from peer {} since startup",
+                        self.config.server.id, peer.id
+                    );
+                }
+                newly_failed.push(peer.id);
+            }
+        }
+        newly_failed
+    }
+
     /// Monitor peer heartbeats and detect failed servers.
     ///
     /// For each monitoring interval:
@@ -965,23 +2040,13 @@ impl ServerMiddleware {
             ))
             .await;
 
-            let now = current_timestamp();
-            let timeout = self.config.election.failure_timeout_secs;
+            // A failed server hears nothing, so it can't judge its peers
+            if self.is_failed.load(Ordering::SeqCst) {
+                continue;
+            }
 
-            // Collect timed-out peers (only holding read lock)
-            let timed_out_peers: Vec<u32> = {
-                let heartbeats = self.last_heartbeat_times.read().await;
-                heartbeats
-                    .iter()
-                    .filter_map(|(peer_id, last_seen)| {
-                        if now - last_seen > timeout {
-                            Some(*peer_id)
-                        } else {
-                            None
-                        }
-                    })
-                    .collect()
-            };
+            let timeout = self.config.election.failure_timeout_secs;
+            let timed_out_peers = self.detect_failed_peers(current_timestamp_millis()).await;
 
             let current_leader = *self.current_leader.read().await;
 
@@ -993,7 +2058,11 @@ impl ServerMiddleware {
                 );
 
                 self.peer_loads.write().await.remove(&peer_id);
+                self.saturated_peers.write().await.remove(&peer_id);
+                self.peer_leaders.write().await.remove(&peer_id);
                 self.last_heartbeat_times.write().await.remove(&peer_id);
+                self.heartbeat_detectors.write().await.remove(&peer_id);
+                self.drop_peer_link(peer_id).await;
 
                 // Check for orphaned tasks assigned to this failed server
                 let orphaned_tasks: Vec<(String, u64)> = {
@@ -1035,6 +2104,7 @@ impl ServerMiddleware {
                         peer_id
                     );
                     *self.current_leader.write().await = None;
+                    self.emit_election_event(ElectionEvent::LeaderChanged { leader_id: None });
                     self.initiate_election().await;
                 }
             }
@@ -1042,44 +2112,320 @@ impl ServerMiddleware {
     }
 
     // ========================================================================
-    // ELECTION LOGIC
+    // RECOVERY
     // ========================================================================
 
-    /// Initiate a new leader election using the Modified Bully Algorithm.
-    ///
-    /// # Election Process
-    ///
-    /// 1. Calculate our priority based on current CPU, tasks, and memory
-    /// 2. Broadcast ELECTION message to all peers with our priority
-    /// 3. Wait for ALIVE responses (from servers with lower priority)
-    /// 4. If no ALIVE received, we won - broadcast COORDINATOR message
-    /// 5. If ALIVE received, we lost - wait for winner to announce
-    ///
-    /// # Priority Calculation
+    /// Ask every peer for the cluster state, and apply the leader's answer.
     ///
-    /// Lower priority score = better candidate (less loaded)
-    /// - 50% weight: CPU usage
-    /// - 30% weight: Active tasks
-    /// - 20% weight: Memory usage
-    async fn initiate_election(&self) {
-        *self.received_alive.write().await = false;
-        info!("🗳️  Server {} initiating election", self.config.server.id);
+    /// Each peer gets a [`Message::RecoveryRequest`] on its own short-lived
+    /// connection (our peer links only write, and the leader's link back to a
+    /// restarted server may still be backing off). Only the leader answers; the
+    /// others are given up on after `election_timeout_secs`. Nothing happens if
+    /// no peer is reachable or none of them leads, e.g. on a cold cluster start.
+    async fn recover_state(&self) {
+        let wait = Duration::from_secs(self.config.election.election_timeout_secs);
+        let mut requests = JoinSet::new();
+        for peer in &self.config.peers.peers {
+            let address = peer.address.clone();
+            let from_id = self.config.server.id;
+            let max_message_size = self.config.server.max_message_size;
+            requests.spawn(async move {
+                let stream = Stream::connect(&address).await.ok()?;
+                let mut conn = Connection::with_timeouts(stream, wait, wait)
+                    .with_max_message_size(max_message_size);
+                conn.handshake().await.ok()?;
+                conn.write_message(&Message::RecoveryRequest { from_id })
+                    .await
+                    .ok()?;
+                match conn.read_message().await {
+                    Ok(Some(state @ Message::StateSync { .. })) => Some(state),
+                    _ => None,
+                }
+            });
+        }
 
-        // Calculate priority based on REAL metrics
-        let my_priority = self.metrics.calculate_priority();
-        let cpu = self.metrics.get_cpu_usage();
-        let tasks = self.metrics.get_active_tasks();
-        let memory = self.metrics.get_available_memory_percent();
+        // The first answer wins; dropping `requests` abandons the rest
+        while let Some(result) = requests.join_next().await {
+            if let Ok(Some(Message::StateSync {
+                leader_id,
+                term,
+                history_entries,
+            })) = result
+            {
+                self.apply_state_sync(leader_id, term, history_entries).await;
+                return;
+            }
+        }
+        debug!(
+            "🩹 Server {} got no cluster state from its peers",
+            self.config.server.id
+        );
+    }
 
-        info!(
+    /// Follow `leader_id` and merge its task history, unless `term` is stale.
+    ///
+    /// History entries are kept where they're newer than ours.
+    ///
+    /// # Arguments
+    /// - `leader_id`: The leader that sent the state
+    /// - `term`: The election term it won
+    /// - `history_entries`: Its task history
+    async fn apply_state_sync(
+        &self,
+        leader_id: u32,
+        term: u64,
+        history_entries: Vec<HistoryEntryTuple>,
+    ) {
+        {
+            let mut current_term = self.current_term.write().await;
+            if term < *current_term {
+                warn!(
+                    "⏪ Server {} ignoring stale state from {} (term {} < {})",
+                    self.config.server.id, leader_id, term, *current_term
+                );
+                return;
+            }
+            *current_term = term;
+        }
+
+        let previous = self.current_leader.write().await.replace(leader_id);
+        if previous != Some(leader_id) {
+            self.emit_election_event(ElectionEvent::LeaderChanged {
+                leader_id: Some(leader_id),
+            });
+        }
+
+        let mut merged = 0;
+        for (client_name, request_id, assigned_server_id, timestamp) in history_entries {
+            if self.removed_after(&client_name, request_id, timestamp).await {
+                continue;
+            }
+            let is_newer = self
+                .task_history
+                .read()
+                .await
+                .get(&(client_name.clone(), request_id))
+                .map(|existing| timestamp > existing._timestamp)
+                .unwrap_or(true);
+            if is_newer {
+                self.insert_history(client_name, request_id, assigned_server_id, timestamp)
+                    .await;
+                merged += 1;
+            }
+        }
+
+        info!(
+            "🩹 Server {} recovered: following leader {} (term {}), merged {} history entries",
+            self.config.server.id, leader_id, term, merged
+        );
+    }
+
+    /// Our task history in wire format.
+    async fn history_entries(&self) -> Vec<HistoryEntryTuple> {
+        self.task_history
+            .read()
+            .await
+            .iter()
+            .map(|((client_name, request_id), entry)| {
+                (
+                    client_name.clone(),
+                    *request_id,
+                    entry.assigned_server_id,
+                    entry._timestamp,
+                )
+            })
+            .collect()
+    }
+
+    // ========================================================================
+    // FAULT INJECTION
+    // ========================================================================
+
+    /// Play dead for `duration`, then recover (see [`Message::SimulateFail`]).
+    ///
+    /// While failed, incoming messages are dropped, no heartbeats are sent and
+    /// peers aren't monitored. A request that arrives while already failed is
+    /// dropped with everything else, so the first duration stands.
+    ///
+    /// # Arguments
+    /// - `duration`: How long to stay failed
+    fn simulate_failure(&self, duration: Duration) {
+        if self.is_failed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        warn!(
+            "💀 Server {} simulating failure for {}s",
+            self.config.server.id,
+            duration.as_secs()
+        );
+
+        let server = self.clone_arc();
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            server.recover_from_simulated_failure().await;
+        });
+    }
+
+    /// End a simulated failure.
+    ///
+    /// Peers' heartbeats were ignored meanwhile, so each peer's failure timer is
+    /// restarted from now rather than reporting every peer as failed at once.
+    async fn recover_from_simulated_failure(&self) {
+        let now = current_timestamp();
+        {
+            let mut heartbeats = self.last_heartbeat_times.write().await;
+            for peer in &self.config.peers.peers {
+                heartbeats.insert(peer.id, now);
+            }
+        }
+        self.heartbeat_detectors.write().await.clear();
+        self.failed_peers.write().await.clear();
+
+        self.is_failed.store(false, Ordering::SeqCst);
+        info!(
+            "💚 Server {} recovered from simulated failure",
+            self.config.server.id
+        );
+    }
+
+    // ========================================================================
+    // ELECTION LOGIC
+    // ========================================================================
+
+    /// Start a leader election (see [`Self::run_election`]).
+    ///
+    /// With `election.pre_vote` enabled, the peers are asked first whether they
+    /// also think the leader is gone (see [`Self::pre_vote`]); if not, no term is
+    /// started and we wait to hear from the leader through its heartbeats.
+    async fn initiate_election(&self) {
+        if self.config.election.pre_vote
+            && !self.config.peers.peers.is_empty()
+            && !self.pre_vote().await
+        {
+            info!(
+                "🗳️  Server {} not starting an election: pre-vote failed",
+                self.config.server.id
+            );
+            return;
+        }
+        self.run_election().await;
+    }
+
+    /// Ask every peer whether the leader is gone, before starting an election.
+    ///
+    /// Each peer gets a [`Message::PreVote`] on its own short-lived connection, as
+    /// in [`Self::recover_state`], and is given up on after `election_timeout_secs`.
+    ///
+    /// # Returns
+    /// `true` if a majority of the peers that answered granted the pre-vote. A
+    /// server that reaches no peer never passes, so an isolated server stays
+    /// leaderless instead of electing itself in a term the rest of the cluster
+    /// would have to adopt.
+    async fn pre_vote(&self) -> bool {
+        let wait = Duration::from_secs(self.config.election.election_timeout_secs);
+        let mut requests = JoinSet::new();
+        for peer in &self.config.peers.peers {
+            let address = peer.address.clone();
+            let from_id = self.config.server.id;
+            let max_message_size = self.config.server.max_message_size;
+            requests.spawn(async move {
+                let stream = Stream::connect(&address).await.ok()?;
+                let mut conn = Connection::with_timeouts(stream, wait, wait)
+                    .with_max_message_size(max_message_size);
+                conn.handshake().await.ok()?;
+                conn.write_message(&Message::PreVote { from_id }).await.ok()?;
+                match conn.read_message().await {
+                    Ok(Some(Message::PreVoteResponse { granted, .. })) => Some(granted),
+                    _ => None,
+                }
+            });
+        }
+
+        let (mut answered, mut granted) = (0, 0);
+        while let Some(result) = requests.join_next().await {
+            if let Ok(Some(vote)) = result {
+                answered += 1;
+                if vote {
+                    granted += 1;
+                }
+            }
+        }
+        info!(
+            "🗳️  Server {} pre-vote: {} of {} reachable peer(s) think the leader is gone",
+            self.config.server.id, granted, answered
+        );
+        granted * 2 > answered
+    }
+
+    /// Whether we'd grant a peer's [`Message::PreVote`]: we follow no leader, or
+    /// haven't heard from ours within `failure_timeout_secs`. A leader never does.
+    async fn leader_seems_gone(&self) -> bool {
+        match *self.current_leader.read().await {
+            None => true,
+            Some(leader_id) if leader_id == self.config.server.id => false,
+            Some(leader_id) => {
+                let last_seen = self
+                    .last_heartbeat_times
+                    .read()
+                    .await
+                    .get(&leader_id)
+                    .copied()
+                    .unwrap_or(self.started_at);
+                current_timestamp().saturating_sub(last_seen)
+                    > self.config.election.failure_timeout_secs
+            }
+        }
+    }
+
+    /// Run a leader election using the Modified Bully Algorithm.
+    ///
+    /// # Election Process
+    ///
+    /// 1. Calculate our priority based on current CPU, tasks, memory and disk
+    /// 2. Broadcast ELECTION message to all peers with our priority
+    /// 3. Wait for ALIVE responses (from servers with lower priority)
+    /// 4. If no ALIVE received, we won - broadcast COORDINATOR message
+    /// 5. If ALIVE received, we lost - wait for winner to announce
+    ///
+    /// # Priority Calculation
+    ///
+    /// Lower priority score = better candidate (less loaded). The score weighs CPU
+    /// usage, active tasks, memory usage and disk usage by `election.priority_weights`
+    /// (`cpu`/`tasks`/`memory`/`disk`, default 0.5/0.3/0.2/0.0); see [`ServerMetrics`].
+    async fn run_election(&self) {
+        *self.received_alive.write().await = false;
+
+        // Start a new term, newer than any we've seen
+        let term = {
+            let mut current_term = self.current_term.write().await;
+            *current_term += 1;
+            *current_term
+        };
+        info!(
+            "🗳️  Server {} initiating election (term {})",
+            self.config.server.id, term
+        );
+
+        // Calculate priority based on REAL metrics
+        let my_priority = self.metrics.calculate_priority();
+        let cpu = self.metrics.get_cpu_usage();
+        let tasks = self.metrics.get_active_tasks();
+        let memory = self.metrics.get_available_memory_percent();
+
+        info!(
             "📊 Server {} priority: {:.2} (CPU: {:.1}%, Tasks: {}, Memory: {:.1}% available)",
             self.config.server.id, my_priority, cpu, tasks, memory
         );
+        self.emit_election_event(ElectionEvent::ElectionStarted {
+            term,
+            priority: my_priority,
+        });
 
         // Send election message with our priority
         let election_msg = Message::Election {
             from_id: self.config.server.id,
             priority: my_priority,
+            term,
         };
 
         info!(
@@ -1098,17 +2444,27 @@ impl ServerMiddleware {
         ))
         .await;
 
-        // Check if we won
-        if !*self.received_alive.read().await {
+        // Check if we won: any peer that outranks us (including an equal score
+        // with a lower ID) will have answered with ALIVE, and a newer term means
+        // another election or leader superseded this one
+        let superseded = *self.current_term.read().await != term;
+        let received_alive = *self.received_alive.read().await;
+        let lacks_quorum = self.config.election.require_quorum && !self.has_quorum().await;
+        if !received_alive && !superseded && !lacks_quorum {
             info!(
                 "🎉 Server {} won election! (lowest priority score: {:.2})",
                 self.config.server.id, my_priority
             );
 
             *self.current_leader.write().await = Some(self.config.server.id);
+            self.emit_election_event(ElectionEvent::WonElection { term });
+            self.emit_election_event(ElectionEvent::LeaderChanged {
+                leader_id: Some(self.config.server.id),
+            });
 
             let coordinator_msg = Message::Coordinator {
                 leader_id: self.config.server.id,
+                term,
             };
 
             info!(
@@ -1130,11 +2486,66 @@ impl ServerMiddleware {
                 self.config.server.id
             );
             self.reassign_all_orphaned_tasks().await;
+        } else if superseded {
+            info!(
+                "⏭️  Server {} abandoning election for term {} (superseded by a newer term)",
+                self.config.server.id, term
+            );
+        } else if !received_alive {
+            warn!(
+                "🚫 Server {} won't lead in term {}: it can't reach a majority of the cluster",
+                self.config.server.id, term
+            );
+            self.emit_election_event(ElectionEvent::LostElection { term });
         } else {
             info!(
                 "📊 Server {} lost election (higher load than others)",
                 self.config.server.id
             );
+            self.emit_election_event(ElectionEvent::LostElection { term });
+        }
+    }
+
+    /// Whether we and `peers` of our peers make up a majority of the cluster, i.e.
+    /// of us plus every configured peer.
+    fn is_majority(&self, peers: usize) -> bool {
+        (peers + 1) * 2 > self.config.peers.peers.len() + 1
+    }
+
+    /// Whether we and the peers heard from within `failure_timeout_secs` make up a
+    /// majority of the cluster.
+    async fn has_quorum(&self) -> bool {
+        let now = current_timestamp();
+        let timeout = self.config.election.failure_timeout_secs;
+        let heartbeats = self.last_heartbeat_times.read().await;
+        let reachable = self
+            .config
+            .peers
+            .peers
+            .iter()
+            .filter(|peer| {
+                heartbeats
+                    .get(&peer.id)
+                    .is_some_and(|last_seen| now.saturating_sub(*last_seen) <= timeout)
+            })
+            .count();
+        self.is_majority(reachable)
+    }
+
+    /// Send `event` to the election event listener, if there is one.
+    fn emit_election_event(&self, event: ElectionEvent) {
+        if let ElectionEvent::LeaderChanged { .. } = event {
+            self.leader_changed_at
+                .store(current_timestamp_millis(), Ordering::SeqCst);
+        }
+
+        if let Some(events) = &self.election_events {
+            if let Err(e) = events.try_send(event) {
+                debug!(
+                    "Server {} dropped election event: {}",
+                    self.config.server.id, e
+                );
+            }
         }
     }
 
@@ -1192,6 +2603,10 @@ impl ServerMiddleware {
 
         for peer_entries in responses {
             for (client_name, request_id, assigned_server_id, timestamp) in peer_entries {
+                // A peer that hasn't seen a removal yet still reports the task
+                if self.removed_after(&client_name, request_id, timestamp).await {
+                    continue;
+                }
                 let key = (client_name.clone(), request_id);
 
                 // Only keep the entry if it's newer than what we have
@@ -1235,6 +2650,23 @@ impl ServerMiddleware {
 
         // Replace our history with the merged version
         *self.task_history.write().await = merged_history.clone();
+        *self.pending_assignments.write().await = pending_counts(&merged_history);
+        if let Some(log) = &self.history_log {
+            let entries: Vec<HistoryEntryTuple> = merged_history
+                .iter()
+                .map(|((client_name, request_id), entry)| {
+                    (
+                        client_name.clone(),
+                        *request_id,
+                        entry.assigned_server_id,
+                        entry._timestamp,
+                    )
+                })
+                .collect();
+            if let Err(e) = log.replace(&entries) {
+                error!("❌ Failed to persist merged task history: {}", e);
+            }
+        }
 
         // Broadcast all merged history entries to peers for consistency
         for ((client_name, request_id), entry) in &merged_history {
@@ -1258,6 +2690,10 @@ impl ServerMiddleware {
     /// This method scans the task history for tasks assigned to servers that are
     /// no longer in the peer list (failed servers), and reassigns them to healthy servers.
     ///
+    /// With [`ServerInfo::replicate_results`], a failed server's tasks go to its
+    /// backup (the next server by ID) if that server is healthy, since it may
+    /// already hold their results.
+    ///
     /// Should be called when:
     /// - A server becomes the new leader (after winning election)
     /// - A peer failure is detected (to immediately handle orphaned tasks)
@@ -1296,39 +2732,36 @@ impl ServerMiddleware {
         );
 
         for (client_name, request_id, failed_server_id) in &orphaned_tasks {
-            // Find the best (least-loaded) healthy server to reassign to
-            let my_load = self.metrics.get_load();
-            let peer_loads = self.peer_loads.read().await;
-
-            let mut lowest_load = my_load;
-            let mut best_server = self.config.server.id;
-
-            // Consider all healthy peers
-            for (peer_id, peer_load) in peer_loads.iter() {
-                if *peer_load < lowest_load {
-                    lowest_load = *peer_load;
-                    best_server = *peer_id;
-                }
-            }
-
-            info!(
-                "   ➡️  Reassigning task #{} from '{}': Server {} → Server {} (load: {:.2})",
-                request_id, client_name, failed_server_id, best_server, lowest_load
-            );
+            // With replication, the failed server's backup may already hold the result
+            let backup = self
+                .config
+                .server
+                .replicate_results
+                .then(|| self.backup_for(*failed_server_id))
+                .flatten()
+                .filter(|id| *id == self.config.server.id || healthy_peers.contains(id));
+
+            let best_server = if let Some(backup) = backup {
+                info!(
+                    "   ➡️  Reassigning task #{} from '{}': Server {} → backup Server {}",
+                    request_id, client_name, failed_server_id, backup
+                );
+                backup
+            } else {
+                // Find the best healthy server to reassign to
+                let (best_server, lowest_load) = self.select_server(client_name).await;
 
-            // Update task history with new assignment
-            let timestamp = current_timestamp();
-            let updated_entry = TaskHistoryEntry {
-                _client_name: client_name.clone(),
-                _request_id: *request_id,
-                assigned_server_id: best_server,
-                _timestamp: timestamp,
+                info!(
+                    "   ➡️  Reassigning task #{} from '{}': Server {} → Server {} (load: {:.2})",
+                    request_id, client_name, failed_server_id, best_server, lowest_load
+                );
+                best_server
             };
 
-            self.task_history
-                .write()
-                .await
-                .insert((client_name.clone(), *request_id), updated_entry);
+            // Update task history with new assignment
+            let timestamp = self.next_history_timestamp();
+            self.insert_history(client_name.clone(), *request_id, best_server, timestamp)
+                .await;
 
             // Broadcast updated history to all peers
             let history_update = Message::HistoryAdd {
@@ -1348,97 +2781,825 @@ impl ServerMiddleware {
         );
     }
 
-    /// Broadcast a message to all connected peers.
+    /// Reconcile our view of the leader with the one a peer's heartbeat reports.
     ///
-    /// # Arguments
-    /// - `message`: The message to send (will be cloned for each peer)
+    /// While we follow no leader, we adopt one as soon as we and the peers whose
+    /// heartbeats report it (in the same term) are a majority of the cluster, so a
+    /// single peer's stale view isn't enough.
     ///
-    /// Messages are sent asynchronously via channels - this method returns
-    /// immediately after queuing the messages.
-    async fn broadcast(&self, message: Message) {
-        let connections = self.peer_connections.read().await;
-        for (peer_id, tx) in connections.iter() {
-            match tx.send(message.clone()).await {
-                Ok(_) => {
-                    debug!("📤 Sent message to peer {}", peer_id);
-                }
-                Err(e) => {
-                    debug!("❌ Failed to send to peer {}: {}", peer_id, e);
+    /// Otherwise a peer's leader wins if it comes from a newer term, or from the
+    /// same term with a lower leader ID (two partitions can each elect a leader in
+    /// the same term). Followers simply switch to it. If we were leader ourselves
+    /// this is a split brain: we step down and start a new election so the
+    /// surviving leader rebuilds history and reassigns orphaned tasks with the
+    /// whole cluster.
+    ///
+    /// # Arguments
+    /// - `from_id`: ID of the peer that sent the heartbeat
+    /// - `their_leader`: The leader the peer follows
+    /// - `their_term`: The peer's election term
+    async fn reconcile_leader(&self, from_id: u32, their_leader: Option<u32>, their_term: u64) {
+        let Some(their_leader) = their_leader else {
+            return;
+        };
+        let agreeing = self
+            .peer_leaders
+            .read()
+            .await
+            .values()
+            .filter(|reported| **reported == (their_leader, their_term))
+            .count();
+
+        let was_leader = {
+            let mut current_term = self.current_term.write().await;
+            let mut current_leader = self.current_leader.write().await;
+
+            let newer = match *current_leader {
+                Some(ours) if ours == their_leader => false,
+                Some(ours) => {
+                    their_term > *current_term
+                        || (their_term == *current_term && their_leader < ours)
                 }
+                None => their_term >= *current_term && self.is_majority(agreeing),
+            };
+            if !newer {
+                return;
             }
+
+            let was_leader = *current_leader == Some(self.config.server.id);
+            *current_term = their_term;
+            *current_leader = Some(their_leader);
+            was_leader
+        };
+        self.emit_election_event(ElectionEvent::LeaderChanged {
+            leader_id: Some(their_leader),
+        });
+
+        if was_leader {
+            warn!(
+                "🧠 Server {} detected split brain: Server {} reports leader {} (term {}), stepping down and re-electing",
+                self.config.server.id, from_id, their_leader, their_term
+            );
+            let server = self.clone_arc();
+            tokio::spawn(async move {
+                server.initiate_election().await;
+            });
+        } else {
+            info!(
+                "👑 Server {} following leader {} (term {}) reported by Server {}",
+                self.config.server.id, their_leader, their_term, from_id
+            );
         }
     }
 
-    /// Send a message to a specific peer.
+    /// Assign a task to a server and record the assignment in history (leader only).
     ///
-    /// # Arguments
-    /// - `peer_id`: The ID of the target peer
-    /// - `message`: The message to send
+    /// A task already in history keeps its assignment (idempotent retry); otherwise
+    /// a server is chosen with [`select_server`](Self::select_server) and a
+    /// `HistoryAdd` is broadcast.
     ///
-    /// If the peer is not connected, the message is silently dropped.
-    async fn send_to_peer(&self, peer_id: u32, message: Message) {
-        let connections = self.peer_connections.read().await;
-        if let Some(tx) = connections.get(&peer_id) {
-            match tx.send(message).await {
-                Ok(_) => {
-                    debug!("📤 Sent message to peer {}", peer_id);
-                }
-                Err(e) => {
-                    debug!("❌ Failed to send to peer {}: {}", peer_id, e);
-                }
+    /// # Returns
+    /// ID of the server the task is assigned to
+    async fn assign_task(&self, client_name: String, request_id: u64) -> u32 {
+        // IDEMPOTENCY: Check if this task already exists in history
+        let existing_assignment = self
+            .task_history
+            .read()
+            .await
+            .get(&(client_name.clone(), request_id))
+            .map(|entry| entry.assigned_server_id);
+
+        if let Some(assigned_server_id) = existing_assignment {
+            // Task already assigned - return same assignment (idempotent retry)
+            info!(
+                "🔁 Task #{} from {} already assigned to Server {} (idempotent retry)",
+                request_id, client_name, assigned_server_id
+            );
+            return assigned_server_id;
+        }
+
+        // NEW TASK: Not in history, find the best server
+
+        // Log current state
+        info!("📊 LOAD DISTRIBUTION:");
+        info!(
+            "   Server {} (me, leader): {:.2}{}",
+            self.config.server.id,
+            self.metrics.get_load(),
+            if self.is_saturated() {
+                " (saturated)"
+            } else {
+                ""
+            }
+        );
+        {
+            let peer_loads = self.peer_loads.read().await;
+            let saturated_peers = self.saturated_peers.read().await;
+            for (peer_id, peer_load) in peer_loads.iter() {
+                info!(
+                    "   Server {}: {:.2}{}",
+                    peer_id,
+                    peer_load,
+                    if saturated_peers.contains(peer_id) {
+                        " (saturated)"
+                    } else {
+                        ""
+                    }
+                );
             }
-        } else {
-            debug!("❌ No connection to peer {}", peer_id);
         }
+
+        // Find server with lowest load (could be us!)
+        let (best_server, lowest_load) = self.select_server(&client_name).await;
+
+        info!(
+            request_id = request_id,
+            "📌 Task #{} from {} assigned to Server {} (load: {:.2})",
+            request_id, client_name, best_server, lowest_load
+        );
+
+        self.record_assignment(client_name, request_id, best_server)
+            .await;
+        best_server
     }
 
-    /// Create an Arc-wrapped clone of this server.
-    ///
-    /// Needed because we need to pass the server to async tasks.
-    /// All fields are already Arc/RwLock wrapped, so this is cheap.
-    fn clone_arc(&self) -> Arc<Self> {
-        Arc::new(Self {
-            core: self.core.clone(),
-            config: self.config.clone(),
-            metrics: self.metrics.clone(),
-            current_leader: self.current_leader.clone(),
-            received_alive: self.received_alive.clone(),
-            peer_connections: self.peer_connections.clone(),
-            last_heartbeat_times: self.last_heartbeat_times.clone(),
-            active_tasks: self.active_tasks.clone(),
-            peer_loads: self.peer_loads.clone(),
-            task_history: self.task_history.clone(),
-            history_sync_responses: self.history_sync_responses.clone(),
-        })
+    /// Add a task assignment to our history and broadcast it to all peers.
+    async fn record_assignment(&self, client_name: String, request_id: u64, server_id: u32) {
+        let timestamp = self.next_history_timestamp();
+        let history_msg = Message::HistoryAdd {
+            client_name: client_name.clone(),
+            request_id,
+            assigned_server_id: server_id,
+            timestamp,
+        };
+
+        self.insert_history(client_name, request_id, server_id, timestamp)
+            .await;
+        self.broadcast(history_msg).await;
     }
 
-    /// Process an encryption task by delegating to ServerCore.
+    /// Check the leader a `TaskRequest` claims assigned it against the one we follow.
     ///
-    /// # Arguments
-    /// - `request_id`: Unique identifier for this task
-    /// - `client_name`: Name of the client that submitted this task
-    /// - `secret_image_data`: Raw image bytes (the secret image to hide)
-    /// - `response_tx`: Optional channel to send response on
+    /// A claim is accepted if it names our leader, if we don't know of a leader
+    /// (yet), or within [`LEADER_CHANGE_GRACE_SECS`] of our leader changing.
     ///
-    /// # Process
+    /// # Returns
+    /// Our leader if the claim is rejected, `None` if it's accepted
+    async fn rejects_assignment_from(&self, assigned_by_leader: u32) -> Option<u32> {
+        let leader_id = (*self.current_leader.read().await)?;
+        let changed_at = self.leader_changed_at.load(Ordering::SeqCst);
+        let since_change = current_timestamp_millis().saturating_sub(changed_at);
+        if leader_id == assigned_by_leader || since_change < LEADER_CHANGE_GRACE_SECS * 1000 {
+            None
+        } else {
+            Some(leader_id)
+        }
+    }
+
+    /// Address of server `server_id` (ourselves or a configured peer).
+    fn server_address(&self, server_id: u32) -> String {
+        if server_id == self.config.server.id {
+            self.config.server.address.clone()
+        } else {
+            self.config
+                .peers
+                .peers
+                .iter()
+                .find(|p| p.id == server_id)
+                .map(|p| p.address.clone())
+                .unwrap_or_default()
+        }
+    }
+
+    /// Run a leader-routed task on the chosen server and return its result.
     ///
-    /// 1. Increment active task counter (for load calculation)
-    /// 2. Spawn async task to perform encryption via ServerCore (embedding secret into carrier)
-    /// 3. Send response back through channel (if provided)
-    /// 4. Remove task from history (broadcast to all peers)
-    /// 5. Decrement active task counter
+    /// The task is assigned like a `TaskAssignmentRequest`, then either processed
+    /// locally or sent to the chosen peer as a `ForwardedTask`, whose
+    /// `ForwardedTaskResult` is awaited for up to [`FORWARD_TIMEOUT_SECS`].
     ///
-    /// The encryption is performed in a blocking thread pool via ServerCore
-    /// to avoid blocking the async runtime.
-    async fn process_task(
+    /// # Returns
+    /// The `TaskResponse` to relay to the client, or a `TaskRejected` if the chosen
+    /// server is at capacity or never answered
+    async fn route_task(
         &self,
-        request_id: u64,
         client_name: String,
+        request_id: u64,
         secret_image_data: Vec<u8>,
+    ) -> Message {
+        let mut target = self.assign_task(client_name.clone(), request_id).await;
+
+        // Without a channel to the chosen peer, do the work ourselves
+        if target != self.config.server.id
+            && !self.peer_connections.read().await.contains_key(&target)
+        {
+            warn!(
+                "⚠️  Leader {} has no connection to Server {}, processing task #{} locally",
+                self.config.server.id, target, request_id
+            );
+            target = self.config.server.id;
+            self.record_assignment(client_name.clone(), request_id, target)
+                .await;
+        }
+
+        if target == self.config.server.id {
+            let (tx, mut rx) = mpsc::channel::<Message>(1);
+            self.process_task(
+                request_id,
+                client_name,
+                secret_image_data,
+                None,
+                Some(tx),
+                false,
+            )
+            .await;
+            return rx.recv().await.unwrap_or_else(|| Message::TaskRejected {
+                request_id,
+                reason: format!("Server {} dropped the task", self.config.server.id),
+            });
+        }
+
+        info!(
+            request_id = request_id,
+            "📨 Leader {} forwarding task #{} from '{}' to Server {}",
+            self.config.server.id, request_id, client_name, target
+        );
+        let (tx, rx) = oneshot::channel();
+        self.pending_forwards
+            .write()
+            .await
+            .insert((client_name.clone(), request_id), tx);
+        self.send_to_peer(
+            target,
+            Message::ForwardedTask {
+                from_server_id: self.config.server.id,
+                client_name: client_name.clone(),
+                request_id,
+                secret_image_data,
+            },
+        )
+        .await;
+
+        match tokio::time::timeout(Duration::from_secs(FORWARD_TIMEOUT_SECS), rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => Message::TaskRejected {
+                request_id,
+                reason: "Superseded by a newer submission of the same task".to_string(),
+            },
+            Err(_) => {
+                self.pending_forwards
+                    .write()
+                    .await
+                    .remove(&(client_name.clone(), request_id));
+                warn!(
+                    "⏰ Server {} did not answer forwarded task #{} within {}s",
+                    target, request_id, FORWARD_TIMEOUT_SECS
+                );
+
+                // Forget the assignment so the client's resubmission gets a fresh one
+                self.remove_task_from_history(client_name, request_id).await;
+                Message::TaskRejected {
+                    request_id,
+                    reason: format!(
+                        "Server {} did not answer within {}s",
+                        target, FORWARD_TIMEOUT_SECS
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Count a task request from `client_name` against its per-client rate limit.
+    ///
+    /// Keeps a sliding window of each client's request times over the last
+    /// [`ServerInfo::client_rate_window_secs`]. A refused request is not counted.
+    ///
+    /// # Returns
+    /// - `None`: The request is within the limit (or no limit is configured)
+    /// - `Some(retry_after)`: The client is over its limit until `retry_after` passes
+    async fn rate_limit(&self, client_name: &str, now: Instant) -> Option<Duration> {
+        let limit = self.config.server.client_rate_limit?;
+        let window = Duration::from_secs(self.config.server.client_rate_window_secs);
+
+        let mut request_times = self.client_request_times.write().await;
+        let times = request_times.entry(client_name.to_string()).or_default();
+        while times
+            .front()
+            .is_some_and(|time| now.duration_since(*time) >= window)
+        {
+            times.pop_front();
+        }
+
+        if times.len() < limit {
+            times.push_back(now);
+            return None;
+        }
+
+        let retry_after = times
+            .front()
+            .map(|oldest| window.saturating_sub(now.duration_since(*oldest)))
+            .unwrap_or(window);
+        warn!(
+            "🚦 Leader {} rate limiting '{}': {} requests in the last {}s (retry in {}ms)",
+            self.config.server.id,
+            client_name,
+            times.len(),
+            window.as_secs(),
+            retry_after.as_millis()
+        );
+        Some(retry_after)
+    }
+
+    /// The backup for `server_id`'s results: the next configured server by ID.
+    fn backup_for(&self, server_id: u32) -> Option<u32> {
+        let server_ids = std::iter::once(self.config.server.id)
+            .chain(self.config.peers.peers.iter().map(|peer| peer.id));
+        next_server_by_id(server_id, server_ids)
+    }
+
+    /// Whether all of this server's task slots are in use.
+    fn is_saturated(&self) -> bool {
+        self.task_slots.available_permits() == 0
+    }
+
+    /// Pick a server (possibly ourselves) for a new task from `client_name` with the
+    /// configured [`AssignmentStrategy`].
+    ///
+    /// Candidates are this server and every peer with a reported load. Each one's
+    /// load includes its assigned but unacknowledged tasks, so a burst of assignments
+    /// spreads out instead of all landing on the last heartbeat's winner.
+    ///
+    /// With `client_affinity` enabled, the client's previous server is chosen again
+    /// as long as it's still a candidate (not failed) and isn't saturated.
+    ///
+    /// # Returns
+    /// `(server_id, load)` of the chosen server
+    async fn select_server(&self, client_name: &str) -> (u32, f64) {
+        let candidates = self.assignment_candidates().await;
+
+        if !self.config.server.client_affinity {
+            let chosen = self.choose_with_strategy(&candidates);
+            return (chosen.id, chosen.load);
+        }
+
+        let mut affinity = self.client_affinity.write().await;
+        let sticky = affinity.get(client_name).and_then(|server_id| {
+            candidates
+                .iter()
+                .find(|candidate| candidate.id == *server_id && !candidate.saturated)
+        });
+        let chosen = match sticky {
+            Some(sticky) => *sticky,
+            None => {
+                let chosen = self.choose_with_strategy(&candidates);
+                affinity.insert(client_name.to_string(), chosen.id);
+                chosen
+            }
+        };
+        (chosen.id, chosen.load)
+    }
+
+    /// Apply the configured [`AssignmentStrategy`] to `candidates`.
+    fn choose_with_strategy(&self, candidates: &[Candidate]) -> Candidate {
+        let round = self.assignment_rounds.fetch_add(1, Ordering::Relaxed);
+        self.config
+            .server
+            .assignment_strategy
+            .choose(candidates, round)
+    }
+
+    /// This server and every peer with a reported load, in ID order.
+    async fn assignment_candidates(&self) -> Vec<Candidate> {
+        let peer_loads = self.peer_loads.read().await;
+        let saturated_peers = self.saturated_peers.read().await;
+        let pending = self.pending_assignments.read().await;
+
+        // Reported loads lag behind our assignments, so count unacknowledged tasks too
+        let candidate = |id: u32, load: f64, saturated: bool| {
+            let pending = pending.get(&id).copied().unwrap_or(0);
+            Candidate {
+                id,
+                load: load + self.metrics.task_load(pending),
+                pending,
+                saturated,
+            }
+        };
+
+        let mut candidates: Vec<Candidate> = peer_loads
+            .iter()
+            .map(|(peer_id, load)| candidate(*peer_id, *load, saturated_peers.contains(peer_id)))
+            .collect();
+        candidates.push(candidate(
+            self.config.server.id,
+            self.metrics.get_load(),
+            self.is_saturated(),
+        ));
+        candidates.sort_by_key(|candidate| candidate.id);
+        candidates
+    }
+
+    /// Look up a still-fresh cached response for a task.
+    async fn cached_result(&self, client_name: &str, request_id: u64) -> Option<Message> {
+        self.completed_results
+            .read()
+            .await
+            .get(&(client_name.to_string(), request_id))
+            .filter(|cached| {
+                cached.completed_at.elapsed() < Duration::from_secs(RESULT_CACHE_TTL_SECS)
+            })
+            .map(|cached| cached.response.clone())
+    }
+
+    /// Remember a successful response, evicting expired entries and then the oldest
+    /// ones to stay within [`RESULT_CACHE_CAPACITY`].
+    async fn cache_result(&self, client_name: String, request_id: u64, response: Message) {
+        let mut cache = self.completed_results.write().await;
+        cache.retain(|_, cached| {
+            cached.completed_at.elapsed() < Duration::from_secs(RESULT_CACHE_TTL_SECS)
+        });
+
+        while cache.len() >= RESULT_CACHE_CAPACITY {
+            let oldest = cache
+                .iter()
+                .min_by_key(|(_, cached)| cached.completed_at)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => cache.remove(&key),
+                None => break,
+            };
+        }
+
+        cache.insert(
+            (client_name, request_id),
+            CachedResult {
+                response,
+                completed_at: Instant::now(),
+            },
+        );
+    }
+
+    /// Broadcast a message to all connected peers.
+    ///
+    /// # Arguments
+    /// - `message`: The message to send (will be cloned for each peer)
+    ///
+    /// Messages are sent asynchronously via channels - this method returns
+    /// after queuing the messages, which for a peer with a full queue depends on
+    /// the message's [`PeerDelivery`].
+    async fn broadcast(&self, message: Message) {
+        let connections = self.peer_connections.read().await;
+        for (peer_id, tx) in connections.iter() {
+            self.queue_for_peer(*peer_id, tx, message.clone()).await;
+        }
+    }
+
+    /// Queue `message` on a peer's send channel according to its [`PeerDelivery`].
+    async fn queue_for_peer(&self, peer_id: u32, tx: &mpsc::Sender<Message>, message: Message) {
+        let result = match PeerDelivery::of(&message) {
+            PeerDelivery::Reliable => tx.send(message).await.map_err(|e| e.to_string()),
+            PeerDelivery::BestEffort => match tx.try_send(message) {
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!(
+                        "⚠️  Server {} dropped a heartbeat for peer {}: send queue full",
+                        self.config.server.id, peer_id
+                    );
+                    return;
+                }
+                result => result.map_err(|e| e.to_string()),
+            },
+        };
+
+        match result {
+            Ok(_) => {
+                debug!("📤 Sent message to peer {}", peer_id);
+            }
+            Err(e) => {
+                debug!("❌ Failed to send to peer {}: {}", peer_id, e);
+            }
+        }
+    }
+
+    /// Record a task assignment in our history and the on-disk log (if enabled).
+    ///
+    /// Does not notify peers; callers broadcast `HistoryAdd` themselves.
+    async fn insert_history(
+        &self,
+        client_name: String,
+        request_id: u64,
+        assigned_server_id: u32,
+        timestamp: u64,
+    ) {
+        self.history_clock.fetch_max(timestamp, Ordering::SeqCst);
+        if let Some(log) = &self.history_log {
+            if let Err(e) = log.record_add(&client_name, request_id, assigned_server_id, timestamp)
+            {
+                error!(
+                    "❌ Failed to persist history entry for task #{}: {}",
+                    request_id, e
+                );
+            }
+        }
+
+        let entry = TaskHistoryEntry {
+            _client_name: client_name.clone(),
+            _request_id: request_id,
+            assigned_server_id,
+            _timestamp: timestamp,
+        };
+        let mut history = self.task_history.write().await;
+        let previous = history.insert((client_name, request_id), entry);
+
+        // A reassigned task moves from its old server's count to the new one's
+        let mut pending = self.pending_assignments.write().await;
+        if let Some(previous) = previous {
+            release_pending(&mut pending, previous.assigned_server_id);
+        }
+        *pending.entry(assigned_server_id).or_insert(0) += 1;
+    }
+
+    /// Remove a task from our history and the on-disk log (if enabled), without
+    /// notifying peers.
+    ///
+    /// The removal is remembered for [`HISTORY_TOMBSTONE_TTL_SECS`], so a
+    /// `HistoryAdd` for the task stamped no later than `timestamp` that arrives
+    /// afterwards is ignored (see [`Self::removed_after`]). An entry stamped
+    /// after `timestamp` is a later assignment and is kept.
+    async fn remove_history(&self, client_name: String, request_id: u64, timestamp: u64) {
+        self.history_clock.fetch_max(timestamp, Ordering::SeqCst);
+
+        {
+            let now = current_timestamp_millis();
+            let mut removed_history = self.removed_history.write().await;
+            removed_history.retain(|_, removed_at| {
+                now.saturating_sub(*removed_at) < HISTORY_TOMBSTONE_TTL_SECS * 1000
+            });
+            let removed_at = removed_history
+                .entry((client_name.clone(), request_id))
+                .or_insert(timestamp);
+            *removed_at = (*removed_at).max(timestamp);
+        }
+
+        let mut history = self.task_history.write().await;
+        let key = (client_name, request_id);
+        if history
+            .get(&key)
+            .is_some_and(|entry| entry._timestamp > timestamp)
+        {
+            return;
+        }
+
+        if let Some(log) = &self.history_log {
+            if let Err(e) = log.record_remove(&key.0, request_id) {
+                error!(
+                    "❌ Failed to persist history removal for task #{}: {}",
+                    request_id, e
+                );
+            }
+        }
+
+        if let Some(removed) = history.remove(&key) {
+            release_pending(
+                &mut *self.pending_assignments.write().await,
+                removed.assigned_server_id,
+            );
+        }
+    }
+
+    /// Remove a task from our history and tell all peers to do the same.
+    ///
+    /// # Arguments
+    /// - `client_name`: Name of the client that submitted the task
+    /// - `request_id`: The task's request ID
+    async fn remove_task_from_history(&self, client_name: String, request_id: u64) {
+        let timestamp = self.next_history_timestamp();
+        self.remove_history(client_name.clone(), request_id, timestamp).await;
+
+        // Broadcast to all peers so they also remove it
+        self.broadcast(Message::HistoryRemove {
+            client_name,
+            request_id,
+            timestamp,
+        })
+        .await;
+    }
+
+    /// Whether the task was removed from history at or after `timestamp`, making
+    /// a `HistoryAdd` stamped `timestamp` a stale, reordered delivery.
+    async fn removed_after(&self, client_name: &str, request_id: u64, timestamp: u64) -> bool {
+        self.removed_history
+            .read()
+            .await
+            .get(&(client_name.to_string(), request_id))
+            .is_some_and(|removed_at| *removed_at >= timestamp)
+    }
+
+    /// Timestamp for a history change we make: the current time in Unix millis,
+    /// or just after the latest history timestamp we've stamped or seen if that's
+    /// later, so it orders after every change we know of.
+    fn next_history_timestamp(&self) -> u64 {
+        let now = current_timestamp_millis();
+        let stamp = |latest: u64| latest.saturating_add(1).max(now);
+        let latest = self
+            .history_clock
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |latest| {
+                Some(stamp(latest))
+            })
+            .unwrap_or_else(|latest| latest);
+        stamp(latest)
+    }
+
+    /// Drop a completed task from history if its `TaskAck` never arrived.
+    ///
+    /// Only removes the entry if it is still assigned to this server; if the task
+    /// was reassigned in the meantime, the new server owns its history entry.
+    ///
+    /// # Returns
+    /// `true` if the entry was expired
+    async fn expire_unacked_task(&self, client_name: String, request_id: u64) -> bool {
+        let still_ours = self
+            .task_history
+            .read()
+            .await
+            .get(&(client_name.clone(), request_id))
+            .is_some_and(|entry| entry.assigned_server_id == self.config.server.id);
+
+        if !still_ours {
+            return false;
+        }
+
+        warn!(
+            "⏰ Server {} never received ACK for task #{} from '{}' after {}s, removing from history",
+            self.config.server.id, request_id, client_name, ACK_TIMEOUT_SECS
+        );
+        self.remove_task_from_history(client_name, request_id).await;
+        true
+    }
+
+    /// Drop our outgoing link to a peer, making its link task reconnect.
+    ///
+    /// # Arguments
+    /// - `peer_id`: The ID of the peer
+    ///
+    /// On a half-open connection writes keep "succeeding" into the socket buffer
+    /// long before one times out, so messages would pile up undelivered. Removing
+    /// the sender closes the link's channel, which ends its send loop; anything
+    /// still queued is discarded.
+    async fn drop_peer_link(&self, peer_id: u32) {
+        if self.peer_connections.write().await.remove(&peer_id).is_some() {
+            warn!(
+                "🔌 Server {} dropped its link to peer {} to reconnect",
+                self.config.server.id, peer_id
+            );
+        }
+    }
+
+    /// Send a message to a specific peer.
+    ///
+    /// # Arguments
+    /// - `peer_id`: The ID of the target peer
+    /// - `message`: The message to send
+    ///
+    /// If the peer is not connected, the message is silently dropped.
+    async fn send_to_peer(&self, peer_id: u32, message: Message) {
+        let connections = self.peer_connections.read().await;
+        if let Some(tx) = connections.get(&peer_id) {
+            self.queue_for_peer(peer_id, tx, message).await;
+        } else {
+            debug!("❌ No connection to peer {}", peer_id);
+        }
+    }
+
+    /// Create an Arc-wrapped clone of this server.
+    ///
+    /// Needed because we need to pass the server to async tasks.
+    /// All fields are already Arc/RwLock wrapped, so this is cheap.
+    fn clone_arc(&self) -> Arc<Self> {
+        Arc::new(Self {
+            core: self.core.clone(),
+            config: self.config.clone(),
+            metrics: self.metrics.clone(),
+            current_leader: self.current_leader.clone(),
+            received_alive: self.received_alive.clone(),
+            current_term: self.current_term.clone(),
+            peer_connections: self.peer_connections.clone(),
+            last_heartbeat_times: self.last_heartbeat_times.clone(),
+            heartbeat_detectors: self.heartbeat_detectors.clone(),
+            failed_peers: self.failed_peers.clone(),
+            started_at: self.started_at,
+            active_tasks: self.active_tasks.clone(),
+            peer_loads: self.peer_loads.clone(),
+            saturated_peers: self.saturated_peers.clone(),
+            peer_total_tasks: self.peer_total_tasks.clone(),
+            peer_leaders: self.peer_leaders.clone(),
+            task_slots: self.task_slots.clone(),
+            completed_results: self.completed_results.clone(),
+            task_history: self.task_history.clone(),
+            removed_history: self.removed_history.clone(),
+            history_clock: self.history_clock.clone(),
+            pending_assignments: self.pending_assignments.clone(),
+            history_sync_responses: self.history_sync_responses.clone(),
+            history_log: self.history_log.clone(),
+            pending_forwards: self.pending_forwards.clone(),
+            client_request_times: self.client_request_times.clone(),
+            election_events: self.election_events.clone(),
+            is_failed: self.is_failed.clone(),
+            assignment_rounds: self.assignment_rounds.clone(),
+            leader_changed_at: self.leader_changed_at.clone(),
+            client_affinity: self.client_affinity.clone(),
+        })
+    }
+
+    /// Process an encryption task by delegating to ServerCore.
+    ///
+    /// # Arguments
+    /// - `request_id`: Unique identifier for this task
+    /// - `client_name`: Name of the client that submitted this task
+    /// - `secret_image_data`: Raw image bytes (the secret image to hide)
+    /// - `metadata`: Text to embed alongside the secret, if the client sent any
+    /// - `response_tx`: Optional channel to send response on
+    /// - `report_progress`: Also send `TaskProgress` messages on `response_tx` while
+    ///   embedding, ahead of the response
+    ///
+    /// # Process
+    ///
+    /// 1. If this `(client_name, request_id)` was completed recently, reply with the
+    ///    cached response instead of encrypting again
+    /// 2. Take a task slot; if all [`ServerInfo::max_concurrent_tasks`] are in use,
+    ///    drop the task from history and reply with `TaskRejected` instead
+    /// 3. Increment active task counter (for load calculation)
+    /// 4. Spawn async task to perform encryption via ServerCore (embedding secret into carrier),
+    ///    cancelling it with a failed `TaskResponse` after
+    ///    [`ServerInfo::task_timeout_secs`]
+    /// 5. Cache a successful response, replicate it to the backup server (if
+    ///    [`ServerInfo::replicate_results`] is set), and send it back through
+    ///    channel (if provided)
+    /// 6. Decrement active task counter and release the slot
+    /// 7. Keep the task in history until the client's `TaskAck` arrives, or remove
+    ///    it after [`ACK_TIMEOUT_SECS`] if the ACK never comes
+    ///
+    /// The encryption is performed in a blocking thread pool via ServerCore
+    /// to avoid blocking the async runtime. A timed-out embed can't be interrupted
+    /// there, but its slot is freed and its result discarded.
+    ///
+    /// Runs in a `task` span keyed by `(client, request_id)`, which the background
+    /// encryption and ACK-expiry tasks inherit.
+    #[instrument(
+        name = "task",
+        skip_all,
+        fields(client = %client_name, request_id = request_id)
+    )]
+    async fn process_task(
+        &self,
+        request_id: u64,
+        client_name: String,
+        secret_image_data: Vec<u8>,
+        metadata: Option<String>,
         response_tx: Option<mpsc::Sender<Message>>,
+        report_progress: bool,
     ) {
-        // START TRACKING: Increment active task count
-        self.metrics.task_started();
+        // IDEMPOTENCY: A resubmitted task we already finished gets the same answer
+        if let Some(response) = self.cached_result(&client_name, request_id).await {
+            info!(
+                "🔁 Server {} returning cached result for task #{} from '{}'",
+                self.config.server.id, request_id, client_name
+            );
+            if let Some(tx) = response_tx {
+                if let Err(e) = tx.send(response).await {
+                    error!("❌ Failed to send response: {}", e);
+                }
+            }
+            return;
+        }
+
+        // BACKPRESSURE: Refuse the task if every slot is busy
+        let Ok(permit) = self.task_slots.clone().try_acquire_owned() else {
+            warn!(
+                "🚫 Server {} rejecting task #{} from '{}': all {} task slots in use",
+                self.config.server.id,
+                request_id,
+                client_name,
+                self.config.server.max_concurrent_tasks
+            );
+
+            // Forget the assignment so the client's resubmission gets a fresh one
+            self.remove_task_from_history(client_name, request_id).await;
+
+            if let Some(tx) = response_tx {
+                let rejection = Message::TaskRejected {
+                    request_id,
+                    reason: format!(
+                        "Server {} is at capacity ({} concurrent tasks)",
+                        self.config.server.id, self.config.server.max_concurrent_tasks
+                    ),
+                };
+                if let Err(e) = tx.send(rejection).await {
+                    error!("❌ Failed to send rejection: {}", e);
+                }
+            }
+            return;
+        };
+
+        // START TRACKING: Increment active task count (until `running` is dropped)
+        let running = RunningTask::start(&self.metrics, permit);
 
         let current_tasks = self.metrics.get_active_tasks();
         let cpu_usage = self.metrics.get_cpu_usage();
@@ -1448,7 +3609,9 @@ impl ServerMiddleware {
             self.config.server.id, request_id, current_tasks, cpu_usage
         );
 
-        // Process task in background
+        // Process task in background. The map stays locked until its handle is in, so a
+        // task that finishes right away can't try to remove its entry before that.
+        let mut active_tasks = self.active_tasks.write().await;
         let server = self.clone_arc();
         let handle = tokio::spawn(async move {
             info!(
@@ -1456,11 +3619,31 @@ impl ServerMiddleware {
                 server.config.server.id, request_id, client_name
             );
 
-            // Delegate to ServerCore for actual encryption
-            let encryption_result = server
-                .core
-                .encrypt_image(request_id, client_name.clone(), secret_image_data)
-                .await;
+            // Delegate to ServerCore for actual encryption, giving up after the timeout
+            let timeout_secs = server.config.server.task_timeout_secs;
+            let progress = response_tx.clone().filter(|_| report_progress).map(|progress_tx| {
+                // A full channel means the connection is behind: skip that update
+                Box::new(move |percent| {
+                    let _ = progress_tx.try_send(Message::TaskProgress { request_id, percent });
+                }) as Box<dyn Fn(u8) + Send + Sync>
+            });
+            let encryption = server.core.encrypt(
+                request_id,
+                client_name.clone(),
+                secret_image_data,
+                None,
+                metadata,
+                progress,
+            );
+            let encryption_result =
+                match tokio::time::timeout(Duration::from_secs(timeout_secs), encryption).await {
+                    Ok(result) => result,
+                    Err(_) => Err(anyhow::anyhow!(
+                        "Task #{} timed out after {}s and was cancelled",
+                        request_id,
+                        timeout_secs
+                    )),
+                };
 
             let response = match encryption_result {
                 Ok(encrypted_data) => {
@@ -1491,6 +3674,24 @@ impl ServerMiddleware {
                 }
             };
 
+            if let Message::TaskResponse { success: true, .. } = response {
+                server
+                    .cache_result(client_name.clone(), request_id, response.clone())
+                    .await;
+
+                // Back the result up before the client can ACK it
+                if server.config.server.replicate_results {
+                    if let Some(backup) = server.backup_for(server.config.server.id) {
+                        let replica = Message::ResultReplicate {
+                            client_name: client_name.clone(),
+                            request_id,
+                            response: Box::new(response.clone()),
+                        };
+                        server.send_to_peer(backup, replica).await;
+                    }
+                }
+            }
+
             // Send response if channel exists
             if let Some(tx) = response_tx {
                 if let Err(e) = tx.send(response).await {
@@ -1502,9 +3703,19 @@ impl ServerMiddleware {
             // Task history will only be removed when we receive a TaskAck from the client,
             // ensuring the client actually received the response.
             // This prevents orphaned work if the TaskResponse is lost in transit.
+            // If the ACK itself is lost, the entry expires after ACK_TIMEOUT_SECS.
+            let expiry = server.clone_arc();
+            let expiry_client = client_name.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(ACK_TIMEOUT_SECS)).await;
+                expiry.expire_unacked_task(expiry_client, request_id).await;
+            }
+            .in_current_span());
 
-            // FINISH TRACKING: Decrement active task count
-            server.metrics.task_finished();
+            // FINISH TRACKING: Forget our handle, decrement active task count and free
+            // the slot
+            server.forget_task_handle(request_id).await;
+            drop(running);
 
             let remaining_tasks = server.metrics.get_active_tasks();
             let new_cpu = server.metrics.get_cpu_usage();
@@ -1513,9 +3724,2044 @@ impl ServerMiddleware {
                 "✅ Server {} completed task #{} (Remaining tasks: {}, CPU: {:.1}%)",
                 server.config.server.id, request_id, remaining_tasks, new_cpu
             );
-        });
+        }
+        .in_current_span());
 
         // Track the task handle
-        self.active_tasks.write().await.insert(request_id, handle);
+        active_tasks.insert(request_id, handle);
+    }
+
+    /// Remove the calling task's own handle from `active_tasks`.
+    ///
+    /// Another task with the same request ID (from a different client) may have
+    /// replaced it in the map, in which case that one's handle is left alone.
+    async fn forget_task_handle(&self, request_id: u64) {
+        let mut active_tasks = self.active_tasks.write().await;
+        if active_tasks
+            .get(&request_id)
+            .is_some_and(|handle| handle.id() == tokio::task::id())
+        {
+            active_tasks.remove(&request_id);
+        }
+    }
+
+    /// Abort the running encryption task for `request_id`.
+    ///
+    /// The task stops without replying to its client, which will resubmit it after
+    /// polling for its status; its task slot and active-task count are released
+    /// once it's dropped.
+    ///
+    /// # Returns
+    /// `true` if a running task was cancelled, `false` if none was running
+    ///
+    /// # Example
+    /// ```ignore
+    /// if middleware.cancel_task(request_id).await {
+    ///     info!("Cancelled task #{}", request_id);
+    /// }
+    /// ```
+    pub async fn cancel_task(&self, request_id: u64) -> bool {
+        let Some(handle) = self.active_tasks.write().await.remove(&request_id) else {
+            return false;
+        };
+        handle.abort();
+
+        info!(
+            "🛑 Server {} cancelled task #{}",
+            self.config.server.id, request_id
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Build the config for server `id` with the given peer IDs.
+    fn test_config(id: u32, peer_ids: &[u32]) -> ServerConfig {
+        let peers = peer_ids
+            .iter()
+            .map(|peer| format!("{{ id = {}, address = \"127.0.0.1:0\" }}", peer))
+            .collect::<Vec<_>>()
+            .join(", ");
+        toml::from_str(&format!(
+            r#"
+            [server]
+            id = {}
+            address = "127.0.0.1:0"
+
+            [peers]
+            peers = [{}]
+
+            [election]
+            heartbeat_interval_secs = 1
+            election_timeout_secs = 2
+            failure_timeout_secs = 5
+            monitor_interval_secs = 1
+            "#,
+            id, peers
+        ))
+        .unwrap()
+    }
+
+    /// Build a middleware for server `id` with the given peer IDs (not started).
+    fn test_middleware(id: u32, peer_ids: &[u32]) -> ServerMiddleware {
+        let config = test_config(id, peer_ids);
+        ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(id, Vec::new())))
+    }
+
+    /// Open a loopback connection to pass as the `conn` argument of `handle_message`.
+    async fn loopback_connection() -> (Connection<Stream>, Connection<Stream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (client, server) = tokio::join!(TcpStream::connect(address), listener.accept());
+        (
+            Connection::new(client.unwrap().into()),
+            Connection::new(server.unwrap().0.into()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_full_peer_queue_drops_heartbeats_but_not_elections() {
+        let server = test_middleware(1, &[2]);
+        let (tx, mut rx) = mpsc::channel(1);
+        server.peer_connections.write().await.insert(2, tx);
+        let heartbeat = Message::Heartbeat {
+            from_id: 1,
+            timestamp: 100,
+            load: 0.0,
+            saturated: false,
+            total_tasks: 0,
+            leader_id: None,
+            term: 0,
+        };
+        let election = Message::Election {
+            from_id: 1,
+            priority: 0.0,
+            term: 1,
+        };
+
+        // Peer 2 isn't draining its queue, which is already full
+        server.broadcast(heartbeat.clone()).await;
+        tokio::time::timeout(Duration::from_secs(1), server.broadcast(heartbeat))
+            .await
+            .expect("heartbeat broadcast blocked on a full queue");
+
+        // An election waits for room instead of being dropped
+        let sending = server.broadcast(election);
+        tokio::pin!(sending);
+        assert!(tokio::time::timeout(Duration::from_millis(100), &mut sending)
+            .await
+            .is_err());
+        assert!(matches!(rx.recv().await, Some(Message::Heartbeat { .. })));
+        sending.await;
+        assert!(matches!(rx.recv().await, Some(Message::Election { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_dropped_peer_link_reconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config = test_config(1, &[2]);
+        config.peers.peers[0].address = listener.local_addr().unwrap().to_string();
+        let server = Arc::new(ServerMiddleware::new(
+            config,
+            Arc::new(ServerCore::from_bytes(1, Vec::new())),
+        ));
+        let linker = server.clone();
+        let links = tokio::spawn(async move { linker.connect_to_peers().await });
+
+        // Peer 2 accepts the link but never reads from it, like a half-open socket
+        let (socket, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept())
+            .await
+            .expect("no link to peer 2")
+            .unwrap();
+        let mut stale = Connection::new(socket);
+        stale.accept_handshake().await.unwrap();
+        while !server.peer_connections.read().await.contains_key(&2) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        server.drop_peer_link(2).await;
+        let (socket, _) = tokio::time::timeout(Duration::from_secs(5), listener.accept())
+            .await
+            .expect("link to peer 2 wasn't re-established")
+            .unwrap();
+        Connection::new(socket).accept_handshake().await.unwrap();
+        links.abort();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_addresses_serve_and_link_peers() {
+        let dir = tempfile::tempdir().unwrap();
+        let own_path = dir.path().join("server1.sock");
+        let peer_path = dir.path().join("server2.sock");
+        let mut config = test_config(1, &[2]);
+        config.server.address = own_path.to_str().unwrap().to_string();
+        config.peers.peers[0].address = peer_path.to_str().unwrap().to_string();
+        assert!(config.validate().is_ok());
+        let server = Arc::new(ServerMiddleware::new(
+            config,
+            Arc::new(ServerCore::from_bytes(1, Vec::new())),
+        ));
+        *server.current_leader.write().await = Some(1);
+
+        let peer = Listener::bind(peer_path.to_str().unwrap()).await.unwrap();
+        let (listening, linking) = (server.clone(), server.clone());
+        let listener = tokio::spawn(async move { listening.start_listener().await });
+        let links = tokio::spawn(async move { linking.connect_to_peers().await });
+
+        // The link to peer 2 goes over its socket rather than TCP
+        let (socket, _) = tokio::time::timeout(Duration::from_secs(5), peer.accept())
+            .await
+            .expect("no link to peer 2")
+            .unwrap();
+        assert!(matches!(socket, Stream::Unix(_)));
+        Connection::new(socket).accept_handshake().await.unwrap();
+
+        // And the server answers on its own socket
+        let stream = Stream::connect(own_path.to_str().unwrap()).await.unwrap();
+        let mut client = Connection::new(stream);
+        client.handshake().await.unwrap();
+        client.write_message(&Message::LeaderQuery).await.unwrap();
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::LeaderResponse { leader_id: 1 })
+        ));
+        listener.abort();
+        links.abort();
+    }
+
+    #[tokio::test]
+    async fn test_connection_survives_malformed_message() {
+        let server = Arc::new(test_middleware(2, &[1]));
+        *server.current_leader.write().await = Some(1);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let handler = server.clone();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            handler.handle_connection(socket.into()).await;
+        });
+
+        let mut client = Connection::new(TcpStream::connect(address).await.unwrap());
+        client.handshake().await.unwrap();
+        client.write_message(&Message::LeaderQuery).await.unwrap();
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::LeaderResponse { leader_id: 1 })
+        ));
+
+        // A JSON-tagged frame that isn't JSON is skipped; the next query is answered
+        client.write_raw_frame(b"\x00not json").await.unwrap();
+        client.write_message(&Message::LeaderQuery).await.unwrap();
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::LeaderResponse { leader_id: 1 })
+        ));
+    }
+
+    /// A `TaskRequest` from Client1 claiming it was assigned by `assigned_by_leader`.
+    fn task_request(request_id: u64, assigned_by_leader: u32) -> Message {
+        Message::TaskRequest {
+            client_name: "Client1".to_string(),
+            request_id,
+            secret_image_data: vec![9u8; 100],
+            assigned_by_leader,
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_request_from_another_leader_is_rejected() {
+        let server = test_middleware(2, &[1, 3]);
+        *server.current_leader.write().await = Some(1);
+        let (mut client, mut conn) = loopback_connection().await;
+
+        server.handle_message(task_request(1, 3), &mut conn).await;
+        match client.read_message().await.unwrap() {
+            Some(Message::TaskRejected { request_id: 1, reason }) => {
+                assert!(reason.contains("leader is Server 1"), "{}", reason)
+            }
+            other => panic!("Unexpected message: {:?}", other),
+        }
+        assert!(server.active_tasks.read().await.is_empty());
+
+        server.handle_message(task_request(2, 1), &mut conn).await;
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::TaskResponse { request_id: 2, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_task_request_accepted_while_leader_unknown_or_just_changed() {
+        let server = test_middleware(2, &[1, 3]);
+        let (mut client, mut conn) = loopback_connection().await;
+
+        // No leader known yet
+        server.handle_message(task_request(1, 3), &mut conn).await;
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::TaskResponse { request_id: 1, .. })
+        ));
+
+        // Right after following a new leader, the client may still know the old one
+        *server.current_leader.write().await = Some(1);
+        server.emit_election_event(ElectionEvent::LeaderChanged { leader_id: Some(1) });
+        server.handle_message(task_request(2, 3), &mut conn).await;
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::TaskResponse { request_id: 2, .. })
+        ));
+
+        // Once the grace period is over, the old leader's assignments are refused
+        server.leader_changed_at.store(
+            current_timestamp_millis() - LEADER_CHANGE_GRACE_SECS * 1000,
+            Ordering::SeqCst,
+        );
+        assert_eq!(server.rejects_assignment_from(3).await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_ping_is_answered_with_its_nonce() {
+        let server = test_middleware(1, &[2]);
+        let (mut client, mut conn) = loopback_connection().await;
+        server
+            .handle_message(Message::Ping { nonce: 7 }, &mut conn)
+            .await;
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::Pong { nonce: 7 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_recovery_request_is_answered_by_the_leader_only() {
+        let leader = test_middleware(1, &[2, 3]);
+        *leader.current_leader.write().await = Some(1);
+        *leader.current_term.write().await = 4;
+        leader.insert_history("Client1".to_string(), 7, 3, 100).await;
+
+        let (mut requester, mut conn) = loopback_connection().await;
+        leader
+            .handle_message(Message::RecoveryRequest { from_id: 2 }, &mut conn)
+            .await;
+        match requester.read_message().await.unwrap() {
+            Some(Message::StateSync {
+                leader_id: 1,
+                term: 4,
+                history_entries,
+            }) => assert_eq!(history_entries, vec![("Client1".to_string(), 7, 3, 100)]),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+
+        // A follower stays quiet
+        let follower = test_middleware(3, &[1, 2]);
+        *follower.current_leader.write().await = Some(1);
+        let (mut requester, mut conn) = loopback_connection().await;
+        follower
+            .handle_message(Message::RecoveryRequest { from_id: 2 }, &mut conn)
+            .await;
+        drop(conn);
+        assert!(requester.read_message().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_state_sync_follows_leader_and_merges_newer_history() {
+        let (events_tx, mut events) = mpsc::channel(10);
+        let server = test_middleware(2, &[1, 3]).with_election_events(events_tx);
+        server.insert_history("Client1".to_string(), 1, 2, 500).await;
+        server.insert_history("Client1".to_string(), 2, 2, 100).await;
+        server.remove_history("Client1".to_string(), 3, 350).await;
+
+        let (_peer_end, mut conn) = loopback_connection().await;
+        server
+            .handle_message(
+                Message::StateSync {
+                    leader_id: 1,
+                    term: 4,
+                    history_entries: vec![
+                        ("Client1".to_string(), 1, 3, 400),
+                        ("Client1".to_string(), 2, 3, 200),
+                        ("Client1".to_string(), 3, 3, 300),
+                        ("Client2".to_string(), 1, 1, 300),
+                    ],
+                },
+                &mut conn,
+            )
+            .await;
+
+        assert_eq!(*server.current_leader.read().await, Some(1));
+        assert_eq!(*server.current_term.read().await, 4);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ElectionEvent::LeaderChanged { leader_id: Some(1) }
+        );
+        let mut history = server.history_entries().await;
+        history.sort();
+        assert_eq!(
+            history,
+            vec![
+                ("Client1".to_string(), 1, 2, 500),
+                ("Client1".to_string(), 2, 3, 200),
+                ("Client2".to_string(), 1, 1, 300),
+            ]
+        );
+
+        // State from an older term is ignored
+        server
+            .handle_message(
+                Message::StateSync {
+                    leader_id: 3,
+                    term: 3,
+                    history_entries: Vec::new(),
+                },
+                &mut conn,
+            )
+            .await;
+        assert_eq!(*server.current_leader.read().await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_history_sync_request_is_answered_over_peer_channel() {
+        let follower = test_middleware(2, &[1]);
+        follower.task_history.write().await.insert(
+            ("Client1".to_string(), 7),
+            TaskHistoryEntry {
+                _client_name: "Client1".to_string(),
+                _request_id: 7,
+                assigned_server_id: 3,
+                _timestamp: 100,
+            },
+        );
+
+        // Stand in for the follower's outgoing connection to the leader
+        let (tx, mut rx) = mpsc::channel(10);
+        follower.peer_connections.write().await.insert(1, tx);
+
+        let (_leader_side, mut conn) = loopback_connection().await;
+        follower
+            .handle_message(Message::HistorySyncRequest { from_server_id: 1 }, &mut conn)
+            .await;
+
+        let response = rx
+            .try_recv()
+            .expect("response should be sent to the leader");
+        let entries = match response {
+            Message::HistorySyncResponse {
+                from_server_id: 2,
+                history_entries,
+            } => history_entries,
+            other => panic!("Unexpected message: {:?}", other),
+        };
+        assert_eq!(entries, vec![("Client1".to_string(), 7, 3, 100)]);
+
+        // The leader stores the response for merging
+        let leader = test_middleware(1, &[2]);
+        leader
+            .handle_message(
+                Message::HistorySyncResponse {
+                    from_server_id: 2,
+                    history_entries: entries.clone(),
+                },
+                &mut conn,
+            )
+            .await;
+        assert_eq!(*leader.history_sync_responses.read().await, vec![entries]);
+    }
+
+    /// History entry for `Client1`'s task `request_id`, assigned to `server_id`.
+    fn history_entry(request_id: u64, server_id: u32) -> TaskHistoryEntry {
+        TaskHistoryEntry {
+            _client_name: "Client1".to_string(),
+            _request_id: request_id,
+            assigned_server_id: server_id,
+            _timestamp: 100,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_ack_removes_history_and_broadcasts() {
+        let server = test_middleware(1, &[2]);
+        let (tx, mut rx) = mpsc::channel(10);
+        server.peer_connections.write().await.insert(2, tx);
+        server
+            .task_history
+            .write()
+            .await
+            .insert(("Client1".to_string(), 5), history_entry(5, 1));
+
+        let (_client_side, mut conn) = loopback_connection().await;
+        server
+            .handle_message(
+                Message::TaskAck {
+                    client_name: "Client1".to_string(),
+                    request_id: 5,
+                },
+                &mut conn,
+            )
+            .await;
+
+        assert!(server.task_history.read().await.is_empty());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Message::HistoryRemove { request_id: 5, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unacked_task_expires_only_if_still_ours() {
+        let server = test_middleware(1, &[2]);
+        {
+            let mut history = server.task_history.write().await;
+            history.insert(("Client1".to_string(), 1), history_entry(1, 1));
+            history.insert(("Client1".to_string(), 2), history_entry(2, 2));
+        }
+
+        // Still assigned to us with no ACK: expired
+        assert!(server.expire_unacked_task("Client1".to_string(), 1).await);
+        // Reassigned to server 2: left for its new owner
+        assert!(!server.expire_unacked_task("Client1".to_string(), 2).await);
+        // Already ACKed (gone): nothing to do
+        assert!(!server.expire_unacked_task("Client1".to_string(), 1).await);
+
+        let history = server.task_history.read().await;
+        assert_eq!(history.len(), 1);
+        assert!(history.contains_key(&("Client1".to_string(), 2)));
+    }
+
+    #[tokio::test]
+    async fn test_history_is_restored_from_log_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(2, &[1]);
+        config.server.history_log_path = Some(
+            dir.path()
+                .join("history.jsonl")
+                .to_string_lossy()
+                .into_owned(),
+        );
+
+        let core = Arc::new(ServerCore::from_bytes(2, Vec::new()));
+        let server = ServerMiddleware::new(config.clone(), core.clone());
+        let (_peer_side, mut conn) = loopback_connection().await;
+        for request_id in 1..=3 {
+            server
+                .handle_message(
+                    Message::HistoryAdd {
+                        client_name: "Client1".to_string(),
+                        request_id,
+                        assigned_server_id: 3,
+                        timestamp: 100 + request_id,
+                    },
+                    &mut conn,
+                )
+                .await;
+        }
+        server
+            .handle_message(
+                Message::HistoryRemove {
+                    client_name: "Client1".to_string(),
+                    request_id: 2,
+                    timestamp: 200,
+                },
+                &mut conn,
+            )
+            .await;
+        drop(server);
+
+        // A fresh middleware pointed at the same log picks up where the old one left off
+        let restarted = ServerMiddleware::new(config, core);
+        let history = restarted.task_history.read().await;
+        let mut restored: Vec<_> = history
+            .iter()
+            .map(|((client, id), entry)| {
+                (
+                    client.clone(),
+                    *id,
+                    entry.assigned_server_id,
+                    entry._timestamp,
+                )
+            })
+            .collect();
+        restored.sort();
+        assert_eq!(
+            restored,
+            vec![
+                ("Client1".to_string(), 1, 3, 101),
+                ("Client1".to_string(), 3, 3, 103),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_history_remove_delivered_before_its_add_wins() {
+        let server = test_middleware(2, &[1, 3]);
+        let (_peer_side, mut conn) = loopback_connection().await;
+        let added_at = current_timestamp_millis();
+        let add = Message::HistoryAdd {
+            client_name: "Client1".to_string(),
+            request_id: 4,
+            assigned_server_id: 3,
+            timestamp: added_at,
+        };
+        let remove = Message::HistoryRemove {
+            client_name: "Client1".to_string(),
+            request_id: 4,
+            timestamp: added_at,
+        };
+
+        // The Remove overtook its Add; applying either twice changes nothing
+        for message in [remove.clone(), remove, add.clone(), add] {
+            server.handle_message(message, &mut conn).await;
+        }
+        assert!(server.task_history.read().await.is_empty());
+        assert_eq!(server.pending_assignments.read().await.get(&3), None);
+    }
+
+    #[tokio::test]
+    async fn test_history_resubmission_right_after_removal_is_kept_everywhere() {
+        let leader = test_middleware(1, &[2, 3]);
+        let (peer_tx, mut peer_rx) = mpsc::channel(10);
+        leader.peer_connections.write().await.insert(2, peer_tx);
+        let (_peer_side, mut conn) = loopback_connection().await;
+
+        leader.record_assignment("Client1".to_string(), 4, 3).await;
+        let add = peer_rx.try_recv().unwrap();
+        let Message::HistoryAdd {
+            timestamp: added_at,
+            ..
+        } = add
+        else {
+            panic!("expected HistoryAdd, got {:?}", add);
+        };
+
+        // Server 3 rejects the task within the same millisecond, and the
+        // client resubmits it straight away
+        let remove = Message::HistoryRemove {
+            client_name: "Client1".to_string(),
+            request_id: 4,
+            timestamp: added_at,
+        };
+        leader.handle_message(remove.clone(), &mut conn).await;
+        leader.record_assignment("Client1".to_string(), 4, 3).await;
+        let readd = peer_rx.try_recv().unwrap();
+        assert!(leader
+            .task_history
+            .read()
+            .await
+            .contains_key(&("Client1".to_string(), 4)));
+
+        // Followers end up agreeing with the leader whichever way the
+        // Remove and the new Add are delivered
+        for messages in [
+            [add.clone(), remove.clone(), readd.clone()],
+            [add.clone(), readd.clone(), remove.clone()],
+        ] {
+            let follower = test_middleware(2, &[1, 3]);
+            for message in messages {
+                follower.handle_message(message, &mut conn).await;
+            }
+            assert_eq!(
+                follower.task_history.read().await[&("Client1".to_string(), 4)]
+                    .assigned_server_id,
+                3
+            );
+            assert_eq!(follower.pending_assignments.read().await.get(&3), Some(&1));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_rejected_when_all_slots_busy() {
+        let mut config = test_config(1, &[2]);
+        config.server.max_concurrent_tasks = 1;
+        let server = ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+        let (peer_tx, mut peer_rx) = mpsc::channel(10);
+        server.peer_connections.write().await.insert(2, peer_tx);
+        server
+            .task_history
+            .write()
+            .await
+            .insert(("Client1".to_string(), 9), history_entry(9, 1));
+
+        // Occupy the only slot
+        let _busy = server.task_slots.clone().try_acquire_owned().unwrap();
+        assert!(server.is_saturated());
+
+        let (tx, mut rx) = mpsc::channel(1);
+        server
+            .process_task(9, "Client1".to_string(), vec![1, 2, 3], None, Some(tx), false)
+            .await;
+
+        assert!(matches!(
+            rx.recv().await,
+            Some(Message::TaskRejected { request_id: 9, .. })
+        ));
+        assert!(server.active_tasks.read().await.is_empty());
+        assert_eq!(server.metrics.get_active_tasks(), 0);
+
+        // The assignment is forgotten everywhere so a resubmission is reassigned
+        assert!(server.task_history.read().await.is_empty());
+        assert!(matches!(
+            peer_rx.try_recv(),
+            Ok(Message::HistoryRemove { request_id: 9, .. })
+        ));
+    }
+
+    #[test]
+    fn test_config_validation_lists_every_problem() {
+        assert!(test_config(1, &[2, 3]).validate().is_ok());
+
+        let mut config = test_config(1, &[1, 2, 2]);
+        config.server.address = "localhost".to_string();
+        config.peers.peers[1].address = "10.0.0.2".to_string();
+        config.election.failure_timeout_secs = 0;
+        config.server.max_concurrent_tasks = 0;
+
+        let message = config.validate().unwrap_err().to_string();
+        for problem in [
+            "server.address 'localhost' is not a valid ip:port address or socket path",
+            "peer id 1 is this server's own id",
+            "peer id 2 is listed more than once",
+            "address '10.0.0.2' of peer 2 is not a valid ip:port address or socket path",
+            "election.failure_timeout_secs must be greater than 0",
+            "server.max_concurrent_tasks must be greater than 0",
+        ] {
+            assert!(message.contains(problem), "missing {:?} in:\n{}", problem, message);
+        }
+        assert_eq!(message.lines().count(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_load_query_is_answered_with_own_metrics() {
+        let server = test_middleware(2, &[1]);
+        *server.current_leader.write().await = Some(1);
+        server.metrics.task_started();
+
+        let (mut client, mut conn) = loopback_connection().await;
+        server.handle_message(Message::LoadQuery, &mut conn).await;
+
+        match client.read_message().await.unwrap() {
+            Some(Message::LoadResponse {
+                server_id,
+                active_tasks,
+                total_tasks,
+                saturated,
+                leader_id,
+                ..
+            }) => {
+                assert_eq!((server_id, active_tasks, total_tasks), (2, 1, 1));
+                assert!(!saturated);
+                assert_eq!(leader_id, Some(1));
+            }
+            other => panic!("Expected LoadResponse, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_simulate_fail_plays_dead_then_recovers() {
+        let server = test_middleware(1, &[2, 3]);
+        let (mut conn, _peer_end) = loopback_connection().await;
+
+        server
+            .handle_message(Message::SimulateFail { duration_secs: 1 }, &mut conn)
+            .await;
+        assert!(server.is_failed.load(Ordering::SeqCst));
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!server.is_failed.load(Ordering::SeqCst));
+
+        // Peers' failure timers restart on recovery instead of all timing out at once
+        let heartbeats = server.last_heartbeat_times.read().await.clone();
+        assert!(heartbeats.contains_key(&2) && heartbeats.contains_key(&3));
+        assert!(server
+            .detect_failed_peers(current_timestamp_millis())
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_carries_lifetime_task_count() {
+        let leader = test_middleware(1, &[2]);
+        let peer = test_middleware(2, &[1]);
+        for _ in 0..3 {
+            peer.metrics.task_started();
+            peer.metrics.task_finished();
+        }
+
+        exchange_heartbeats(&leader, &peer).await;
+
+        assert_eq!(leader.peer_total_tasks.read().await.get(&2), Some(&3));
+        assert_eq!(peer.peer_total_tasks.read().await.get(&1), Some(&0));
+    }
+
+    #[tokio::test]
+    async fn test_select_server_skips_saturated_peers() {
+        let server = test_middleware(1, &[2, 3]);
+        server
+            .peer_loads
+            .write()
+            .await
+            .extend([(2, -1.0), (3, f64::MAX)]);
+
+        // Idle peer 2 wins outright
+        assert_eq!(server.select_server("Client1").await.0, 2);
+
+        // Once saturated, the next best unsaturated server is chosen instead
+        server.saturated_peers.write().await.insert(2);
+        assert_eq!(server.select_server("Client1").await.0, 1);
+
+        // With every server saturated, fall back to the least loaded
+        server.saturated_peers.write().await.insert(3);
+        let _busy = server
+            .task_slots
+            .clone()
+            .try_acquire_many_owned(server.config.server.max_concurrent_tasks as u32)
+            .unwrap();
+        assert_eq!(server.select_server("Client1").await.0, 2);
+    }
+
+    /// Candidates 1..=3: Server 2 is least loaded, Server 3 has the fewest pending tasks.
+    fn strategy_candidates() -> Vec<Candidate> {
+        [(1, 50.0, 2), (2, 20.0, 4), (3, 30.0, 0)]
+            .into_iter()
+            .map(|(id, load, pending)| Candidate {
+                id,
+                load,
+                pending,
+                saturated: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lowest_load_strategy_picks_least_loaded() {
+        let candidates = strategy_candidates();
+        assert_eq!(AssignmentStrategy::LowestLoad.choose(&candidates, 0).id, 2);
+    }
+
+    #[test]
+    fn test_least_connections_strategy_picks_fewest_pending() {
+        let mut candidates = strategy_candidates();
+        assert_eq!(
+            AssignmentStrategy::LeastConnections.choose(&candidates, 0).id,
+            3
+        );
+
+        // Ties on pending tasks go to the lower load
+        candidates[0].pending = 0;
+        candidates[0].load = 10.0;
+        assert_eq!(
+            AssignmentStrategy::LeastConnections.choose(&candidates, 0).id,
+            1
+        );
+    }
+
+    #[test]
+    fn test_round_robin_strategy_cycles_unsaturated_servers() {
+        let mut candidates = strategy_candidates();
+        let picks: Vec<u32> = (0..4)
+            .map(|round| AssignmentStrategy::RoundRobin.choose(&candidates, round).id)
+            .collect();
+        assert_eq!(picks, vec![1, 2, 3, 1]);
+
+        candidates[1].saturated = true;
+        let picks: Vec<u32> = (0..4)
+            .map(|round| AssignmentStrategy::RoundRobin.choose(&candidates, round).id)
+            .collect();
+        assert_eq!(picks, vec![1, 3, 1, 3]);
+    }
+
+    #[test]
+    fn test_random_strategy_picks_only_unsaturated_servers() {
+        let mut candidates = strategy_candidates();
+        candidates[0].saturated = true;
+        let picks: HashSet<u32> = (0..100)
+            .map(|round| AssignmentStrategy::Random.choose(&candidates, round).id)
+            .collect();
+        assert_eq!(picks, HashSet::from([2, 3]));
+    }
+
+    #[tokio::test]
+    async fn test_configured_strategy_is_used_for_assignment() {
+        let mut config = test_config(1, &[2, 3]);
+        assert_eq!(config.server.assignment_strategy, AssignmentStrategy::LowestLoad);
+        config.server = toml::from_str(
+            r#"
+            id = 1
+            address = "127.0.0.1:0"
+            assignment_strategy = "round_robin"
+            "#,
+        )
+        .unwrap();
+
+        let leader = ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+        leader.peer_loads.write().await.extend([(2, 0.0), (3, 0.0)]);
+        let mut assigned = Vec::new();
+        for request_id in 1..=6 {
+            assigned.push(leader.assign_task("Client1".to_string(), request_id).await);
+        }
+        assert_eq!(assigned, vec![1, 2, 3, 1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_client_affinity_sticks_until_server_fails() {
+        let mut config = test_config(1, &[2, 3]);
+        config.server.client_affinity = true;
+        let mut leader =
+            ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+        leader.metrics = ServerMetrics::default().with_source(Arc::new(FixedLoad(80.0)));
+        leader.peer_loads.write().await.extend([(2, 10.0), (3, 20.0)]);
+
+        // Client1 lands on the least loaded server and stays there though it gets busier
+        assert_eq!(leader.assign_task("Client1".to_string(), 1).await, 2);
+        leader.peer_loads.write().await.insert(2, 60.0);
+        for request_id in 2..=4 {
+            assert_eq!(leader.assign_task("Client1".to_string(), request_id).await, 2);
+        }
+
+        // Other clients are still balanced by load
+        assert_eq!(leader.assign_task("Client2".to_string(), 1).await, 3);
+
+        // Once Server 2 fails Client1 moves, and sticks to its new server
+        leader.peer_loads.write().await.remove(&2);
+        assert_eq!(leader.assign_task("Client1".to_string(), 5).await, 3);
+        leader.peer_loads.write().await.insert(2, 0.0);
+        assert_eq!(leader.assign_task("Client1".to_string(), 6).await, 3);
+    }
+
+    /// A blank PNG usable as a carrier image.
+    fn test_carrier(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(width, height)
+            .write_to(&mut bytes, image::ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[tokio::test]
+    async fn test_progress_is_sent_ahead_of_the_response_when_enabled() {
+        let mut config = test_config(1, &[]);
+        config.server.report_progress = true;
+        let server = ServerMiddleware::new(
+            config,
+            Arc::new(ServerCore::from_bytes(1, test_carrier(800, 400))),
+        );
+        *server.current_leader.write().await = Some(1);
+        let (mut client, mut conn) = loopback_connection().await;
+
+        // Enough secret to span several progress reports
+        let request = Message::TaskRequest {
+            client_name: "Client1".to_string(),
+            request_id: 3,
+            secret_image_data: vec![9u8; 100_000],
+            assigned_by_leader: 1,
+            metadata: None,
+        };
+        let read_until_response = async {
+            let mut percents = Vec::new();
+            loop {
+                match client.read_message().await.unwrap() {
+                    Some(Message::TaskProgress { request_id: 3, percent }) => {
+                        percents.push(percent)
+                    }
+                    Some(response) => return (percents, response),
+                    None => panic!("connection closed before the response"),
+                }
+            }
+        };
+        let ((), (percents, response)) =
+            tokio::join!(server.handle_message(request, &mut conn), read_until_response);
+
+        // Updates the connection fell behind on are skipped, but never reordered
+        assert!(!percents.is_empty());
+        assert!(percents.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(percents.iter().all(|percent| *percent <= 100));
+        assert!(matches!(
+            response,
+            Message::TaskResponse { request_id: 3, success: true, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_task_request_is_encrypted_once() {
+        let server = ServerMiddleware::new(
+            test_config(1, &[]),
+            Arc::new(ServerCore::from_bytes(1, test_carrier(64, 64))),
+        );
+
+        let mut responses = Vec::new();
+        for _ in 0..2 {
+            let (tx, mut rx) = mpsc::channel(1);
+            server
+                .process_task(4, "Client1".to_string(), vec![9u8; 100], None, Some(tx), false)
+                .await;
+            responses.push(rx.recv().await.unwrap());
+        }
+
+        assert_eq!(
+            server.metrics.get_total_tasks(),
+            1,
+            "encrypt_image should run once"
+        );
+        match (&responses[0], &responses[1]) {
+            (
+                Message::TaskResponse {
+                    success: true,
+                    encrypted_image_data: first,
+                    ..
+                },
+                Message::TaskResponse {
+                    encrypted_image_data: second,
+                    ..
+                },
+            ) => assert_eq!(first, second),
+            other => panic!("Unexpected responses: {:?}", other),
+        }
+
+        // A different request from the same client is still processed
+        let (tx, mut rx) = mpsc::channel(1);
+        server
+            .process_task(5, "Client1".to_string(), vec![9u8; 100], None, Some(tx), false)
+            .await;
+        rx.recv().await.unwrap();
+        assert_eq!(server.metrics.get_total_tasks(), 2);
+    }
+
+    /// Wait (up to 5s) for every task to remove its handle from `active_tasks`.
+    async fn wait_for_task_handles_to_clear(server: &ServerMiddleware) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !server.active_tasks.read().await.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("a finished task kept its handle");
+    }
+
+    #[tokio::test]
+    async fn test_slow_task_times_out_with_failed_response() {
+        let mut config = test_config(1, &[]);
+        config.server.max_concurrent_tasks = 1;
+        config.server.task_timeout_secs = 1;
+        let core = ServerCore::from_bytes(1, test_carrier(64, 64))
+            .with_processing_delay(Duration::from_secs(60));
+        let server = ServerMiddleware::new(config, Arc::new(core));
+
+        let (tx, mut rx) = mpsc::channel(1);
+        server
+            .process_task(4, "Client1".to_string(), vec![9u8; 100], None, Some(tx), false)
+            .await;
+        let response = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .expect("timed-out task never answered");
+
+        match response {
+            Some(Message::TaskResponse {
+                request_id: 4,
+                success: false,
+                error_message: Some(error),
+                ..
+            }) => assert!(error.contains("timed out after 1s"), "{}", error),
+            other => panic!("Unexpected response: {:?}", other),
+        }
+
+        // The task no longer counts towards load, its slot is free and nothing is cached
+        wait_for_task_handles_to_clear(&server).await;
+        assert_eq!(server.metrics.get_active_tasks(), 0);
+        assert!(!server.is_saturated());
+        assert!(server.cached_result("Client1", 4).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_finished_task_removes_its_handle() {
+        let server = ServerMiddleware::new(
+            test_config(1, &[]),
+            Arc::new(ServerCore::from_bytes(1, test_carrier(64, 64))),
+        );
+
+        let (tx, mut rx) = mpsc::channel(1);
+        server
+            .process_task(4, "Client1".to_string(), vec![9u8; 100], None, Some(tx), false)
+            .await;
+        rx.recv().await.unwrap();
+
+        // The handle goes just after the response is sent
+        wait_for_task_handles_to_clear(&server).await;
+        assert_eq!(server.metrics.get_active_tasks(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_task_aborts_and_releases_slot() {
+        let mut config = test_config(1, &[]);
+        config.server.max_concurrent_tasks = 1;
+        let core = ServerCore::from_bytes(1, test_carrier(64, 64))
+            .with_processing_delay(Duration::from_secs(60));
+        let server = ServerMiddleware::new(config, Arc::new(core));
+
+        let (tx, mut rx) = mpsc::channel(1);
+        server
+            .process_task(4, "Client1".to_string(), vec![9u8; 100], None, Some(tx), false)
+            .await;
+        assert_eq!(server.metrics.get_active_tasks(), 1);
+        assert!(server.is_saturated());
+
+        assert!(server.cancel_task(4).await);
+        assert!(!server.cancel_task(4).await);
+
+        // Aborted without a reply; once dropped it no longer holds a slot or counts
+        assert!(rx.recv().await.is_none());
+        assert!(server.active_tasks.read().await.is_empty());
+        assert_eq!(server.metrics.get_active_tasks(), 0);
+        assert!(!server.is_saturated());
+    }
+
+    #[tokio::test]
+    async fn test_leader_forwards_routed_task_and_relays_result() {
+        let leader = test_middleware(1, &[2]);
+        let (peer_tx, mut peer_rx) = mpsc::channel(10);
+        leader.peer_connections.write().await.insert(2, peer_tx);
+        leader
+            .task_history
+            .write()
+            .await
+            .insert(("Client1".to_string(), 6), history_entry(6, 2));
+        let routed = || Message::RoutedTaskRequest {
+            client_name: "Client1".to_string(),
+            request_id: 6,
+            secret_image_data: vec![1, 2, 3],
+        };
+
+        // Only the leader accepts routed tasks
+        let (mut client, mut conn) = loopback_connection().await;
+        leader.handle_message(routed(), &mut conn).await;
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::TaskRejected { request_id: 6, .. })
+        ));
+
+        *leader.current_leader.write().await = Some(1);
+        let routing = leader.clone_arc();
+        let relay = tokio::spawn(async move {
+            routing.handle_message(routed(), &mut conn).await;
+        });
+
+        // The task goes to the server it's assigned to
+        match peer_rx.recv().await {
+            Some(Message::ForwardedTask {
+                from_server_id: 1,
+                request_id: 6,
+                secret_image_data,
+                ..
+            }) => assert_eq!(secret_image_data, vec![1, 2, 3]),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+
+        // Server 2's result is relayed to the waiting client
+        let (_peer_side, mut peer_conn) = loopback_connection().await;
+        leader
+            .handle_message(
+                Message::ForwardedTaskResult {
+                    client_name: "Client1".to_string(),
+                    request_id: 6,
+                    response: Box::new(Message::TaskResponse {
+                        request_id: 6,
+                        encrypted_image_data: vec![4, 5, 6],
+                        success: true,
+                        error_message: None,
+                    }),
+                },
+                &mut peer_conn,
+            )
+            .await;
+        relay.await.unwrap();
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::TaskResponse {
+                request_id: 6,
+                success: true,
+                ..
+            })
+        ));
+        assert!(leader.pending_forwards.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_forwarded_task_result_is_sent_back_to_leader() {
+        let server = ServerMiddleware::new(
+            test_config(2, &[1]),
+            Arc::new(ServerCore::from_bytes(2, test_carrier(64, 64))),
+        );
+        let (peer_tx, mut peer_rx) = mpsc::channel(10);
+        server.peer_connections.write().await.insert(1, peer_tx);
+
+        let (_leader_side, mut conn) = loopback_connection().await;
+        server
+            .handle_message(
+                Message::ForwardedTask {
+                    from_server_id: 1,
+                    client_name: "Client1".to_string(),
+                    request_id: 8,
+                    secret_image_data: vec![9u8; 100],
+                },
+                &mut conn,
+            )
+            .await;
+
+        match peer_rx.recv().await {
+            Some(Message::ForwardedTaskResult {
+                request_id: 8,
+                response,
+                ..
+            }) => assert!(matches!(
+                *response,
+                Message::TaskResponse {
+                    request_id: 8,
+                    success: true,
+                    ..
+                }
+            )),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_over_rate_limit_is_refused() {
+        let mut config = test_config(1, &[]);
+        config.server.client_rate_limit = Some(3);
+        let leader = ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+        *leader.current_leader.write().await = Some(1);
+
+        let (mut client, mut conn) = loopback_connection().await;
+        let request = |client_name: &str, request_id| Message::TaskAssignmentRequest {
+            client_name: client_name.to_string(),
+            request_id,
+        };
+
+        for request_id in 1..=3 {
+            leader
+                .handle_message(request("Client1", request_id), &mut conn)
+                .await;
+            assert!(matches!(
+                client.read_message().await.unwrap(),
+                Some(Message::TaskAssignmentResponse { .. })
+            ));
+        }
+
+        // The 4th request within the window is refused
+        leader
+            .handle_message(request("Client1", 4), &mut conn)
+            .await;
+        match client.read_message().await.unwrap() {
+            Some(Message::RateLimited {
+                request_id: 4,
+                retry_after_ms,
+            }) => assert!(retry_after_ms <= 10_000),
+            other => panic!("Unexpected message: {:?}", other),
+        }
+        assert!(!leader
+            .task_history
+            .read()
+            .await
+            .contains_key(&("Client1".to_string(), 4)));
+
+        // Other clients have their own budget
+        leader
+            .handle_message(request("Client2", 1), &mut conn)
+            .await;
+        assert!(matches!(
+            client.read_message().await.unwrap(),
+            Some(Message::TaskAssignmentResponse { .. })
+        ));
+
+        // Once the window slides past the earlier requests, Client1 is allowed again
+        let later = Instant::now() + Duration::from_secs(10);
+        assert_eq!(leader.rate_limit("Client1", later).await, None);
+    }
+
+    #[test]
+    fn test_backup_is_next_server_by_id() {
+        assert_eq!(next_server_by_id(1, [1, 2, 3]), Some(2));
+        assert_eq!(next_server_by_id(2, [3, 1, 2]), Some(3));
+        assert_eq!(next_server_by_id(3, [1, 2, 3]), Some(1));
+        assert_eq!(next_server_by_id(1, [1]), None);
+    }
+
+    #[tokio::test]
+    async fn test_completed_result_is_replicated_and_served_by_backup() {
+        let mut config = test_config(1, &[2, 3]);
+        config.server.replicate_results = true;
+        let server = ServerMiddleware::new(
+            config,
+            Arc::new(ServerCore::from_bytes(1, test_carrier(64, 64))),
+        );
+        let (peer_tx, mut peer_rx) = mpsc::channel(10);
+        server.peer_connections.write().await.insert(2, peer_tx);
+
+        let (tx, mut rx) = mpsc::channel(1);
+        server
+            .process_task(4, "Client1".to_string(), vec![9u8; 100], None, Some(tx), false)
+            .await;
+        let response = rx.recv().await.unwrap();
+
+        // The result went to Server 2, the next server by ID
+        let replica = peer_rx.recv().await.unwrap();
+        assert!(matches!(
+            replica,
+            Message::ResultReplicate { request_id: 4, .. }
+        ));
+
+        // The backup answers a resubmission from the replica without encrypting
+        let backup = test_middleware(2, &[1, 3]);
+        let (_peer_side, mut conn) = loopback_connection().await;
+        backup.handle_message(replica, &mut conn).await;
+
+        let (tx, mut rx) = mpsc::channel(1);
+        backup
+            .process_task(4, "Client1".to_string(), vec![9u8; 100], None, Some(tx), false)
+            .await;
+        match (rx.recv().await.unwrap(), response) {
+            (
+                Message::TaskResponse {
+                    success: true,
+                    encrypted_image_data: replicated,
+                    ..
+                },
+                Message::TaskResponse {
+                    encrypted_image_data: original,
+                    ..
+                },
+            ) => assert_eq!(replicated, original),
+            other => panic!("Unexpected responses: {:?}", other),
+        }
+        assert_eq!(backup.metrics.get_total_tasks(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_task_is_reassigned_to_backup() {
+        let mut config = test_config(1, &[2, 3]);
+        config.server.replicate_results = true;
+        let leader = ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+
+        // Server 2 failed; Server 3 is its backup even though it's the busiest
+        leader.peer_loads.write().await.insert(3, f64::MAX);
+        leader
+            .task_history
+            .write()
+            .await
+            .insert(("Client1".to_string(), 5), history_entry(5, 2));
+
+        leader.reassign_all_orphaned_tasks().await;
+
+        let history = leader.task_history.read().await;
+        assert_eq!(history[&("Client1".to_string(), 5)].assigned_server_id, 3);
+    }
+
+    #[tokio::test]
+    async fn test_assignments_count_towards_load_until_acknowledged() {
+        // Leader at 40.0; each unacknowledged task adds 3.0 with default weights
+        let (leader, _events) = election_node(1, &[2, 3], 80.0);
+        *leader.current_leader.write().await = Some(1);
+        leader.peer_loads.write().await.insert(2, 10.0);
+        leader.peer_loads.write().await.insert(3, 12.0);
+
+        let mut assigned = Vec::new();
+        for request_id in 1..=4 {
+            assigned.push(leader.assign_task("Client1".to_string(), request_id).await);
+        }
+        // Stale heartbeats alone would have sent every task to Server 2
+        assert_eq!(assigned, vec![2, 3, 2, 3]);
+
+        // Once Server 2's tasks are acknowledged it's the least loaded again
+        // (stamped after the Adds it saw)
+        for request_id in [1, 3] {
+            leader
+                .handle_message(
+                    Message::HistoryRemove {
+                        client_name: "Client1".to_string(),
+                        request_id,
+                        timestamp: leader.next_history_timestamp(),
+                    },
+                    &mut loopback_connection().await.1,
+                )
+                .await;
+        }
+        assert_eq!(leader.pending_assignments.read().await.get(&2), None);
+        assert_eq!(leader.assign_task("Client1".to_string(), 5).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_result_cache_evicts_oldest_beyond_capacity() {
+        let server = test_middleware(1, &[]);
+        for request_id in 0..RESULT_CACHE_CAPACITY as u64 + 3 {
+            let response = Message::TaskResponse {
+                request_id,
+                encrypted_image_data: Vec::new(),
+                success: true,
+                error_message: None,
+            };
+            server
+                .cache_result("Client1".to_string(), request_id, response)
+                .await;
+        }
+
+        assert_eq!(
+            server.completed_results.read().await.len(),
+            RESULT_CACHE_CAPACITY
+        );
+        assert!(server.cached_result("Client1", 0).await.is_none());
+        assert!(server
+            .cached_result("Client1", RESULT_CACHE_CAPACITY as u64 + 2)
+            .await
+            .is_some());
+    }
+
+    #[test]
+    fn test_equal_priority_election_has_single_winner() {
+        // Idle servers commonly all report 0.0
+        let servers = [(3, 0.0), (1, 0.0), (2, 0.0)];
+
+        // A server wins if no peer outranks it (no peer would send it ALIVE)
+        let winners: Vec<u32> = servers
+            .iter()
+            .filter(|(id, priority)| {
+                !servers.iter().any(|(other_id, other_priority)| {
+                    outranks(*other_priority, *other_id, *priority, *id)
+                })
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        assert_eq!(winners, vec![1]);
+
+        // Load still dominates the ID
+        assert!(outranks(10.0, 3, 10.5, 1));
+        assert!(!outranks(10.5, 1, 10.0, 3));
+        assert!(!outranks(0.0, 1, 0.0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_stale_coordinator_is_ignored() {
+        let server = test_middleware(1, &[2, 3]);
+        let (_other, mut conn) = loopback_connection().await;
+
+        server
+            .handle_message(
+                Message::Coordinator {
+                    leader_id: 2,
+                    term: 5,
+                },
+                &mut conn,
+            )
+            .await;
+        assert_eq!(*server.current_leader.read().await, Some(2));
+
+        // A delayed announcement from an older election doesn't flip the leader back
+        server
+            .handle_message(
+                Message::Coordinator {
+                    leader_id: 3,
+                    term: 4,
+                },
+                &mut conn,
+            )
+            .await;
+        assert_eq!(*server.current_leader.read().await, Some(2));
+        assert_eq!(*server.current_term.read().await, 5);
+
+        server
+            .handle_message(
+                Message::Coordinator {
+                    leader_id: 3,
+                    term: 6,
+                },
+                &mut conn,
+            )
+            .await;
+        assert_eq!(*server.current_leader.read().await, Some(3));
+        assert_eq!(*server.current_term.read().await, 6);
+    }
+
+    #[tokio::test]
+    async fn test_stale_election_gets_current_leader_instead() {
+        let server = test_middleware(1, &[2, 3]);
+        let (tx, mut rx) = mpsc::channel(10);
+        server.peer_connections.write().await.insert(3, tx);
+        *server.current_term.write().await = 7;
+        *server.current_leader.write().await = Some(2);
+
+        // Server 3 recovered with an old term and starts an election
+        let (_other, mut conn) = loopback_connection().await;
+        server
+            .handle_message(
+                Message::Election {
+                    from_id: 3,
+                    priority: f64::MAX,
+                    term: 1,
+                },
+                &mut conn,
+            )
+            .await;
+
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Message::Coordinator {
+                leader_id: 2,
+                term: 7
+            })
+        ));
+        assert!(rx.try_recv().is_err(), "no ALIVE for a stale election");
+        assert_eq!(*server.current_term.read().await, 7);
+    }
+
+    #[tokio::test]
+    async fn test_silent_configured_peer_is_detected_as_failed() {
+        let server = test_middleware(1, &[2, 3]);
+        let timeout = server.config.election.failure_timeout_secs;
+        let after_grace = server.started_at + timeout + 1;
+
+        // Peer 3 heartbeats normally; peer 2 never sends a single heartbeat
+        let (_other, mut conn) = loopback_connection().await;
+        let heartbeat_from_3 = |timestamp| Message::Heartbeat {
+            from_id: 3,
+            timestamp,
+            load: 0.0,
+            saturated: false,
+            total_tasks: 0,
+            leader_id: None,
+            term: 0,
+        };
+        server
+            .handle_message(heartbeat_from_3(after_grace), &mut conn)
+            .await;
+
+        // Still within the startup grace period
+        assert!(server
+            .detect_failed_peers(server.started_at * 1000)
+            .await
+            .is_empty());
+
+        assert_eq!(
+            server.detect_failed_peers(after_grace * 1000).await,
+            vec![2]
+        );
+        // Reported once, not on every monitor tick
+        assert!(server
+            .detect_failed_peers((after_grace + 1) * 1000)
+            .await
+            .is_empty());
+
+        // A peer that stops heartbeating is still detected as before
+        assert_eq!(
+            server
+                .detect_failed_peers((after_grace + timeout + 1) * 1000)
+                .await,
+            vec![3]
+        );
+
+        // Heartbeating again clears the failure so a later outage is reported
+        server
+            .handle_message(heartbeat_from_3(after_grace + timeout + 2), &mut conn)
+            .await;
+        assert!(!server.failed_peers.read().await.contains(&3));
+    }
+
+    #[tokio::test]
+    async fn test_phi_threshold_replaces_fixed_timeout() {
+        let mut config = test_config(1, &[2]);
+        config.election.failure_timeout_secs = 30;
+        config.election.phi_threshold = Some(8.0);
+        let server = ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+
+        // Peer 2 heartbeats every second, then goes silent
+        let start_ms = server.started_at * 1000;
+        let mut detector = PhiAccrualDetector::new(1_000.0);
+        for i in 1..=10 {
+            detector.heartbeat(start_ms + i * 1_000);
+        }
+        let last_ms = start_ms + 10_000;
+        server.heartbeat_detectors.write().await.insert(2, detector);
+        server
+            .last_heartbeat_times
+            .write()
+            .await
+            .insert(2, last_ms / 1000);
+
+        assert!(server.detect_failed_peers(last_ms + 1_200).await.is_empty());
+        // Flagged after a few missed beats, long before the 30s fixed timeout
+        assert_eq!(server.detect_failed_peers(last_ms + 4_000).await, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_resign_triggers_immediate_election() {
+        let server = test_middleware(1, &[2, 3]);
+        let (tx, mut rx) = mpsc::channel(10);
+        server.peer_connections.write().await.insert(3, tx);
+        *server.current_leader.write().await = Some(2);
+
+        let (_other, mut conn) = loopback_connection().await;
+        server
+            .handle_message(Message::Resign { leader_id: 2 }, &mut conn)
+            .await;
+
+        assert_eq!(*server.current_leader.read().await, None);
+        let election = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .expect("resign should trigger an election");
+        assert!(matches!(
+            election,
+            Some(Message::Election { from_id: 1, .. })
+        ));
+    }
+
+    /// Send server 2 a pre-vote from server 3 and return whether it was granted.
+    async fn pre_vote_granted(server: &ServerMiddleware) -> bool {
+        let (mut other, mut conn) = loopback_connection().await;
+        server
+            .handle_message(Message::PreVote { from_id: 3 }, &mut conn)
+            .await;
+        match other.read_message().await.unwrap() {
+            Some(Message::PreVoteResponse { from_id: 2, granted }) => granted,
+            other => panic!("expected a pre-vote response, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pre_vote_granted_only_when_leader_seems_gone() {
+        let server = test_middleware(2, &[1, 3]);
+
+        // No leader
+        assert!(pre_vote_granted(&server).await);
+
+        // A leader heard from just now, then one silent for longer than failure_timeout_secs
+        *server.current_leader.write().await = Some(1);
+        let now = current_timestamp();
+        server.last_heartbeat_times.write().await.insert(1, now);
+        assert!(!pre_vote_granted(&server).await);
+        server.last_heartbeat_times.write().await.insert(1, now - 10);
+        assert!(pre_vote_granted(&server).await);
+
+        // We lead ourselves
+        *server.current_leader.write().await = Some(2);
+        assert!(!pre_vote_granted(&server).await);
+    }
+
+    #[tokio::test]
+    async fn test_election_needs_quorum_to_win() {
+        let mut config = test_config(1, &[2, 3]);
+        config.election.require_quorum = true;
+        let server = ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+
+        // Alone out of three: nobody outranks us, but we don't lead
+        server.initiate_election().await;
+        assert_eq!(*server.current_leader.read().await, None);
+
+        // Peer 3 is gone for good, but with peer 2 we're a majority again
+        server
+            .last_heartbeat_times
+            .write()
+            .await
+            .insert(2, current_timestamp());
+        server.initiate_election().await;
+        assert_eq!(*server.current_leader.read().await, Some(1));
+    }
+
+    /// Address of a fake peer that answers one [`Message::PreVote`] with `granted`.
+    async fn pre_vote_peer(granted: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut conn = Connection::new(socket);
+            conn.accept_handshake().await.unwrap();
+            if let Ok(Some(Message::PreVote { .. })) = conn.read_message().await {
+                let response = Message::PreVoteResponse { from_id: 2, granted };
+                conn.write_message(&response).await.unwrap();
+            }
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_election_only_starts_after_pre_vote_passes() {
+        for (granted, term) in [(false, 0), (true, 1)] {
+            let mut config = test_config(1, &[2]);
+            config.election.pre_vote = true;
+            config.peers.peers[0].address = pre_vote_peer(granted).await;
+            let server =
+                ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+
+            server.initiate_election().await;
+            assert_eq!(*server.current_term.read().await, term);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_isolated_server_fails_pre_vote() {
+        let mut config = test_config(1, &[2]);
+        config.election.pre_vote = true;
+        // Nothing listens on the peer's address
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        config.peers.peers[0].address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+        let server = ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(1, Vec::new())));
+
+        server.initiate_election().await;
+        assert_eq!(*server.current_term.read().await, 0);
+        assert_eq!(*server.current_leader.read().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_leader_shutdown_resigns_and_drains_tasks() {
+        let server = test_middleware(1, &[2]);
+        let (tx, mut rx) = mpsc::channel(10);
+        server.peer_connections.write().await.insert(2, tx);
+        *server.current_leader.write().await = Some(1);
+
+        // An in-flight task that takes a while to finish
+        let finished = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let task_finished = finished.clone();
+        let handle = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            task_finished.store(true, std::sync::atomic::Ordering::SeqCst);
+        });
+        server.active_tasks.write().await.insert(1, handle);
+
+        server.shutdown().await;
+
+        assert!(finished.load(std::sync::atomic::Ordering::SeqCst));
+        assert!(server.active_tasks.read().await.is_empty());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(Message::Resign { leader_id: 1 })
+        ));
+    }
+
+    /// Deliver each server's heartbeat to the other.
+    async fn exchange_heartbeats(a: &ServerMiddleware, b: &ServerMiddleware) {
+        let (_other, mut conn) = loopback_connection().await;
+        for (from, to) in [(a, b), (b, a)] {
+            let leader_id = *from.current_leader.read().await;
+            let term = *from.current_term.read().await;
+            let heartbeat = Message::Heartbeat {
+                from_id: from.config.server.id,
+                timestamp: current_timestamp(),
+                load: 0.0,
+                saturated: false,
+                total_tasks: from.metrics.get_total_tasks(),
+                leader_id,
+                term,
+            };
+            to.handle_message(heartbeat, &mut conn).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_split_brain_converges_to_one_leader() {
+        // Two leaders elected on either side of a healed partition
+        let server1 = test_middleware(1, &[2]);
+        let server2 = test_middleware(2, &[1]);
+        *server1.current_leader.write().await = Some(1);
+        *server1.current_term.write().await = 3;
+        *server2.current_leader.write().await = Some(2);
+        *server2.current_term.write().await = 5;
+
+        let (tx, mut rx) = mpsc::channel(10);
+        server1.peer_connections.write().await.insert(2, tx);
+
+        exchange_heartbeats(&server1, &server2).await;
+
+        // The older leader steps down in favor of the newer term...
+        assert_eq!(*server1.current_leader.read().await, Some(2));
+        assert_eq!(*server2.current_leader.read().await, Some(2));
+
+        // ...and starts a fresh election so the final leader sees the whole cluster
+        let election = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+            .await
+            .expect("step-down should trigger an election");
+        assert!(matches!(
+            election,
+            Some(Message::Election {
+                from_id: 1,
+                term: 6,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_leaderless_server_adopts_leader_reported_by_a_majority() {
+        let server = test_middleware(1, &[2, 3, 4, 5]);
+        let (_other, mut conn) = loopback_connection().await;
+        let heartbeat = |from_id, leader_id, term| Message::Heartbeat {
+            from_id,
+            timestamp: current_timestamp(),
+            load: 0.0,
+            saturated: false,
+            total_tasks: 0,
+            leader_id,
+            term,
+        };
+
+        // One peer's view, and another peer following someone else, aren't enough
+        for message in [heartbeat(2, Some(2), 3), heartbeat(3, Some(4), 3)] {
+            server.handle_message(message, &mut conn).await;
+        }
+        assert_eq!(*server.current_leader.read().await, None);
+
+        // With peer 3 agreeing on leader 2, we're three of five
+        server
+            .handle_message(heartbeat(3, Some(2), 3), &mut conn)
+            .await;
+        assert_eq!(*server.current_leader.read().await, Some(2));
+        assert_eq!(*server.current_term.read().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_split_brain_with_equal_terms_keeps_lower_leader_id() {
+        let server1 = test_middleware(1, &[2]);
+        let server2 = test_middleware(2, &[1]);
+        for server in [&server1, &server2] {
+            *server.current_leader.write().await = Some(server.config.server.id);
+            *server.current_term.write().await = 4;
+        }
+
+        exchange_heartbeats(&server1, &server2).await;
+
+        assert_eq!(*server1.current_leader.read().await, Some(1));
+        assert_eq!(*server2.current_leader.read().await, Some(1));
+    }
+
+    /// Metric source reporting a fixed CPU load with memory and disk free, so a
+    /// server's priority is `0.5 * load` with the default weights.
+    #[derive(Debug)]
+    struct FixedLoad(f64);
+
+    impl crate::server::election::MetricSource for FixedLoad {
+        fn cpu_usage(&self) -> f64 {
+            self.0
+        }
+
+        fn available_memory_percent(&self) -> f64 {
+            100.0
+        }
+
+        fn available_disk_percent(&self) -> f64 {
+            100.0
+        }
+    }
+
+    /// Build server `id` with a fixed CPU load and a 1s election timeout, reporting
+    /// its election events on the returned channel.
+    fn election_node(
+        id: u32,
+        peer_ids: &[u32],
+        load: f64,
+    ) -> (Arc<ServerMiddleware>, mpsc::Receiver<ElectionEvent>) {
+        let mut config = test_config(id, peer_ids);
+        config.election.election_timeout_secs = 1;
+        let (tx, rx) = mpsc::channel(100);
+        let mut node =
+            ServerMiddleware::new(config, Arc::new(ServerCore::from_bytes(id, Vec::new())))
+                .with_election_events(tx);
+        node.metrics = ServerMetrics::default().with_source(Arc::new(FixedLoad(load)));
+        (Arc::new(node), rx)
+    }
+
+    /// Connect `nodes` in memory: whatever a node sends to a peer is handed straight
+    /// to that peer's `handle_message`.
+    async fn wire_cluster(nodes: &[Arc<ServerMiddleware>]) {
+        for from in nodes {
+            for to in nodes {
+                if from.config.server.id == to.config.server.id {
+                    continue;
+                }
+                let (tx, mut rx) = mpsc::channel(100);
+                from.peer_connections
+                    .write()
+                    .await
+                    .insert(to.config.server.id, tx);
+                let (peer_end, mut conn) = loopback_connection().await;
+                let to = to.clone();
+                tokio::spawn(async move {
+                    // Keep the other end open so replies written to `conn` don't fail
+                    let _peer_end = peer_end;
+                    while let Some(message) = rx.recv().await {
+                        to.handle_message(message, &mut conn).await;
+                    }
+                });
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_three_node_cluster_elects_exactly_one_leader() {
+        let (node1, events1) = election_node(1, &[2, 3], 60.0);
+        let (node2, events2) = election_node(2, &[1, 3], 20.0);
+        let (node3, events3) = election_node(3, &[1, 2], 40.0);
+        let nodes = [node1, node2, node3];
+        wire_cluster(&nodes).await;
+
+        // Everyone starts an election at once, as after a cold start
+        for node in &nodes {
+            let node = node.clone();
+            tokio::spawn(async move { node.initiate_election().await });
+        }
+
+        // Wait for the cluster to agree, then for any election still running to end
+        let deadline = Instant::now() + Duration::from_secs(15);
+        loop {
+            let mut leaders = Vec::new();
+            for node in &nodes {
+                leaders.push(*node.current_leader.read().await);
+            }
+            if leaders.iter().all(|leader| *leader == Some(2)) {
+                break;
+            }
+            assert!(Instant::now() < deadline, "no agreement, leaders: {:?}", leaders);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(1_500)).await;
+
+        let mut events = Vec::new();
+        for mut rx in [events1, events2, events3] {
+            let mut node_events = Vec::new();
+            while let Ok(event) = rx.try_recv() {
+                node_events.push(event);
+            }
+            events.push(node_events);
+        }
+
+        // Exactly one election was won, by the least loaded server
+        let wins: Vec<usize> = events
+            .iter()
+            .enumerate()
+            .flat_map(|(node, node_events)| {
+                node_events
+                    .iter()
+                    .filter(|event| matches!(event, ElectionEvent::WonElection { .. }))
+                    .map(move |_| node + 1)
+            })
+            .collect();
+        assert_eq!(wins, vec![2]);
+
+        for (node, node_events) in events.iter().enumerate() {
+            assert!(node_events
+                .iter()
+                .any(|event| matches!(event, ElectionEvent::ElectionStarted { .. })));
+
+            let last_leader = node_events.iter().rev().find_map(|event| match event {
+                ElectionEvent::LeaderChanged { leader_id } => Some(*leader_id),
+                _ => None,
+            });
+            assert_eq!(last_leader, Some(Some(2)), "node {}", node + 1);
+        }
+
+        // The others heard from a better candidate (their elections then end as
+        // lost, or superseded by server 2's newer term)
+        for node_events in [&events[0], &events[2]] {
+            assert!(node_events
+                .iter()
+                .any(|event| matches!(event, ElectionEvent::ReceivedAlive { from_id: 2 })));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lower_priority_score_wins_whoever_calls_the_election_first() {
+        // Server 2 is less loaded, so it must lead despite its higher ID
+        for first in [1, 2] {
+            let (node1, events1) = election_node(1, &[2], 60.0);
+            let (node2, events2) = election_node(2, &[1], 20.0);
+            let nodes = [node1, node2];
+            wire_cluster(&nodes).await;
+
+            // One server's ELECTION arrives mid-way through the other's election
+            let (early, late) = if first == 1 {
+                (nodes[0].clone(), nodes[1].clone())
+            } else {
+                (nodes[1].clone(), nodes[0].clone())
+            };
+            tokio::spawn(async move { early.initiate_election().await });
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            tokio::spawn(async move { late.initiate_election().await });
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            loop {
+                let leaders = [
+                    *nodes[0].current_leader.read().await,
+                    *nodes[1].current_leader.read().await,
+                ];
+                if leaders == [Some(2), Some(2)] {
+                    break;
+                }
+                assert!(
+                    Instant::now() < deadline,
+                    "server {} first: leaders {:?}",
+                    first,
+                    leaders
+                );
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(1_500)).await;
+
+            // Server 1 never won, and deferred to server 2 (or was told so by it)
+            for (mut rx, id) in [(events1, 1), (events2, 2)] {
+                while let Ok(event) = rx.try_recv() {
+                    if let ElectionEvent::WonElection { .. } = event {
+                        assert_eq!(id, 2, "server {} first: server 1 won", first);
+                    }
+                }
+            }
+            assert_eq!(*nodes[0].current_leader.read().await, Some(2));
+        }
+    }
+
+    #[test]
+    fn test_next_backoff_doubles_up_to_cap() {
+        assert_eq!(next_backoff(0), Duration::from_millis(250));
+        assert_eq!(next_backoff(1), Duration::from_millis(500));
+        assert_eq!(next_backoff(2), Duration::from_millis(1_000));
+        assert_eq!(next_backoff(5), Duration::from_millis(8_000));
+        assert_eq!(
+            next_backoff(6),
+            Duration::from_millis(RECONNECT_MAX_DELAY_MS)
+        );
+        assert_eq!(
+            next_backoff(u32::MAX),
+            Duration::from_millis(RECONNECT_MAX_DELAY_MS)
+        );
+    }
+
+    #[test]
+    fn test_heartbeat_interval_jitter_stays_within_bounds() {
+        let interval = Duration::from_secs(1);
+        let mut seen = HashSet::new();
+        for _ in 0..200 {
+            let next = jittered_interval(interval, 0.15);
+            assert!(next >= Duration::from_millis(850) && next <= Duration::from_millis(1_150));
+            seen.insert(next);
+        }
+        assert!(seen.len() > 1, "intervals should actually vary");
+
+        // No jitter, and nonsense values are clamped rather than trusted
+        assert_eq!(jittered_interval(interval, 0.0), interval);
+        assert_eq!(jittered_interval(interval, f64::NAN), interval);
+        for _ in 0..50 {
+            let next = jittered_interval(interval, 5.0);
+            assert!(next >= interval / 2 && next <= interval * 3 / 2);
+        }
+    }
+
+    #[test]
+    fn test_jitter_stays_within_half_to_full_delay() {
+        for attempt in 0..8 {
+            let delay = next_backoff(attempt);
+            for _ in 0..50 {
+                let jittered = with_jitter(delay);
+                assert!(jittered >= delay / 2 && jittered <= delay);
+            }
+        }
     }
 }
@@ -14,12 +14,21 @@
 //! - Task assignment and load balancing
 //! - Fault tolerance and orphaned task cleanup
 //! - Message routing and coordination
+//!
+//! ## Failure Detection ([`failure_detector`])
+//! Optional phi-accrual detector that adapts to each peer's heartbeat timing.
+//!
+//! ## Task History Log ([`history`])
+//! Persists the task history to disk so it survives a server restart.
 
+#[allow(clippy::module_inception)]
 pub mod server;
 pub mod middleware;
 pub mod election;
+pub mod failure_detector;
+pub mod history;
 
 // Re-export for convenience
-pub use middleware::ServerMiddleware;
+pub use middleware::{ElectionEvent, ServerMiddleware};
 pub use server::ServerCore;
-pub use election::ServerMetrics;
+pub use election::{MetricSource, ServerMetrics, ServerMetricsSnapshot, SystemMetricSource};
@@ -5,12 +5,20 @@
 //!
 //! All distributed system concerns (leader election, heartbeats, task distribution, etc.)
 //! are handled by the [`ServerMiddleware`](super::middleware::ServerMiddleware).
+//!
+//! ## Carrier Pool
+//!
+//! A server holds one or more carrier images, kept sorted by capacity. Each task is
+//! embedded into the smallest carrier that fits the secret (and any requested minimum
+//! capacity), so large secrets are only rejected when no carrier can hold them.
 
 use anyhow::Result;
-use log::info;
+use log::{info, warn};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::processing::steganography;
+use crate::processing::steganography::{self, EmbedOptions};
 
 /// Core server component that performs image encryption tasks.
 ///
@@ -19,8 +27,20 @@ use crate::processing::steganography;
 pub struct ServerCore {
     /// Server ID for logging purposes
     server_id: u32,
-    /// Default carrier image used to hide secret images
-    default_carrier_image: Arc<Vec<u8>>,
+    /// Carrier images used to hide secret images, sorted by ascending capacity
+    carriers: Vec<Carrier>,
+    /// Extra time each encryption takes, to simulate a slow server (zero by default)
+    processing_delay: Duration,
+}
+
+/// A carrier image loaded into the pool.
+struct Carrier {
+    /// File name (or label) of the carrier, for logging
+    name: String,
+    /// Encoded image bytes
+    image: Arc<Vec<u8>>,
+    /// Largest secret image this carrier can hold (bytes), computed once at load
+    capacity: usize,
 }
 
 impl ServerCore {
@@ -62,7 +82,7 @@ impl ServerCore {
             ))?;
 
         let (width, height) = img.dimensions();
-        let capacity = (width * height * 3) / 8;
+        let capacity = steganography::image_capacity_bytes(&carrier_image_bytes)?;
 
         info!(
             "✅ Server {} loaded cover image: {}x{} pixels ({} KB capacity)",
@@ -71,7 +91,98 @@ impl ServerCore {
 
         Ok(Self {
             server_id,
-            default_carrier_image: Arc::new(carrier_image_bytes),
+            carriers: vec![Carrier {
+                name: cover_image_path.to_string(),
+                image: Arc::new(carrier_image_bytes),
+                capacity,
+            }],
+            processing_delay: Duration::ZERO,
+        })
+    }
+
+    /// Create a server core with a pool of carrier images loaded from a directory.
+    ///
+    /// Every decodable image in `carrier_dir` is loaded and indexed by capacity;
+    /// files that are not images are skipped with a warning.
+    ///
+    /// # Arguments
+    /// - `server_id`: Unique identifier for this server (used for logging)
+    /// - `carrier_dir`: Directory containing the carrier images
+    ///
+    /// # Returns
+    /// - `Ok(ServerCore)`: Successfully created with at least one carrier
+    /// - `Err`: If the directory can't be read or contains no usable images
+    ///
+    /// # Example
+    /// ```ignore
+    /// let core = ServerCore::with_carrier_pool(1, "test_images/carriers")?;
+    /// ```
+    pub fn with_carrier_pool(server_id: u32, carrier_dir: &str) -> Result<Self> {
+        info!(
+            "📂 Server {} loading carrier pool from: {}",
+            server_id, carrier_dir
+        );
+
+        let entries = std::fs::read_dir(carrier_dir).map_err(|e| {
+            anyhow::anyhow!("Failed to read carrier directory '{}': {}", carrier_dir, e)
+        })?;
+
+        let mut carriers = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            match Self::load_carrier(&path) {
+                Ok(carrier) => carriers.push(carrier),
+                Err(e) => warn!(
+                    "⚠️  Server {} skipping carrier '{}': {}",
+                    server_id,
+                    path.display(),
+                    e
+                ),
+            }
+        }
+
+        if carriers.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No usable carrier images found in '{}'",
+                carrier_dir
+            ));
+        }
+
+        // Smallest first, so selection can take the first carrier that fits
+        carriers.sort_by(|a, b| a.capacity.cmp(&b.capacity).then_with(|| a.name.cmp(&b.name)));
+
+        info!(
+            "✅ Server {} loaded {} carrier image(s) ({} KB - {} KB capacity)",
+            server_id,
+            carriers.len(),
+            carriers[0].capacity / 1024,
+            carriers[carriers.len() - 1].capacity / 1024
+        );
+
+        Ok(Self {
+            server_id,
+            carriers,
+            processing_delay: Duration::ZERO,
+        })
+    }
+
+    /// Load a single carrier image from disk and compute its capacity.
+    fn load_carrier(path: &Path) -> Result<Carrier> {
+        let image = std::fs::read(path)?;
+        let capacity = steganography::image_capacity_bytes(&image)?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        Ok(Carrier {
+            name,
+            image: Arc::new(image),
+            capacity,
         })
     }
 
@@ -80,12 +191,44 @@ impl ServerCore {
     /// This is kept for backward compatibility.
     #[allow(dead_code)]
     pub fn from_bytes(server_id: u32, carrier_image_bytes: Vec<u8>) -> Self {
+        // An undecodable carrier has no capacity, so every task is rejected up front
+        let capacity = steganography::image_capacity_bytes(&carrier_image_bytes).unwrap_or(0);
+
         Self {
             server_id,
-            default_carrier_image: Arc::new(carrier_image_bytes),
+            carriers: vec![Carrier {
+                name: "default".to_string(),
+                image: Arc::new(carrier_image_bytes),
+                capacity,
+            }],
+            processing_delay: Duration::ZERO,
         }
     }
 
+    /// Make every encryption take at least `delay` longer, to simulate a slow or
+    /// overloaded server.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let core = ServerCore::new(1, "test_images/medium.jpg")?
+    ///     .with_processing_delay(Duration::from_secs(5));
+    /// ```
+    pub fn with_processing_delay(mut self, delay: Duration) -> Self {
+        self.processing_delay = delay;
+        self
+    }
+
+    /// Largest secret image (in bytes) any carrier in the pool can hold, accounting
+    /// for the image info block the embed step prepends.
+    pub fn carrier_capacity(&self) -> usize {
+        self.carriers.last().map_or(0, |carrier| carrier.capacity)
+    }
+
+    /// Pick the smallest carrier that can hold `required` bytes.
+    fn select_carrier(&self, required: usize) -> Option<&Carrier> {
+        self.carriers.iter().find(|carrier| carrier.capacity >= required)
+    }
+
     /// Process an encryption task by embedding a secret image into the server's carrier image.
     ///
     /// This function:
@@ -102,6 +245,10 @@ impl ServerCore {
     /// - `Ok(Vec<u8>)`: Carrier image bytes with embedded secret (PNG format)
     /// - `Err`: Encryption failed (carrier too small, invalid format, etc.)
     ///
+    /// The secret is embedded into the smallest pooled carrier that fits it. Secrets
+    /// larger than [`carrier_capacity`](Self::carrier_capacity) are rejected
+    /// immediately, before any embedding work is done.
+    ///
     /// # Example
     /// ```ignore
     /// let secret_image = std::fs::read("secret.jpg")?;
@@ -116,19 +263,168 @@ impl ServerCore {
         request_id: u64,
         client_name: String,
         secret_image_data: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.encrypt_image_with_hint(request_id, client_name, secret_image_data, None)
+            .await
+    }
+
+    /// Process an encryption task, choosing a carrier with at least `min_capacity` bytes.
+    ///
+    /// Behaves like [`encrypt_image`](Self::encrypt_image), but the carrier must also
+    /// be able to hold `min_capacity` bytes. A client can use this to request headroom
+    /// beyond the secret size, e.g. to get the same carrier size for a batch of images.
+    ///
+    /// # Errors
+    /// - No carrier in the pool can hold the secret (or `min_capacity`)
+    /// - Embedding fails (invalid carrier, encoding failure)
+    ///
+    /// # Example
+    /// ```ignore
+    /// let result = core
+    ///     .encrypt_image_with_hint(1, "Client1".to_string(), secret_image, Some(512 * 1024))
+    ///     .await?;
+    /// ```
+    pub async fn encrypt_image_with_hint(
+        &self,
+        request_id: u64,
+        client_name: String,
+        secret_image_data: Vec<u8>,
+        min_capacity: Option<usize>,
+    ) -> Result<Vec<u8>> {
+        self.encrypt(
+            request_id,
+            client_name,
+            secret_image_data,
+            min_capacity,
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Process an encryption task, calling `progress` as the secret is embedded.
+    ///
+    /// Behaves like [`encrypt_image`](Self::encrypt_image). `progress` gets the
+    /// percentage embedded so far (see
+    /// [`ProgressCallback`](steganography::ProgressCallback)), on the blocking
+    /// thread pool, so it must not block for long.
+    ///
+    /// # Example
+    /// ```ignore
+    /// let result = core
+    ///     .encrypt_image_with_progress(1, "Client1".to_string(), secret_image, move |percent| {
+    ///         let _ = tx.try_send(percent);
+    ///     })
+    ///     .await?;
+    /// ```
+    pub async fn encrypt_image_with_progress(
+        &self,
+        request_id: u64,
+        client_name: String,
+        secret_image_data: Vec<u8>,
+        progress: impl Fn(u8) + Send + Sync + 'static,
+    ) -> Result<Vec<u8>> {
+        self.encrypt(
+            request_id,
+            client_name,
+            secret_image_data,
+            None,
+            None,
+            Some(Box::new(progress)),
+        )
+        .await
+    }
+
+    /// Process an encryption task, embedding `metadata` text alongside the secret.
+    ///
+    /// Behaves like [`encrypt_image`](Self::encrypt_image); the text can be read
+    /// back from the result with [`steganography::extract_metadata`].
+    ///
+    /// # Errors
+    /// - `metadata` is over 65535 bytes
+    /// - Same as [`encrypt_image`](Self::encrypt_image)
+    ///
+    /// # Example
+    /// ```ignore
+    /// let result = core
+    ///     .encrypt_image_with_metadata(1, "Client1".to_string(), secret_image, caption)
+    ///     .await?;
+    /// ```
+    pub async fn encrypt_image_with_metadata(
+        &self,
+        request_id: u64,
+        client_name: String,
+        secret_image_data: Vec<u8>,
+        metadata: String,
+    ) -> Result<Vec<u8>> {
+        self.encrypt(
+            request_id,
+            client_name,
+            secret_image_data,
+            None,
+            Some(metadata),
+            None,
+        )
+        .await
+    }
+
+    /// Process an encryption task with any combination of the options above.
+    pub(crate) async fn encrypt(
+        &self,
+        request_id: u64,
+        client_name: String,
+        secret_image_data: Vec<u8>,
+        min_capacity: Option<usize>,
+        metadata: Option<String>,
+        progress: Option<Box<dyn Fn(u8) + Send + Sync>>,
     ) -> Result<Vec<u8>> {
         info!(
             "📷 Server {} processing encryption request #{} from client '{}' (secret image size: {} bytes)",
             self.server_id, request_id, client_name, secret_image_data.len()
         );
 
+        // Reject oversized secrets before spending CPU on the embed loop
+        let metadata_size = metadata
+            .as_ref()
+            .map_or(0, |text| steganography::METADATA_PREFIX_SIZE + text.len());
+        let required =
+            (secret_image_data.len() + metadata_size).max(min_capacity.unwrap_or(0));
+        let carrier = self.select_carrier(required).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Secret image too large: {} bytes but carrier can hold at most {} bytes",
+                required,
+                self.carrier_capacity()
+            )
+        })?;
+
+        info!(
+            "🖼️  Server {} using carrier '{}' ({} KB capacity) for request #{}",
+            self.server_id,
+            carrier.name,
+            carrier.capacity / 1024,
+            request_id
+        );
+
+        if !self.processing_delay.is_zero() {
+            tokio::time::sleep(self.processing_delay).await;
+        }
+
         // Clone the carrier image for this task
-        let carrier_image = self.default_carrier_image.clone();
+        let carrier_image = carrier.image.clone();
 
         // Perform encryption in a blocking thread pool to avoid blocking async runtime
         // This is important because steganography is CPU-intensive
         let encryption_result = tokio::task::spawn_blocking(move || {
-            steganography::embed_image_bytes(&carrier_image, &secret_image_data)
+            steganography::embed_image_payload(
+                &carrier_image,
+                &secret_image_data,
+                metadata.as_deref(),
+                &EmbedOptions::default(),
+                None,
+                progress
+                    .as_deref()
+                    .map(|progress| progress as &steganography::ProgressCallback<'_>),
+            )
         })
         .await
         .map_err(|e| anyhow::anyhow!("Encryption task panicked: {}", e))??;
@@ -173,3 +469,90 @@ impl ServerCore {
         Ok(encryption_result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a blank PNG of the given size into `dir` under `name`.
+    fn write_carrier(dir: &Path, name: &str, width: u32, height: u32) {
+        image::RgbaImage::new(width, height)
+            .save_with_format(dir.join(name), image::ImageFormat::Png)
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_carrier_pool_picks_smallest_fitting_carrier() {
+        let dir = tempfile::tempdir().unwrap();
+        write_carrier(dir.path(), "small.png", 32, 32);
+        write_carrier(dir.path(), "large.png", 128, 128);
+        std::fs::write(dir.path().join("notes.txt"), "not an image").unwrap();
+
+        let core = ServerCore::with_carrier_pool(1, dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(core.carriers.len(), 2);
+        assert_eq!(core.carriers[0].name, "small.png");
+
+        let small_capacity = core.carriers[0].capacity;
+        assert_eq!(core.select_carrier(small_capacity).unwrap().name, "small.png");
+        assert_eq!(core.select_carrier(small_capacity + 1).unwrap().name, "large.png");
+        assert!(core.select_carrier(core.carrier_capacity() + 1).is_none());
+
+        // Too big for the small carrier, but still accepted via the large one
+        let secret = vec![7u8; small_capacity + 100];
+        let encoded = core
+            .encrypt_image(1, "Client1".to_string(), secret.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            image::load_from_memory(&encoded).unwrap().width(),
+            128,
+            "secret should land in the large carrier"
+        );
+        assert_eq!(steganography::extract_image_bytes(&encoded).unwrap(), secret);
+
+        // A capacity hint forces the larger carrier even for a tiny secret
+        let hinted = core
+            .encrypt_image_with_hint(
+                2,
+                "Client1".to_string(),
+                vec![1u8; 8],
+                Some(small_capacity + 1),
+            )
+            .await
+            .unwrap();
+        assert_eq!(image::load_from_memory(&hinted).unwrap().width(), 128);
+
+        assert!(core
+            .encrypt_image(3, "Client1".to_string(), vec![0u8; core.carrier_capacity() + 1])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_is_embedded_and_counts_towards_carrier_choice() {
+        let dir = tempfile::tempdir().unwrap();
+        write_carrier(dir.path(), "small.png", 32, 32);
+        write_carrier(dir.path(), "large.png", 128, 128);
+        let core = ServerCore::with_carrier_pool(1, dir.path().to_str().unwrap()).unwrap();
+
+        // The secret alone just fits the small carrier, but not with the metadata
+        let secret = vec![7u8; core.carriers[0].capacity];
+        let encoded = core
+            .encrypt_image_with_metadata(1, "Client1".to_string(), secret.clone(), "x".repeat(40))
+            .await
+            .unwrap();
+        assert_eq!(image::load_from_memory(&encoded).unwrap().width(), 128);
+        assert_eq!(
+            steganography::extract_metadata(&encoded).unwrap(),
+            Some("x".repeat(40))
+        );
+        assert_eq!(steganography::extract_image_bytes(&encoded).unwrap(), secret);
+    }
+
+    #[test]
+    fn test_carrier_pool_rejects_directory_without_images() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("readme.md"), "no images here").unwrap();
+        assert!(ServerCore::with_carrier_pool(1, dir.path().to_str().unwrap()).is_err());
+    }
+}
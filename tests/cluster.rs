@@ -0,0 +1,68 @@
+//! Leader election and failover on a real (in-process, TCP) three-node cluster.
+
+mod common;
+
+use std::time::Duration;
+
+use common::{TestCluster, ELECTION_WAIT};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_new_leader_elected_after_leader_is_killed() {
+    let mut cluster = TestCluster::start(3).await;
+
+    let leader = cluster.wait_for_leader(ELECTION_WAIT).await;
+
+    cluster.kill(leader);
+    let new_leader = cluster.wait_for_leader(ELECTION_WAIT).await;
+    assert_ne!(new_leader, leader);
+    assert_eq!(cluster.running_ids().len(), 2);
+    for id in cluster.running_ids() {
+        let middleware = cluster.middleware(id);
+        assert_eq!(middleware.current_leader().await, Some(new_leader));
+        assert_eq!(middleware.is_leader().await, id == new_leader);
+    }
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_restarted_node_rejoins_the_cluster() {
+    let mut cluster = TestCluster::start(3).await;
+    let leader = cluster.wait_for_leader(ELECTION_WAIT).await;
+
+    let follower = cluster
+        .running_ids()
+        .into_iter()
+        .find(|id| *id != leader)
+        .unwrap();
+    cluster.stop(follower).await;
+    assert_eq!(cluster.leader_seen_by(follower), None);
+
+    // The leader's state sync lets it rejoin before its initial election timer
+    cluster.restart(follower);
+    let rejoined_leader = cluster.wait_for_leader(Duration::from_secs(2)).await;
+    assert_eq!(rejoined_leader, leader);
+    assert_eq!(cluster.running_ids(), vec![1, 2, 3]);
+
+    cluster.shutdown().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_leader_replaced_while_simulating_failure() {
+    let cluster = TestCluster::start(3).await;
+    let leader = cluster.wait_for_leader(ELECTION_WAIT).await;
+
+    cluster.simulate_fail(leader, Duration::from_secs(8)).await;
+    let others: Vec<_> = cluster
+        .running_ids()
+        .into_iter()
+        .filter(|id| *id != leader)
+        .collect();
+    let new_leader = cluster.wait_for_leader_among(&others, ELECTION_WAIT).await;
+    assert_ne!(new_leader, leader);
+
+    // Once recovered, the old leader learns of the newer term and all three agree
+    cluster.wait_for_leader(ELECTION_WAIT).await;
+
+    cluster.shutdown().await;
+}
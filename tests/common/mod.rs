@@ -0,0 +1,281 @@
+//! # In-Process Cluster Harness
+//!
+//! Starts several [`ServerMiddleware`] instances on ephemeral localhost ports,
+//! wired to each other as peers, and lets tests stop, kill and restart them.
+//!
+//! Each node reports its [`ElectionEvent`]s to the harness, which tracks the
+//! leader every running node currently follows.
+//!
+//! ## Example
+//!
+//! ```ignore
+//! let mut cluster = TestCluster::start(3).await;
+//! let leader = cluster.wait_for_leader(ELECTION_WAIT).await;
+//! cluster.kill(leader);
+//! ```
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cloud_p2p::common::connection::Connection;
+use cloud_p2p::server::middleware::ServerConfig;
+use cloud_p2p::server::{ElectionEvent, ServerCore, ServerMiddleware};
+use cloud_p2p::Message;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+
+/// Generous upper bound for a cluster to agree on a leader, covering the
+/// initial election delay, failure detection and peer reconnect backoff.
+pub const ELECTION_WAIT: Duration = Duration::from_secs(30);
+
+/// How often [`TestCluster::wait_for_leader`] re-checks the nodes' views.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A node that is currently running.
+struct RunningNode {
+    /// Fires the shutdown signal passed to `run_until`
+    stop: oneshot::Sender<()>,
+    /// The task running the middleware
+    handle: JoinHandle<()>,
+    /// The leader this node follows, as reported by its `LeaderChanged` events
+    leader: watch::Receiver<Option<u32>>,
+    /// The running middleware, for querying its state directly
+    middleware: Arc<ServerMiddleware>,
+}
+
+/// A cluster member: its configuration plus its running instance, if any.
+struct Node {
+    config: ServerConfig,
+    running: Option<RunningNode>,
+}
+
+/// A cluster of servers running inside the test process.
+pub struct TestCluster {
+    nodes: BTreeMap<u32, Node>,
+}
+
+impl TestCluster {
+    /// Start `size` servers (IDs 1..=size), each listing all the others as peers.
+    pub async fn start(size: u32) -> Self {
+        let addresses = free_addresses(size as usize);
+        let mut nodes = BTreeMap::new();
+        for id in 1..=size {
+            let peers = (1..=size)
+                .filter(|peer| *peer != id)
+                .map(|peer| {
+                    format!(
+                        "{{ id = {}, address = \"{}\" }}",
+                        peer,
+                        addresses[peer as usize - 1]
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let config = node_config(id, &addresses[id as usize - 1], &peers);
+            nodes.insert(
+                id,
+                Node {
+                    config,
+                    running: None,
+                },
+            );
+        }
+
+        let mut cluster = Self { nodes };
+        for id in 1..=size {
+            cluster.restart(id);
+        }
+        cluster
+    }
+
+    /// IDs of the nodes that are currently running.
+    pub fn running_ids(&self) -> Vec<u32> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.running.is_some())
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    /// The leader node `id` currently follows (`None` if stopped or leaderless).
+    pub fn leader_seen_by(&self, id: u32) -> Option<u32> {
+        self.node(id)
+            .running
+            .as_ref()
+            .and_then(|running| *running.leader.borrow())
+    }
+
+    /// The middleware of running node `id`.
+    ///
+    /// # Panics
+    /// If node `id` isn't running.
+    pub fn middleware(&self, id: u32) -> Arc<ServerMiddleware> {
+        self.node(id)
+            .running
+            .as_ref()
+            .map(|running| running.middleware.clone())
+            .unwrap_or_else(|| panic!("node {} isn't running", id))
+    }
+
+    /// Wait until every running node follows the same running leader.
+    ///
+    /// # Panics
+    /// If they haven't agreed within `timeout`.
+    pub async fn wait_for_leader(&self, timeout: Duration) -> u32 {
+        self.wait_for_leader_among(&self.running_ids(), timeout)
+            .await
+    }
+
+    /// Wait until nodes `ids` all follow the same leader, itself one of `ids`.
+    ///
+    /// # Panics
+    /// If they haven't agreed within `timeout`.
+    pub async fn wait_for_leader_among(&self, ids: &[u32], timeout: Duration) -> u32 {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let views: Vec<_> = ids.iter().map(|id| self.leader_seen_by(*id)).collect();
+            if let Some(Some(leader)) = views.first() {
+                if ids.contains(leader) && views.iter().all(|view| view == &Some(*leader)) {
+                    return *leader;
+                }
+            }
+            assert!(
+                tokio::time::Instant::now() < deadline,
+                "no agreed leader after {:?}: nodes {:?} follow {:?}",
+                timeout,
+                ids,
+                views
+            );
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Stop node `id` cleanly through its shutdown signal (a leader resigns first).
+    pub async fn stop(&mut self, id: u32) {
+        if let Some(running) = self.node_mut(id).running.take() {
+            let _ = running.stop.send(());
+            running.handle.await.expect("server task panicked");
+        }
+    }
+
+    /// Crash node `id`: its tasks are aborted without resigning or draining.
+    pub fn kill(&mut self, id: u32) {
+        if let Some(running) = self.node_mut(id).running.take() {
+            running.handle.abort();
+        }
+    }
+
+    /// Make node `id` play dead for `duration` with a [`Message::SimulateFail`].
+    pub async fn simulate_fail(&self, id: u32, duration: Duration) {
+        let address = &self.node(id).config.server.address;
+        let stream = TcpStream::connect(address).await.expect("node unreachable");
+        let mut conn = Connection::new(stream);
+        conn.handshake().await.expect("handshake failed");
+        conn.write_message(&Message::SimulateFail {
+            duration_secs: duration.as_secs(),
+        })
+        .await
+        .expect("failed to send SimulateFail");
+    }
+
+    /// Start node `id` again on its original address with fresh state.
+    ///
+    /// Does nothing if it's already running.
+    pub fn restart(&mut self, id: u32) {
+        let node = self.node_mut(id);
+        if node.running.is_some() {
+            return;
+        }
+
+        let (events_tx, mut events_rx) = mpsc::channel(100);
+        let (leader_tx, leader_rx) = watch::channel(None);
+        tokio::spawn(async move {
+            while let Some(event) = events_rx.recv().await {
+                if let ElectionEvent::LeaderChanged { leader_id } = event {
+                    let _ = leader_tx.send(leader_id);
+                }
+            }
+        });
+
+        let core = Arc::new(ServerCore::from_bytes(id, Vec::new()));
+        let middleware = Arc::new(
+            ServerMiddleware::new(node.config.clone(), core).with_election_events(events_tx),
+        );
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+        let runner = middleware.clone();
+        let handle = tokio::spawn(async move {
+            runner
+                .run_until(async {
+                    let _ = stop_rx.await;
+                })
+                .await;
+        });
+
+        node.running = Some(RunningNode {
+            stop: stop_tx,
+            handle,
+            leader: leader_rx,
+            middleware,
+        });
+    }
+
+    /// Stop every running node cleanly.
+    pub async fn shutdown(mut self) {
+        for id in self.running_ids() {
+            self.stop(id).await;
+        }
+    }
+
+    fn node(&self, id: u32) -> &Node {
+        self.nodes
+            .get(&id)
+            .unwrap_or_else(|| panic!("no node {}", id))
+    }
+
+    fn node_mut(&mut self, id: u32) -> &mut Node {
+        self.nodes
+            .get_mut(&id)
+            .unwrap_or_else(|| panic!("no node {}", id))
+    }
+}
+
+/// Reserve `count` distinct free localhost addresses.
+///
+/// All listeners are held until every port is chosen so none repeats; they're
+/// released on return for the servers to bind.
+fn free_addresses(count: usize) -> Vec<String> {
+    let listeners: Vec<_> = (0..count)
+        .map(|_| std::net::TcpListener::bind("127.0.0.1:0").expect("no free port"))
+        .collect();
+    listeners
+        .iter()
+        .map(|listener| listener.local_addr().unwrap().to_string())
+        .collect()
+}
+
+/// Configuration for node `id` with short timings so failover happens in seconds.
+fn node_config(id: u32, address: &str, peers: &str) -> ServerConfig {
+    let config: ServerConfig = toml::from_str(&format!(
+        r#"
+        [server]
+        id = {}
+        address = "{}"
+
+        [peers]
+        peers = [{}]
+
+        [election]
+        heartbeat_interval_secs = 1
+        election_timeout_secs = 1
+        initial_election_delay_ms = 200
+        failure_timeout_secs = 3
+        monitor_interval_secs = 1
+        "#,
+        id, address, peers
+    ))
+    .expect("invalid test config");
+    config.validate().expect("invalid test config");
+    config
+}
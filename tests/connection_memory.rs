@@ -0,0 +1,133 @@
+//! Peak memory of reading an image message off a [`Connection`].
+//!
+//! Measuring it takes a counting `#[global_allocator]`, which would replace the
+//! allocator of every other test in the binary it's declared in, so these tests
+//! get a binary of their own.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use cloud_p2p::common::connection::Connection;
+use cloud_p2p::common::messages::Message;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+/// Allocator that tracks the bytes each thread has allocated, and their peak,
+/// so a test can measure how much memory reading a message takes.
+struct PeakAlloc;
+
+thread_local! {
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Add `delta` bytes to this thread's allocation count.
+fn track(delta: isize) {
+    let _ = ALLOCATED.try_with(|allocated| {
+        allocated.set(allocated.get() + delta);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(allocated.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            track(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        track(-(layout.size() as isize));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            track(new_size as isize - layout.size() as isize);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakAlloc = PeakAlloc;
+
+/// Most bytes this thread held at once, beyond what it held on entry, while
+/// running `future` to completion.
+async fn peak_allocation<T>(future: impl std::future::Future<Output = T>) -> (T, usize) {
+    let start = ALLOCATED.with(Cell::get);
+    PEAK.with(|peak| peak.set(start));
+    let output = future.await;
+    (output, (PEAK.with(Cell::get) - start).max(0) as usize)
+}
+
+/// Open a connected (client, server) socket pair over loopback.
+async fn socket_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (client, server) = tokio::join!(TcpStream::connect(address), listener.accept());
+    (client.unwrap(), server.unwrap().0)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_payload_frame_halves_peak_memory_when_reading() {
+    const IMAGE_SIZE: usize = 8 * 1024 * 1024;
+    let (payload_client, payload_server) = socket_pair().await;
+    let (mut whole_client, whole_server) = socket_pair().await;
+    let (mut payload_server, mut whole_server) = (
+        Connection::new(payload_server),
+        Connection::new(whole_server),
+    );
+    let response = Message::TaskResponse {
+        request_id: 1,
+        encrypted_image_data: vec![0xEF; IMAGE_SIZE],
+        success: true,
+        error_message: None,
+    };
+    // The same message as a single encoded frame, as sent before payload frames
+    let format = response.preferred_format();
+    let mut whole_frame = vec![format.tag()];
+    whole_frame.extend(response.encode(format).unwrap());
+
+    // Writers run on other threads; only this thread's allocations are counted
+    let writer = tokio::spawn(async move {
+        let mut client = Connection::new(payload_client);
+        client.write_message(&response).await.unwrap();
+        whole_client
+            .write_all(&(whole_frame.len() as u32).to_be_bytes())
+            .await
+            .unwrap();
+        whole_client.write_all(&whole_frame).await.unwrap();
+        (client, whole_client)
+    });
+    let (payload_read, payload_peak) = peak_allocation(payload_server.read_message()).await;
+    let (whole_read, whole_peak) = peak_allocation(whole_server.read_message()).await;
+    writer.await.unwrap();
+
+    for read in [payload_read, whole_read] {
+        let message = read.unwrap().unwrap();
+        assert_eq!(message.payload().map(<[u8]>::len), Some(IMAGE_SIZE));
+    }
+    // The image is held once instead of twice (frame, then decoded copy)
+    assert!(
+        payload_peak < IMAGE_SIZE + IMAGE_SIZE / 10,
+        "payload frame peaked at {} bytes",
+        payload_peak
+    );
+    assert!(
+        whole_peak >= 2 * IMAGE_SIZE,
+        "whole frame peaked at {} bytes",
+        whole_peak
+    );
+}